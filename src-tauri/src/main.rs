@@ -6,31 +6,38 @@
 )]
 
 use walkdir::WalkDir;
+use rayon::prelude::*;
+use rand::seq::SliceRandom;
 use ini::Ini;
 use tauri::PathResolver;
 use regex::Regex;
 use lazy_static::lazy_static;
 use rusqlite::{Connection, OptionalExtension, Result as SqlResult, params, OpenFlags};
 use serde::{Serialize, Deserialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{self, BufReader, BufRead, Read, Seek, Cursor, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Mutex, Arc};
+use std::sync::{Mutex, Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
 use tauri::{
-    command, generate_context, generate_handler, AppHandle, Manager, State, api::dialog,
+    command, generate_context, generate_handler, AppHandle, GlobalShortcutManager, Manager, State, api::dialog,
     api::process::Command, Window
 };
 use std::process::exit;
 use thiserror::Error;
 use once_cell::sync::Lazy;
+use tokio::sync::Semaphore;
 use tauri::async_runtime;
 use toml;
 use tauri::api::file::read_binary;
 use sevenz_rust::{Password, decompress_file};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher, EventKind};
 use zip::{ZipArchive, result::ZipError};
 use unrar::{Archive, Process, List, ListSplit};
-use rusqlite::Transaction;
+use rusqlite::{Transaction, TransactionBehavior};
+use r2d2_sqlite::SqliteConnectionManager;
+use sha2::{Digest, Sha256};
 use std::ffi::OsStr;
 
 // --- Structs for Deserializing Definitions ---
@@ -66,14 +73,281 @@ struct Preset {
     is_favorite: bool,
 }
 
+// Presets created automatically by disable_all_mods; hidden from get_presets unless requested
+const AUTO_SNAPSHOT_PRESET_PREFIX: &str = "__autosnapshot_";
+const AUTO_SNAPSHOT_KEEP_COUNT: i64 = 10; // Older auto-snapshots of the profile are pruned when a new one is saved
+
+// Portable preset file: assets are keyed by their relative folder path, not DB id
+const PRESET_EXPORT_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PresetExportEntry {
+    folder_name: String,
+    is_enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PresetExportFile {
+    format_version: u32,
+    name: String,
+    assets: Vec<PresetExportEntry>,
+    #[serde(default)]
+    favorites: Vec<String>, // Folder names of favorited assets in the library
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct PresetAssetChange {
+    asset_id: i64,
+    name: String,
+    current_enabled: bool,
+    target_enabled: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct PresetMissingAsset {
+    asset_id: i64,
+    name: String,
+    folder_name: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct PresetApplyPreview {
+    changes: Vec<PresetAssetChange>,
+    missing: Vec<PresetMissingAsset>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct PresetDiffEntry {
+    asset_id: i64,
+    name: String,
+    entity_slug: Option<String>, // None if the asset was deleted after the preset was saved
+    asset_deleted: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct PresetDiff {
+    enabled_only_in_a: Vec<PresetDiffEntry>, // In both presets, enabled in A and disabled in B
+    enabled_only_in_b: Vec<PresetDiffEntry>,
+    only_in_a: Vec<PresetDiffEntry>, // Not part of B's snapshot at all
+    only_in_b: Vec<PresetDiffEntry>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct PresetAssetDetail {
+    asset_id: i64,
+    is_enabled: bool, // State stored in the preset
+    name: String,
+    folder_name: Option<String>, // None if the asset was deleted after the preset was saved
+    entity_slug: Option<String>,
+    asset_deleted: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct PresetAssetDetails {
+    preset_id: i64,
+    entries: Vec<PresetAssetDetail>,
+    deleted_count: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct PresetImportResult {
+    preset: Preset,
+    warnings: Vec<String>,
+}
+
+// Whole-library backup (export_library / import_library). Like preset files, assets are keyed
+// by their clean relative folder path so the file works against a fresh database.
+const LIBRARY_EXPORT_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LibraryExportCategory {
+    name: String,
+    slug: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LibraryExportEntity {
+    category_slug: String,
+    name: String,
+    slug: String,
+    description: Option<String>,
+    details: Option<String>,
+    base_image: Option<String>,
+    // Contents of a custom image set with set_entity_image; bundled images are not included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image_data: Option<Vec<u8>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LibraryExportAsset {
+    folder_name: String,
+    entity_slug: String,
+    name: String,
+    description: Option<String>,
+    image_filename: Option<String>,
+    author: Option<String>,
+    category_tag: Option<String>,
+    notes: Option<String>,
+    source_url: Option<String>,
+    version: Option<String>,
+    #[serde(default)]
+    is_favorite: bool,
+    #[serde(default)]
+    is_pinned: bool,
+    #[serde(default)]
+    is_locked: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LibraryExportPreset {
+    name: String,
+    #[serde(default)]
+    is_favorite: bool,
+    assets: Vec<PresetExportEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LibraryExportFile {
+    format_version: u32,
+    exported_at: i64, // Unix seconds
+    categories: Vec<LibraryExportCategory>,
+    entities: Vec<LibraryExportEntity>,
+    assets: Vec<LibraryExportAsset>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    presets: Vec<LibraryExportPreset>,
+}
+
+// Manifest written at the root of export_assets_as_archive packs. Each mod sits in a top-level
+// folder named after its clean folder name; the slugs let an importer place it without deducing.
+const MOD_PACK_MANIFEST_FILENAME: &str = "gmm_manifest.json";
+const MOD_PACK_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ModPackEntry {
+    folder: String, // Top-level folder inside the archive
+    name: String,
+    author: Option<String>,
+    description: Option<String>,
+    version: Option<String>,
+    source_url: Option<String>,
+    entity_slug: String,
+    entity_name: String,
+    category_slug: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ModPackManifest {
+    format_version: u32,
+    exported_at: i64, // Unix seconds
+    mods: Vec<ModPackEntry>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+struct LibraryImportResult {
+    categories_added: usize,
+    entities_added: usize,
+    assets_added: usize,
+    assets_updated: usize,
+    presets_added: usize,
+    conflicts: Vec<String>, // Existing data that was kept instead of the file's version
+    warnings: Vec<String>,
+}
+
 #[derive(Clone, serde::Serialize)]
 struct ApplyProgress {
+  operation_id: String, // Same for every event of one command invocation
   processed: usize,
   total: usize,
   current_asset_id: Option<i64>,
   message: String,
 }
 
+#[derive(Serialize, Debug, Clone)]
+struct BulkToggleFailure {
+    asset_id: i64,
+    error: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct BulkToggleSummary {
+    succeeded: Vec<i64>,
+    failed: Vec<BulkToggleFailure>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct ReconcileEnabledSummary {
+    checked: usize,
+    updated: usize, // Rows whose stored is_enabled differed from disk
+    missing: usize, // Folders not found on disk; their is_enabled is cleared
+}
+
+// Result of disable_all_mods: the snapshot preset that restores the previous state
+#[derive(Serialize, Debug, Clone)]
+struct DisableAllSummary {
+    snapshot: Preset,
+    result: BulkToggleSummary,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct AssetVariant {
+    id: i64,
+    asset_id: i64,
+    name: String,
+    folder_name: String, // Clean child folder name relative to the asset folder
+    is_enabled: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct RelocateAssetsSummary {
+    relocated: Vec<i64>,
+    skipped: Vec<BulkToggleFailure>, // Destination already exists, or already in place
+    failed: Vec<BulkToggleFailure>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct RededuceResult {
+    asset_id: i64,
+    old_entity_slug: String,
+    new_entity_slug: String,
+    moved: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct AssetFolderRename {
+    asset_id: i64,
+    old_folder_name: String, // Clean relative paths
+    new_folder_name: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct MergeEntityResult {
+    moved: Vec<i64>,
+    renamed: Vec<AssetFolderRename>, // Moved assets that got a suffix because the name was taken
+    source_deleted: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct LaunchTargetStatus {
+    path: Option<String>,
+    exists: bool,
+    is_file: bool,
+    is_executable: bool,
+    d3dx_ini_found: Option<bool>, // None when not checked
+    problems: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct AssetPage {
+    assets: Vec<Asset>,
+    total: i64, // All assets of the entity in the DB
+    offset: i64,
+    limit: i64,
+}
+
 #[derive(Serialize, Debug, Clone)]
 struct DashboardStats {
     total_mods: i64,
@@ -89,16 +363,132 @@ struct KeybindInfo {
     key: String,
 }
 
+#[derive(Serialize, Debug, Clone)]
+struct AssetKeybind {
+    ini_file: String, // Relative to the mod folder
+    section: String,
+    key: Option<String>,
+    back: Option<String>,
+    description: Option<String>, // From the comment lines directly above the section header
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct KeybindConflict {
+    key: String,
+    first_asset_id: i64,
+    second_asset_id: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct NamespaceCollision {
+    namespace: String,
+    asset_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct MissingIniReference {
+    ini_file: String, // Relative to the mod folder
+    line: usize,
+    reference: String, // As written after `filename =`
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct IniSyntaxError {
+    ini_file: String, // Relative to the mod folder
+    line: usize,
+    message: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct AssetValidation {
+    asset_id: i64,
+    ini_files: Vec<String>, // Active INIs found, relative to the mod folder
+    missing_files: Vec<MissingIniReference>,
+    syntax_errors: Vec<IniSyntaxError>,
+    is_valid: bool, // At least one INI, nothing missing and no syntax errors
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct BrokenAsset {
+    asset_id: i64,
+    name: String,
+    entity_slug: String,
+    entity_name: String,
+    category_slug: String,
+    validation: Option<AssetValidation>,
+    error: Option<String>, // Set instead of validation when the INIs couldn't be read
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct IniSectionSummary {
+    name: String,
+    key_count: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct IniFileOverview {
+    ini_file: String, // Relative to the mod folder
+    is_active: bool, // false for DISABLED-prefixed INIs, which 3DMigoto skips
+    sections: Vec<IniSectionSummary>, // In file order; repeated section names are listed each time
+}
+
+// Enabled mods overriding the same resource. Texture overrides that differ only in
+// match_first_index hit different parts of a model, so that value is part of the key.
+#[derive(Serialize, Debug, Clone)]
+struct HashConflict {
+    hash: String,
+    match_first_index: Option<String>,
+    asset_ids: Vec<i64>,
+}
+
 // Type alias for the top-level structure (HashMap: category_slug -> CategoryDefinition)
 type Definitions = HashMap<String, CategoryDefinition>;
 
 // --- Constants for Settings Keys ---
 const SETTINGS_KEY_MODS_FOLDER: &str = "mods_folder_path";
+const SETTINGS_KEY_QUICK_LAUNCH: &str = "quick_launch_path";
+const SETTINGS_KEY_ACTIVE_PROFILE: &str = "active_profile_id";
+const SETTINGS_KEY_SCHEMA_VERSION: &str = "schema_version";
+// Max Levenshtein distance for fuzzy entity/category name matching during deduction (0 disables it)
+const SETTINGS_KEY_FUZZY_MATCH_MAX_DISTANCE: &str = "fuzzy_match_max_distance";
+const DEFAULT_FUZZY_MATCH_MAX_DISTANCE: usize = 2;
+// How many disk-stat-heavy commands (dashboard/entity counts) may hit the disk at once
+const SETTINGS_KEY_DISK_STAT_CONCURRENCY: &str = "disk_stat_concurrency";
+const DEFAULT_DISK_STAT_CONCURRENCY: usize = 2;
+// Entity slug that receives mods deduction can't place at all; unset means "<DEFAULT_FALLBACK_CATEGORY>-other"
+const SETTINGS_KEY_FALLBACK_ENTITY: &str = "fallback_entity_slug";
+const DEFAULT_FALLBACK_CATEGORY: &str = "characters";
+// "true" blocks every command that writes to the mods folder (browsing a backup copy, etc.)
+const SETTINGS_KEY_SAFE_MODE: &str = "safe_mode";
+// "false" leaves content hashes of changed folders to compute_missing_hashes so scans stay quick
+const SETTINGS_KEY_SCAN_HASHING: &str = "scan_content_hashing";
+// "true" lets maybe_autoscan rescan on launch when the mods folder changed since the last full scan
+const SETTINGS_KEY_AUTOSCAN: &str = "autoscan";
+// Unix seconds at which the last successful full scan started (written by run_mods_scan)
+const SETTINGS_KEY_LAST_SCAN_COMPLETED_AT: &str = "last_scan_completed_at";
+const AUTOSCAN_MTIME_DEPTH: usize = 2; // Category and entity folders, where mod folders get added or renamed
+// "true" makes the toggle commands (single, bulk, randomize) snapshot a mod's INI files first, but
+// not preset apply (see AssetToggleContext and create_ini_backup)
+const SETTINGS_KEY_INI_BACKUP_ON_TOGGLE: &str = "ini_backup_on_toggle";
+const HASH_BATCH_SIZE: usize = 25; // Hashes committed per transaction by compute_missing_hashes
+const SQL_IN_LIST_CHUNK_SIZE: usize = 500; // Bound values per `IN (...)` query, below SQLite's 999 variable limit
+const DEFAULT_PROFILE_NAME: &str = "Default";
 const OTHER_ENTITY_SUFFIX: &str = "-other";
 const OTHER_ENTITY_NAME: &str = "其他/未知";
-const DB_NAME: &str = "app_data.sqlite";
-const DISABLED_PREFIX: &str = "DISABLED_";
+const DISABLED_PREFIX: &str = "DISABLED_"; // Default disabled-folder marker (see DisableConvention)
+const MIGOTO_DISABLED_PREFIX: &str = "DISABLED"; // 3DMigoto ignores files and folders starting with this, in any case
+// Stored as "prefix:<marker>" or "suffix:<marker>"; change it through set_disable_convention
+const SETTINGS_KEY_DISABLE_CONVENTION: &str = "disable_convention";
 const TARGET_IMAGE_FILENAME: &str = "preview.png";
+// "rename" (default) toggles mods by renaming their folders; "symlink" keeps them in the
+// staging folder below and enables them by linking them into place
+const SETTINGS_KEY_ENABLE_MODE: &str = "enable_mode";
+// 3DMigoto skips folders starting with DISABLED, so staged mods are never loaded twice
+const SYMLINK_STAGING_FOLDER_NAME: &str = "DISABLED_gmm_staging";
+// Comma-separated preview image filenames, matched case-insensitively; earlier entries win
+const SETTINGS_KEY_PREVIEW_CANDIDATES: &str = "preview_image_candidates";
+const DEFAULT_PREVIEW_CANDIDATES: &[&str] = &["preview.png", "preview.jpg", "icon.png", "icon.jpg", "thumbnail.png", "thumbnail.jpg"];
+const SUPPORTED_ARCHIVE_EXTENSIONS: &[&str] = &["zip", "7z", "rar"];
 
 // --- Error Handling ---
 #[derive(Debug, Error)]
@@ -121,31 +511,108 @@ enum AppError {
     UserCancelled,
     #[error("Shell command failed: {0}")]
     ShellCommand(String),
-    #[error("Zip error: {0}")]
-    Zip(#[from] zip::result::ZipError),
-    #[error("7z error: {0}")]
-    SevenZ(#[from] sevenz_rust::Error),
-    #[error("RAR error: {0}")]
-    Rar(#[from] unrar::error::UnrarError),
+    #[error("File watcher error: {0}")]
+    Watcher(#[from] notify::Error),
+    #[error("Database pool error: {0}")]
+    Pool(#[from] r2d2::Error),
+    #[error("Archive error: {0}")]
+    Archive(String),
     #[error("Unsupported archive type: {0}")]
     UnsupportedArchive(String),
 }
 
+// Container-specific errors all surface as AppError::Archive
+impl From<zip::result::ZipError> for AppError {
+    fn from(e: zip::result::ZipError) -> Self { AppError::Archive(format!("Zip: {}", e)) }
+}
+impl From<sevenz_rust::Error> for AppError {
+    fn from(e: sevenz_rust::Error) -> Self { AppError::Archive(format!("7z: {}", e)) }
+}
+impl From<unrar::error::UnrarError> for AppError {
+    fn from(e: unrar::error::UnrarError) -> Self { AppError::Archive(format!("RAR: {}", e)) }
+}
+
 // --- Event Payload Struct ---
 #[derive(Clone, serde::Serialize)]
 struct ScanProgress {
+  operation_id: String, // Same for every event of one scan
   processed: usize,
   total: usize,
   current_path: Option<String>,
   message: String,
 }
 
+// Payload of import://progress, emitted per extracted file
+#[derive(Clone, serde::Serialize)]
+struct ImportProgress {
+  operation_id: String, // Same for every event of one import (or batch)
+  processed: usize,
+  total: usize,
+  current_file: Option<String>,
+  message: String,
+}
+
+// Payload of the complete/error events that end a scan, import, preset apply or prune,
+// tagged like its progress events so the frontend can tell which operation finished
+#[derive(Clone, serde::Serialize)]
+struct OperationResult {
+  operation_id: String,
+  message: String,
+}
+
+fn emit_operation_result(app_handle: &AppHandle, event: &str, operation_id: &str, message: impl Into<String>) {
+    let payload = OperationResult { operation_id: operation_id.to_string(), message: message.into() };
+    app_handle.emit_all(event, payload).unwrap_or_else(|e| eprintln!("Failed to emit {}: {}", event, e));
+}
+
+// Payload of scan://preview, emitted by dry-run scans instead of writing to the DB
+#[derive(Clone, serde::Serialize, Default)]
+struct ScanPreview {
+  would_add: Vec<ScanPreviewEntry>,
+  would_prune: Vec<ScanPreviewEntry>,
+  would_rename: Vec<String>, // Folders with a "DISABLED" prefix missing its underscore
+}
+
+#[derive(Clone, serde::Serialize)]
+struct ScanPreviewEntry {
+  asset_id: Option<i64>, // None for assets that would be added
+  name: String,
+  folder_name: String,
+  entity_slug: String,
+}
+
+// Safety limits for scan pruning; `force_prune` bypasses them. Scans only report missing mods
+// unless `prune_missing` is set; otherwise deletion goes through prune_missing_assets.
+#[derive(Clone, Copy, Debug)]
+struct PruneGuard {
+  min_found_ratio: f64,
+  force_prune: bool,
+  prune_missing: bool,
+}
+
+impl Default for PruneGuard {
+  fn default() -> Self {
+    PruneGuard { min_found_ratio: DEFAULT_MIN_FOUND_RATIO, force_prune: false, prune_missing: false }
+  }
+}
+
+impl PruneGuard {
+  fn from_options(min_found_ratio: Option<f64>, force_prune: Option<bool>) -> Self {
+    PruneGuard {
+      min_found_ratio: min_found_ratio.unwrap_or(DEFAULT_MIN_FOUND_RATIO).clamp(0.0, 1.0),
+      force_prune: force_prune.unwrap_or(false),
+      ..PruneGuard::default()
+    }
+  }
+}
+
 const APP_CONFIG_FILENAME: &str = "app_config.json";
 const DEFAULT_GAME_SLUG: &str = "genshin";
 const PREDEFINED_GAMES: [&str; 2] = ["genshin", "zzz"];
 const DB_INTERNAL_GAME_SLUG_KEY: &str = "database_game_slug";
 const DB_FILENAME_PREFIX: &str = "app_data_"; // Prefix for archived game dbs
 const ACTIVE_DB_FILENAME: &str = "app_data.sqlite";
+const DB_POOL_MAX_SIZE: u32 = 8;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct AppConfig {
@@ -157,101 +624,577 @@ struct AppConfig {
 const SCAN_PROGRESS_EVENT: &str = "scan://progress";
 const SCAN_COMPLETE_EVENT: &str = "scan://complete";
 const SCAN_ERROR_EVENT: &str = "scan://error";
+const IMPORT_PROGRESS_EVENT: &str = "import://progress";
+const IMPORT_COMPLETE_EVENT: &str = "import://complete";
+const IMPORT_ERROR_EVENT: &str = "import://error";
+const BATCH_IMPORT_PROGRESS_EVENT: &str = "import://batch_progress";
+const SCAN_PREVIEW_EVENT: &str = "scan://preview";
+const DEFAULT_MIN_FOUND_RATIO: f64 = 0.1; // Below this share of known mods found on disk, pruning is skipped
 // Add Preset Apply Event Names
 const PRESET_APPLY_START_EVENT: &str = "preset://apply_start";
 const PRESET_APPLY_PROGRESS_EVENT: &str = "preset://apply_progress";
 const PRESET_APPLY_COMPLETE_EVENT: &str = "preset://apply_complete";
 const PRESET_APPLY_ERROR_EVENT: &str = "preset://apply_error";
+const RANDOMIZE_START_EVENT: &str = "randomize://start";
+const RANDOMIZE_PROGRESS_EVENT: &str = "randomize://progress";
+const RANDOMIZE_COMPLETE_EVENT: &str = "randomize://complete";
+
+// Bulk Enable/Disable Event Names
+const BULK_TOGGLE_START_EVENT: &str = "bulk://toggle_start";
+const BULK_TOGGLE_PROGRESS_EVENT: &str = "bulk://toggle_progress";
+const BULK_TOGGLE_COMPLETE_EVENT: &str = "bulk://toggle_complete";
 
 // --- Add Pruning Event ---
 const PRUNING_START_EVENT: &str = "prune://start";
 const PRUNING_PROGRESS_EVENT: &str = "prune://progress";
 const PRUNING_COMPLETE_EVENT: &str = "prune://complete";
 const PRUNING_ERROR_EVENT: &str = "prune://error";
+const EXPORT_PROGRESS_EVENT: &str = "export://progress";
+const HASH_PROGRESS_EVENT: &str = "hash://progress";
 // -------------------------
 
+// --- Mods Folder Watcher Event ---
+const MODS_CHANGED_EVENT: &str = "mods://changed";
+const WATCHER_DEBOUNCE_MS: u64 = 750;
+// Files found inside mod folders; the watcher ignores them when the event kind doesn't say
+// whether a file or a folder changed (Windows, renames)
+const WATCHER_IGNORED_FILE_EXTENSIONS: &[&str] = &[
+    "ini", "dds", "buf", "ib", "vb", "vb0", "vb1", "fmt", "hlsl", "txt", "md", "json",
+    "png", "jpg", "jpeg", "webp", "gif", "bmp", "zip", "7z", "rar", "tmp",
+];
+
 const SETTINGS_KEY_TRAVELER_MIGRATION_COMPLETE: &str = "traveler_migration_complete_v1"; // Added v1 for potential future migrations
 
 type CmdResult<T> = Result<T, String>;
 
-struct DbState(Arc<Mutex<Connection>>);
+type DbPool = r2d2::Pool<SqliteConnectionManager>;
+type DbConn = r2d2::PooledConnection<SqliteConnectionManager>;
 
-static DB_CONNECTION: Lazy<Mutex<SqlResult<Connection>>> = Lazy::new(|| {
-    Mutex::new(Err(rusqlite::Error::InvalidPath("DB not initialized yet".into())))
-});
+// Pooled connections to the active DB; WAL lets reads run while a write transaction is open
+struct DbState(DbPool);
 
-lazy_static! {
-    static ref MOD_NAME_CLEANUP_REGEX: Regex = Regex::new(r"(?i)(_v\d+(\.\d+)*|_DISABLED|DISABLED_|\(disabled\)|^DISABLED_)").unwrap();
-    static ref CHARACTER_NAME_REGEX: Regex = Regex::new(r"(?i)(Raiden|Shogun|HuTao|Tao|Zhongli|Ganyu|Ayaka|Kazuha|Yelan|Eula|Klee|Nahida)").unwrap();
-    static ref EXCLUDED_INI_FILENAMES: HashSet<String> = {
-        let mut set = HashSet::new();
-        set.insert("orfix.ini".to_string());
-        set.insert("region.ini".to_string());
-        set.insert("offset.ini".to_string());
-        set.insert("water.ini".to_string());
-        set.insert("fixdash.ini".to_string());
-        set.insert("deltatime.ini".to_string());
-        set.insert("object.ini".to_string());
-        set.insert("timer.ini".to_string());
-        set
-    };
-    static ref NAME_CLEANUP_REGEX: Regex = Regex::new(r"(?i)[_\-.\s]+|(_v\d+(\.\d+)*)|(_af)|(_nsfw)|(\(disabled\))|(\(.*\))|(\[.*\])|(^DISABLED_)").unwrap();
-    static ref POTENTIAL_NAME_PART_REGEX: Regex = Regex::new(r"^[a-zA-Z\s]+").unwrap();
+impl DbState {
+    fn conn(&self) -> CmdResult<DbConn> {
+        self.0.get().map_err(|e| format!("DB connection unavailable: {}", e))
+    }
 }
 
-#[derive(Debug)]
-struct DeducedInfo {
-    entity_slug: String,
-    mod_name: String,
-    mod_type_tag: Option<String>,
-    author: Option<String>,
+// --- Undo Log ---
+const MAX_UNDO_OPERATIONS: usize = 20;
+const TRASH_FOLDER_NAME: &str = ".gmm_trash";
+const INI_BACKUP_FOLDER_NAME: &str = "ini_backups";
+// Previous version of a mod while an overwrite-import runs; outside the mods folder so 3DMigoto and scans ignore it
+const OVERWRITE_BACKUP_FOLDER_NAME: &str = "overwrite_backups";
+const INI_BACKUP_MAX_PER_ASSET: usize = 10; // Oldest backups beyond this are removed
+const THUMBNAIL_CACHE_FOLDER_NAME: &str = "thumbnails";
+const ENTITY_IMAGES_FOLDER_NAME: &str = "entity_images";
+const ENTITY_IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif", "bmp"];
+// Archive previews extracted under the system temp dir; stale ones are pruned on each
+// extraction, and the whole folder is removed on startup and on exit
+const ARCHIVE_PREVIEW_TEMP_FOLDER_NAME: &str = "gmm_archive_previews";
+const ARCHIVE_PREVIEW_MAX_AGE_SECS: u64 = 30 * 60;
+// Frontend asset URLs (public/images) for definition images and entities without one
+const BUNDLED_ENTITY_IMAGES_URL: &str = "/images/entities";
+const DEFAULT_ENTITY_IMAGE_URL: &str = "/images/unknown.jpg";
+const DEFAULT_THUMBNAIL_MAX_DIM: u32 = 320;
+// Cap for previews re-encoded on import/edit when normalize_preview is requested
+const DEFAULT_PREVIEW_MAX_DIM: u32 = 1920;
+
+// Everything needed to put a deleted asset back into the DB
+#[derive(Debug, Clone)]
+struct DeletedAssetRecord {
+    id: i64,
+    entity_id: i64,
+    name: String,
     description: Option<String>,
+    folder_name: String,
     image_filename: Option<String>,
+    author: Option<String>,
+    category_tag: Option<String>,
+    notes: Option<String>,
+    source_url: Option<String>,
+    is_favorite: bool,
+    is_locked: bool,
+    is_pinned: bool,
+    sort_order: Option<i64>,
+    version: Option<String>,
+    content_hash: Option<String>,
+    exclusive_group: Option<String>,
+    created_at: Option<i64>,
+    profile_id: Option<i64>,
+    preset_states: Vec<(i64, bool)>, // (preset_id, is_enabled)
+    tag_ids: Vec<i64>,
 }
 
-#[derive(Clone)]
-struct DeductionMaps {
-    category_slug_to_id: HashMap<String, i64>,
-    entity_slug_to_id: HashMap<String, i64>,
-    lowercase_category_name_to_slug: HashMap<String, String>,
-    lowercase_entity_name_to_slug: HashMap<String, String>,
-    entity_slug_to_category_slug: HashMap<String, String>,
-    lowercase_entity_firstname_to_slug: HashMap<String, String>, // e.g., "ellen" -> "ellen-joe"
-    lowercase_entity_first_two_words_to_slug: HashMap<String, String>, // e.g., "ellen joe" -> "ellen-joe"
+// A reversible filesystem mutation. Paths are absolute.
+#[derive(Debug, Clone)]
+enum Operation {
+    Toggle { asset_id: i64, from_path: PathBuf, to_path: PathBuf },
+    // Symlink mode and exclusive group members: undone through set_asset_enabled_on_disk rather than a rename
+    StagedToggle { asset_id: i64, clean_relative_path: String, was_enabled: bool },
+    Relocate { asset_id: i64, from_path: PathBuf, to_path: PathBuf, old_entity_id: i64, old_relative_path: String },
+    Delete { record: Box<DeletedAssetRecord>, original_path: PathBuf, trash_path: PathBuf },
 }
 
-#[derive(Serialize, Deserialize, Debug)] struct Category { id: i64, name: String, slug: String }
-#[derive(Serialize, Deserialize, Debug)] struct Entity { id: i64, category_id: i64, name: String, slug: String, description: Option<String>, details: Option<String>, base_image: Option<String>, mod_count: i32, enabled_mod_count: Option<i32>, recent_mod_count: Option<i32>, favorite_mod_count: Option<i32> }
-#[derive(Serialize, Deserialize, Debug, Clone)] struct Asset { id: i64, entity_id: i64, name: String, description: Option<String>, folder_name: String, image_filename: Option<String>, author: Option<String>, category_tag: Option<String>, is_enabled: bool }
+struct OperationLogState(Mutex<Vec<Operation>>);
 
 #[derive(Serialize, Debug, Clone)]
-struct EntityWithCounts {
-    // Include all fields from Entity that the frontend card needs
+struct Profile {
     id: i64,
-    category_id: i64,
     name: String,
-    slug: String,
-    details: Option<String>, // JSON string
-    base_image: Option<String>,
-    // Counts
-    total_mods: i64,
-    enabled_mods: i64,
+    mods_folder_path: String,
+    quick_launch_path: Option<String>,
+    is_active: bool,
 }
 
-// Structs for Import/Analysis
 #[derive(Serialize, Debug, Clone)]
-struct ArchiveEntry {
-    path: String,
-    is_dir: bool,
-    is_likely_mod_root: bool,
+struct TrashedAsset {
+    id: i64,
+    name: String,
+    original_relative_path: String,
+    trash_folder_name: String,
+    deleted_at: i64, // Unix seconds
 }
 
-#[derive(Serialize, Debug, Clone)]
+// Holds the active mods folder watcher; dropping it stops the watch and its debounce thread
+struct ModWatcherState(Mutex<Option<RecommendedWatcher>>);
+
+// Cancellation flags for long-running operations; each run clears its flag when it starts
+#[derive(Default)]
+struct CancellationState {
+    scan: Arc<AtomicBool>,
+    preset_apply: Arc<AtomicBool>,
+}
+
+// Values derived from the DB that hot paths would otherwise re-query on every call.
+// Cleared when the settings, entities or deduction rules they come from change.
+// The on-disk naming settings are loaded at startup and replaced when their setting is saved.
+struct CachedConfig {
+    mods_base_path: Option<PathBuf>,
+    deduction_maps: Option<DeductionMaps>,
+    disable_convention: DisableConvention,
+    preview_candidates: Vec<String>,
+    symlink_enable_mode: bool,
+}
+
+impl Default for CachedConfig {
+    fn default() -> Self {
+        CachedConfig {
+            mods_base_path: None,
+            deduction_maps: None,
+            disable_convention: DisableConvention::default(),
+            preview_candidates: parse_preview_candidates(""),
+            symlink_enable_mode: false,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+struct CachedConfigState(Arc<Mutex<CachedConfig>>);
+
+// The managed state is a clone of this instance, so the free path helpers below
+// (disabled_folder_name, find_preview_image, ...) read the same values as the commands.
+static CACHED_CONFIG: Lazy<CachedConfigState> = Lazy::new(CachedConfigState::default);
+
+impl CachedConfigState {
+    fn config(&self) -> std::sync::MutexGuard<'_, CachedConfig> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn invalidate_mods_base_path(&self) {
+        self.config().mods_base_path = None;
+    }
+
+    fn invalidate_deduction_maps(&self) {
+        self.config().deduction_maps = None;
+    }
+
+    fn disable_convention(&self) -> DisableConvention {
+        self.config().disable_convention.clone()
+    }
+
+    fn set_disable_convention(&self, convention: DisableConvention) {
+        self.config().disable_convention = convention;
+    }
+
+    fn preview_candidates(&self) -> Vec<String> {
+        self.config().preview_candidates.clone()
+    }
+
+    fn is_symlink_enable_mode(&self) -> bool {
+        self.config().symlink_enable_mode
+    }
+
+    fn load_disable_convention(&self, conn: &Connection) {
+        let convention = match get_setting_value(conn, SETTINGS_KEY_DISABLE_CONVENTION) {
+            Ok(Some(value)) => DisableConvention::from_setting(&value).unwrap_or_else(|| {
+                eprintln!("[load_disable_convention] Invalid setting '{}', using the default", value);
+                DisableConvention::default()
+            }),
+            _ => DisableConvention::default(),
+        };
+        println!("[load_disable_convention] Using {:?}", convention);
+        self.set_disable_convention(convention);
+    }
+
+    fn load_preview_candidates(&self, conn: &Connection) {
+        let value = get_setting_value(conn, SETTINGS_KEY_PREVIEW_CANDIDATES).ok().flatten().unwrap_or_default();
+        let candidates = parse_preview_candidates(&value);
+        println!("[load_preview_candidates] Using {:?}", candidates);
+        self.config().preview_candidates = candidates;
+    }
+
+    fn load_enable_mode(&self, conn: &Connection) {
+        let symlink_mode = matches!(get_setting_value(conn, SETTINGS_KEY_ENABLE_MODE), Ok(Some(value)) if value == "symlink");
+        println!("[load_enable_mode] Using {} mode", if symlink_mode { "symlink" } else { "rename" });
+        self.config().symlink_enable_mode = symlink_mode;
+    }
+}
+
+static DB_CONNECTION: Lazy<Mutex<SqlResult<Connection>>> = Lazy::new(|| {
+    Mutex::new(Err(rusqlite::Error::InvalidPath("DB not initialized yet".into())))
+});
+
+// How a disabled mod folder is named on disk. Loaded from settings at startup; every
+// enabled/disabled check goes through the helpers below so the whole app uses one convention.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "mode", content = "marker", rename_all = "lowercase")]
+enum DisableConvention {
+    Prefix(String), // "DISABLED_Mod", ".Mod"
+    Suffix(String), // "Mod.disabled"
+}
+
+impl Default for DisableConvention {
+    fn default() -> Self {
+        DisableConvention::Prefix(DISABLED_PREFIX.to_string())
+    }
+}
+
+impl DisableConvention {
+    fn from_setting(value: &str) -> Option<Self> {
+        let (mode, marker) = value.split_once(':')?;
+        DisableConvention::new(mode, marker).ok()
+    }
+
+    fn new(mode: &str, marker: &str) -> Result<Self, String> {
+        if marker.is_empty() || marker.trim() != marker {
+            return Err("Disabled marker must not be empty or start/end with whitespace.".to_string());
+        }
+        if marker.chars().any(|c| c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')) {
+            return Err(format!("Disabled marker '{}' contains characters that are not allowed in folder names.", marker));
+        }
+        match mode {
+            "prefix" => Ok(DisableConvention::Prefix(marker.to_string())),
+            "suffix" => Ok(DisableConvention::Suffix(marker.to_string())),
+            _ => Err(format!("Unknown disable convention '{}', expected 'prefix' or 'suffix'.", mode)),
+        }
+    }
+
+    fn to_setting(&self) -> String {
+        match self {
+            DisableConvention::Prefix(marker) => format!("prefix:{}", marker),
+            DisableConvention::Suffix(marker) => format!("suffix:{}", marker),
+        }
+    }
+
+    fn disabled_name(&self, clean_name: &str) -> String {
+        match self {
+            DisableConvention::Prefix(marker) => format!("{}{}", marker, clean_name),
+            DisableConvention::Suffix(marker) => format!("{}{}", clean_name, marker),
+        }
+    }
+
+    fn strip<'a>(&self, name: &'a str) -> Option<&'a str> {
+        match self {
+            DisableConvention::Prefix(marker) => name.strip_prefix(marker.as_str()),
+            DisableConvention::Suffix(marker) => name.strip_suffix(marker.as_str()),
+        }.filter(|clean| !clean.is_empty())
+    }
+}
+
+// Replaced (not resized) when the setting changes; tasks holding a permit of the old one finish normally
+static DISK_STAT_LIMITER: Lazy<RwLock<Arc<Semaphore>>> = Lazy::new(|| RwLock::new(Arc::new(Semaphore::new(DEFAULT_DISK_STAT_CONCURRENCY))));
+
+fn load_disk_stat_concurrency(conn: &Connection) {
+    let permits = match get_setting_value(conn, SETTINGS_KEY_DISK_STAT_CONCURRENCY) {
+        Ok(Some(value)) => value.trim().parse::<usize>().map(|n| n.clamp(1, 32)).unwrap_or_else(|_| {
+            eprintln!("[load_disk_stat_concurrency] Invalid setting '{}', using the default", value);
+            DEFAULT_DISK_STAT_CONCURRENCY
+        }),
+        _ => DEFAULT_DISK_STAT_CONCURRENCY,
+    };
+    println!("[load_disk_stat_concurrency] Allowing {} concurrent disk stat tasks", permits);
+    *DISK_STAT_LIMITER.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(Semaphore::new(permits));
+}
+
+// Runs a command body that stats many folders on the blocking pool, at most
+// DISK_STAT_LIMITER permits at a time, so slow storage doesn't stall the invoke thread.
+async fn run_disk_stat_task<T, F>(app_handle: AppHandle, task: F) -> CmdResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(AppHandle) -> CmdResult<T> + Send + 'static,
+{
+    let limiter = DISK_STAT_LIMITER.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+    let _permit = limiter.acquire_owned().await.map_err(|e| format!("Disk stat limiter closed: {}", e))?;
+    async_runtime::spawn_blocking(move || task(app_handle)).await
+        .map_err(|e| format!("Disk stat task failed: {}", e))?
+}
+
+// Lowercased, trimmed candidate names; falls back to the defaults when the list is empty
+fn parse_preview_candidates(value: &str) -> Vec<String> {
+    let candidates: Vec<String> = value.split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+    if candidates.is_empty() {
+        DEFAULT_PREVIEW_CANDIDATES.iter().map(|name| name.to_string()).collect()
+    } else {
+        candidates
+    }
+}
+
+// On-disk name of a disabled mod folder
+fn disabled_folder_name(clean_name: &str) -> String {
+    CACHED_CONFIG.disable_convention().disabled_name(clean_name)
+}
+
+// Clean name of a disabled mod folder, or None if the name is not disabled
+fn strip_disabled_marker(name: &str) -> Option<&str> {
+    CACHED_CONFIG.disable_convention().strip(name)
+}
+
+fn is_disabled_folder_name(name: &str) -> bool {
+    strip_disabled_marker(name).is_some()
+}
+
+// Strips the disabled marker from the last component of a '/'-separated relative folder path
+fn clean_folder_relative_path(relative_path: &str) -> String {
+    match relative_path.rsplit_once('/') {
+        Some((parent, last)) => format!("{}/{}", parent, strip_disabled_marker(last).unwrap_or(last)),
+        None => strip_disabled_marker(relative_path).unwrap_or(relative_path).to_string(),
+    }
+}
+
+lazy_static! {
+    static ref MOD_NAME_CLEANUP_REGEX: Regex = Regex::new(r"(?i)(_v\d+(\.\d+)*|_DISABLED|DISABLED_|\(disabled\)|^DISABLED_)").unwrap();
+    static ref CHARACTER_NAME_REGEX: Regex = Regex::new(r"(?i)(Raiden|Shogun|HuTao|Tao|Zhongli|Ganyu|Ayaka|Kazuha|Yelan|Eula|Klee|Nahida)").unwrap();
+    static ref EXCLUDED_INI_FILENAMES: HashSet<String> = {
+        let mut set = HashSet::new();
+        set.insert("orfix.ini".to_string());
+        set.insert("region.ini".to_string());
+        set.insert("offset.ini".to_string());
+        set.insert("water.ini".to_string());
+        set.insert("fixdash.ini".to_string());
+        set.insert("deltatime.ini".to_string());
+        set.insert("object.ini".to_string());
+        set.insert("timer.ini".to_string());
+        set
+    };
+    static ref NAME_CLEANUP_REGEX: Regex = Regex::new(r"(?i)[_\-.\s]+|(_v\d+(\.\d+)*)|(_af)|(_nsfw)|(\(disabled\))|(\(.*\))|(\[.*\])|(^DISABLED_)").unwrap();
+    static ref POTENTIAL_NAME_PART_REGEX: Regex = Regex::new(r"^[a-zA-Z\s]+").unwrap();
+    // Key names 3DMigoto accepts in [Key...] sections (besides single characters and hex codes)
+    static ref KEYBIND_KEY_TOKENS: HashSet<String> = {
+        let mut set: HashSet<String> = [
+            "VK_LBUTTON", "VK_RBUTTON", "VK_MBUTTON", "VK_XBUTTON1", "VK_XBUTTON2", "VK_CANCEL",
+            "VK_BACK", "VK_TAB", "VK_CLEAR", "VK_RETURN", "VK_SHIFT", "VK_CONTROL", "VK_MENU", "VK_PAUSE",
+            "VK_CAPITAL", "VK_ESCAPE", "VK_SPACE", "VK_PRIOR", "VK_NEXT", "VK_END", "VK_HOME",
+            "VK_LEFT", "VK_UP", "VK_RIGHT", "VK_DOWN", "VK_SELECT", "VK_PRINT", "VK_EXECUTE", "VK_SNAPSHOT",
+            "VK_INSERT", "VK_DELETE", "VK_HELP", "VK_LWIN", "VK_RWIN", "VK_APPS", "VK_SLEEP",
+            "VK_MULTIPLY", "VK_ADD", "VK_SEPARATOR", "VK_SUBTRACT", "VK_DECIMAL", "VK_DIVIDE",
+            "VK_NUMLOCK", "VK_SCROLL", "VK_LSHIFT", "VK_RSHIFT", "VK_LCONTROL", "VK_RCONTROL", "VK_LMENU", "VK_RMENU",
+            "VK_OEM_1", "VK_OEM_2", "VK_OEM_3", "VK_OEM_4", "VK_OEM_5", "VK_OEM_6", "VK_OEM_7", "VK_OEM_8", "VK_OEM_102",
+            "VK_OEM_PLUS", "VK_OEM_COMMA", "VK_OEM_MINUS", "VK_OEM_PERIOD",
+            "XB_LEFT_TRIGGER", "XB_RIGHT_TRIGGER", "XB_LEFT_SHOULDER", "XB_RIGHT_SHOULDER",
+            "XB_LEFT_THUMB", "XB_RIGHT_THUMB", "XB_DPAD_UP", "XB_DPAD_DOWN", "XB_DPAD_LEFT", "XB_DPAD_RIGHT",
+            "XB_A", "XB_B", "XB_X", "XB_Y", "XB_START", "XB_BACK", "XB_GUIDE",
+        ].iter().map(|s| s.to_string()).collect();
+        for n in 0..10 { set.insert(format!("VK_NUMPAD{}", n)); }
+        for n in 1..=24 { set.insert(format!("VK_F{}", n)); }
+        set
+    };
+    // Volumes of split archives: name.part2.rar, name.r00, name.z01, name.7z.001, name.zip.001
+    static ref MULTIPART_ARCHIVE_REGEX: Regex = Regex::new(r"(?i)\.(part\d+\.rar|r\d{2}|z\d{2}|(zip|7z|rar)\.\d{3})$").unwrap();
+    static ref KEYBIND_MODIFIER_TOKENS: HashSet<String> = [
+        "CTRL", "ALT", "SHIFT", "WINDOWS", "NO_CTRL", "NO_ALT", "NO_SHIFT", "NO_WINDOWS", "NO_MODIFIERS",
+    ].iter().map(|s| s.to_string()).collect();
+}
+
+// Keys tried in order by auto_resolve_keybind_conflict. F10 is left out (3DMigoto reload).
+const KEYBIND_AUTO_CANDIDATES: &[&str] = &[
+    "VK_F1", "VK_F2", "VK_F3", "VK_F4", "VK_F5", "VK_F6", "VK_F7", "VK_F8", "VK_F9", "VK_F11", "VK_F12",
+    "VK_NUMPAD0", "VK_NUMPAD1", "VK_NUMPAD2", "VK_NUMPAD3", "VK_NUMPAD4",
+    "VK_NUMPAD5", "VK_NUMPAD6", "VK_NUMPAD7", "VK_NUMPAD8", "VK_NUMPAD9",
+    "VK_ADD", "VK_SUBTRACT", "VK_MULTIPLY", "VK_DIVIDE", "VK_DECIMAL",
+    "ctrl VK_F1", "ctrl VK_F2", "ctrl VK_F3", "ctrl VK_F4", "ctrl VK_F5", "ctrl VK_F6",
+    "ctrl VK_F7", "ctrl VK_F8", "ctrl VK_F9", "ctrl VK_F11", "ctrl VK_F12",
+];
+
+#[derive(Serialize, Debug)]
+struct DeducedInfo {
+    entity_slug: String,
+    mod_name: String,
+    mod_type_tag: Option<String>,
+    author: Option<String>,
+    description: Option<String>,
+    image_filename: Option<String>,
+    version: Option<String>,
+}
+
+#[derive(Clone)]
+struct DeductionMaps {
+    category_slug_to_id: HashMap<String, i64>,
+    entity_slug_to_id: HashMap<String, i64>,
+    lowercase_category_name_to_slug: HashMap<String, String>,
+    lowercase_entity_name_to_slug: HashMap<String, String>,
+    entity_slug_to_category_slug: HashMap<String, String>,
+    lowercase_entity_firstname_to_slug: HashMap<String, String>, // e.g., "ellen" -> "ellen-joe"
+    lowercase_entity_first_two_words_to_slug: HashMap<String, String>, // e.g., "ellen joe" -> "ellen-joe"
+    custom_rules: Vec<(DeductionPattern, String)>, // User rules in priority order -> entity slug
+    fuzzy_max_distance: usize, // 0 disables Levenshtein matching
+    fallback_entity_slug: String, // Last resort when no entity or category hint matches
+}
+
+#[derive(Clone)]
+enum DeductionPattern {
+    Regex(Regex),
+    Substring(String), // Lowercased, matched case-insensitively
+}
+
+impl DeductionPattern {
+    fn compile(pattern: &str, is_regex: bool) -> Result<Self, String> {
+        if is_regex {
+            Regex::new(pattern).map(DeductionPattern::Regex).map_err(|e| format!("Invalid regex '{}': {}", pattern, e))
+        } else {
+            Ok(DeductionPattern::Substring(pattern.to_lowercase()))
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            DeductionPattern::Regex(re) => re.is_match(text),
+            DeductionPattern::Substring(needle) => text.to_lowercase().contains(needle),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct DeductionRule {
+    id: i64,
+    pattern: String,
+    is_regex: bool,
+    entity_slug: String,
+    priority: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)] struct Category { id: i64, name: String, slug: String }
+#[derive(Serialize, Deserialize, Debug)] struct Entity { id: i64, category_id: i64, name: String, slug: String, description: Option<String>, details: Option<String>, base_image: Option<String>, mod_count: i32, enabled_mod_count: Option<i32>, recent_mod_count: Option<i32>, favorite_mod_count: Option<i32> }
+#[derive(Serialize, Deserialize, Debug, Clone)] struct Asset { id: i64, entity_id: i64, name: String, description: Option<String>, folder_name: String, image_filename: Option<String>, author: Option<String>, category_tag: Option<String>, is_enabled: bool, #[serde(default)] tags: Vec<String>, #[serde(default)] notes: Option<String>, #[serde(default)] source_url: Option<String>, #[serde(default)] is_favorite: bool, #[serde(default)] is_pinned: bool, #[serde(default)] is_locked: bool, #[serde(default)] created_at: Option<i64>, #[serde(default)] updated_at: Option<i64>, #[serde(default)] last_enabled_at: Option<i64> }
+#[derive(Serialize, Debug, Clone)] struct AssetSearchResult { #[serde(flatten)] asset: Asset, entity_slug: String, category_slug: String }
+
+#[derive(Serialize, Debug, Clone)]
+struct CategoryWithCounts {
+    id: i64,
+    name: String,
+    slug: String,
+    total_mods: i64,
+    enabled_mods: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct EntityWithCounts {
+    // Include all fields from Entity that the frontend card needs
+    id: i64,
+    category_id: i64,
+    name: String,
+    slug: String,
+    details: Option<String>, // JSON string
+    base_image: Option<String>,
+    // Counts
+    total_mods: i64,
+    enabled_mods: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct EntityCounts {
+    slug: String,
+    total_mods: i64,
+    enabled_mods: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct StorageUsage {
+    slug: String,
+    name: String,
+    asset_count: i64,
+    size_bytes: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct DuplicateAssetEntry {
+    asset_id: i64,
+    name: String,
+    folder_name: String, // Clean relative path
+    entity_slug: String,
+    size_bytes: Option<i64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct DuplicateAssetGroup {
+    content_hash: String,
+    assets: Vec<DuplicateAssetEntry>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct AuditAssetEntry {
+    asset_id: i64,
+    name: String,
+    folder_name: String, // Clean relative path as stored
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct AuditCaseMismatch {
+    asset_id: i64,
+    name: String,
+    stored_path: String,
+    disk_path: String, // Clean relative path with the casing found on disk
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct OrphanFolder {
+    path: String, // Relative to the mods folder, as found on disk
+    size_bytes: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct LibraryAudit {
+    missing_on_disk: Vec<AuditAssetEntry>,
+    untracked_folders: Vec<String>, // Clean relative paths of mod folders with no asset row
+    case_mismatches: Vec<AuditCaseMismatch>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct StorageReport {
+    total_bytes: i64,
+    by_entity: Vec<StorageUsage>,
+    by_category: Vec<StorageUsage>,
+    unmeasured_assets: i64, // Assets whose folder couldn't be found on disk
+}
+
+// Structs for Import/Analysis
+#[derive(Serialize, Debug, Clone)]
+struct ArchiveEntry {
+    path: String,
+    is_dir: bool,
+    is_likely_mod_root: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
 struct ArchiveAnalysisResult {
     file_path: String,
     entries: Vec<ArchiveEntry>,
     deduced_mod_name: Option<String>,
     deduced_author: Option<String>,
+    deduced_version: Option<String>,
     deduced_category_slug: Option<String>, // Keep for potential future backend use
     deduced_entity_slug: Option<String>,   // Keep for potential future backend use
     // --> Added Raw INI fields <--
@@ -259,6 +1202,27 @@ struct ArchiveAnalysisResult {
     raw_ini_target: Option<String>,        // e.g., "Nahida", "Raiden Shogun", "Aqua Simulacra"
     // --------------------------
     detected_preview_internal_path: Option<String>,
+    encrypted: bool, // Some entries need a password; INIs are only read when one was given
+    multipart: bool, // One volume of a split archive; zip/7z volumes are not listed
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct ArchiveAnalysisFailure {
+    file_path: String,
+    error: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct BatchArchiveAnalysis {
+    results: Vec<ArchiveAnalysisResult>,
+    failed: Vec<ArchiveAnalysisFailure>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct ArchiveVerifyResult {
+    intact: bool,
+    entries_checked: usize,
+    corrupt_entries: Vec<String>, // "path: error"; "<archive>" when the archive itself can't be read
 }
 
 // --- Migration Logic ---
@@ -272,7 +1236,7 @@ fn run_traveler_migration_logic(
         .map_err(|e| format!("[Migration] Failed to get mods base path: {}", e))?;
 
     // --- Use a single lock scope for all DB operations ---
-    let mut conn_guard = db_state.0.lock().map_err(|_| "[Migration] DB lock poisoned".to_string())?;
+    let mut conn_guard = db_state.conn().map_err(|e| format!("[Migration] {}", e))?;
     let conn = &mut *conn_guard; // Get mutable access for the transaction
 
     // --- Check if migration already done ---
@@ -366,7 +1330,7 @@ fn run_traveler_migration_logic(
 
 
     // --- Start Transaction ---
-    let tx = conn.transaction().map_err(|e| format!("[Migration] Failed to start transaction: {}", e))?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("[Migration] Failed to start transaction: {}", e))?;
 
     let mut migrated_count = 0;
     let mut errors: Vec<String> = Vec::new();
@@ -424,7 +1388,7 @@ fn run_traveler_migration_logic(
         let new_clean_relative_path_str = new_clean_relative_path_buf.to_string_lossy().replace("\\", "/");
 
         // Determine the current *actual* path on disk (check enabled/disabled)
-        let disabled_filename_current = format!("{}{}", DISABLED_PREFIX, mod_folder_base_name_from_db);
+        let disabled_filename_current = disabled_folder_name(&mod_folder_base_name_from_db);
         let relative_parent_path_current = current_relative_path_buf.parent();
 
         let full_path_if_enabled_current = base_mods_path.join(&current_relative_path_buf);
@@ -448,7 +1412,7 @@ fn run_traveler_migration_logic(
 
         // Construct the new *actual* destination path on disk, preserving disabled state
         let new_folder_name_on_disk = if is_currently_disabled {
-            format!("{}{}", DISABLED_PREFIX, mod_folder_base_name_from_db)
+            disabled_folder_name(&mod_folder_base_name_from_db)
         } else {
             mod_folder_base_name_from_db.to_string()
         };
@@ -564,6 +1528,34 @@ fn clean_and_extract_name(input: &str) -> String {
     }
 }
 
+// Finds the known name closest to `hint` by Levenshtein distance, ignoring spaces and punctuation.
+// Short names are skipped and ties between different slugs are treated as no match.
+fn closest_name_by_distance<'a>(hint: &str, lowercase_name_to_slug: &'a HashMap<String, String>, max_distance: usize) -> Option<(&'a String, &'a String, usize)> {
+    let squash = |s: &str| s.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+    let squashed_hint = squash(hint);
+    if max_distance == 0 || squashed_hint.chars().count() < 4 { return None; }
+
+    let mut best: Option<(&'a String, &'a String, usize)> = None;
+    let mut ambiguous = false;
+    for (name_lower, slug) in lowercase_name_to_slug {
+        let squashed_name = squash(name_lower);
+        if squashed_name.chars().count() < 4 { continue; }
+        let distance = strsim::levenshtein(&squashed_hint, &squashed_name);
+        if distance > max_distance { continue; }
+        match best {
+            Some((_, best_slug, best_distance)) if distance == best_distance => {
+                if best_slug != slug { ambiguous = true; }
+            }
+            Some((_, _, best_distance)) if distance > best_distance => {}
+            _ => {
+                best = Some((name_lower, slug, distance));
+                ambiguous = false;
+            }
+        }
+    }
+    if ambiguous { None } else { best }
+}
+
 // Helper function to find entity slug based on a hint string
 fn find_entity_slug_from_hint(hint: &str, maps: &DeductionMaps) -> Option<String> {
     if hint.is_empty() { return None; }
@@ -670,6 +1662,11 @@ fn find_entity_slug_from_hint(hint: &str, maps: &DeductionMaps) -> Option<String
              }
          }
       }
+      // Priority 14: Closest known full name by Levenshtein distance (typos like "Hutou" -> "Hu Tao")
+      if let Some((entity_name_lower, entity_slug, distance)) = closest_name_by_distance(&cleaned_hint, &maps.lowercase_entity_name_to_slug, maps.fuzzy_max_distance) {
+          println!("[find_entity_slug]   -> Match via P14: fuzzy name match ('{}', distance {}).", entity_name_lower, distance);
+          return Some(entity_slug.clone());
+      }
 
     println!("[find_entity_slug]   -> No match found.");
     None // No match found
@@ -703,7 +1700,7 @@ fn find_asset_ini_paths(conn: &Connection, asset_id: i64, base_mods_path: &PathB
         println!("[find_asset_ini_paths] ERROR: Filename extracted from DB path is empty: {}", asset_info.clean_relative_path);
         return Err(AppError::ModOperation("Current filename is empty".to_string()));
      }
-    let disabled_filename = format!("{}{}", DISABLED_PREFIX, filename_str);
+    let disabled_filename = disabled_folder_name(&filename_str);
     let relative_parent_path = relative_path_buf.parent();
 
     let full_path_if_enabled = base_mods_path.join(&relative_path_buf);
@@ -807,6 +1804,28 @@ fn fetch_deduction_maps(conn: &Connection) -> SqlResult<DeductionMaps> {
     }
     println!("[fetch_deduction_maps] Processed {} entities.", entity_count);
 
+    // --- User-defined rules (lowest priority value first) ---
+    let mut custom_rules = Vec::new();
+    let mut rule_stmt = conn.prepare("SELECT pattern, is_regex, entity_slug FROM deduction_rules ORDER BY priority, id")?;
+    let rule_rows = rule_stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?, row.get::<_, String>(2)?)))?;
+    for row in rule_rows {
+        let (pattern, is_regex, entity_slug) = row?;
+        if !entity_slug_to_id.contains_key(&entity_slug) {
+            eprintln!("[fetch_deduction_maps] Skipping rule '{}': entity '{}' does not exist.", pattern, entity_slug);
+            continue;
+        }
+        match DeductionPattern::compile(&pattern, is_regex) {
+            Ok(compiled) => custom_rules.push((compiled, entity_slug)),
+            Err(e) => eprintln!("[fetch_deduction_maps] Skipping rule: {}", e),
+        }
+    }
+    println!("[fetch_deduction_maps] Loaded {} custom deduction rules.", custom_rules.len());
+
+    let fuzzy_max_distance = conn.query_row("SELECT value FROM settings WHERE key = ?1", params![SETTINGS_KEY_FUZZY_MATCH_MAX_DISTANCE], |row| row.get::<_, String>(0))
+        .optional()?
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_FUZZY_MATCH_MAX_DISTANCE);
+    let fallback_entity_slug = read_fallback_entity_slug(conn)?;
 
     Ok(DeductionMaps {
         category_slug_to_id,
@@ -816,40 +1835,179 @@ fn fetch_deduction_maps(conn: &Connection) -> SqlResult<DeductionMaps> {
         entity_slug_to_category_slug,
         lowercase_entity_firstname_to_slug,
         lowercase_entity_first_two_words_to_slug,
+        custom_rules,
+        fuzzy_max_distance,
+        fallback_entity_slug,
     })
 }
 
-fn deduce_mod_info_v2(
-    mod_folder_path: &PathBuf,
-    base_mods_path: &PathBuf,
-    maps: &DeductionMaps,
-) -> Option<DeducedInfo> {
-    println!("[Deduce V2 - Entity First] Input Path: {}", mod_folder_path.display());
+// The configured fallback entity, or the built-in "<DEFAULT_FALLBACK_CATEGORY>-other" when it is
+// unset or points at an entity that no longer exists.
+fn read_fallback_entity_slug(conn: &Connection) -> SqlResult<String> {
+    let default_slug = format!("{}{}", DEFAULT_FALLBACK_CATEGORY, OTHER_ENTITY_SUFFIX);
+    let configured = conn.query_row("SELECT value FROM settings WHERE key = ?1", params![SETTINGS_KEY_FALLBACK_ENTITY], |row| row.get::<_, String>(0))
+        .optional()?
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let Some(slug) = configured else { return Ok(default_slug) };
+    let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM entities WHERE slug = ?1)", params![slug], |row| row.get(0))?;
+    if exists {
+        Ok(slug)
+    } else {
+        eprintln!("[read_fallback_entity_slug] Fallback entity '{}' does not exist, using '{}'.", slug, default_slug);
+        Ok(default_slug)
+    }
+}
 
-    let mod_folder_name = match mod_folder_path.file_name() {
-         Some(name) => name.to_string_lossy().to_string(),
-         None => {
-             eprintln!("[Deduce V2] Error: Cannot get folder name from path: {}", mod_folder_path.display());
-             return None;
-         }
-     };
+// Metadata fields read from an INI's [Mod]/[Settings]/[Info]/[General] sections
+#[derive(Default, Debug)]
+struct IniModMetadata {
+    name: Option<String>,
+    author: Option<String>,
+    description: Option<String>,
+    version: Option<String>,
+    target: Option<String>,
+    mod_type: Option<String>,
+}
 
-    // --- Initial Info ---
-    let mut info = DeducedInfo {
-        entity_slug: format!("{}{}", "unknown", OTHER_ENTITY_SUFFIX),
-        mod_name: mod_folder_name.clone(),
-        mod_type_tag: None, author: None, description: None,
-        image_filename: find_preview_image(mod_folder_path),
-    };
+impl IniModMetadata {
+    // Values already read win; `other` only fills the gaps
+    fn fill_missing(&mut self, other: IniModMetadata) {
+        self.name = self.name.take().or(other.name);
+        self.author = self.author.take().or(other.author);
+        self.description = self.description.take().or(other.description);
+        self.version = self.version.take().or(other.version);
+        self.target = self.target.take().or(other.target);
+        self.mod_type = self.mod_type.take().or(other.mod_type);
+    }
+}
+
+// How many levels of INI includes deduction follows; the mod's own INI is level 0
+const INI_INCLUDE_MAX_DEPTH: usize = 1;
+
+// Files pulled in by `[Include] include = ...` or by `run = ...` references that name another
+// INI (namespaced command lists), resolved relative to the mod folder
+fn read_ini_include_paths(ini_content: &str, mod_folder_path: &Path) -> Vec<PathBuf> {
+    let mut includes = Vec::new();
+    let mut in_include_section = false;
+    for line in ini_content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            in_include_section = line[1..line.len() - 1].trim().eq_ignore_ascii_case("include");
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().trim_matches('"').replace('\\', "/");
+        let reference = if in_include_section && key == "include" {
+            value
+        } else if key == "run" {
+            // e.g. "CommandList/Mods/Shared/common.ini/Setup" -> "Mods/Shared/common.ini"
+            let Some(end) = value.to_ascii_lowercase().find(".ini").map(|pos| pos + ".ini".len()) else { continue };
+            value[..end].trim_start_matches('/').trim_start_matches("CommandList").trim_start_matches('/').to_string()
+        } else {
+            continue;
+        };
+        let include_path = mod_folder_path.join(&reference);
+        if include_path.is_file() && !includes.contains(&include_path) {
+            includes.push(include_path);
+        }
+    }
+    includes
+}
+
+// Reads the metadata of one INI, then fills missing values from the INIs it includes. `visited`
+// holds canonical paths already read so include cycles stop instead of recursing
+fn read_ini_mod_metadata(ini_path: &Path, mod_folder_path: &Path, depth: usize, visited: &mut HashSet<PathBuf>) -> IniModMetadata {
+    let mut metadata = IniModMetadata::default();
+    if !visited.insert(ini_path.canonicalize().unwrap_or_else(|_| ini_path.to_path_buf())) {
+        println!("[Deduce V2] Skipping already visited INI include: {}", ini_path.display());
+        return metadata;
+    }
+    let ini_content = match fs::read_to_string(ini_path) {
+        Ok(content) => content,
+        Err(_) => {
+            eprintln!("[Deduce V2] Warning: Failed to read INI file content from {}", ini_path.display());
+            return metadata;
+        }
+    };
+
+    if let Ok(ini) = Ini::load_from_str(&ini_content) {
+        for section_name in ["Mod", "Settings", "Info", "General"] {
+            if let Some(section) = ini.section(Some(section_name)) {
+                // Update metadata if found
+                if let Some(name) = section.get("Name").or_else(|| section.get("ModName")) { metadata.name = Some(name.trim().to_string()); }
+                if let Some(author) = section.get("Author") { metadata.author = Some(author.trim().to_string()); }
+                if let Some(desc) = section.get("Description") { metadata.description = Some(desc.trim().to_string()); }
+                if let Some(version) = section.get("Version").or_else(|| section.get("ModVersion")) { metadata.version = Some(version.trim().to_string()); }
+                if let Some(target) = section.get("Target").or_else(|| section.get("Entity")).or_else(|| section.get("Character")) { metadata.target = Some(target.trim().to_string()); }
+                if let Some(typ) = section.get("Type").or_else(|| section.get("Category")) { metadata.mod_type = Some(typ.trim().to_string()); }
+            }
+        }
+    } else {
+        eprintln!("[Deduce V2] Warning: Failed to parse INI content from {}", ini_path.display());
+    }
+
+    if depth < INI_INCLUDE_MAX_DEPTH {
+        for include_path in read_ini_include_paths(&ini_content, mod_folder_path) {
+            println!("[Deduce V2] Following INI include: {}", include_path.display());
+            metadata.fill_missing(read_ini_mod_metadata(&include_path, mod_folder_path, depth + 1, visited));
+        }
+    }
+    metadata
+}
+
+fn deduce_mod_info_v2(
+    mod_folder_path: &PathBuf,
+    base_mods_path: &PathBuf,
+    maps: &DeductionMaps,
+) -> Option<DeducedInfo> {
+    println!("[Deduce V2 - Entity First] Input Path: {}", mod_folder_path.display());
+    // A staged mod (symlink mode) is deduced from the path it has once linked in, so rules and
+    // parent hints never see the staging folder
+    let staging_root = base_mods_path.join(SYMLINK_STAGING_FOLDER_NAME);
+    let base_mods_path = if mod_folder_path.starts_with(&staging_root) { &staging_root } else { base_mods_path };
+
+    let mod_folder_name = match mod_folder_path.file_name() {
+         Some(name) => name.to_string_lossy().to_string(),
+         None => {
+             eprintln!("[Deduce V2] Error: Cannot get folder name from path: {}", mod_folder_path.display());
+             return None;
+         }
+     };
+
+    // --- Initial Info ---
+    let mut info = DeducedInfo {
+        entity_slug: format!("{}{}", "unknown", OTHER_ENTITY_SUFFIX),
+        mod_name: mod_folder_name.clone(),
+        mod_type_tag: None, author: None, description: None,
+        image_filename: find_preview_image(mod_folder_path),
+        version: None,
+    };
 
     let mut found_entity_slug: Option<String> = None;
     let mut ini_target_hint: Option<String> = None;
     let mut ini_type_hint: Option<String> = None;
 
+    // --- 0. User-defined rules (first match wins, before any built-in matching) ---
+    // Tested against the folder name and the path relative to the mods folder, both without DISABLED_
+    let clean_folder_name = strip_disabled_marker(&mod_folder_name).unwrap_or(&mod_folder_name).to_string();
+    let clean_relative_path = mod_folder_path.strip_prefix(base_mods_path).ok()
+        .map(|rel| rel.with_file_name(&clean_folder_name).to_string_lossy().replace("\\", "/"))
+        .unwrap_or_else(|| clean_folder_name.clone());
+    if let Some((_, slug)) = maps.custom_rules.iter()
+        .find(|(pattern, _)| pattern.is_match(&clean_folder_name) || pattern.is_match(&clean_relative_path)) {
+        println!("[Deduce V2]   -> Found entity via custom rule: '{}' -> {}", clean_relative_path, slug);
+        found_entity_slug = Some(slug.clone());
+    }
+
     // --- 1. Check Parent Folders for ENTITY Match ---
     println!("[Deduce V2] Checking parent folders for ENTITY match...");
     // ... (existing parent folder checking logic using find_entity_slug_from_hint) ...
-    let mut current_path = mod_folder_path.parent();
+    let mut current_path = if found_entity_slug.is_none() { mod_folder_path.parent() } else { None };
     while let Some(path) = current_path {
         if path == *base_mods_path || path.parent() == Some(base_mods_path) { break; }
         if let Some(folder_name) = path.file_name().and_then(|n| n.to_str()) {
@@ -874,26 +2032,15 @@ fn deduce_mod_info_v2(
 
     if let Some(ini_path) = ini_path_option {
         println!("[Deduce V2] Found INI: {}", ini_path.display());
-        if let Ok(ini_content) = fs::read_to_string(&ini_path) {
-            if let Ok(ini) = Ini::load_from_str(&ini_content) {
-                 for section_name in ["Mod", "Settings", "Info", "General"] {
-                    if let Some(section) = ini.section(Some(section_name)) {
-                        // Update metadata if found
-                        if let Some(name) = section.get("Name").or_else(|| section.get("ModName")) { info.mod_name = name.trim().to_string(); }
-                        if let Some(author) = section.get("Author") { info.author = Some(author.trim().to_string()); }
-                        if let Some(desc) = section.get("Description") { info.description = Some(desc.trim().to_string()); }
-                        // Get hints (even if entity found, these might be useful someday)
-                        if let Some(target) = section.get("Target").or_else(|| section.get("Entity")).or_else(|| section.get("Character")) { ini_target_hint = Some(target.trim().to_string()); }
-                        if let Some(typ) = section.get("Type").or_else(|| section.get("Category")) { info.mod_type_tag = Some(typ.trim().to_string()); ini_type_hint = info.mod_type_tag.clone(); } // Store type hint
-                    }
-                }
-                println!("[Deduce V2] INI parsed. Name='{}', Author='{:?}', TargetHint='{:?}', TypeHint='{:?}'", info.mod_name, info.author, ini_target_hint, ini_type_hint);
-            } else {
-                eprintln!("[Deduce V2] Warning: Failed to parse INI content from {}", ini_path.display());
-            }
-        } else {
-             eprintln!("[Deduce V2] Warning: Failed to read INI file content from {}", ini_path.display());
-        }
+        let metadata = read_ini_mod_metadata(&ini_path, mod_folder_path, 0, &mut HashSet::new());
+        if let Some(name) = metadata.name { info.mod_name = name; }
+        info.author = metadata.author;
+        info.description = metadata.description;
+        info.version = metadata.version;
+        // Get hints (even if entity found, these might be useful someday)
+        ini_target_hint = metadata.target;
+        if metadata.mod_type.is_some() { info.mod_type_tag = metadata.mod_type; ini_type_hint = info.mod_type_tag.clone(); } // Store type hint
+        println!("[Deduce V2] INI parsed. Name='{}', Author='{:?}', TargetHint='{:?}', TypeHint='{:?}'", info.mod_name, info.author, ini_target_hint, ini_type_hint);
     } else {
         println!("[Deduce V2] No INI file found in mod folder.");
     }
@@ -1089,16 +2236,33 @@ fn deduce_mod_info_v2(
              } else { println!("[Deduce V2]     Could not strip base path prefix."); }
         }
 
+        // Fallback Priority 4: Closest CATEGORY name by Levenshtein distance (INI type hint, then top-level folder)
+        if fallback_category_slug.is_none() && maps.fuzzy_max_distance > 0 {
+            println!("[Deduce V2]   Fallback Prio 4: Trying fuzzy CATEGORY name match...");
+            let top_folder_name = mod_folder_path.strip_prefix(base_mods_path).ok()
+                .and_then(|relative_path| relative_path.components().next())
+                .and_then(|component| component.as_os_str().to_str().map(String::from));
+            for candidate in ini_type_hint.iter().chain(top_folder_name.iter()) {
+                if let Some((cat_name_lower, cat_slug, distance)) = closest_name_by_distance(candidate, &maps.lowercase_category_name_to_slug, maps.fuzzy_max_distance) {
+                    fallback_category_slug = Some(cat_slug.clone());
+                    println!("[Deduce V2]       -> Matched category via fuzzy name: '{}' -> {} ('{}', distance {})", candidate, cat_slug, cat_name_lower, distance);
+                    break;
+                }
+            }
+        }
+
         // --- Assign final fallback slug ---
-        if let Some(cat_slug) = fallback_category_slug {
+        let category_other_slug = fallback_category_slug
+            .map(|cat_slug| format!("{}{}", cat_slug, OTHER_ENTITY_SUFFIX))
+            .filter(|other_slug| maps.entity_slug_to_id.contains_key(other_slug));
+        if let Some(other_slug) = category_other_slug {
              // Found a category hint, assign to its -other group
-             info.entity_slug = format!("{}{}", cat_slug, OTHER_ENTITY_SUFFIX);
+             info.entity_slug = other_slug;
              println!("[Deduce V2] Assigning fallback category slug: {}", info.entity_slug);
         } else {
-             // Absolute last resort: Use a default category's -other group
-             let hardcoded_fallback_category = "characters"; // Or use "unknown" if you have an unknown-other slug
-             info.entity_slug = format!("{}{}", hardcoded_fallback_category, OTHER_ENTITY_SUFFIX);
-             println!("[Deduce V2] No category hint found, assigning hardcoded fallback: {}", info.entity_slug);
+             // Absolute last resort: the configured fallback entity
+             info.entity_slug = maps.fallback_entity_slug.clone();
+             println!("[Deduce V2] No category hint found, assigning fallback entity: {}", info.entity_slug);
         }
     } // --- End of else block (Entity Not Found) ---
 
@@ -1141,6 +2305,79 @@ fn get_asset_location_info(conn: &Connection, asset_id: i64) -> Result<AssetLoca
     })
 }
 
+// Folder modification time in whole seconds since the UNIX epoch
+fn folder_mtime_secs(path: &Path) -> Option<i64> {
+    fs::metadata(path).ok()?
+        .modified().ok()?
+        .duration_since(std::time::UNIX_EPOCH).ok()
+        .map(|d| d.as_secs() as i64)
+}
+
+// Newest modification time of the mods folder and the folders up to AUTOSCAN_MTIME_DEPTH below it.
+// Adding, removing or renaming a mod folder changes its parent's mtime, not the base folder's.
+fn mods_folder_mtime_secs(base_mods_path: &Path) -> Option<i64> {
+    WalkDir::new(base_mods_path)
+        .max_depth(AUTOSCAN_MTIME_DEPTH)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_dir())
+        .filter_map(|entry| folder_mtime_secs(entry.path()))
+        .max()
+}
+
+// Total size of all files under a folder (recursive). Unreadable entries are skipped.
+fn folder_size_bytes(path: &Path) -> u64 {
+    WalkDir::new(path).into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+type FolderMeasurement = (i64, String); // (size_bytes, content_hash)
+
+// Size and content fingerprint of a mod folder from a single walk. The hash covers the sorted
+// relative file paths and sizes (FNV-1a, stable across builds), so copies of the same mod match
+// regardless of where they are installed or whether they are disabled.
+fn measure_folder(path: &Path) -> FolderMeasurement {
+    let mut files: Vec<(String, u64)> = WalkDir::new(path).min_depth(1).into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let len = entry.metadata().ok()?.len();
+            let rel = entry.path().strip_prefix(path).ok()?.to_string_lossy().replace("\\", "/").to_lowercase();
+            Some((rel, len))
+        })
+        .collect();
+    files.sort();
+
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut total_bytes: u64 = 0;
+    for (rel, len) in &files {
+        total_bytes += len;
+        let len_bytes = len.to_le_bytes();
+        for byte in rel.as_bytes().iter().chain(&[0u8]).chain(len_bytes.iter()) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    (total_bytes as i64, format!("{:016x}", hash))
+}
+
+// Adds a column to an existing table if it is missing (for databases created by older versions)
+fn ensure_column(conn: &Connection, table: &str, column: &str, column_def: &str) -> Result<(), AppError> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt.query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name.eq_ignore_ascii_case(column));
+    if !exists {
+        println!("Adding missing column '{}.{}'", table, column);
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, column_def), [])?;
+    }
+    Ok(())
+}
+
 fn has_ini_file(dir_path: &PathBuf) -> bool {
     if !dir_path.is_dir() { return false; }
 
@@ -1158,16 +2395,12 @@ fn has_ini_file(dir_path: &PathBuf) -> bool {
 
                             // Get the filename, convert to lowercase
                             if let Some(filename_osstr) = entry.path().file_name() {
-                                let filename_lower = filename_osstr.to_string_lossy().to_lowercase();
+                                let filename = filename_osstr.to_string_lossy();
 
-                                // Check if it's an excluded file (considering DISABLED_ prefix)
-                                let base_filename = if filename_lower.starts_with(DISABLED_PREFIX.to_lowercase().as_str()) {
-                                    filename_lower.trim_start_matches(DISABLED_PREFIX.to_lowercase().as_str())
-                                } else {
-                                    filename_lower.as_str()
-                                };
+                                // Check if it's an excluded file (considering the disabled marker)
+                                let base_filename = strip_disabled_marker(&filename).unwrap_or(&filename).to_lowercase();
 
-                                if !EXCLUDED_INI_FILENAMES.contains(base_filename) {
+                                if !EXCLUDED_INI_FILENAMES.contains(base_filename.as_str()) {
                                     // Found an INI file that is NOT excluded
                                     has_non_excluded_ini = true;
                                     // Optimization: We can stop searching as soon as we find one non-excluded INI
@@ -1192,20 +2425,106 @@ fn has_ini_file(dir_path: &PathBuf) -> bool {
     has_non_excluded_ini
 }
 
+// Resolves where a relocated asset's folder currently lives on disk and where it should go under
+// `target_entity_slug`, keeping the DISABLED_ prefix if present. Nothing on disk is changed.
+// Returns (current full path, new full path, new clean relative path for the DB).
+fn relocation_paths(
+    base_mods_path: &Path,
+    current_info: &AssetLocationInfo,
+    target_entity_slug: &str,
+    target_category_slug: &str
+) -> Result<(PathBuf, PathBuf, String), String> {
+    // --- Determine Current Full Path on Disk (Check Enabled/Disabled) ---
+    let current_relative_path_buf = PathBuf::from(&current_info.clean_relative_path);
+    let current_filename_osstr = current_relative_path_buf.file_name().ok_or("Cannot get current filename")?;
+    let current_filename_str = current_filename_osstr.to_string_lossy();
+    let disabled_filename = disabled_folder_name(&current_filename_str);
+    let relative_parent_path = current_relative_path_buf.parent();
+    let full_path_if_enabled = base_mods_path.join(&current_relative_path_buf);
+    let full_path_if_disabled = match relative_parent_path {
+       Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_filename),
+       _ => base_mods_path.join(&disabled_filename),
+    };
+    let is_staged = base_mods_path.join(staged_relative_path(&current_info.clean_relative_path)).is_dir();
+    let current_full_path = if is_staged {
+        // Where unstage_asset_folder puts it: the link's place when enabled, DISABLED_ otherwise
+        if is_folder_link(&full_path_if_enabled) { full_path_if_enabled } else { full_path_if_disabled }
+    } else if full_path_if_enabled.is_dir() {
+        full_path_if_enabled
+    } else if full_path_if_disabled.is_dir() {
+        full_path_if_disabled
+    } else {
+        return Err(format!("Cannot relocate: Source folder not found at '{}' or disabled variant.", full_path_if_enabled.display()));
+    };
+
+    // --- Construct New Relative (for DB) and Full (for Disk) Paths ---
+    let mod_base_name = strip_disabled_marker(&current_filename_str).unwrap_or(&current_filename_str);
+    let new_relative_path_buf = PathBuf::new().join(target_category_slug).join(target_entity_slug).join(mod_base_name);
+    let new_relative_path_str = new_relative_path_buf.to_string_lossy().replace("\\", "/");
+
+    // Determine the name to use on disk (keep disabled prefix if present)
+    let new_filename_to_use_on_disk = if current_full_path.file_name().map_or(false, |name| is_disabled_folder_name(&name.to_string_lossy())) {
+         disabled_filename // Keep disabled prefix
+    } else {
+         mod_base_name.to_string() // Use clean name
+    };
+    let new_full_dest_path_on_disk = base_mods_path.join(target_category_slug).join(target_entity_slug).join(&new_filename_to_use_on_disk);
+
+    // --- Guard: never move a folder onto itself or into its own subtree ---
+    if path_is_same_or_within(&new_full_dest_path_on_disk, &current_full_path) {
+        return Err(format!(
+            "Cannot relocate: Target path '{}' is the same as, or nested inside, the current folder '{}'.",
+            new_full_dest_path_on_disk.display(), current_full_path.display()
+        ));
+    }
+    Ok((current_full_path, new_full_dest_path_on_disk, new_relative_path_str))
+}
+
+// Plans moving an asset's folder under `target_entity_slug` (see relocation_paths) and refuses a
+// target that is already taken. Every caller moves the folder next, so a staged mod becomes a
+// real folder first; that only happens once the move is known to be valid, so a refused move
+// leaves the disk untouched.
+fn plan_asset_relocation(
+    base_mods_path: &Path,
+    current_info: &AssetLocationInfo,
+    target_entity_slug: &str,
+    target_category_slug: &str
+) -> Result<(PathBuf, PathBuf, String), String> {
+    let plan = relocation_paths(base_mods_path, current_info, target_entity_slug, target_category_slug)?;
+    if asset_move_target_taken(base_mods_path, &plan.1, &plan.2) {
+        return Err(format!("Cannot relocate: Target path '{}' already exists.", plan.1.display()));
+    }
+    unstage_for_relocation(base_mods_path, current_info)?;
+    Ok(plan)
+}
+
+fn unstage_for_relocation(base_mods_path: &Path, current_info: &AssetLocationInfo) -> Result<(), String> {
+    unstage_asset_folder(base_mods_path, &current_info.clean_relative_path)
+        .map_err(|e| format!("Cannot relocate: Failed to move the mod out of staging: {}", e))
+}
+
+// Checks if `candidate` is `base` itself or a path nested inside it (component-wise)
+fn path_is_same_or_within(candidate: &Path, base: &Path) -> bool {
+    if cfg!(target_os = "windows") {
+        // Windows paths are case-insensitive and may mix separators
+        let candidate_norm = PathBuf::from(candidate.to_string_lossy().to_lowercase().replace("/", "\\"));
+        let base_norm = PathBuf::from(base.to_string_lossy().to_lowercase().replace("/", "\\"));
+        return candidate_norm.starts_with(&base_norm);
+    }
+    candidate.starts_with(base)
+}
+
 fn find_preview_image(dir_path: &PathBuf) -> Option<String> {
-    let common_names = ["preview.png", "preview.jpg", "icon.png", "icon.jpg", "thumbnail.png", "thumbnail.jpg"];
      if !dir_path.is_dir() { return None; }
     // Use walkdir limited to depth 1
-    for entry in WalkDir::new(dir_path).max_depth(1).min_depth(1).into_iter().filter_map(|e| e.ok()) {
-        if entry.file_type().is_file() {
-             if let Some(filename) = entry.path().file_name().and_then(|n| n.to_str()) {
-                 if common_names.contains(&filename.to_lowercase().as_str()) {
-                     return Some(filename.to_string());
-                 }
-             }
-        }
-    }
-    None
+    let filenames: Vec<String> = WalkDir::new(dir_path).max_depth(1).min_depth(1).into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.path().file_name().and_then(|n| n.to_str()).map(|n| n.to_string()))
+        .collect();
+    // Candidate order decides which image wins, not directory order
+    CACHED_CONFIG.preview_candidates().iter()
+        .find_map(|candidate| filenames.iter().find(|filename| filename.to_lowercase() == *candidate).cloned())
 }
 
 fn get_app_config_path(app_handle: &AppHandle) -> Result<PathBuf, AppError> {
@@ -1253,3078 +2572,10697 @@ fn get_current_asset_enabled_state(conn: &Connection, asset_id: i64, base_mods_p
     Ok(full_path_if_enabled.is_dir()) // Return true if the 'enabled' path exists
 }
 
-// --- Database Initialization (Result type uses AppError internally) ---
-fn initialize_database(app_handle: &AppHandle, active_game_slug: &str) -> Result<Connection, AppError> {
-    let data_dir = get_app_data_dir(app_handle)?;
-    let db_path = data_dir.join(ACTIVE_DB_FILENAME);
-    println!("Initializing database for game '{}' at: {}", active_game_slug, db_path.display());
-    let needs_schema_setup = !db_path.exists();
-
-    let conn = Connection::open(&db_path)?;
-    conn.execute("PRAGMA foreign_keys = ON;", [])?;
+// A relocation target is taken when the planned folder exists or the new clean path already
+// resolves to a folder in either state
+fn asset_move_target_taken(base_mods_path: &Path, new_full_path: &Path, new_relative_path: &str) -> bool {
+    new_full_path.exists() || resolve_asset_disk_state(base_mods_path, new_relative_path).is_some()
+}
 
-    if needs_schema_setup {
-        println!("Performing initial schema setup for {}", db_path.display());
-        // --- Create Tables (Same as before) ---
-        conn.execute_batch(
-            "BEGIN;
-             CREATE TABLE categories ( id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT UNIQUE NOT NULL, slug TEXT UNIQUE NOT NULL );
-             CREATE TABLE entities ( id INTEGER PRIMARY KEY AUTOINCREMENT, category_id INTEGER NOT NULL, name TEXT NOT NULL, slug TEXT UNIQUE NOT NULL, description TEXT, details TEXT, base_image TEXT, FOREIGN KEY (category_id) REFERENCES categories (id) ON DELETE CASCADE );
-             CREATE TABLE assets ( id INTEGER PRIMARY KEY AUTOINCREMENT, entity_id INTEGER NOT NULL, name TEXT NOT NULL, description TEXT, folder_name TEXT NOT NULL UNIQUE, image_filename TEXT, author TEXT, category_tag TEXT, FOREIGN KEY (entity_id) REFERENCES entities (id) ON DELETE CASCADE );
-             CREATE TABLE settings ( key TEXT PRIMARY KEY NOT NULL, value TEXT NOT NULL );
-             CREATE TABLE presets ( id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT UNIQUE NOT NULL, is_favorite INTEGER NOT NULL DEFAULT 0 );
-             CREATE TABLE preset_assets ( preset_id INTEGER NOT NULL, asset_id INTEGER NOT NULL, is_enabled INTEGER NOT NULL, PRIMARY KEY (preset_id, asset_id), FOREIGN KEY (preset_id) REFERENCES presets(id) ON DELETE CASCADE, FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE );
-             COMMIT;",
-        )?;
-        println!("Database tables created for {}.", db_path.display());
-        println!("Storing internal game slug '{}' in the new database.", active_game_slug);
-        conn.execute(
-            "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
-            params![DB_INTERNAL_GAME_SLUG_KEY, active_game_slug],
-        )?;
+// Carries out a plan from plan_asset_relocation: moves the folder and points the asset's row at
+// `new_entity_id`. The folder is moved back if the DB update fails. Returns the undo entry,
+// which callers record once their transaction commits.
+fn move_asset_folder(
+    conn: &Connection,
+    base_mods_path: &Path,
+    info: &AssetLocationInfo,
+    plan: &(PathBuf, PathBuf, String),
+    new_entity_id: i64,
+) -> Result<Operation, String> {
+    let (current_full_path, new_full_path, new_relative_path) = plan;
+    if asset_move_target_taken(base_mods_path, new_full_path, new_relative_path) {
+        return Err(format!("Target path '{}' already exists", new_full_path.display()));
+    }
+    new_full_path.parent()
+        .ok_or_else(|| "Could not determine parent for new path".to_string())
+        .and_then(|parent| fs::create_dir_all(parent).map_err(|e| e.to_string()))
+        .and_then(|_| fs::rename(current_full_path, new_full_path).map_err(|e| e.to_string()))?;
+    if let Err(e) = conn.execute(
+        "UPDATE assets SET entity_id = ?1, folder_name = ?2 WHERE id = ?3",
+        params![new_entity_id, new_relative_path, info.id],
+    ) {
+        // Keep disk and DB consistent for this asset
+        fs::rename(new_full_path, current_full_path).ok();
+        return Err(format!("DB update failed: {}", e));
+    }
+    Ok(Operation::Relocate {
+        asset_id: info.id,
+        from_path: current_full_path.clone(),
+        to_path: new_full_path.clone(),
+        old_entity_id: info.entity_id,
+        old_relative_path: info.clean_relative_path.clone(),
+    })
+}
 
-    } else {
-        println!("Database file {} already exists.", db_path.display());
-        // Optional: Verify internal slug matches expected active_game_slug?
-        match get_internal_db_slug(&db_path) {
-            Ok(Some(internal_slug)) if internal_slug != active_game_slug => {
-                 eprintln!("WARNING: Existing database {} contains slug '{}' but expected '{}'. Check startup logic.", db_path.display(), internal_slug, active_game_slug);
-                 // We proceed, assuming the startup logic handled the rename correctly.
-            },
-            Err(e) => eprintln!("Warning: Could not read internal slug from existing DB {}: {}", db_path.display(), e),
-            _ => {} // Slug matches or doesn't exist (old DB?)
+// Moves folders back newest first, for when the transaction covering the moves fails
+fn undo_asset_moves(moves: &[Operation]) {
+    for operation in moves.iter().rev() {
+        if let Operation::Relocate { from_path, to_path, .. } = operation {
+            fs::rename(to_path, from_path).ok();
         }
     }
+}
 
-    // --- Load Definitions ---
-    let definition_resource_path = format!("definitions/{}.toml", active_game_slug);
-    println!("Attempting to load definitions from resource: {}", definition_resource_path);
+// Resolves an asset's CLEAN relative path to its state on disk.
+// Returns (is_enabled, relative path as currently on disk), or None if neither variant exists.
+// Staged mods (symlink mode) resolve to their folder in the staging folder and are enabled
+// while a link to it exists at the clean path.
+fn resolve_asset_disk_state(base_mods_path: &Path, clean_relative_path: &str) -> Option<(bool, String)> {
+    let clean_path = PathBuf::from(clean_relative_path);
+    let filename_str = clean_path.file_name()?.to_string_lossy().to_string();
+    if filename_str.is_empty() {
+        return None;
+    }
+    let staged_relative = staged_relative_path(clean_relative_path);
+    if base_mods_path.join(&staged_relative).is_dir() {
+        return Some((is_folder_link(&base_mods_path.join(&clean_path)), staged_relative));
+    }
+    if base_mods_path.join(&clean_path).is_dir() {
+        return Some((true, clean_path.to_string_lossy().replace("\\", "/")));
+    }
+    let disabled_filename = disabled_folder_name(&filename_str);
+    let disabled_relative_path = match clean_path.parent() {
+        Some(parent) if parent.as_os_str().len() > 0 => parent.join(&disabled_filename),
+        _ => PathBuf::from(&disabled_filename),
+    };
+    if base_mods_path.join(&disabled_relative_path).is_dir() {
+        return Some((false, disabled_relative_path.to_string_lossy().replace("\\", "/")));
+    }
+    None
+}
 
-    let definitions: Definitions = match app_handle.path_resolver().resolve_resource(&definition_resource_path) {
-        Some(path) => {
-            println!("Found definition file at: {}", path.display());
-            match fs::read_to_string(&path) {
-                Ok(toml_content) => {
-                    match toml::from_str(&toml_content) {
-                        Ok(defs) => {
-                            println!("Successfully parsed definitions for '{}'.", active_game_slug);
-                            defs
-                        },
-                        Err(e) => {
-                            eprintln!("ERROR: Failed to parse TOML from {}: {}. Using empty definitions.", path.display(), e);
-                            HashMap::new() // Use empty definitions on parse error
-                        }
-                    }
-                },
-                Err(e) => {
-                    eprintln!("ERROR: Failed to read definition file {}: {}. Using empty definitions.", path.display(), e);
-                    HashMap::new() // Use empty definitions on read error
-                }
-            }
-        },
-        None => {
-            eprintln!("ERROR: Definition file resource '{}' not found. Using empty definitions.", definition_resource_path);
-            HashMap::new() // Use empty definitions if file not found
+// Renames an asset's folder on disk so it matches `desired_enabled`. No-op if already in that state.
+// In symlink mode (or for mods that are already staged) this links/unlinks instead.
+fn set_asset_enabled_on_disk(base_mods_path: &Path, clean_relative_path: &str, desired_enabled: bool) -> Result<(), AppError> {
+    let (current_enabled, current_relative_path) = resolve_asset_disk_state(base_mods_path, clean_relative_path)
+        .ok_or_else(|| AppError::NotFound(format!("Mod folder not found on disk (path: '{}')", clean_relative_path)))?;
+    if current_enabled == desired_enabled {
+        return Ok(());
+    }
+    if CACHED_CONFIG.is_symlink_enable_mode() || current_relative_path == staged_relative_path(clean_relative_path) {
+        return set_staged_asset_enabled(base_mods_path, clean_relative_path, &current_relative_path, desired_enabled);
+    }
+    set_folder_enabled_by_rename(base_mods_path, clean_relative_path, desired_enabled)
+}
+
+// Rename-only version of set_asset_enabled_on_disk, for folders that are never staged (variants)
+fn set_folder_enabled_by_rename(base_mods_path: &Path, clean_relative_path: &str, desired_enabled: bool) -> Result<(), AppError> {
+    let (current_enabled, current_relative_path) = resolve_asset_disk_state(base_mods_path, clean_relative_path)
+        .ok_or_else(|| AppError::NotFound(format!("Mod folder not found on disk (path: '{}')", clean_relative_path)))?;
+    if current_enabled == desired_enabled {
+        return Ok(());
+    }
+
+    let clean_path = PathBuf::from(clean_relative_path);
+    let target_relative_path = if desired_enabled {
+        clean_path
+    } else {
+        let filename_str = clean_path.file_name().unwrap_or_default().to_string_lossy();
+        let disabled_filename = disabled_folder_name(&filename_str);
+        match clean_path.parent() {
+            Some(parent) if parent.as_os_str().len() > 0 => parent.join(disabled_filename),
+            _ => PathBuf::from(disabled_filename),
         }
     };
+    rename_long_path(&base_mods_path.join(&current_relative_path), &base_mods_path.join(&target_relative_path))
+        .map_err(AppError::ModOperation)?;
+    Ok(())
+}
 
-    println!("Loaded {} categories from definitions for '{}'.", definitions.len(), active_game_slug);
-
-    // --- Populate DB from loaded definitions (Same logic as before) ---
-    if !definitions.is_empty() {
-         for (category_slug, category_def) in definitions.iter() {
-             // Wrap inserts in transaction for potential rollback if needed later
-             conn.execute( "INSERT OR IGNORE INTO categories (name, slug) VALUES (?1, ?2)", params![category_def.name, category_slug],)?;
-             let category_id: i64 = conn.query_row( "SELECT id FROM categories WHERE slug = ?1", params![category_slug], |row| row.get(0), )?;
+// --- Long paths ---
+
+// Classic Windows path limit; nested mod folders plus the disabled marker can go past it
+const WINDOWS_MAX_PATH: usize = 260;
+
+// OS error codes meaning "path or file name too long"
+#[cfg(windows)]
+const PATH_TOO_LONG_OS_ERRORS: &[i32] = &[206]; // ERROR_FILENAME_EXCED_RANGE
+#[cfg(target_os = "macos")]
+const PATH_TOO_LONG_OS_ERRORS: &[i32] = &[63]; // ENAMETOOLONG
+#[cfg(not(any(windows, target_os = "macos")))]
+const PATH_TOO_LONG_OS_ERRORS: &[i32] = &[36]; // ENAMETOOLONG
+
+// Extended-length (`\\?\`) form of an absolute path so Windows file APIs accept it past
+// MAX_PATH. The prefix disables path normalization, so `.` components are dropped here and paths
+// containing `..` are returned unchanged.
+#[cfg(windows)]
+fn extended_length_path(path: &Path) -> PathBuf {
+    use std::path::{Component, Prefix};
+    let mut components = path.components();
+    let prefix = match components.next() {
+        Some(Component::Prefix(prefix)) => prefix,
+        _ => return path.to_path_buf(),
+    };
+    if components.clone().any(|c| c == Component::ParentDir) {
+        return path.to_path_buf();
+    }
+    let mut extended = match prefix.kind() {
+        Prefix::Disk(_) => {
+            let mut extended = std::ffi::OsString::from(r"\\?\");
+            extended.push(prefix.as_os_str());
+            extended
+        }
+        // "\\server\share" -> "\\?\UNC\server\share"
+        Prefix::UNC(_, _) => std::ffi::OsString::from(format!(r"\\?\UNC{}", &prefix.as_os_str().to_string_lossy()[1..])),
+        _ => return path.to_path_buf(), // Already verbatim, or a device path
+    };
+    let rest: PathBuf = components.filter(|c| *c != Component::CurDir).collect();
+    extended.push(rest.as_os_str());
+    PathBuf::from(extended)
+}
 
-             let other_slug = format!("{}{}", category_slug, OTHER_ENTITY_SUFFIX);
-             conn.execute( "INSERT OR IGNORE INTO entities (category_id, name, slug, description, details, base_image) VALUES (?1, ?2, ?3, ?4, ?5, ?6)", params![ category_id, OTHER_ENTITY_NAME, other_slug, "未分类的资源。", "{}", None::<String> ] )?;
+#[cfg(not(windows))]
+fn extended_length_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
 
-             for entity_def in category_def.entities.iter() {
-                 conn.execute( "INSERT OR IGNORE INTO entities (category_id, name, slug, description, details, base_image) VALUES (?1, ?2, ?3, ?4, ?5, ?6)", params![ category_id, entity_def.name, entity_def.slug, entity_def.description, entity_def.details.as_ref().map(|s| s.to_string()).unwrap_or("{}".to_string()), entity_def.base_image, ] )?;
-             }
-         }
-         println!("Populated database with definitions for '{}'.", active_game_slug);
+// Path for messages, without the extended-length prefix (like `dunce::simplified`)
+fn display_path(path: &Path) -> String {
+    let raw = path.to_string_lossy();
+    if let Some(unc) = raw.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", unc)
     } else {
-         println!("Skipping definition population as no definitions were loaded for '{}'.", active_game_slug);
+        raw.strip_prefix(r"\\?\").unwrap_or(&raw).to_string()
     }
-    // --- End Definition Population ---
-    
-    Ok(conn)
 }
 
-// --- Utility Functions ---
-fn get_app_data_dir(app_handle: &AppHandle) -> Result<PathBuf, AppError> { // Internal error type
-    app_handle.path_resolver()
-        .app_data_dir()
-        .ok_or_else(|| AppError::TauriPath("Failed to resolve app data directory".to_string()))
+// Clear message for a file operation that failed because `path` is too long, None otherwise.
+// Without the extended-length prefix Windows often reports over-long paths as "path not found".
+fn path_too_long_message(path: &Path, e: &io::Error) -> Option<String> {
+    let shown = display_path(path);
+    let length = shown.chars().count();
+    let too_long = match e.raw_os_error() {
+        Some(code) if PATH_TOO_LONG_OS_ERRORS.contains(&code) => true,
+        Some(3) => cfg!(windows) && length > WINDOWS_MAX_PATH, // ERROR_PATH_NOT_FOUND
+        _ => false,
+    };
+    too_long.then(|| format!(
+        "Path is too long ({} characters): '{}'. Shorten the mod or entity folder name, or move the mods folder closer to the drive root.",
+        length, shown
+    ))
 }
 
-// Helper to get a setting value (Internal error type)
-fn get_setting_value(conn: &Connection, key: &str) -> Result<Option<String>, AppError> { // Internal error type
-    let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
-    let result = stmt.query_row(params![key], |row| row.get(0)).optional()?;
-    Ok(result)
+// `context '<path>': <error>`, or the too-long message when that is the cause
+fn io_error_message(context: &str, path: &Path, e: &io::Error) -> String {
+    path_too_long_message(path, e).unwrap_or_else(|| format!("{} '{}': {}", context, display_path(path), e))
 }
 
-// Helper to get the configured mods base path (Internal error type)
-fn get_mods_base_path_from_settings(db_state: &DbState) -> Result<PathBuf, AppError> { // Internal error type
-    let conn = db_state.0.lock().map_err(|_| AppError::Config("DB lock poisoned".into()))?;
-    get_setting_value(&conn, SETTINGS_KEY_MODS_FOLDER)?
-        .map(PathBuf::from)
-        .ok_or_else(|| AppError::Config("Mods folder path not set".to_string()))
+fn rename_long_path(from: &Path, to: &Path) -> Result<(), String> {
+    fs::rename(extended_length_path(from), extended_length_path(to)).map_err(|e| {
+        path_too_long_message(to, &e)
+            .or_else(|| path_too_long_message(from, &e))
+            .unwrap_or_else(|| format!("Failed to rename '{}' to '{}': {}", display_path(from), display_path(to), e))
+    })
 }
 
-// Helper to get entity mods path using settings (Internal error type)
-// FIX: Removed unused app_handle parameter
-fn get_entity_mods_path(db_state: &DbState, entity_slug: &str) -> Result<PathBuf, AppError> {
-    let base_path = get_mods_base_path_from_settings(db_state)?;
-    Ok(base_path.join(entity_slug))
+fn create_dir_all_long_path(path: &Path) -> io::Result<()> {
+    fs::create_dir_all(extended_length_path(path))
 }
 
-// --- Tauri Commands (Return CmdResult<T> = Result<T, String>) ---
+// --- Symlink enable mode ---
 
-// == Settings Commands ==
+// Where a mod folder lives while staged, relative to the mods folder
+fn staged_relative_path(clean_relative_path: &str) -> String {
+    format!("{}/{}", SYMLINK_STAGING_FOLDER_NAME, clean_relative_path.replace("\\", "/"))
+}
 
-#[command]
-fn get_setting(key: String, db_state: State<DbState>) -> CmdResult<Option<String>> {
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    get_setting_value(&conn, &key).map_err(|e| e.to_string()) // Convert internal error to string
+// Directory symlink, or junction on Windows
+fn is_folder_link(path: &Path) -> bool {
+    fs::symlink_metadata(path).map(|meta| meta.file_type().is_symlink()).unwrap_or(false)
 }
 
-#[command]
-fn set_setting(key: String, value: String, db_state: State<DbState>) -> CmdResult<()> { // Returns Result<(), String>
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    conn.execute(
-        "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
-        params![key, value],
-    ).map_err(|e| e.to_string())?; // Convert error
-    println!("Set setting '{}' to '{}'", key, value);
-    Ok(())
+#[cfg(unix)]
+fn create_folder_link(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
 }
 
-#[command]
-async fn select_directory() -> CmdResult<Option<PathBuf>> { // Removed AppHandle
-    // FIX: Remove AppHandle from new(), use blocking dialog directly
-    let result = dialog::blocking::FileDialogBuilder::new()
-        .set_title("Select Mods Folder")
-        .pick_folder();
+// Directory symlinks need admin rights or Developer Mode; junctions work without either
+#[cfg(windows)]
+fn create_folder_link(target: &Path, link: &Path) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    let symlink_error = match std::os::windows::fs::symlink_dir(target, link) {
+        Ok(()) => return Ok(()),
+        Err(e) => e,
+    };
+    let status = std::process::Command::new("cmd")
+        .args(["/C", "mklink", "/J"])
+        .arg(link)
+        .arg(target)
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()?;
+    if status.success() && is_folder_link(link) { Ok(()) } else { Err(symlink_error) }
+}
 
-    match result {
-        Some(path) => Ok(Some(path)),
-        None => Ok(None), // User cancelled
-    }
+#[cfg(windows)]
+fn remove_folder_link(link: &Path) -> io::Result<()> {
+    fs::remove_dir(link)
 }
 
-#[command]
-async fn select_file() -> CmdResult<Option<PathBuf>> { // Removed AppHandle
-    // FIX: Use add_filter instead of dialog::Filter struct
-    let result = dialog::blocking::FileDialogBuilder::new() // FIX: Remove AppHandle
-        .set_title("Select Quick Launch Executable")
-        .add_filter("Executable", &["exe", "bat", "cmd", "sh", "app"]) // FIX: Use add_filter
-        .add_filter("All Files", &["*"]) // FIX: Use add_filter
-        .pick_file();
+#[cfg(not(windows))]
+fn remove_folder_link(link: &Path) -> io::Result<()> {
+    fs::remove_file(link)
+}
 
-    match result {
-        Some(path) => Ok(Some(path)),
-        None => Ok(None), // User cancelled
+// Symlink-mode half of set_asset_enabled_on_disk. Un-staged folders are moved into the staging
+// folder first. If the link can't be created (unsupported filesystem, no privilege) or rename
+// mode is active again, enabling moves the folder back out to its clean path instead.
+fn set_staged_asset_enabled(base_mods_path: &Path, clean_relative_path: &str, current_relative_path: &str, desired_enabled: bool) -> Result<(), AppError> {
+    let link_path = base_mods_path.join(clean_relative_path);
+    let staged_path = base_mods_path.join(staged_relative_path(clean_relative_path));
+    let current_path = base_mods_path.join(current_relative_path);
+
+    if !desired_enabled && is_folder_link(&link_path) {
+        remove_folder_link(&link_path)?;
+        return Ok(());
+    }
+    if current_path != staged_path {
+        if let Some(parent) = staged_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&current_path, &staged_path)?;
+    }
+    if !desired_enabled {
+        return Ok(());
+    }
+    if CACHED_CONFIG.is_symlink_enable_mode() {
+        match create_folder_link(&staged_path, &link_path) {
+            Ok(()) => return Ok(()),
+            Err(e) => eprintln!("[set_asset_enabled_on_disk] Could not link '{}' ({}), falling back to renaming", link_path.display(), e),
+        }
     }
+    fs::rename(&staged_path, &link_path)?;
+    Ok(())
 }
 
-#[cfg(target_os = "windows")]
-#[command]
-fn launch_executable_elevated(path: String) -> Result<(), String> {
-    use std::os::windows::ffi::OsStrExt;
-    use windows::core::PCWSTR;
-    use windows::Win32::Foundation::HWND;
-    use windows::Win32::UI::Shell::ShellExecuteW;
-    use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+// Turns a staged mod back into a plain (enabled or DISABLED_) folder so code that moves mod
+// folders around doesn't strand the link or the staged copy. No-op for un-staged mods.
+fn unstage_asset_folder(base_mods_path: &Path, clean_relative_path: &str) -> Result<(), AppError> {
+    let staged_path = base_mods_path.join(staged_relative_path(clean_relative_path));
+    if !staged_path.is_dir() {
+        return Ok(());
+    }
+    let link_path = base_mods_path.join(clean_relative_path);
+    let target_path = if is_folder_link(&link_path) {
+        remove_folder_link(&link_path)?;
+        link_path
+    } else {
+        let filename = link_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        link_path.with_file_name(disabled_folder_name(&filename))
+    };
+    fs::rename(&staged_path, &target_path)?;
+    Ok(())
+}
 
-    println!("Attempting elevated launch for: {}", path);
+// Reverse of unstage_asset_folder: moves a plain mod folder into the staging folder and links it
+// back if it was enabled
+fn restage_asset_folder(base_mods_path: &Path, clean_relative_path: &str) -> Result<(), AppError> {
+    let (is_enabled, current_relative_path) = resolve_asset_disk_state(base_mods_path, clean_relative_path)
+        .ok_or_else(|| AppError::NotFound(format!("Mod folder not found on disk (path: '{}')", clean_relative_path)))?;
+    set_staged_asset_enabled(base_mods_path, clean_relative_path, &current_relative_path, is_enabled)
+}
 
-    // Convert the path and verb to Windows wide strings (UTF-16)
-    let path_wide: Vec<u16> = std::ffi::OsStr::new(&path)
-        .encode_wide()
-        .chain(std::iter::once(0)) // Null-terminate
-        .collect();
-    let operation_wide: Vec<u16> = std::ffi::OsStr::new("runas") // "runas" verb requests elevation
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect();
+// Path of a scanned folder relative to the mods folder, with the staging folder stripped off.
+// The flag tells whether the folder was found in the staging folder.
+fn mods_relative_path(base_mods_path: &Path, path: &Path) -> Option<(PathBuf, bool)> {
+    let relative = path.strip_prefix(base_mods_path).ok()?;
+    match relative.strip_prefix(SYMLINK_STAGING_FOLDER_NAME) {
+        Ok(staged) => Some((staged.to_path_buf(), true)),
+        Err(_) => Some((relative.to_path_buf(), false)),
+    }
+}
 
-    // Call ShellExecuteW to request elevation
-    // HINSTANCE returned is actually an integer value; > 32 indicates success.
-    let result = unsafe {
-        ShellExecuteW(
-            Some(HWND::default()),               // Pass as Option<HWND>
-            PCWSTR(operation_wide.as_ptr()),     // Operation: "runas"
-            PCWSTR(path_wide.as_ptr()),          // File path
-            None,                                // No parameters
-            None,                                // Default working directory
-            SW_SHOWNORMAL,                       // Show command normal state
-        )
-    };
+// Child folders of a mod folder that carry their own INI, as clean (un-prefixed) names
+fn find_variant_folders(mod_path: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(mod_path)
+        .map(|entries| entries
+            .filter_map(|e| e.ok())
+            .filter(|entry| entry.path().is_dir() && has_ini_file(&entry.path()))
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                strip_disabled_marker(&name).map(str::to_string).unwrap_or(name)
+            })
+            .collect())
+        .unwrap_or_default();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.dedup();
+    names
+}
 
-    // --- FIX 1: Explicitly cast result.0 to isize BEFORE comparison ---
-    let result_value = result.0 as isize;
+// Makes asset_variants for `asset_id` match the variant folders found on disk
+fn sync_asset_variants(conn: &Connection, asset_id: i64, variant_folders: &[String]) -> SqlResult<()> {
+    let mut existing_stmt = conn.prepare("SELECT id, folder_name FROM asset_variants WHERE asset_id = ?1")?;
+    let existing: Vec<(i64, String)> = existing_stmt
+        .query_map(params![asset_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<SqlResult<_>>()?;
+    for (variant_id, folder_name) in &existing {
+        if !variant_folders.contains(folder_name) {
+            conn.execute("DELETE FROM asset_variants WHERE id = ?1", params![variant_id])?;
+        }
+    }
+    for folder_name in variant_folders {
+        conn.execute(
+            "INSERT OR IGNORE INTO asset_variants (asset_id, name, folder_name) VALUES (?1, ?2, ?3)",
+            params![asset_id, folder_name.replace('_', " "), folder_name],
+        )?;
+    }
+    Ok(())
+}
 
-    if result_value > 32 { // Compare the casted value
-        println!("Elevated launch initiated successfully via ShellExecuteW.");
-        Ok(())
-    } else {
-        // result.0 contains the error code as an isize, cast to i32 if needed elsewhere
-        let error_code = result_value as i32; // Cast for error reporting
-        let error_message = format!(
-            "Failed to request elevated launch for '{}'. ShellExecuteW error code: {}",
-            path, error_code
-        );
-        eprintln!("{}", error_message);
+// Creates the profiles table and scopes assets to the active profile.
+// Older databases get a default profile from the current settings, and the assets table is
+// rebuilt so folder_name is unique per profile instead of globally.
+fn setup_profiles(conn: &Connection) -> Result<(), AppError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS profiles ( id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE COLLATE NOCASE, mods_folder_path TEXT NOT NULL, quick_launch_path TEXT )",
+        [],
+    )?;
 
-        // --- FIX 2: Cast the ERROR_CANCELLED constant to i32 for comparison ---
-        if error_code == windows::Win32::Foundation::ERROR_CANCELLED.0 as i32 {
-             Err("Operation cancelled by user.".to_string())
-        } else {
-             Err(error_message)
-        }
+    let profile_count: i64 = conn.query_row("SELECT COUNT(*) FROM profiles", [], |row| row.get(0))?;
+    if profile_count == 0 {
+        let mods_folder = get_setting_value(conn, SETTINGS_KEY_MODS_FOLDER)?.unwrap_or_default();
+        let quick_launch = get_setting_value(conn, SETTINGS_KEY_QUICK_LAUNCH)?;
+        conn.execute(
+            "INSERT INTO profiles (name, mods_folder_path, quick_launch_path) VALUES (?1, ?2, ?3)",
+            params![DEFAULT_PROFILE_NAME, mods_folder, quick_launch],
+        )?;
+        let default_id = conn.last_insert_rowid();
+        conn.execute("INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)", params![SETTINGS_KEY_ACTIVE_PROFILE, default_id.to_string()])?;
+        println!("Created default profile (ID {}).", default_id);
+    }
+
+    let has_profile_column: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info('assets') WHERE name = 'profile_id')",
+        [], |row| row.get(0),
+    )?;
+    if !has_profile_column {
+        println!("Rebuilding assets table for per-profile folder uniqueness...");
+        let active_profile_id = get_active_profile_id(conn)?;
+        // Runs inside the migration transaction with foreign keys off (see run_migrations).
+        // The search index and its triggers are recreated by the search index migration afterwards
+        conn.execute_batch(&format!(
+            "DROP VIEW IF EXISTS active_assets;
+             DROP TRIGGER IF EXISTS assets_fts_ai;
+             DROP TRIGGER IF EXISTS assets_fts_ad;
+             DROP TRIGGER IF EXISTS assets_fts_au;
+             DROP TABLE IF EXISTS assets_fts;
+             CREATE TABLE assets_rebuild ( id INTEGER PRIMARY KEY AUTOINCREMENT, entity_id INTEGER NOT NULL, name TEXT NOT NULL, description TEXT, folder_name TEXT NOT NULL, image_filename TEXT, author TEXT, category_tag TEXT, last_scanned_mtime INTEGER, profile_id INTEGER REFERENCES profiles (id) ON DELETE CASCADE, FOREIGN KEY (entity_id) REFERENCES entities (id) ON DELETE CASCADE, UNIQUE (profile_id, folder_name) );
+             INSERT INTO assets_rebuild (id, entity_id, name, description, folder_name, image_filename, author, category_tag, last_scanned_mtime, profile_id)
+                 SELECT id, entity_id, name, description, folder_name, image_filename, author, category_tag, last_scanned_mtime, {} FROM assets;
+             DROP TABLE assets;
+             ALTER TABLE assets_rebuild RENAME TO assets;",
+            active_profile_id
+        ))?;
     }
+
+    // Read queries go through active_assets; new rows default to the active profile
+    conn.execute_batch(&format!(
+        "CREATE VIEW IF NOT EXISTS active_assets AS
+             SELECT * FROM assets WHERE profile_id = (SELECT CAST(value AS INTEGER) FROM settings WHERE key = '{key}');
+         CREATE TRIGGER IF NOT EXISTS assets_default_profile AFTER INSERT ON assets WHEN new.profile_id IS NULL BEGIN
+             UPDATE assets SET profile_id = (SELECT CAST(value AS INTEGER) FROM settings WHERE key = '{key}') WHERE id = new.id;
+         END;",
+        key = SETTINGS_KEY_ACTIVE_PROFILE
+    ))?;
+    Ok(())
 }
 
-#[command]
-async fn launch_executable(path: String, _app_handle: AppHandle) -> CmdResult<()> { // app_handle might not be needed now
-    println!("Attempting to launch (non-elevated) via Command::new: {}", path);
+fn get_active_profile_id(conn: &Connection) -> Result<i64, AppError> {
+    get_setting_value(conn, SETTINGS_KEY_ACTIVE_PROFILE)?
+        .and_then(|v| v.parse::<i64>().ok())
+        .ok_or_else(|| AppError::Config("No active profile set".to_string()))
+}
 
-    // FIX: Use Command::new for launching executables
-    let cmd = Command::new(path) // Use the path directly as the command
-        // .args([]) // Add arguments if needed later
-        .spawn(); // Spawn the process
+// Creates the FTS5 search index over assets (kept in sync by triggers).
+// Returns false if this SQLite build lacks FTS5; search then falls back to LIKE.
+fn setup_asset_search_index(conn: &Connection) -> bool {
+    let already_exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'assets_fts')",
+        [], |row| row.get(0),
+    ).unwrap_or(false);
+    if already_exists {
+        return true;
+    }
 
-    match cmd {
-        Ok((mut rx, _child)) => {
-            // You can optionally read stdout/stderr here if needed
-             while let Some(event) = rx.recv().await {
-                 match event {
-                    tauri::api::process::CommandEvent::Stdout(line) => {
-                        println!("Launcher stdout: {}", line);
-                    }
-                    tauri::api::process::CommandEvent::Stderr(line) => {
-                        eprintln!("Launcher stderr: {}", line);
-                    }
-                    tauri::api::process::CommandEvent::Error(e) => {
-                         eprintln!("Launcher error event: {}", e);
-                         // If we get the elevation error here, we could suggest the elevated launch
-                         if e.contains("os error 740") {
-                             return Err(format!("Failed to launch: The application requires administrator privileges. Try the 'Launch as Admin' button if available, or run GMM as administrator (not recommended). Original error: {}", e));
-                         }
-                         // Decide if other errors constitute a failure
-                         // return Err(format!("Launcher process event error: {}", e));
-                    }
-                     tauri::api::process::CommandEvent::Terminated(payload) => {
-                        println!("Launcher terminated: {:?}", payload);
-                        if let Some(code) = payload.code {
-                             if code != 0 {
-                                println!("Launcher exited with non-zero code: {}", code);
-                                // Optionally return error based on exit code
-                                // return Err(format!("Launcher exited with code {}", code));
-                             }
-                         } else {
-                             println!("Launcher terminated without exit code (possibly killed).");
-                         }
-                         // Process terminated, break the loop
-                         break;
-                     }
-                    _ => {} // Ignore other events
-                }
-             }
-             println!("Launcher process finished or detached.");
-             Ok(()) // Assume success if spawn worked and process finished/detached
+    // Savepoint rather than BEGIN so this can run inside the migration transaction
+    let setup_result = conn.execute_batch(
+        "SAVEPOINT asset_search_index;
+         CREATE VIRTUAL TABLE assets_fts USING fts5(name, description, author, category_tag, content='assets', content_rowid='id');
+         CREATE TRIGGER assets_fts_ai AFTER INSERT ON assets BEGIN
+             INSERT INTO assets_fts(rowid, name, description, author, category_tag) VALUES (new.id, new.name, new.description, new.author, new.category_tag);
+         END;
+         CREATE TRIGGER assets_fts_ad AFTER DELETE ON assets BEGIN
+             INSERT INTO assets_fts(assets_fts, rowid, name, description, author, category_tag) VALUES ('delete', old.id, old.name, old.description, old.author, old.category_tag);
+         END;
+         CREATE TRIGGER assets_fts_au AFTER UPDATE ON assets BEGIN
+             INSERT INTO assets_fts(assets_fts, rowid, name, description, author, category_tag) VALUES ('delete', old.id, old.name, old.description, old.author, old.category_tag);
+             INSERT INTO assets_fts(rowid, name, description, author, category_tag) VALUES (new.id, new.name, new.description, new.author, new.category_tag);
+         END;
+         INSERT INTO assets_fts(assets_fts) VALUES ('rebuild');
+         RELEASE asset_search_index;",
+    );
+    match setup_result {
+        Ok(_) => {
+            println!("Created FTS5 asset search index.");
+            true
         }
         Err(e) => {
-             eprintln!("Failed to spawn launcher: {}", e);
-             // Check for the specific error here too
-             if e.to_string().contains("os error 740") {
-                 Err(format!("Failed to launch: The application requires administrator privileges. Try running GMM as administrator (not recommended). Error: {}", e))
-             } else {
-                  Err(format!("Failed to spawn executable: {}", e)) // Convert error to string
-             }
+            conn.execute_batch("ROLLBACK TO asset_search_index; RELEASE asset_search_index;").ok();
+            eprintln!("WARN: FTS5 unavailable, asset search will use LIKE matching: {}", e);
+            false
         }
     }
 }
 
+fn record_operation(op_log: &OperationLogState, operation: Operation) {
+    match op_log.0.lock() {
+        Ok(mut log) => {
+            log.push(operation);
+            if log.len() > MAX_UNDO_OPERATIONS {
+                let overflow = log.len() - MAX_UNDO_OPERATIONS;
+                log.drain(0..overflow);
+            }
+        }
+        Err(_) => eprintln!("[record_operation] Operation log lock poisoned, undo entry dropped."),
+    }
+}
 
-// == Core Commands (Return CmdResult<T>) ==
+fn get_trash_dir(app_handle: &AppHandle) -> Result<PathBuf, AppError> {
+    Ok(get_app_data_dir(app_handle)?.join(TRASH_FOLDER_NAME))
+}
 
-#[command]
-fn get_categories(db_state: State<DbState>) -> CmdResult<Vec<Category>> {
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    let mut stmt = conn.prepare("SELECT id, name, slug FROM categories ORDER BY name")
-        .map_err(|e| e.to_string())?; // Convert error
-    let category_iter = stmt.query_map([], |row| {
-        Ok(Category {
-            id: row.get(0)?, name: row.get(1)?, slug: row.get(2)?,
-        })
-    }).map_err(|e| e.to_string())?; // Convert error
-    category_iter.collect::<SqlResult<Vec<Category>>>().map_err(|e| e.to_string()) // Convert error
+// Tags the progress events of one long-running command so concurrent runs can be told apart.
+// Callers may pass their own id to filter events before the command returns.
+fn new_operation_id() -> String {
+    uuid::Uuid::new_v4().to_string()
 }
 
-#[command]
-fn get_category_entities(category_slug: String, db_state: State<DbState>) -> CmdResult<Vec<Entity>> {
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-     let category_id: i64 = conn.query_row(
-        "SELECT id FROM categories WHERE slug = ?1",
-        params![category_slug],
-        |row| row.get(0),
-    ).map_err(|e| match e {
-        rusqlite::Error::QueryReturnedNoRows => format!("Category '{}' not found", category_slug),
-        _ => e.to_string(),
-    })?;
+fn unix_timestamp_millis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
 
-     // Fetch id, name, slug - ORDER BY to put 'Other' first
-     let mut stmt = conn.prepare(
-        "SELECT id, name, slug
-         FROM entities
-         WHERE category_id = ?1
-         ORDER BY
-            CASE WHEN slug LIKE '%-other' THEN 0 ELSE 1 END ASC,
-            name ASC"
-    ).map_err(|e| e.to_string())?; // Corrected SQL query
+// Ordered schema migrations, applied once each and tracked by the schema_version setting.
+// Steps must be idempotent: databases created before versioning start at version 0 and
+// replay every step, even if some of the changes were already applied by older builds.
+type MigrationStep = fn(&Connection) -> Result<(), AppError>;
+
+const MIGRATIONS: &[(i64, &str, MigrationStep)] = &[
+    (1, "add assets.last_scanned_mtime", migrate_add_scan_mtime),
+    (2, "create tags, asset_tags and trashed_assets tables", migrate_create_tag_and_trash_tables),
+    (3, "add profiles and scope assets per profile", setup_profiles),
+    (4, "create asset search index", migrate_create_search_index),
+    (5, "add assets.size_bytes", migrate_add_size_bytes),
+    (6, "create deduction_rules table", migrate_create_deduction_rules),
+    (7, "add notes and source_url to assets and trashed_assets", migrate_add_asset_notes),
+    (8, "add assets.sort_order", migrate_add_sort_order),
+    (9, "add assets.is_favorite", migrate_add_asset_favorite),
+    (10, "add assets.content_hash", migrate_add_content_hash),
+    (11, "create asset_variants table", migrate_create_asset_variants),
+    (12, "add assets.is_pinned", migrate_add_asset_pinned),
+    (13, "add assets.version", migrate_add_asset_version),
+    (14, "add assets.is_locked", migrate_add_asset_locked),
+    (15, "add assets.created_at and updated_at", migrate_add_asset_timestamps),
+    (16, "add assets.is_enabled", migrate_add_asset_enabled),
+    (17, "create preset_shortcuts table", migrate_create_preset_shortcuts),
+    (18, "add assets.last_enabled_at", migrate_add_asset_last_enabled),
+    (19, "keep preset entries of deleted assets", migrate_keep_stale_preset_entries),
+    (20, "create pinned_entities table", migrate_create_pinned_entities),
+    (21, "add exclusive groups", migrate_add_exclusive_groups),
+    (22, "scope presets and trashed_assets per profile", migrate_scope_presets_and_trash),
+];
+
+fn migrate_add_scan_mtime(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "last_scanned_mtime", "INTEGER")
+}
 
-    let entity_iter = stmt.query_map(params![category_id], |row| {
-        Ok(Entity {
-            id: row.get(0)?,
-            category_id: category_id,
-            name: row.get(1)?,
-            slug: row.get(2)?,
-            description: None,
-            details: None,
-            base_image: None,
-            mod_count: 0,
-            enabled_mod_count: None,
-            recent_mod_count: None,
-            favorite_mod_count: None,
-        })
-    }).map_err(|e| e.to_string())?;
-    entity_iter.collect::<SqlResult<Vec<Entity>>>().map_err(|e| e.to_string())
+fn migrate_add_size_bytes(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "size_bytes", "INTEGER")
 }
 
-#[command]
-fn get_entities_by_category(category_slug: String, db_state: State<DbState>) -> CmdResult<Vec<Entity>> {
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-     let category_id: i64 = conn.query_row(
-        "SELECT id FROM categories WHERE slug = ?1",
-        params![category_slug],
-        |row| row.get(0),
-    ).map_err(|e| match e {
-        rusqlite::Error::QueryReturnedNoRows => format!("Category '{}' not found", category_slug),
-        _ => e.to_string(),
-    })?;
+fn migrate_create_deduction_rules(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS deduction_rules ( id INTEGER PRIMARY KEY AUTOINCREMENT, pattern TEXT NOT NULL, is_regex INTEGER NOT NULL DEFAULT 0, entity_slug TEXT NOT NULL, priority INTEGER NOT NULL DEFAULT 0 );",
+    )?;
+    Ok(())
+}
 
-     // Fetch full entity details - ORDER BY to put 'Other' first
-     let mut stmt = conn.prepare(
-        "SELECT e.id, e.category_id, e.name, e.slug, e.description, e.details, e.base_image, COUNT(a.id) as mod_count
-         FROM entities e LEFT JOIN assets a ON e.id = a.entity_id
-         WHERE e.category_id = ?1
-         GROUP BY e.id
-         ORDER BY
-            CASE WHEN e.slug LIKE '%-other' THEN 0 ELSE 1 END ASC,
-            e.name ASC" // Corrected SQL query
-    ).map_err(|e| e.to_string())?;
+fn migrate_add_asset_notes(conn: &Connection) -> Result<(), AppError> {
+    for table in ["assets", "trashed_assets"] {
+        ensure_column(conn, table, "notes", "TEXT")?;
+        ensure_column(conn, table, "source_url", "TEXT")?;
+    }
+    Ok(())
+}
 
-    let entity_iter = stmt.query_map(params![category_id], |row| {
-        Ok(Entity {
-            id: row.get(0)?, category_id: row.get(1)?, name: row.get(2)?,
-            slug: row.get(3)?, description: row.get(4)?, details: row.get(5)?,
-            base_image: row.get(6)?, mod_count: row.get(7)?,
-            enabled_mod_count: None,
-            recent_mod_count: None,
-            favorite_mod_count: None
-        })
-    }).map_err(|e| e.to_string())?;
-    entity_iter.collect::<SqlResult<Vec<Entity>>>().map_err(|e| e.to_string())
+// Existing assets are numbered by name within their entity. New or relocated assets are
+// appended to the end of their entity by triggers, so every insert path gets a value.
+fn migrate_add_sort_order(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "sort_order", "INTEGER")?;
+    conn.execute_batch(
+        "UPDATE assets SET sort_order = (
+             SELECT COUNT(*) FROM assets a2
+             WHERE a2.entity_id = assets.entity_id AND (a2.name < assets.name OR (a2.name = assets.name AND a2.id <= assets.id))
+         ) WHERE sort_order IS NULL;
+         CREATE TRIGGER IF NOT EXISTS assets_default_sort_order AFTER INSERT ON assets WHEN new.sort_order IS NULL BEGIN
+             UPDATE assets SET sort_order = (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM assets WHERE entity_id = new.entity_id) WHERE id = new.id;
+         END;
+         CREATE TRIGGER IF NOT EXISTS assets_relocated_sort_order AFTER UPDATE OF entity_id ON assets WHEN new.entity_id != old.entity_id BEGIN
+             UPDATE assets SET sort_order = (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM assets WHERE entity_id = new.entity_id AND id != new.id) WHERE id = new.id;
+         END;",
+    )?;
+    Ok(())
 }
 
+fn migrate_add_asset_favorite(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "is_favorite", "INTEGER NOT NULL DEFAULT 0")
+}
 
-#[command]
-fn get_entity_details(entity_slug: String, db_state: State<DbState>) -> CmdResult<Entity> {
-    println!("[get_entity_details] Starting for entity: {}", entity_slug);
-    
-    // PART 1: Get base entity info with a brief lock
-    let entity_info = {
-        let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-        let conn = &*conn_guard; // Dereference the guard
-        
-        let mut stmt = conn.prepare(
-            "SELECT e.id, e.category_id, e.name, e.slug, e.description, e.details, e.base_image, COUNT(a.id) as mod_count
-             FROM entities e LEFT JOIN assets a ON e.id = a.entity_id
-             WHERE e.slug = ?1 GROUP BY e.id"
-        ).map_err(|e| format!("[get_entity_details] DB prepare error: {}", e))?;
-        
-        // Get basic entity details first
-        stmt.query_row(params![entity_slug], |row| {
-             Ok(Entity {
-                id: row.get(0)?, 
-                category_id: row.get(1)?, 
-                name: row.get(2)?,
-                slug: row.get(3)?, 
-                description: row.get(4)?, 
-                details: row.get(5)?,
-                base_image: row.get(6)?, 
-                mod_count: row.get(7)?,
-                enabled_mod_count: None,  // Will be populated later
-                recent_mod_count: None,   // Will be populated later
-                favorite_mod_count: None  // Will be populated later
-            })
-        }).map_err(|e| match e { 
-            rusqlite::Error::QueryReturnedNoRows => format!("Entity '{}' not found", entity_slug),
-            _ => format!("[get_entity_details] DB row error: {}", e),
-        })?
-    }; // conn_guard is released here
-    
-    // Create a mutable copy we'll update with the additional counts
-    let mut entity = entity_info;
-    
-    // PART 2: Get folder paths from DB with a separate brief lock
-    let asset_folder_paths: Vec<String> = {
-        let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-        let conn = &*conn_guard;
-        
-        // Prepare statement and collect all folder paths while holding lock
-        let mut stmt = conn.prepare("SELECT folder_name FROM assets WHERE entity_id = ?1")
-            .map_err(|e| format!("[get_entity_details] Error preparing folder query: {}", e))?;
-            
-        let folder_iter = stmt.query_map(params![entity.id], |row| row.get::<_, String>(0))
-            .map_err(|e| format!("[get_entity_details] Error executing folder query: {}", e))?;
-            
-        // Collect all paths into a Vec to release the lock sooner
-        let mut paths = Vec::new();
-        for result in folder_iter {
-            match result {
-                Ok(path) => paths.push(path.replace("\\", "/")),
-                Err(e) => println!("[get_entity_details] Warning: Error fetching path: {}", e),
-            }
-        }
-        paths
-    }; // conn_guard is released here
-    
-    // PART 3: Get mods base path (uses a lock internally, so call outside of any lock section)
-    let base_mods_path = match get_mods_base_path_from_settings(&db_state) {
-        Ok(path) => path,
-        Err(e) => {
-            println!("[get_entity_details] Warning: Error getting base mods path: {}", e);
-            // We'll proceed with empty counts since we can't check the disk
-            entity.enabled_mod_count = Some(0);
-            entity.recent_mod_count = Some(0);
-            entity.favorite_mod_count = Some(0);
-            return Ok(entity);
-        }
-    };
-    
-    // PART 4: Count enabled mods by checking disk paths (NO DB LOCK NEEDED)
-    let mut enabled_count = 0;
-    for clean_relative_path_str in &asset_folder_paths {
-        let clean_relative_path = PathBuf::from(clean_relative_path_str);
-        let filename_osstr = match clean_relative_path.file_name() {
-            Some(name) => name,
-            None => continue, // Skip if we can't get filename
-        };
-        
-        let filename_str = filename_osstr.to_string_lossy();
-        if filename_str.is_empty() { continue; }
-        
-        // Check only enabled state path
-        let full_path_if_enabled = base_mods_path.join(&clean_relative_path);
-        if full_path_if_enabled.is_dir() {
-            enabled_count += 1;
-        }
-    }
-    entity.enabled_mod_count = Some(enabled_count);
-    
-    // PART 5: Get recent mod count and favorite counts with a final lock
-    {
-        let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-        let conn = &*conn_guard;
-        
-        // Count recent mods (approximation using ID sorting, assuming higher IDs are more recent)
-        if entity.mod_count > 0 {
-            match conn.query_row(
-                "SELECT COUNT(*) FROM assets 
-                 WHERE entity_id = ?1 
-                 AND id > (SELECT MAX(id) - (COUNT(*) / 4) FROM assets WHERE entity_id = ?1)",
-                params![entity.id],
-                |row| row.get::<_, i32>(0),
-            ) {
-                Ok(count) => {
-                    entity.recent_mod_count = Some(count);
-                },
-                Err(e) => {
-                    println!("[get_entity_details] Warning: Error counting recent mods: {}", e);
-                    entity.recent_mod_count = Some(0);
-                }
-            }
-        } else {
-            entity.recent_mod_count = Some(0);
-        }
-        
-        // Count mods in favorite presets
-        match conn.query_row(
-            "SELECT COUNT(DISTINCT a.id) FROM assets a
-             JOIN preset_assets pa ON a.id = pa.asset_id
-             JOIN presets p ON pa.preset_id = p.id
-             WHERE a.entity_id = ?1 AND p.is_favorite = 1",
-            params![entity.id],
-            |row| row.get::<_, i32>(0),
-        ) {
-            Ok(count) => {
-                entity.favorite_mod_count = Some(count);
-            },
-            Err(e) => {
-                println!("[get_entity_details] Warning: Error counting mods in favorite presets: {}", e);
-                entity.favorite_mod_count = Some(0);
-            }
-        }
-    } // Final conn_guard is released here
-    
-    println!("[get_entity_details] Completed for entity: {}", entity_slug);
-    Ok(entity)
+fn migrate_add_content_hash(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "content_hash", "TEXT")?;
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_assets_content_hash ON assets (content_hash);")?;
+    Ok(())
 }
 
-#[command]
-fn get_assets_for_entity(entity_slug: String, db_state: State<DbState>, _app_handle: AppHandle) -> CmdResult<Vec<Asset>> {
-    let base_mods_path = get_mods_base_path_from_settings(&db_state)
-                             .map_err(|e| format!("[get_assets_for_entity {}] Error getting base mods path: {}", entity_slug, e))?;
-
-    let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    let conn = &*conn_guard;
-
-    // --- Entity ID Lookup ---
-    let entity_id: i64 = conn.query_row(
-        "SELECT id FROM entities WHERE slug = ?1",
-        params![entity_slug],
-        |row| row.get(0),
-    ).map_err(|e| match e {
-        rusqlite::Error::QueryReturnedNoRows => format!("[get_assets_for_entity {}] Entity not found for assets lookup", entity_slug),
-        _ => format!("[get_assets_for_entity {}] DB Error getting entity ID: {}", entity_slug, e),
-    })?;
-
-    // --- Prepare Statement ---
-    let mut stmt = conn.prepare(
-        "SELECT id, entity_id, name, description, folder_name, image_filename, author, category_tag
-         FROM assets WHERE entity_id = ?1 ORDER BY name"
-    ).map_err(|e| format!("[get_assets_for_entity {}] DB Error preparing asset statement: {}", entity_slug, e))?;
+fn migrate_add_asset_pinned(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "is_pinned", "INTEGER NOT NULL DEFAULT 0")
+}
 
-    // --- Query Rows ---
-    let asset_rows_result = stmt.query_map(params![entity_id], |row| {
-        let folder_name_raw: String = row.get(4)?;
-        Ok(Asset {
-            id: row.get(0)?,
-            entity_id: row.get(1)?,
-            name: row.get(2)?,
-            description: row.get(3)?,
-            // Store the CLEAN relative path from DB directly for now
-            folder_name: folder_name_raw.replace("\\", "/"),
-            image_filename: row.get(5)?,
-            author: row.get(6)?,
-            category_tag: row.get(7)?,
-            is_enabled: false, // Default, will be determined below
-        })
-    });
+fn migrate_add_asset_version(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "version", "TEXT")
+}
 
-    let mut assets_to_return = Vec::new();
+fn migrate_add_asset_locked(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "is_locked", "INTEGER NOT NULL DEFAULT 0")
+}
 
-    match asset_rows_result {
-        Ok(asset_iter) => {
-             for (index, asset_result) in asset_iter.enumerate() {
-                 match asset_result {
-                     Ok(mut asset_from_db) => {
-                         // --- Corrected State Detection Logic ---
-                         // `asset_from_db.folder_name` currently holds the CLEAN relative path from DB
-                         let clean_relative_path_from_db = PathBuf::from(&asset_from_db.folder_name);
-
-                         // Construct potential paths based on the CLEAN relative path
-                         let filename_osstr = clean_relative_path_from_db.file_name().unwrap_or_default();
-                         let filename_str = filename_osstr.to_string_lossy();
-                         if filename_str.is_empty() {
-                             continue;
-                         }
-                         let disabled_filename = format!("{}{}", DISABLED_PREFIX, filename_str);
-                         let relative_parent_path = clean_relative_path_from_db.parent();
-
-                         // Path if enabled = base / clean_relative_path
-                         let full_path_if_enabled = base_mods_path.join(&clean_relative_path_from_db);
-
-                         // Path if disabled = base / relative_parent / disabled_filename
-                         let full_path_if_disabled = match relative_parent_path {
-                            Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_filename),
-                            _ => base_mods_path.join(&disabled_filename), // No parent or parent is root
-                         };
-
-                         // Determine state based on which path exists
-                         if full_path_if_enabled.is_dir() {
-                             asset_from_db.is_enabled = true;
-                             // Set folder_name to the actual path found on disk
-                             asset_from_db.folder_name = clean_relative_path_from_db.to_string_lossy().replace("\\", "/");
-                         } else if full_path_if_disabled.is_dir() {
-                             asset_from_db.is_enabled = false;
-                             // Set folder_name to the actual path found on disk (the disabled one)
-                              let disabled_relative_path = match relative_parent_path {
-                                 Some(parent) if parent.as_os_str().len() > 0 => parent.join(&disabled_filename),
-                                 _ => PathBuf::from(&disabled_filename),
-                              };
-                             asset_from_db.folder_name = disabled_relative_path.to_string_lossy().replace("\\", "/");
-                         } else {
-                             // Mod folder doesn't exist in either state
-                             continue; // Skip this asset
-                         }
+// Unix seconds. Existing assets are backfilled from their last scan mtime. New rows get both
+// stamps from a trigger so scan, import and restore inserts don't each have to set them.
+fn migrate_add_asset_timestamps(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "created_at", "INTEGER")?;
+    ensure_column(conn, "assets", "updated_at", "INTEGER")?;
+    conn.execute_batch(
+        "UPDATE assets SET created_at = COALESCE(last_scanned_mtime, CAST(strftime('%s','now') AS INTEGER)) WHERE created_at IS NULL;
+         UPDATE assets SET updated_at = created_at WHERE updated_at IS NULL;
+         CREATE INDEX IF NOT EXISTS idx_assets_created_at ON assets (created_at);
+         CREATE INDEX IF NOT EXISTS idx_assets_updated_at ON assets (updated_at);
+         CREATE TRIGGER IF NOT EXISTS assets_default_created_at AFTER INSERT ON assets WHEN new.created_at IS NULL BEGIN
+             UPDATE assets SET created_at = CAST(strftime('%s','now') AS INTEGER), updated_at = COALESCE(new.updated_at, CAST(strftime('%s','now') AS INTEGER)) WHERE id = new.id;
+         END;",
+    )?;
+    Ok(())
+}
 
-                         assets_to_return.push(asset_from_db);
-                         // --- End Corrected State Detection ---
-                     }
-                     Err(e) => {
-                         eprintln!("[get_assets_for_entity {}] Error processing asset row index {}: {}", entity_slug, index, e);
-                     }
-                 }
-             }
-        }
-        Err(e) => {
-             let err_msg = format!("[get_assets_for_entity {}] DB Error preparing asset iterator: {}", entity_slug, e);
-             return Err(err_msg);
-        }
-    }
+// Cached enabled state so counts don't need a disk stat per asset. NULL means unknown (not yet
+// scanned, or missing on disk); get_dashboard_stats fills unknown rows in on first use.
+fn migrate_add_asset_enabled(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "is_enabled", "INTEGER")
+}
 
-    Ok(assets_to_return)
+// Global hotkeys that apply a preset; accelerators use Tauri's syntax ("CmdOrCtrl+Shift+1")
+fn migrate_create_preset_shortcuts(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS preset_shortcuts ( preset_id INTEGER PRIMARY KEY, accelerator TEXT NOT NULL UNIQUE COLLATE NOCASE, FOREIGN KEY (preset_id) REFERENCES presets(id) ON DELETE CASCADE );",
+    )?;
+    Ok(())
 }
 
-#[command]
-fn toggle_asset_enabled(entity_slug: String, asset: Asset, db_state: State<DbState>) -> CmdResult<bool> {
-    // Note: asset.folder_name passed from frontend is the CURRENT name on disk.
-    // We use the asset.id to get the CLEAN relative path from DB for robust path construction.
-    println!("[toggle_asset_enabled] Toggling asset: ID={}, Name={}, UI Folder='{}', UI Enabled State={}", asset.id, asset.name, asset.folder_name, asset.is_enabled);
+// Unix seconds of the last time the app enabled the mod; NULL means never (or before tracking)
+fn migrate_add_asset_last_enabled(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "last_enabled_at", "INTEGER")?;
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_assets_last_enabled_at ON assets (last_enabled_at);")?;
+    Ok(())
+}
 
-    // Get BASE mods path
-    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+// Drops the asset foreign key so deleting a mod leaves its preset entries behind; they are
+// reported by get_preset_asset_details and removed with clean_preset
+fn migrate_keep_stale_preset_entries(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE preset_assets_rebuild ( preset_id INTEGER NOT NULL, asset_id INTEGER NOT NULL, is_enabled INTEGER NOT NULL, PRIMARY KEY (preset_id, asset_id), FOREIGN KEY (preset_id) REFERENCES presets(id) ON DELETE CASCADE );
+         INSERT INTO preset_assets_rebuild (preset_id, asset_id, is_enabled) SELECT preset_id, asset_id, is_enabled FROM preset_assets;
+         DROP TABLE preset_assets;
+         ALTER TABLE preset_assets_rebuild RENAME TO preset_assets;",
+    )?;
+    Ok(())
+}
 
-    // Fetch the CLEAN STORED relative path from DB using asset ID
-    let clean_relative_path_from_db_str = {
-         let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-         conn.query_row::<String, _, _>(
-            "SELECT folder_name FROM assets WHERE id = ?1", // Expecting clean path here
-            params![asset.id],
-            |row| row.get(0),
-         ).map_err(|e| format!("Failed to get relative path from DB for asset ID {}: {}", asset.id, e))?
-    };
-     // Ensure forward slashes for PathBuf consistency
-     let clean_relative_path_from_db_str = clean_relative_path_from_db_str.replace("\\", "/");
-     let clean_relative_path_from_db = PathBuf::from(&clean_relative_path_from_db_str);
-     println!("[toggle_asset_enabled] Clean relative path from DB: '{}'", clean_relative_path_from_db.display());
+// Entities pinned to the home-screen quick bar, in the user's order
+fn migrate_create_pinned_entities(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS pinned_entities ( entity_id INTEGER PRIMARY KEY, sort_order INTEGER NOT NULL, FOREIGN KEY (entity_id) REFERENCES entities(id) ON DELETE CASCADE );",
+    )?;
+    Ok(())
+}
 
+// Assets sharing an exclusive_group are alternatives (e.g. outfits of one character). Only groups
+// marked enforced in exclusive_groups make the toggle commands (single, bulk, randomize) disable
+// the others when one member is enabled; preset apply restores the saved states as they are.
+fn migrate_add_exclusive_groups(conn: &Connection) -> Result<(), AppError> {
+    ensure_column(conn, "assets", "exclusive_group", "TEXT")?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS exclusive_groups ( name TEXT PRIMARY KEY, enforced INTEGER NOT NULL DEFAULT 0 );",
+    )?;
+    Ok(())
+}
 
-    // --- FIX: Construct potential paths correctly ---
-    let filename_osstr = clean_relative_path_from_db.file_name().ok_or_else(|| format!("Could not extract filename from DB path: {}", clean_relative_path_from_db.display()))?;
-    let filename_str = filename_osstr.to_string_lossy();
-    if filename_str.is_empty() {
-        return Err(format!("Filename extracted from DB path is empty: {}", clean_relative_path_from_db.display()));
+// Presets and trash entries belong to the profile they were created in, like assets: reads go
+// through active_presets / active_trashed_assets and new rows default to the active profile.
+// Preset names only need to be unique within a profile, which needs a table rebuild.
+fn migrate_scope_presets_and_trash(conn: &Connection) -> Result<(), AppError> {
+    let active_profile_id = get_active_profile_id(conn)?;
+    let presets_have_profile: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info('presets') WHERE name = 'profile_id')",
+        [], |row| row.get(0),
+    )?;
+    if !presets_have_profile {
+        conn.execute_batch(&format!(
+            "CREATE TABLE presets_rebuild ( id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, is_favorite INTEGER NOT NULL DEFAULT 0, profile_id INTEGER REFERENCES profiles (id) ON DELETE CASCADE, UNIQUE (profile_id, name) );
+             INSERT INTO presets_rebuild (id, name, is_favorite, profile_id) SELECT id, name, is_favorite, {} FROM presets;
+             DROP TABLE presets;
+             ALTER TABLE presets_rebuild RENAME TO presets;",
+            active_profile_id
+        ))?;
     }
-    let disabled_filename = format!("{}{}", DISABLED_PREFIX, filename_str);
-    let relative_parent_path = clean_relative_path_from_db.parent();
-
-    // Full path if enabled = base / clean_relative_path
-    let full_path_if_enabled = base_mods_path.join(&clean_relative_path_from_db);
-
-    // Full path if disabled = base / relative_parent / disabled_filename
-    let full_path_if_disabled = match relative_parent_path {
-       Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_filename),
-       _ => base_mods_path.join(&disabled_filename), // No parent or parent is root
-    };
-
-    println!("[toggle_asset_enabled] Constructed enabled path check: {}", full_path_if_enabled.display());
-    println!("[toggle_asset_enabled] Constructed disabled path check: {}", full_path_if_disabled.display());
+    ensure_column(conn, "trashed_assets", "profile_id", "INTEGER")?;
+    conn.execute("UPDATE trashed_assets SET profile_id = ?1 WHERE profile_id IS NULL", params![active_profile_id])?;
+
+    conn.execute_batch(&format!(
+        "CREATE VIEW IF NOT EXISTS active_presets AS
+             SELECT * FROM presets WHERE profile_id = (SELECT CAST(value AS INTEGER) FROM settings WHERE key = '{key}');
+         CREATE TRIGGER IF NOT EXISTS presets_default_profile AFTER INSERT ON presets WHEN new.profile_id IS NULL BEGIN
+             UPDATE presets SET profile_id = (SELECT CAST(value AS INTEGER) FROM settings WHERE key = '{key}') WHERE id = new.id;
+         END;
+         CREATE VIEW IF NOT EXISTS active_trashed_assets AS
+             SELECT * FROM trashed_assets WHERE profile_id = (SELECT CAST(value AS INTEGER) FROM settings WHERE key = '{key}');
+         CREATE TRIGGER IF NOT EXISTS trashed_assets_default_profile AFTER INSERT ON trashed_assets WHEN new.profile_id IS NULL BEGIN
+             UPDATE trashed_assets SET profile_id = (SELECT CAST(value AS INTEGER) FROM settings WHERE key = '{key}') WHERE id = new.id;
+         END;",
+        key = SETTINGS_KEY_ACTIVE_PROFILE
+    ))?;
+    Ok(())
+}
 
+// Variants are the toggleable child folders (each with its own INI) of a mod folder that has
+// a top-level INI. folder_name is the clean child folder name relative to the asset folder.
+fn migrate_create_asset_variants(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS asset_variants ( id INTEGER PRIMARY KEY AUTOINCREMENT, asset_id INTEGER NOT NULL, name TEXT NOT NULL, folder_name TEXT NOT NULL, UNIQUE (asset_id, folder_name), FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE );",
+    )?;
+    Ok(())
+}
 
-    // Determine the CURRENT full path and the TARGET full path based on the *actual* state on disk
-    let (current_full_path, target_full_path, new_enabled_state) =
-        if full_path_if_enabled.is_dir() { // Check if the ENABLED path exists
-            // It's currently enabled on disk, target is the disabled path
-             println!("[toggle_asset_enabled] Detected state on disk: ENABLED (found {})", full_path_if_enabled.display());
-            (full_path_if_enabled, full_path_if_disabled, false) // New state will be disabled
-        } else if full_path_if_disabled.is_dir() { // Check if the DISABLED path exists
-            // It's currently disabled on disk, target is the enabled path
-             println!("[toggle_asset_enabled] Detected state on disk: DISABLED (found {})", full_path_if_disabled.display());
-            (full_path_if_disabled, full_path_if_enabled, true) // New state will be enabled
-        } else {
-            // Neither exists, something is wrong. Error based on DB path.
-             println!("[toggle_asset_enabled] Error: Mod folder not found on disk based on DB relative path!");
-            // Use the better error message from before
-             return Err(format!(
-                "Cannot toggle mod '{}': Folder not found at expected locations derived from DB path '{}' (Checked {} and {}). Did the folder get moved or deleted?",
-                asset.name, // Use the display name from the asset object
-                clean_relative_path_from_db.display(), // Show the clean path we checked against
-                full_path_if_enabled.display(),
-                full_path_if_disabled.display()
-            ));
-        };
+fn migrate_create_tag_and_trash_tables(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS tags ( id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE COLLATE NOCASE );
+         CREATE TABLE IF NOT EXISTS trashed_assets ( id INTEGER PRIMARY KEY AUTOINCREMENT, trash_folder_name TEXT NOT NULL UNIQUE, original_relative_path TEXT NOT NULL, entity_id INTEGER NOT NULL, name TEXT NOT NULL, description TEXT, folder_name TEXT NOT NULL, image_filename TEXT, author TEXT, category_tag TEXT, deleted_at INTEGER NOT NULL );
+         CREATE TABLE IF NOT EXISTS asset_tags ( asset_id INTEGER NOT NULL, tag_id INTEGER NOT NULL, PRIMARY KEY (asset_id, tag_id), FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE, FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE );",
+    )?;
+    Ok(())
+}
 
-    println!("[toggle_asset_enabled] Current actual path: {}", current_full_path.display());
-    println!("[toggle_asset_enabled] Target path for rename: {}", target_full_path.display());
+fn migrate_create_search_index(conn: &Connection) -> Result<(), AppError> {
+    // A missing FTS5 module is not fatal, search falls back to LIKE matching
+    setup_asset_search_index(conn);
+    Ok(())
+}
 
-    // Perform the rename
-    fs::rename(&current_full_path, &target_full_path)
-        .map_err(|e| format!("Failed to rename '{}' to '{}': {}", current_full_path.display(), target_full_path.display(), e))?;
+fn get_schema_version(conn: &Connection) -> Result<i64, AppError> {
+    Ok(get_setting_value(conn, SETTINGS_KEY_SCHEMA_VERSION)?
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(0))
+}
 
-    println!("[toggle_asset_enabled] Renamed successfully. New logical state should be: {}", new_enabled_state);
+// Applies pending migrations in order, each in its own transaction together with the
+// version bump, so a failed step leaves the database at the last completed version.
+fn run_migrations(conn: &mut Connection) -> Result<(), AppError> {
+    let current_version = get_schema_version(conn)?;
+    let latest_version = MIGRATIONS.last().map(|(version, _, _)| *version).unwrap_or(0);
+    if current_version > latest_version {
+        eprintln!("WARNING: Database schema version {} is newer than this build supports ({}). Skipping migrations.", current_version, latest_version);
+        return Ok(());
+    }
 
-    // Return the actual NEW state after the rename
-    Ok(new_enabled_state)
+    // Table rebuilds need foreign keys off, and the pragma is ignored inside a transaction
+    conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+    let mut migration_result = Ok(());
+    for (version, description, migrate) in MIGRATIONS.iter().filter(|(version, _, _)| *version > current_version) {
+        println!("Applying schema migration {}: {}", version, description);
+        let step_result = (|| -> Result<(), AppError> {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            migrate(&tx)?;
+            tx.execute(
+                "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+                params![SETTINGS_KEY_SCHEMA_VERSION, version.to_string()],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })();
+        if let Err(e) = step_result {
+            eprintln!("ERROR: Schema migration {} ({}) failed: {}", version, description, e);
+            migration_result = Err(e);
+            break;
+        }
+    }
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    migration_result
 }
 
+// --- Database Initialization (Result type uses AppError internally) ---
+fn initialize_database(app_handle: &AppHandle, active_game_slug: &str) -> Result<DbPool, AppError> {
+    let data_dir = get_app_data_dir(app_handle)?;
+    let db_path = data_dir.join(ACTIVE_DB_FILENAME);
+    println!("Initializing database for game '{}' at: {}", active_game_slug, db_path.display());
+    let needs_schema_setup = !db_path.exists();
 
-#[command]
-fn get_asset_image_path(
-    asset_id: i64,
-    db_state: State<DbState>
-) -> CmdResult<String> {
-    // --- Data needed from DB ---
-    let base_mods_path_str: String;
-    let clean_relative_path_str: String;
-    let image_filename: String;
-
-    // --- Acquire lock *only* for DB reads ---
-    { // Scope for the MutexGuard
-        println!("[get_asset_image_path ID: {}] Acquiring DB lock...", asset_id);
-        let conn_guard = db_state.0.lock().map_err(|_| format!("[get_asset_image_path ID: {}] DB lock poisoned", asset_id))?;
-        let conn = &*conn_guard;
+    let mut conn = Connection::open(&db_path)?;
+    conn.execute("PRAGMA foreign_keys = ON;", [])?;
+    // Journal mode is persistent, so setting it once here covers every pooled connection
+    let journal_mode: String = conn.query_row("PRAGMA journal_mode = WAL;", [], |row| row.get(0))?;
+    println!("Database journal mode: {}", journal_mode);
 
-        // 1. Get base mods path from settings
-        base_mods_path_str = get_setting_value(conn, SETTINGS_KEY_MODS_FOLDER)
-            .map_err(|e| format!("[get_asset_image_path ID: {}] DB Error getting base path: {}", asset_id, e))?
-            .ok_or_else(|| format!("[get_asset_image_path ID: {}] Mods folder path not set", asset_id))?;
+    if needs_schema_setup {
+        println!("Performing initial schema setup for {}", db_path.display());
+        // --- Create Tables (Same as before) ---
+        conn.execute_batch(
+            "BEGIN;
+             CREATE TABLE categories ( id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT UNIQUE NOT NULL, slug TEXT UNIQUE NOT NULL );
+             CREATE TABLE entities ( id INTEGER PRIMARY KEY AUTOINCREMENT, category_id INTEGER NOT NULL, name TEXT NOT NULL, slug TEXT UNIQUE NOT NULL, description TEXT, details TEXT, base_image TEXT, FOREIGN KEY (category_id) REFERENCES categories (id) ON DELETE CASCADE );
+             CREATE TABLE assets ( id INTEGER PRIMARY KEY AUTOINCREMENT, entity_id INTEGER NOT NULL, name TEXT NOT NULL, description TEXT, folder_name TEXT NOT NULL UNIQUE, image_filename TEXT, author TEXT, category_tag TEXT, FOREIGN KEY (entity_id) REFERENCES entities (id) ON DELETE CASCADE );
+             CREATE TABLE settings ( key TEXT PRIMARY KEY NOT NULL, value TEXT NOT NULL );
+             CREATE TABLE presets ( id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT UNIQUE NOT NULL, is_favorite INTEGER NOT NULL DEFAULT 0 );
+             CREATE TABLE preset_assets ( preset_id INTEGER NOT NULL, asset_id INTEGER NOT NULL, is_enabled INTEGER NOT NULL, PRIMARY KEY (preset_id, asset_id), FOREIGN KEY (preset_id) REFERENCES presets(id) ON DELETE CASCADE, FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE );
+             COMMIT;",
+        )?;
+        println!("Database tables created for {}.", db_path.display());
+        println!("Storing internal game slug '{}' in the new database.", active_game_slug);
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+            params![DB_INTERNAL_GAME_SLUG_KEY, active_game_slug],
+        )?;
 
-        // 2. Fetch asset info (clean path and image filename) using asset_id
-        let (fetched_path, fetched_image_opt): (String, Option<String>) = conn.query_row(
-            "SELECT folder_name, image_filename FROM assets WHERE id = ?1",
-            params![asset_id],
-            |row| Ok((row.get(0)?, row.get(1)?))
-        ).map_err(|e| match e {
-            rusqlite::Error::QueryReturnedNoRows => format!("[get_asset_image_path ID: {}] Asset not found.", asset_id),
-            _ => format!("[get_asset_image_path ID: {}] DB Error getting asset info: {}", asset_id, e),
-        })?;
+    } else {
+        println!("Database file {} already exists.", db_path.display());
+        // Optional: Verify internal slug matches expected active_game_slug?
+        match get_internal_db_slug(&db_path) {
+            Ok(Some(internal_slug)) if internal_slug != active_game_slug => {
+                 eprintln!("WARNING: Existing database {} contains slug '{}' but expected '{}'. Check startup logic.", db_path.display(), internal_slug, active_game_slug);
+                 // We proceed, assuming the startup logic handled the rename correctly.
+            },
+            Err(e) => eprintln!("Warning: Could not read internal slug from existing DB {}: {}", db_path.display(), e),
+            _ => {} // Slug matches or doesn't exist (old DB?)
+        }
+    }
 
-        clean_relative_path_str = fetched_path.replace("\\", "/"); // Normalize path separators immediately
-        image_filename = match fetched_image_opt {
-             Some(name) if !name.is_empty() => name,
-             _ => {
-                 // If no image filename in DB, we can stop early. Release lock implicitly.
-                 return Err(format!("[get_asset_image_path ID: {}] Asset does not have an associated image filename.", asset_id));
-             }
-        };
+    // --- Schema Upgrades (apply to new and existing databases) ---
+    run_migrations(&mut conn)?;
 
-        println!("[get_asset_image_path ID: {}] Releasing DB lock.", asset_id);
-        // MutexGuard `conn_guard` is dropped here, releasing the lock
+    // --- Load Definitions & Populate DB ---
+    let definitions = load_definitions(app_handle, active_game_slug);
+    if !definitions.is_empty() {
+        populate_definitions(&conn, &definitions)?;
+        println!("Populated database with definitions for '{}'.", active_game_slug);
+    } else {
+        println!("Skipping definition population as no definitions were loaded for '{}'.", active_game_slug);
     }
-    // --- Lock is released ---
+    // --- End Definition Population ---
+    drop(conn);
 
-    // --- Filesystem operations (No DB lock needed) ---
-    println!("[get_asset_image_path ID: {}] Performing filesystem checks...", asset_id);
-    let base_mods_path = PathBuf::from(base_mods_path_str);
-    let clean_relative_path_buf = PathBuf::from(&clean_relative_path_str); // Already normalized
+    let manager = SqliteConnectionManager::file(&db_path)
+        .with_init(|c| c.execute_batch("PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000;"));
+    let pool = r2d2::Pool::builder().max_size(DB_POOL_MAX_SIZE).build(manager)?;
+    Ok(pool)
+}
 
-    // 3. Determine current folder path (enabled or disabled)
-    let mod_folder_filename_osstr = clean_relative_path_buf.file_name()
-        .ok_or_else(|| format!("[get_asset_image_path ID: {}] Cannot get folder filename from '{}'", asset_id, clean_relative_path_str))?;
-    let mod_folder_filename_str = mod_folder_filename_osstr.to_string_lossy();
-    let disabled_mod_folder_filename = format!("{}{}", DISABLED_PREFIX, mod_folder_filename_str);
-    let relative_parent_path = clean_relative_path_buf.parent();
+// Bundled base definitions (definitions/<game>.toml resource); empty if missing or invalid
+fn load_definitions(app_handle: &AppHandle, active_game_slug: &str) -> Definitions {
+    let definition_resource_path = format!("definitions/{}.toml", active_game_slug);
+    println!("Attempting to load definitions from resource: {}", definition_resource_path);
 
-    let full_path_if_enabled = base_mods_path.join(&clean_relative_path_buf);
-    let full_path_if_disabled = match relative_parent_path {
-        Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_mod_folder_filename),
-        _ => base_mods_path.join(&disabled_mod_folder_filename),
+    let definitions: Definitions = match app_handle.path_resolver().resolve_resource(&definition_resource_path) {
+        Some(path) => {
+            println!("Found definition file at: {}", path.display());
+            match fs::read_to_string(&path) {
+                Ok(toml_content) => {
+                    match toml::from_str(&toml_content) {
+                        Ok(defs) => {
+                            println!("Successfully parsed definitions for '{}'.", active_game_slug);
+                            defs
+                        },
+                        Err(e) => {
+                            eprintln!("ERROR: Failed to parse TOML from {}: {}. Using empty definitions.", path.display(), e);
+                            HashMap::new() // Use empty definitions on parse error
+                        }
+                    }
+                },
+                Err(e) => {
+                    eprintln!("ERROR: Failed to read definition file {}: {}. Using empty definitions.", path.display(), e);
+                    HashMap::new() // Use empty definitions on read error
+                }
+            }
+        },
+        None => {
+            eprintln!("ERROR: Definition file resource '{}' not found. Using empty definitions.", definition_resource_path);
+            HashMap::new() // Use empty definitions if file not found
+        }
     };
 
-    // Use is_dir which might be slightly faster than exists() if we only care about directories
-    let current_mod_folder_path = if full_path_if_enabled.is_dir() {
-        println!("[get_asset_image_path ID: {}] Found enabled path: {}", asset_id, full_path_if_enabled.display());
-        full_path_if_enabled
-    } else if full_path_if_disabled.is_dir() {
-        println!("[get_asset_image_path ID: {}] Found disabled path: {}", asset_id, full_path_if_disabled.display());
-        full_path_if_disabled
-    } else {
-        // Folder not found. This isn't necessarily an error for *this* function,
-        // but we can't construct the image path. Return an error.
-        println!("[get_asset_image_path ID: {}] Mod folder not found on disk.", asset_id);
-        return Err(format!("Mod folder for asset ID {} not found on disk (Checked '{}' and '{}').", asset_id, full_path_if_enabled.display(), full_path_if_disabled.display()));
-    };
+    println!("Loaded {} categories from definitions for '{}'.", definitions.len(), active_game_slug);
+    definitions
+}
 
-    // 4. Construct the FULL path to the image file within the found folder
-    let image_full_path = current_mod_folder_path.join(&image_filename);
-    println!("[get_asset_image_path ID: {}] Checking image file: {}", asset_id, image_full_path.display());
+#[derive(Serialize, Debug, Clone, Default)]
+struct DefinitionSyncResult {
+    added_categories: Vec<String>, // Slugs
+    added_entities: Vec<String>, // Slugs, including new "-other" entities
+}
 
-    // 5. Check if the image file *itself* exists
-    if !image_full_path.is_file() {
-        println!("[get_asset_image_path ID: {}] Image file does not exist.", asset_id);
-        return Err(format!("Image file '{}' not found in mod folder '{}'.", image_filename, current_mod_folder_path.display()));
-    }
+// Inserts the categories and entities of `definitions` that are missing. Existing rows, custom
+// entities and assets are left alone.
+fn populate_definitions(conn: &Connection, definitions: &Definitions) -> SqlResult<DefinitionSyncResult> {
+    let mut result = DefinitionSyncResult::default();
+    for (category_slug, category_def) in definitions.iter() {
+        if conn.execute("INSERT OR IGNORE INTO categories (name, slug) VALUES (?1, ?2)", params![category_def.name, category_slug])? > 0 {
+            result.added_categories.push(category_slug.clone());
+        }
+        let category_id: i64 = conn.query_row("SELECT id FROM categories WHERE slug = ?1", params![category_slug], |row| row.get(0))?;
 
-    // Return the absolute path string for the frontend
-    println!("[get_asset_image_path ID: {}] Success, returning path: {}", asset_id, image_full_path.display());
-    Ok(image_full_path.to_string_lossy().into_owned())
+        if insert_other_entity(conn, category_id, category_slug)? > 0 {
+            result.added_entities.push(format!("{}{}", category_slug, OTHER_ENTITY_SUFFIX));
+        }
+
+        for entity_def in category_def.entities.iter() {
+            let inserted = conn.execute(
+                "INSERT OR IGNORE INTO entities (category_id, name, slug, description, details, base_image) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![category_id, entity_def.name, entity_def.slug, entity_def.description, entity_def.details.as_ref().map(|s| s.to_string()).unwrap_or("{}".to_string()), entity_def.base_image],
+            )?;
+            if inserted > 0 {
+                result.added_entities.push(entity_def.slug.clone());
+            }
+        }
+    }
+    Ok(result)
 }
 
+// Pulls categories/entities added to the bundled definitions (e.g. by an app update) into the
+// active database without restarting
 #[command]
-fn open_mods_folder(_app_handle: AppHandle, db_state: State<DbState>) -> CmdResult<()> { // Mark app_handle unused
-    let mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
-    println!("Opening mods folder: {}", mods_path.display());
-
-    if !mods_path.exists() || !mods_path.is_dir() { // Check it's a directory
-        eprintln!("Configured mods folder does not exist or is not a directory: {}", mods_path.display());
-        return Err(format!("Configured mods folder does not exist or is not a directory: {}", mods_path.display()));
+fn resync_definitions(db_state: State<DbState>, config_state: State<CachedConfigState>, app_handle: AppHandle) -> CmdResult<DefinitionSyncResult> {
+    let mut conn = db_state.conn()?;
+    let game_slug = match get_setting_value(&conn, DB_INTERNAL_GAME_SLUG_KEY).map_err(|e| e.to_string())? {
+        Some(slug) => slug,
+        None => read_app_config(&app_handle).map_err(|e| e.to_string())?.requested_active_game,
+    };
+    let definitions = load_definitions(&app_handle, &game_slug);
+    if definitions.is_empty() {
+        return Err(format!("No definitions could be loaded for '{}'", game_slug));
     }
 
-    let command_name;
-    let arg; // Variable to hold the single argument string
-
-    // Determine OS-specific command and prepare the argument
-    if cfg!(target_os = "windows") {
-        command_name = "explorer";
-        // Windows explorer doesn't always handle forward slashes well, especially in UNC paths, canonicalize might help sometimes
-        // Or just ensure it's a string representation
-         arg = mods_path.to_string_lossy().to_string();
-    } else if cfg!(target_os = "macos") {
-        command_name = "open";
-         arg = mods_path.to_str().ok_or("Invalid path string for macOS")?.to_string();
-    } else { // Assume Linux/Unix-like
-        command_name = "xdg-open";
-         arg = mods_path.to_str().ok_or("Invalid path string for Linux")?.to_string();
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let result = populate_definitions(&tx, &definitions).map_err(|e| format!("Failed to sync definitions: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit definitions: {}", e))?;
+    if !result.added_categories.is_empty() || !result.added_entities.is_empty() {
+        config_state.invalidate_deduction_maps();
     }
 
-    println!("Executing: {} \"{}\"", command_name, arg); // Log with quotes for clarity
+    println!("[resync_definitions] '{}': added {} categories, {} entities", game_slug, result.added_categories.len(), result.added_entities.len());
+    Ok(result)
+}
 
-    // FIX: Use .args() with a slice containing the single argument
-    match Command::new(command_name).args(&[arg]).spawn() {
-        Ok((_, _child)) => {
-             println!("File explorer command spawned successfully.");
-             Ok(())
-        },
-        Err(e) => {
-             eprintln!("Failed to spawn file explorer command '{}': {}", command_name, e);
-             Err(format!("Failed to open folder using '{}': {}", command_name, e))
-        }
+// Folds the WAL back into the main DB file so it can be renamed or copied on its own
+fn checkpoint_db_wal(conn: &Connection) {
+    if let Err(e) = conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);") {
+        eprintln!("[checkpoint_db_wal] Failed to checkpoint WAL: {}", e);
     }
 }
 
-#[command]
-async fn scan_mods_directory(db_state: State<'_, DbState>, app_handle: AppHandle) -> CmdResult<()> {
-    println!("Starting robust mod directory scan with pruning...");
-    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
-    println!("Scanning base path: {}", base_mods_path.display());
-
-    if !base_mods_path.is_dir() {
-        let err_msg = format!("Mods directory path is not a valid directory: {}", base_mods_path.display());
-        app_handle.emit_all(SCAN_ERROR_EVENT, &err_msg).unwrap_or_else(|e| eprintln!("Failed to emit scan error event: {}", e));
-        return Err(err_msg);
-    }
+// Every category has a "<slug>-other" entity that collects mods deduction can't place
+fn insert_other_entity(conn: &Connection, category_id: i64, category_slug: &str) -> SqlResult<usize> {
+    let other_slug = format!("{}{}", category_slug, OTHER_ENTITY_SUFFIX);
+    conn.execute( "INSERT OR IGNORE INTO entities (category_id, name, slug, description, details, base_image) VALUES (?1, ?2, ?3, ?4, ?5, ?6)", params![ category_id, OTHER_ENTITY_NAME, other_slug, "未分类的资源。", "{}", None::<String> ] )
+}
 
-    // --- Preparation ---
-    let deduction_maps = {
-        let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-        let conn = &*conn_guard;
-        fetch_deduction_maps(conn).map_err(|e| format!("Failed to pre-fetch deduction maps: {}", e))?
-    };
-    println!("[Scan Prep] Deduction maps loaded.");
+// --- Utility Functions ---
+fn get_app_data_dir(app_handle: &AppHandle) -> Result<PathBuf, AppError> { // Internal error type
+    app_handle.path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| AppError::TauriPath("Failed to resolve app data directory".to_string()))
+}
 
-    let db_path = {
-        let data_dir = get_app_data_dir(&app_handle).map_err(|e| e.to_string())?;
-        data_dir.join(DB_NAME)
-    };
-    let db_path_str = db_path.to_string_lossy().to_string();
-    let base_mods_path_clone = base_mods_path.clone();
-    let app_handle_clone = app_handle.clone();
-    let maps_clone = deduction_maps.clone();
+// Helper to get a setting value (Internal error type)
+fn get_setting_value(conn: &Connection, key: &str) -> Result<Option<String>, AppError> { // Internal error type
+    let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
+    let result = stmt.query_row(params![key], |row| row.get(0)).optional()?;
+    Ok(result)
+}
 
-    println!("[Scan Prep] Calculating total potential mod folders...");
-    let potential_mod_folders_for_count: Vec<PathBuf> = WalkDir::new(&base_mods_path)
-        .min_depth(1)
-        .into_iter()
-        .filter_map(|e| e.ok().filter(|entry| entry.file_type().is_dir()))
-        .filter(|e| {
-             // Temporary check for rename condition as well for count (might be slightly inaccurate if rename fails later)
-             let path = e.path();
-             let filename = path.file_name().unwrap_or_default().to_string_lossy();
-             // Check for INI OR if it needs renaming (so it's counted)
-             has_ini_file(&path.to_path_buf()) || (filename.starts_with("DISABLED") && !filename.starts_with(DISABLED_PREFIX))
-         })
-        .map(|e| e.path().to_path_buf())
-        .collect();
-    let total_to_process = potential_mod_folders_for_count.len();
-    println!("[Scan Prep] Found {} potential mod folders for progress total (includes folders needing rename).", total_to_process);
+fn is_safe_mode(conn: &Connection) -> bool {
+    matches!(get_setting_value(conn, SETTINGS_KEY_SAFE_MODE), Ok(Some(value)) if value == "true")
+}
 
-    app_handle.emit_all(SCAN_PROGRESS_EVENT, ScanProgress {
-            processed: 0, total: total_to_process, current_path: None, message: "Starting scan...".to_string()
-        }).unwrap_or_else(|e| eprintln!("Failed to emit initial scan progress: {}", e));
+fn is_ini_backup_on_toggle(conn: &Connection) -> bool {
+    matches!(get_setting_value(conn, SETTINGS_KEY_INI_BACKUP_ON_TOGGLE), Ok(Some(value)) if value == "true")
+}
 
+// Hashing during scans stays on unless explicitly turned off
+fn is_scan_hashing_enabled(conn: &Connection) -> bool {
+    !matches!(get_setting_value(conn, SETTINGS_KEY_SCAN_HASHING), Ok(Some(value)) if value == "false")
+}
 
-    // --- Process folders and collect FOUND asset IDs in a blocking task ---
-    let scan_task = async_runtime::spawn_blocking(move || {
-        // Open a new connection inside the blocking task
-        let conn = Connection::open(&db_path_str).map_err(|e| format!("Failed to open DB connection in scan task: {}", e))?;
+// Guard for commands that mutate the mods folder
+fn ensure_not_safe_mode(conn: &Connection) -> Result<(), AppError> {
+    if is_safe_mode(conn) {
+        return Err(AppError::ModOperation("safe mode active".to_string()));
+    }
+    Ok(())
+}
 
-        // --- Fetch ALL asset IDs and their CLEAN relative paths from DB first ---
-        let mut initial_db_assets = HashMap::<i64, String>::new(); // asset_id -> clean_relative_path
-        { // Scope for the statement
-            let mut stmt = conn.prepare("SELECT id, folder_name FROM assets")
-                .map_err(|e| format!("Failed to prepare asset fetch statement: {}", e))?;
-            let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)));
-             let row_iter = rows.map_err(|e| format!("Error creating asset query iterator: {}", e))?;
-            for row_result in row_iter {
-                 match row_result {
-                     Ok((id, folder_name)) => {
-                         initial_db_assets.insert(id, folder_name.replace("\\", "/"));
-                     }
-                     Err(e) => {
-                          eprintln!("[Scan Task Prep] Error fetching asset row from DB: {}", e);
-                     }
-                 }
-            }
-        }
-        println!("[Scan Task Prep] Fetched {} assets from DB initially.", initial_db_assets.len());
+// Helper to get the configured mods base path (Internal error type)
+fn get_mods_base_path_from_settings(db_state: &DbState) -> Result<PathBuf, AppError> { // Internal error type
+    let conn = db_state.0.get()?;
+    get_setting_value(&conn, SETTINGS_KEY_MODS_FOLDER)?
+        .map(PathBuf::from)
+        .ok_or_else(|| AppError::Config("Mods folder path not set".to_string()))
+}
 
-        let mut processed_count = 0; // Counts folders *identified* as mods and processed
-        let mut mods_added_count = 0;
-        let mut mods_updated_count = 0;
-        let mut errors_count = 0;
-        let mut processed_mod_paths = HashSet::new(); // Track processed paths to avoid duplicates if structure is odd
-        let mut found_asset_ids = HashSet::<i64>::new(); // Track IDs found on disk
-        let mut renamed_count = 0; // Count renamed folders
+// Cached variant of get_mods_base_path_from_settings for frequently called commands
+fn cached_mods_base_path(config_state: &CachedConfigState, db_state: &DbState) -> Result<PathBuf, AppError> {
+    if let Some(path) = config_state.0.lock().map_err(|_| AppError::Config("Config lock poisoned".into()))?.mods_base_path.clone() {
+        return Ok(path);
+    }
+    let path = get_mods_base_path_from_settings(db_state)?;
+    config_state.0.lock().map_err(|_| AppError::Config("Config lock poisoned".into()))?.mods_base_path = Some(path.clone());
+    Ok(path)
+}
 
-        // --- Iterate using WalkDir ---
-        let mut walker = WalkDir::new(&base_mods_path_clone).min_depth(1).into_iter();
+// Deduction maps from the cache, built from `conn` on first use after an invalidation
+fn cached_deduction_maps(config_state: &CachedConfigState, conn: &Connection) -> Result<DeductionMaps, AppError> {
+    if let Some(maps) = config_state.0.lock().map_err(|_| AppError::Config("Config lock poisoned".into()))?.deduction_maps.clone() {
+        return Ok(maps);
+    }
+    let maps = fetch_deduction_maps(conn)?;
+    config_state.0.lock().map_err(|_| AppError::Config("Config lock poisoned".into()))?.deduction_maps = Some(maps.clone());
+    Ok(maps)
+}
 
-        while let Some(entry_result) = walker.next() {
-            match entry_result {
-                Ok(entry) => {
-                    // Use mutable path as it might be changed by rename logic
-                    let mut current_path = entry.path().to_path_buf();
-                    let is_directory = entry.file_type().is_dir(); // Check type once
+// Helper to get entity mods path using settings (Internal error type)
+// FIX: Removed unused app_handle parameter
+fn get_entity_mods_path(db_state: &DbState, entity_slug: &str) -> Result<PathBuf, AppError> {
+    let base_path = get_mods_base_path_from_settings(db_state)?;
+    Ok(base_path.join(entity_slug))
+}
 
-                    if is_directory && !processed_mod_paths.contains(&current_path) {
-                        // --- START: Check for DISABLED without underscore and rename ---
-                        let filename_osstr = current_path.file_name().unwrap_or_default();
-                        let filename_str = filename_osstr.to_string_lossy();
+// --- Tauri Commands (Return CmdResult<T> = Result<T, String>) ---
 
-                        let needs_rename = filename_str.starts_with("DISABLED") && !filename_str.starts_with(DISABLED_PREFIX);
-                        let mut current_path_for_processing = current_path.clone(); // Path to use for has_ini and processing
+// == Settings Commands ==
 
-                        if needs_rename {
-                            let new_filename = format!("{}{}", DISABLED_PREFIX, filename_str.strip_prefix("DISABLED").unwrap_or(&filename_str));
-                            if let Some(parent_path) = current_path.parent() {
-                                let new_path = parent_path.join(&new_filename);
-                                println!("[Scan Task - Rename] Found incorrect prefix: '{}'. Renaming to '{}'", current_path.display(), new_path.display());
+#[command]
+fn get_setting(key: String, db_state: State<DbState>) -> CmdResult<Option<String>> {
+    let conn = db_state.conn()?;
+    get_setting_value(&conn, &key).map_err(|e| e.to_string()) // Convert internal error to string
+}
 
-                                // Emit progress before rename attempt
-                                app_handle_clone.emit_all(SCAN_PROGRESS_EVENT, ScanProgress {
-                                     processed: processed_count, // Don't increment processed count for rename yet
-                                     total: total_to_process,
-                                     current_path: Some(current_path.display().to_string()),
-                                     message: format!("Renaming: {}", filename_str)
-                                }).unwrap_or_else(|e| eprintln!("Failed to emit rename progress: {}", e));
-
-                                match fs::rename(&current_path, &new_path) {
-                                    Ok(_) => {
-                                        println!("[Scan Task - Rename] Successfully renamed.");
-                                        current_path_for_processing = new_path; // Use the NEW path for further processing
-                                        renamed_count += 1;
-                                    }
-                                    Err(e) => {
-                                        eprintln!("[Scan Task - Rename] ERROR: Failed to rename folder '{}': {}. Skipping folder.", current_path.display(), e);
-                                        errors_count += 1;
-                                        // Don't process this folder if rename failed
-                                        walker.skip_current_dir(); // Skip children as well
-                                        continue; // Move to the next entry in WalkDir
-                                    }
-                                }
-                            } else {
-                                eprintln!("[Scan Task - Rename] ERROR: Cannot get parent path for '{}'. Skipping rename and folder.", current_path.display());
-                                errors_count += 1;
-                                walker.skip_current_dir(); // Skip children
-                                continue; // Move to the next entry
-                            }
-                        }
-                        // --- END: Rename Check ---
-
-                        // Now check if the (potentially renamed) folder has an INI file
-                        if has_ini_file(&current_path_for_processing) {
-                            // This is a mod folder (or was successfully renamed to be treated as one)
-                            processed_count += 1; // Increment processed count *here*
-                            processed_mod_paths.insert(current_path_for_processing.clone()); // Add the path we actually processed
-                            let path_display = current_path_for_processing.display().to_string();
-                            let folder_name_only = current_path_for_processing.file_name().unwrap_or_default().to_string_lossy();
-
-                            // Emit progress for actual mod processing
-                            app_handle_clone.emit_all(SCAN_PROGRESS_EVENT, ScanProgress {
-                                processed: processed_count,
-                                total: total_to_process,
-                                current_path: Some(path_display.clone()),
-                                message: format!("Processing: {}", folder_name_only)
-                            }).unwrap_or_else(|e| eprintln!("Failed to emit scan progress: {}", e));
-
-                            // --- Start Original Deduction/DB Logic (using current_path_for_processing) ---
-                            match deduce_mod_info_v2(&current_path_for_processing, &base_mods_path_clone, &maps_clone) {
-                                Some(deduced) => {
-                                    println!("[Scan Task] Deduced slug for '{}': {}", path_display, deduced.entity_slug);
-                                    let target_entity_id_result: Option<i64> = maps_clone.entity_slug_to_id.get(&deduced.entity_slug).copied();
-
-                                    if let Some(target_entity_id) = target_entity_id_result {
-                                        println!("[Scan Task] Found entity ID {} for slug '{}'", target_entity_id, deduced.entity_slug);
-
-                                        let relative_path_buf = match current_path_for_processing.strip_prefix(&base_mods_path_clone) {
-                                            Ok(p) => p.to_path_buf(),
-                                            Err(_) => {
-                                                eprintln!("[Scan Task] Error: Could not strip base path prefix from '{}'. Skipping.", path_display);
-                                                errors_count += 1;
-                                                continue; // Skip only this mod folder deduction/DB part
-                                            }
-                                        };
-
-                                        let filename_osstr = relative_path_buf.file_name().unwrap_or_default();
-                                        let filename_str = filename_osstr.to_string_lossy();
-                                        // --- Critical: Ensure stripping the CORRECT prefix after potential rename ---
-                                        let clean_filename = filename_str.strip_prefix(DISABLED_PREFIX).unwrap_or(&filename_str);
-                                        // ---
-                                        let relative_parent_path = relative_path_buf.parent();
-                                        let relative_path_to_store = match relative_parent_path {
-                                            Some(parent) if parent.as_os_str().len() > 0 => parent.join(clean_filename).to_string_lossy().to_string(),
-                                            _ => clean_filename.to_string(),
-                                        };
-                                        let relative_path_to_store = relative_path_to_store.replace("\\", "/");
-                                        println!("[Scan Task] Calculated DB path: '{}'", relative_path_to_store);
-
-                                        let existing_db_asset_id: Option<i64> = conn.query_row(
-                                            "SELECT id FROM assets WHERE entity_id = ?1 AND folder_name = ?2",
-                                            params![target_entity_id, relative_path_to_store],
-                                            |row| row.get(0),
-                                        ).optional().map_err(|e| format!("DB error checking for existing asset '{}': {}", relative_path_to_store, e))?;
-
-                                        if let Some(asset_id) = existing_db_asset_id {
-                                            println!("[Scan Task] Asset already in DB (ID: {}), path '{}'. Marking as found.", asset_id, relative_path_to_store);
-                                            found_asset_ids.insert(asset_id);
-                                            // mods_updated_count += 1; // Optional update logic here
-                                        } else {
-                                            println!("[Scan Task] Inserting new asset: EntityID={}, Name='{}', Path='{}'", target_entity_id, deduced.mod_name, relative_path_to_store);
-                                            let insert_result = conn.execute(
-                                                "INSERT INTO assets (entity_id, name, description, folder_name, image_filename, author, category_tag) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-                                                params![
-                                                    target_entity_id,
-                                                    deduced.mod_name,
-                                                    deduced.description,
-                                                    relative_path_to_store,
-                                                    deduced.image_filename,
-                                                    deduced.author,
-                                                    deduced.mod_type_tag
-                                                ]
-                                            );
-
-                                            match insert_result {
-                                                Ok(changes) => {
-                                                    if changes > 0 {
-                                                        mods_added_count += 1;
-                                                        let new_id = conn.last_insert_rowid();
-                                                        found_asset_ids.insert(new_id);
-                                                        println!("[Scan Task]   -> Insert successful (New ID: {})", new_id);
-                                                    } else {
-                                                        eprintln!("[Scan Task]   -> Insert reported 0 changes for '{}'.", relative_path_to_store);
-                                                        errors_count += 1;
-                                                    }
-                                                }
-                                                Err(e) => {
-                                                    if e.to_string().contains("UNIQUE constraint failed: assets.folder_name") {
-                                                        eprintln!("[Scan Task]   -> Insert failed due to UNIQUE constraint on folder_name '{}'. Asset might exist under a different entity or needs pruning. Skipping insert.", relative_path_to_store);
-                                                        // Maybe don't count as error if pruning will fix it?
-                                                    } else {
-                                                        eprintln!("[Scan Task]   -> DB error inserting new asset '{}': {}", relative_path_to_store, e);
-                                                        errors_count += 1;
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    } else {
-                                        eprintln!("[Scan Task] CRITICAL ERROR: Deduced slug '{}' for path '{}' does NOT exist in the entity map! Skipping mod. Check DB initialization and deduction logic.", deduced.entity_slug, path_display);
-                                        errors_count += 1;
-                                    }
-                                }
-                                None => {
-                                    eprintln!("[Scan Task] Error: Failed to deduce mod info for path '{}'", path_display);
-                                    errors_count += 1;
-                                }
-                            }
-                            // --- End Original Deduction/DB Logic ---
-                            walker.skip_current_dir(); // Skip children after processing a mod folder
-                        }
-                        // If it's a directory but doesn't have an INI (and wasn't renamed+processed),
-                        // we just let WalkDir continue into its children.
-                    }
-                    // If it's not a directory, or already processed, ignore.
-                }
-                Err(e) => {
-                     eprintln!("[Scan Task] Error accessing path during scan: {}", e);
-                     errors_count += 1;
-                }
-            }
+#[command]
+fn set_setting(
+    key: String,
+    value: String,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>,
+    watcher_state: State<ModWatcherState>,
+    app_handle: AppHandle
+) -> CmdResult<()> { // Returns Result<(), String>
+    if key == SETTINGS_KEY_DISABLE_CONVENTION {
+        return Err("Use set_disable_convention to change how disabled folders are named.".to_string());
+    }
+    if key == SETTINGS_KEY_ENABLE_MODE && value != "rename" && value != "symlink" {
+        return Err(format!("Unknown enable mode '{}', expected 'rename' or 'symlink'.", value));
+    }
+    let conn = db_state.conn()?;
+    if key == SETTINGS_KEY_FALLBACK_ENTITY && !value.trim().is_empty() {
+        let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM entities WHERE slug = ?1)", params![value.trim()], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            return Err(format!("Entity '{}' not found.", value.trim()));
         }
-
-        // --- Pruning Logic (Remains the same) ---
-        let mut mods_to_prune_ids = Vec::new();
-        for (asset_id, _clean_path) in initial_db_assets.iter() {
-            if !found_asset_ids.contains(asset_id) {
-                 mods_to_prune_ids.push(*asset_id);
+    }
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+        params![key, value],
+    ).map_err(|e| e.to_string())?; // Convert error
+    // The mods folder and quick launch settings mirror the active profile
+    let profile_column = match key.as_str() {
+        SETTINGS_KEY_MODS_FOLDER => Some("mods_folder_path"),
+        SETTINGS_KEY_QUICK_LAUNCH => Some("quick_launch_path"),
+        _ => None,
+    };
+    if let Some(column) = profile_column {
+        let profile_id = get_active_profile_id(&conn).map_err(|e| e.to_string())?;
+        conn.execute(&format!("UPDATE profiles SET {} = ?1 WHERE id = ?2", column), params![value, profile_id])
+            .map_err(|e| e.to_string())?;
+    }
+    match key.as_str() {
+        SETTINGS_KEY_MODS_FOLDER => {
+            config_state.invalidate_mods_base_path();
+            // Re-point the folder watcher if it is running
+            let mut watcher_guard = watcher_state.0.lock().map_err(|_| "Watcher lock poisoned".to_string())?;
+            if watcher_guard.is_some() {
+                *watcher_guard = watch_mods_folder_if_present(&app_handle, Path::new(&value));
             }
         }
-        let prune_count = mods_to_prune_ids.len();
-        let mut pruned_count = 0;
-        let mut pruning_errors_count = 0;
-
-        if !mods_to_prune_ids.is_empty() {
-            println!("[Scan Task Pruning] Found {} mods in DB missing from disk. Pruning...", prune_count);
-            app_handle_clone.emit_all(PRUNING_START_EVENT, prune_count).ok();
-
-             let ids_to_delete_sql: Vec<Box<dyn rusqlite::ToSql>> = mods_to_prune_ids
-                .into_iter()
-                .map(|id| Box::new(id) as Box<dyn rusqlite::ToSql>)
-                .collect();
+        SETTINGS_KEY_FUZZY_MATCH_MAX_DISTANCE | SETTINGS_KEY_FALLBACK_ENTITY => config_state.invalidate_deduction_maps(),
+        SETTINGS_KEY_PREVIEW_CANDIDATES => CACHED_CONFIG.load_preview_candidates(&conn),
+        SETTINGS_KEY_ENABLE_MODE => CACHED_CONFIG.load_enable_mode(&conn),
+        SETTINGS_KEY_DISK_STAT_CONCURRENCY => load_disk_stat_concurrency(&conn),
+        _ => {}
+    }
+    println!("Set setting '{}' to '{}'", key, value);
+    Ok(())
+}
 
-            if !ids_to_delete_sql.is_empty() {
-                let placeholders = ids_to_delete_sql.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-                let sql = format!("DELETE FROM assets WHERE id IN ({})", placeholders);
+#[command]
+fn get_safe_mode(db_state: State<DbState>) -> CmdResult<bool> {
+    let conn = db_state.conn()?;
+    Ok(is_safe_mode(&conn))
+}
 
-                app_handle_clone.emit_all(PRUNING_PROGRESS_EVENT, format!("Deleting {} entries...", ids_to_delete_sql.len())).ok();
+#[command]
+fn get_disable_convention() -> CmdResult<DisableConvention> {
+    Ok(CACHED_CONFIG.disable_convention())
+}
 
-                let delete_result = conn.execute(&sql, rusqlite::params_from_iter(ids_to_delete_sql))
-                                        .map_err(|e| format!("DB error during pruning: {}", e));
+// Switches the disabled-folder naming convention and renames every disabled mod folder and
+// disabled variant subfolder of every profile to match, so no mod changes state. If a rename fails, the ones already done
+// are moved back and the old convention stays. Returns how many folders were renamed.
+#[command]
+fn set_disable_convention(mode: String, marker: String, db_state: State<DbState>) -> CmdResult<usize> {
+    let new_convention = DisableConvention::new(mode.trim(), &marker)?;
+    let conn = db_state.conn()?;
+    ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+    let old_convention = CACHED_CONFIG.disable_convention();
+    if new_convention == old_convention {
+        return Ok(0);
+    }
+    println!("[set_disable_convention] {:?} -> {:?}", old_convention, new_convention);
+
+    // (profile mods folder, clean relative path) for all profiles
+    let folders: Vec<(String, String)> = {
+        let mut stmt = conn.prepare("SELECT p.mods_folder_path, a.folder_name FROM assets a JOIN profiles p ON a.profile_id = p.id")
+            .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?.replace("\\", "/"))))
+            .map_err(|e| format!("Failed to query assets: {}", e))
+            .and_then(|rows| rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e)))?
+    };
 
-                match delete_result {
-                    Ok(count) => {
-                         pruned_count = count;
-                         println!("[Scan Task Pruning] Successfully pruned {} asset entries.", pruned_count);
-                         app_handle_clone.emit_all(PRUNING_COMPLETE_EVENT, pruned_count).ok();
-                    },
-                    Err(e) => {
-                        eprintln!("[Scan Task Pruning] {}", e);
-                         pruning_errors_count += 1;
-                         app_handle_clone.emit_all(PRUNING_ERROR_EVENT, e).ok();
-                    }
-                }
-            } else {
-                 println!("[Scan Task Pruning] No valid IDs to prune after conversion.");
-                 app_handle_clone.emit_all(PRUNING_COMPLETE_EVENT, 0).ok();
+    // Variant subfolders are renamed before their parent so the recorded paths stay valid for rollback
+    let mut renames: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (mods_folder, clean_relative_path) in folders {
+        let clean_path = Path::new(&mods_folder).join(&clean_relative_path);
+        let clean_name = match clean_path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => continue,
+        };
+        let old_path = clean_path.with_file_name(old_convention.disabled_name(&clean_name));
+        let current_path = if old_path.is_dir() { old_path.clone() } else { clean_path.clone() };
+        if !current_path.is_dir() {
+            continue; // Missing
+        }
+        // Still uses the old convention here, so disabled variants come back with their clean names
+        for variant in find_variant_folders(&current_path) {
+            let old_variant_path = current_path.join(old_convention.disabled_name(&variant));
+            if old_variant_path.is_dir() {
+                renames.push((old_variant_path, current_path.join(new_convention.disabled_name(&variant))));
             }
+        }
+        if current_path == old_path {
+            renames.push((old_path, clean_path.with_file_name(new_convention.disabled_name(&clean_name))));
+        }
+    }
+
+    let mut renamed: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (old_path, new_path) in renames {
+        let result = if new_path.exists() {
+            Err(format!("'{}' already exists", new_path.display()))
         } else {
-             println!("[Scan Task Pruning] No missing mods found. Skipping pruning.");
+            fs::rename(&old_path, &new_path).map_err(|e| e.to_string())
+        };
+        if let Err(e) = result {
+            for (from, to) in renamed.iter().rev() {
+                fs::rename(to, from).ok();
+            }
+            return Err(format!("Failed to rename '{}', the convention was not changed: {}", old_path.display(), e));
         }
-        // --- End Pruning Logic ---
+        renamed.push((old_path, new_path));
+    }
 
-        let total_errors = errors_count + pruning_errors_count;
-        // Return renamed_count as well
-        Ok::<_, String>((processed_count, mods_added_count, mods_updated_count, total_errors, pruned_count, renamed_count))
-    });
+    if let Err(e) = conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+        params![SETTINGS_KEY_DISABLE_CONVENTION, new_convention.to_setting()],
+    ) {
+        for (from, to) in renamed.iter().rev() {
+            fs::rename(to, from).ok();
+        }
+        return Err(format!("Failed to save the convention, folders were renamed back: {}", e));
+    }
+    CACHED_CONFIG.set_disable_convention(new_convention);
 
-    // --- Handle Task Result ---
-     match scan_task.await {
-         Ok(Ok((processed, added, _updated, errors, pruned, renamed))) => { // Add renamed here
-             let rename_msg = if renamed > 0 { format!(" Renamed {} incorrectly prefixed folders.", renamed) } else { "".to_string() };
-             let summary = format!(
-                 "Scan complete. Processed {} mod folders. Added {} new mods. Pruned {} missing mods.{} {} errors occurred.",
-                 processed, added, pruned, rename_msg, errors
-            );
-             println!("{}", summary);
-             app_handle.emit_all(SCAN_COMPLETE_EVENT, summary.clone()).unwrap_or_else(|e| eprintln!("Failed to emit scan complete event: {}", e));
-             Ok(())
-         }
-         Ok(Err(e)) => {
-             eprintln!("Scan task failed internally: {}", e);
-              app_handle.emit_all(SCAN_ERROR_EVENT, e.clone()).unwrap_or_else(|e| eprintln!("Failed to emit scan error event: {}", e));
-             Err(e)
-         }
-         Err(e) => {
-             let err_msg = format!("Scan task panicked or failed to join: {}", e);
-             eprintln!("{}", err_msg);
-             app_handle.emit_all(SCAN_ERROR_EVENT, err_msg.clone()).unwrap_or_else(|e| eprintln!("Failed to emit scan error event: {}", e));
-             Err(err_msg)
-         }
-     }
+    println!("[set_disable_convention] Renamed {} disabled folders", renamed.len());
+    Ok(renamed.len())
 }
 
 #[command]
-fn get_total_asset_count(db_state: State<DbState>) -> CmdResult<i64> {
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    conn.query_row("SELECT COUNT(*) FROM assets", [], |row| row.get(0))
-        .map_err(|e| e.to_string())
+fn create_profile(name: String, mods_folder_path: String, quick_launch_path: Option<String>, db_state: State<DbState>) -> CmdResult<Profile> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name cannot be empty.".to_string());
+    }
+    if !PathBuf::from(&mods_folder_path).is_dir() {
+        return Err(format!("Mods folder does not exist: {}", mods_folder_path));
+    }
+    let conn = db_state.conn()?;
+    conn.execute(
+        "INSERT INTO profiles (name, mods_folder_path, quick_launch_path) VALUES (?1, ?2, ?3)",
+        params![name, mods_folder_path, quick_launch_path],
+    ).map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(_, Some(ref msg)) if msg.contains("UNIQUE") => format!("Profile name '{}' already exists.", name),
+        _ => format!("Failed to create profile: {}", e),
+    })?;
+    let id = conn.last_insert_rowid();
+    println!("[create_profile] Created profile '{}' (ID {})", name, id);
+    Ok(Profile { id, name: name.to_string(), mods_folder_path, quick_launch_path, is_active: false })
 }
 
 #[command]
-fn update_asset_info(
-    asset_id: i64,
-    name: String,
-    description: Option<String>,
-    author: Option<String>,
-    category_tag: Option<String>,
-    selected_image_absolute_path: Option<String>,
-    image_data: Option<Vec<u8>>,
-    new_target_entity_slug: Option<String>,
-    db_state: State<DbState>
-) -> CmdResult<()> { // Returns Result<(), String>
-    println!("[update_asset_info] Start for asset ID: {}. Relocate to: {:?}. Image Data Provided: {}",
-        asset_id, new_target_entity_slug, image_data.is_some());
+fn list_profiles(db_state: State<DbState>) -> CmdResult<Vec<Profile>> {
+    let conn = db_state.conn()?;
+    let active_id = get_active_profile_id(&conn).ok();
+    let mut stmt = conn.prepare("SELECT id, name, mods_folder_path, quick_launch_path FROM profiles ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let profiles = stmt.query_map([], |row| {
+        let id: i64 = row.get(0)?;
+        Ok(Profile { id, name: row.get(1)?, mods_folder_path: row.get(2)?, quick_launch_path: row.get(3)?, is_active: Some(id) == active_id })
+    }).map_err(|e| e.to_string())?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(profiles)
+}
 
-    let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    let conn = &*conn_guard;
+#[command]
+fn switch_profile(
+    profile_id: i64,
+    db_state: State<DbState>,
+    watcher_state: State<ModWatcherState>,
+    app_handle: AppHandle
+) -> CmdResult<Profile> {
+    println!("[switch_profile] Switching to profile ID {}", profile_id);
+    let profile = {
+        let mut conn = db_state.conn()?;
+        let (name, mods_folder_path, quick_launch_path): (String, String, Option<String>) = conn.query_row(
+            "SELECT name, mods_folder_path, quick_launch_path FROM profiles WHERE id = ?1",
+            params![profile_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Profile with ID {} not found.", profile_id),
+            _ => format!("Failed to read profile: {}", e),
+        })?;
 
-    // --- 1. Get Current Asset Location Info ---
-    let current_info = get_asset_location_info(conn, asset_id)
-        .map_err(|e| format!("Failed get current asset info: {}", e))?;
-    println!("[update_asset_info] Current Info: {:?}", current_info);
+        // The existing settings keys keep working for every command, so just point them at this profile
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+        tx.execute("INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)", params![SETTINGS_KEY_ACTIVE_PROFILE, profile_id.to_string()])
+            .map_err(|e| e.to_string())?;
+        tx.execute("INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)", params![SETTINGS_KEY_MODS_FOLDER, mods_folder_path])
+            .map_err(|e| e.to_string())?;
+        tx.execute("INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)", params![SETTINGS_KEY_QUICK_LAUNCH, quick_launch_path.clone().unwrap_or_default()])
+            .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        Profile { id: profile_id, name, mods_folder_path, quick_launch_path, is_active: true }
+    };
+    app_handle.state::<CachedConfigState>().invalidate_mods_base_path();
 
-    // --- 2. Relocation Logic ---
-    let needs_relocation = new_target_entity_slug.is_some() && new_target_entity_slug.as_deref() != Some(&current_info.entity_slug);
-    let mut final_entity_id = current_info.entity_id;
-    let mut final_relative_path_str = current_info.clean_relative_path.clone();
-    let mut final_path_on_disk: Option<PathBuf> = None;
+    // Re-point the folder watcher if it is running
+    let mut watcher_guard = watcher_state.0.lock().map_err(|_| "Watcher lock poisoned".to_string())?;
+    if watcher_guard.is_some() {
+        *watcher_guard = watch_mods_folder_if_present(&app_handle, &PathBuf::from(&profile.mods_folder_path));
+    }
 
-    let base_mods_path = PathBuf::from(
-        get_setting_value(conn, SETTINGS_KEY_MODS_FOLDER)
-           .map_err(|e|e.to_string())?
-           .ok_or_else(|| "Mods folder path not set".to_string())?
-    );
-    println!("[update_asset_info] Base mods path: {}", base_mods_path.display());
+    println!("[switch_profile] Active profile is now '{}'", profile.name);
+    Ok(profile)
+}
 
-    if needs_relocation {
-        // ... (setup for relocation: target_slug, new_entity_id, etc.) ...
-        let target_slug = new_target_entity_slug.as_ref().unwrap();
-        let (new_entity_id, new_category_slug): (i64, String) = conn.query_row(
-            "SELECT e.id, c.slug FROM entities e JOIN categories c ON e.category_id = c.id WHERE e.slug = ?1",
-            params![target_slug],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        ).map_err(|e| format!("DB Error getting new target entity info: {}", e))?;
+// New watcher for a mods folder, or None if the folder is missing or can't be watched
+fn watch_mods_folder_if_present(app_handle: &AppHandle, base_mods_path: &Path) -> Option<RecommendedWatcher> {
+    if !base_mods_path.is_dir() {
+        return None;
+    }
+    start_watching_mods_folder(app_handle, base_mods_path)
+        .map_err(|e| eprintln!("[mod_watcher] Failed to restart mods folder watcher: {}", e))
+        .ok()
+}
 
-        // --- Determine Current Full Path on Disk (Check Enabled/Disabled) ---
-        let current_relative_path_buf = PathBuf::from(&current_info.clean_relative_path);
-        let current_filename_osstr = current_relative_path_buf.file_name().ok_or("Cannot get current filename")?;
-        let current_filename_str = current_filename_osstr.to_string_lossy();
-        let disabled_filename = format!("{}{}", DISABLED_PREFIX, current_filename_str);
-        let relative_parent_path = current_relative_path_buf.parent();
-        let full_path_if_enabled = base_mods_path.join(&current_relative_path_buf);
-        let full_path_if_disabled = match relative_parent_path {
-           Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_filename),
-           _ => base_mods_path.join(&disabled_filename),
-        };
-        let current_full_path = if full_path_if_enabled.is_dir() { full_path_if_enabled }
-            else if full_path_if_disabled.is_dir() { full_path_if_disabled }
-            else { return Err(format!("Cannot relocate: Source folder not found at '{}' or disabled variant.", full_path_if_enabled.display())); };
-        println!("[update_asset_info] Current full path on disk: {}", current_full_path.display());
-
-        // --- Construct New Relative (for DB) and Full (for Disk) Paths ---
-        let mod_base_name = current_filename_str.trim_start_matches(DISABLED_PREFIX);
-        let new_relative_path_buf = PathBuf::new().join(&new_category_slug).join(target_slug).join(mod_base_name);
-        final_relative_path_str = new_relative_path_buf.to_string_lossy().replace("\\", "/"); // For DB
-
-        // Determine the name to use on disk (keep disabled prefix if present)
-        let new_filename_to_use_on_disk = if current_full_path.file_name().map_or(false, |name| name.to_string_lossy().starts_with(DISABLED_PREFIX)) {
-             disabled_filename // Keep disabled prefix
-        } else {
-             mod_base_name.to_string() // Use clean name
-        };
-        let new_full_dest_path_on_disk = base_mods_path.join(&new_category_slug).join(target_slug).join(&new_filename_to_use_on_disk);
-        println!("[update_asset_info] New relative path for DB: {}", final_relative_path_str);
-        println!("[update_asset_info] New full destination path on disk: {}", new_full_dest_path_on_disk.display());
-
-        // --- Create Parent Directory & Perform Move ---
-        if let Some(parent) = new_full_dest_path_on_disk.parent() {
-            fs::create_dir_all(parent).map_err(|e| e.to_string())? // Add map_err
-       } else {
-            return Err("Could not determine parent for new path".into());
-       }
-        if new_full_dest_path_on_disk.exists() { return Err(format!("Cannot relocate: Target path '{}' already exists.", new_full_dest_path_on_disk.display())); }
-        fs::rename(&current_full_path, &new_full_dest_path_on_disk)
-            .map_err(|e| e.to_string())?; // Add map_err
-        // --- END FIX 2 ---
+#[derive(Serialize, Debug, Clone)]
+struct ModsFolderChange {
+    mods_folder_path: String,
+    moved_count: usize,
+    // Clean relative paths of mods not found: in the new folder when validating, in the old one when moving
+    missing: Vec<String>,
+}
 
-        println!("[update_asset_info] Successfully moved mod folder.");
+// Points the active profile at another mods folder. With move_existing the managed mod folders
+// are moved over first (keeping their relative paths, staged mods are staged again in whichever
+// folder ends up active) and the setting
+// only changes once every move succeeded; otherwise the new folder is checked against the stored
+// paths and mods it doesn't contain are reported.
+#[command]
+async fn change_mods_folder(new_path: String, move_existing: bool, app_handle: AppHandle) -> CmdResult<ModsFolderChange> {
+    async_runtime::spawn_blocking(move || mods_folder_change_task(&app_handle, &new_path, move_existing)).await
+        .map_err(|e| format!("Mods folder change task failed: {}", e))?
+}
 
-        final_entity_id = new_entity_id;
-        final_path_on_disk = Some(new_full_dest_path_on_disk);
+fn mods_folder_change_task(app_handle: &AppHandle, new_path: &str, move_existing: bool) -> CmdResult<ModsFolderChange> {
+    let db_state = app_handle.state::<DbState>();
+    let new_base = PathBuf::from(new_path.trim());
+    if new_path.trim().is_empty() || !new_base.is_absolute() {
+        return Err(format!("Invalid mods folder path '{}': expected an absolute path.", new_path));
     }
 
-    // --- 4. Handle Image Saving (Handles Paste > File Path > Existing) ---
-
-    // Determine the mod folder path ON DISK where the image should be saved
-    // This uses the path *after* potential relocation if it happened.
-    let mod_folder_on_disk = if let Some(relocated_path) = final_path_on_disk {
-        relocated_path
-    } else {
-        // If no relocation, determine current path (enabled/disabled) based on current_info
-        let current_relative_path_buf = PathBuf::from(&current_info.clean_relative_path);
-        let current_filename_osstr = current_relative_path_buf.file_name().ok_or("Cannot get current filename")?;
-        let current_filename_str = current_filename_osstr.to_string_lossy();
-        let disabled_filename = format!("{}{}", DISABLED_PREFIX, current_filename_str);
-        let relative_parent_path = current_relative_path_buf.parent();
-        let full_path_if_enabled = base_mods_path.join(&current_relative_path_buf);
-        let full_path_if_disabled = match relative_parent_path {
-            Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_filename),
-            _ => base_mods_path.join(&disabled_filename),
-        };
-        if full_path_if_enabled.is_dir() { full_path_if_enabled }
-        else if full_path_if_disabled.is_dir() { full_path_if_disabled }
-        else { return Err(format!("Mod folder not found on disk at '{}' or disabled variant.", full_path_if_enabled.display())); }
+    let assets: Vec<String> = {
+        let conn = db_state.conn()?;
+        if move_existing {
+            ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+        }
+        let mut stmt = conn.prepare("SELECT folder_name FROM active_assets ORDER BY folder_name")
+            .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok(row.get::<_, String>(0)?.replace("\\", "/")))
+            .map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
     };
-    println!("[update_asset_info] Confirmed mod path on disk for image: {}", mod_folder_on_disk.display());
-
-    // Ensure the target directory exists (it should, but double-check)
-    if !mod_folder_on_disk.is_dir() {
-        // This might happen if the folder got deleted between checks, try creating it.
-        println!("[update_asset_info] Warning: Target mod folder {} does not exist, attempting to create.", mod_folder_on_disk.display());
-        fs::create_dir_all(&mod_folder_on_disk).map_err(|e| e.to_string())?;
-    }
-
-    let mut image_filename_to_save: Option<String> = None; // Default to None
-
-    // --- Priority 1: Handle pasted/provided image data ---
-    if let Some(data) = image_data {
-        println!("[update_asset_info] Handling provided image data ({} bytes)", data.len());
-        let target_image_path = mod_folder_on_disk.join(TARGET_IMAGE_FILENAME);
-        // Use fs::write which creates/truncates the file
-        fs::write(&target_image_path, data)
-            .map_err(|e| format!("Failed to save pasted image data to '{}': {}", target_image_path.display(), e))?;
-        println!("[update_asset_info] Image data written successfully.");
-        image_filename_to_save = Some(TARGET_IMAGE_FILENAME.to_string());
-    }
-    // --- Priority 2: Handle selected file path (only if no data was provided) ---
-    else if let Some(source_path_str) = selected_image_absolute_path {
-        println!("[update_asset_info] Handling selected image file path: {}", source_path_str);
-        let source_path = PathBuf::from(&source_path_str);
-        if !source_path.is_file() { return Err(format!("Selected image file does not exist: {}", source_path.display())); }
-        let target_image_path = mod_folder_on_disk.join(TARGET_IMAGE_FILENAME);
-        fs::copy(&source_path, &target_image_path)
-             .map_err(|e| format!("Failed to copy selected image to '{}': {}", target_image_path.display(), e))?;
-        println!("[update_asset_info] Image file copied successfully.");
-        image_filename_to_save = Some(TARGET_IMAGE_FILENAME.to_string());
-    }
-    // --- Priority 3: No new image provided, fetch existing filename from DB ---
-    else {
-         println!("[update_asset_info] No new image data or path provided. Fetching existing filename.");
-         // Query existing filename. Ok if it doesn't exist (returns None)
-         image_filename_to_save = conn.query_row::<Option<String>, _, _>(
-            "SELECT image_filename FROM assets WHERE id=?1",
-             params![asset_id],
-             |r|r.get(0)
-         ).optional().map_err(|e| format!("DB error fetching existing image name: {}", e))?.flatten(); // flatten Option<Option<String>>
-    }
-    println!("[update_asset_info] Image handling complete. Filename to save in DB: {:?}", image_filename_to_save);
-
-
-    // --- 5. Update Database ---
-    println!("[update_asset_info] Attempting DB update for asset ID {}...", asset_id);
-    let changes = conn.execute(
-        "UPDATE assets SET name = ?1, description = ?2, author = ?3, category_tag = ?4, image_filename = ?5, entity_id = ?6, folder_name = ?7 WHERE id = ?8",
-        params![
-            name, // Use name from arguments
-            description,
-            author,
-            category_tag,
-            image_filename_to_save, // Use the determined filename
-            final_entity_id,        // Use potentially updated entity ID
-            final_relative_path_str, // Use potentially updated relative path (for DB only)
-            asset_id
-        ]
-    ).map_err(|e| format!("Failed update asset info in DB for ID {}: {}", asset_id, e))?;
-
-    println!("[update_asset_info] DB update executed. Changes: {}", changes);
-    if changes == 0 { eprintln!("[update_asset_info] Warning: DB update affected 0 rows for asset ID {}.", asset_id); }
 
-    println!("[update_asset_info] Asset ID {} updated successfully. END", asset_id);
-    Ok(())
-}
+    let mut change = ModsFolderChange { mods_folder_path: new_base.to_string_lossy().to_string(), moved_count: 0, missing: Vec::new() };
+    let watcher_state = app_handle.state::<ModWatcherState>();
+    let mut watcher_guard = watcher_state.0.lock().map_err(|_| "Watcher lock poisoned".to_string())?;
+    let watcher_was_running = watcher_guard.is_some();
 
-#[command]
-fn delete_asset(asset_id: i64, db_state: State<DbState>) -> CmdResult<()> {
-     println!("[delete_asset] Attempting to delete asset ID: {}", asset_id);
+    if move_existing {
+        let old_base = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+        if !old_base.is_dir() {
+            return Err(format!("Current mods folder not found: {}", old_base.display()));
+        }
+        let old_canonical = old_base.canonicalize().map_err(|e| io_error_message("Failed to resolve", &old_base, &e))?;
+        let new_canonical = canonicalize_existing_ancestor(&new_base).map_err(|e| io_error_message("Failed to resolve", &new_base, &e))?;
+        if old_canonical.starts_with(&new_canonical) || new_canonical.starts_with(&old_canonical) {
+            return Err("The new mods folder must not be the current one or contain / be inside it.".to_string());
+        }
+        create_dir_all_long_path(&new_base).map_err(|e| io_error_message("Failed to create mods folder", &new_base, &e))?;
 
-    let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    let conn = &*conn_guard;
-    println!("[delete_asset] DB lock acquired.");
+        // Check every destination before touching anything
+        if let Some(conflict) = assets.iter().find(|clean_relative_path| resolve_asset_disk_state(&new_base, clean_relative_path).is_some()) {
+            return Err(format!("'{}' already exists in the new mods folder. Nothing was moved.", conflict));
+        }
 
-    // --- 1. Get Asset Info ---
-    let asset_info = get_asset_location_info(conn, asset_id)
-        .map_err(|e| format!("Failed to get asset info for deletion: {}", e))?;
-    println!("[delete_asset] Asset info found: {:?}", asset_info);
+        // The old folder's watcher would report every move; it is restarted on whichever folder ends up active
+        *watcher_guard = None;
+        // Parents sort before their children, which then move along with them
+        let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let mut unstaged: Vec<&str> = Vec::new();
+        let mut failure = None;
+        for clean_relative_path in &assets {
+            let was_staged = old_base.join(staged_relative_path(clean_relative_path)).is_dir();
+            if let Err(e) = unstage_asset_folder(&old_base, clean_relative_path) {
+                failure = Some(format!("Failed to unstage '{}': {}", clean_relative_path, e));
+                break;
+            }
+            if was_staged {
+                unstaged.push(clean_relative_path);
+            }
+            let current_relative_path = match resolve_asset_disk_state(&old_base, clean_relative_path) {
+                Some((_, current_relative_path)) => current_relative_path,
+                None => {
+                    if resolve_asset_disk_state(&new_base, clean_relative_path).is_none() {
+                        change.missing.push(clean_relative_path.clone());
+                    }
+                    continue;
+                }
+            };
+            let source = old_base.join(&current_relative_path);
+            let destination = new_base.join(&current_relative_path);
+            if let Some(parent) = destination.parent() {
+                if let Err(e) = create_dir_all_long_path(parent) {
+                    failure = Some(io_error_message("Failed to create", parent, &e));
+                    break;
+                }
+            }
+            if let Err(e) = move_dir(&extended_length_path(&source), &extended_length_path(&destination)) {
+                failure = Some(io_error_message(&format!("Failed to move '{}' to", clean_relative_path), &destination, &e));
+                break;
+            }
+            moved.push((source, destination));
+        }
 
-    // --- 2. Get Base Mods Path ---
-    let base_mods_path_str = get_setting_value(conn, SETTINGS_KEY_MODS_FOLDER)
-        .map_err(|e| format!("Failed to query mods folder setting: {}", e))?
-        .ok_or_else(|| "Mods folder path not set".to_string())?;
-    let base_mods_path = PathBuf::from(base_mods_path_str);
+        if let Some(error) = failure {
+            // Put back what was already moved so the old folder stays complete
+            for (source, destination) in moved.iter().rev() {
+                if let Err(e) = move_dir(&extended_length_path(destination), &extended_length_path(source)) {
+                    eprintln!("[change_mods_folder] Failed to move '{}' back: {}", destination.display(), e);
+                }
+            }
+            restage_asset_folders(&old_base, &unstaged);
+            if watcher_was_running {
+                *watcher_guard = watch_mods_folder_if_present(app_handle, &old_base);
+            }
+            return Err(format!("{} The mods folder was not changed.", error));
+        }
+        restage_asset_folders(&new_base, &unstaged);
+        change.moved_count = moved.len();
+        println!("[change_mods_folder] Moved {} mod folders from {} to {}", moved.len(), old_base.display(), new_base.display());
+    } else {
+        if !new_base.is_dir() {
+            return Err(format!("Mods folder not found: {}", new_base.display()));
+        }
+        change.missing = assets.into_iter()
+            .filter(|clean_relative_path| resolve_asset_disk_state(&new_base, clean_relative_path).is_none())
+            .collect();
+        if !change.missing.is_empty() {
+            println!("[change_mods_folder] {} stored mods not found under {}", change.missing.len(), new_base.display());
+        }
+    }
 
-    // --- 3. Determine Full Path on Disk (Check Enabled/Disabled) ---
-     let relative_path_buf = PathBuf::from(&asset_info.clean_relative_path);
-     let filename_osstr = relative_path_buf.file_name().ok_or_else(|| format!("Could not extract filename from DB path: {}", asset_info.clean_relative_path))?;
-     let filename_str = filename_osstr.to_string_lossy();
-     let disabled_filename = format!("{}{}", DISABLED_PREFIX, filename_str);
-     let relative_parent_path = relative_path_buf.parent();
-
-     let full_path_if_enabled = base_mods_path.join(&relative_path_buf);
-     let full_path_if_disabled = match relative_parent_path {
-        Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_filename),
-        _ => base_mods_path.join(&disabled_filename),
-     };
-
-    let path_to_delete = if full_path_if_enabled.is_dir() {
-        Some(full_path_if_enabled)
-    } else if full_path_if_disabled.is_dir() {
-        Some(full_path_if_disabled)
-    } else {
-         // Folder not found, maybe already deleted? Log a warning but proceed to DB deletion.
-         eprintln!("[delete_asset] Warning: Mod folder not found on disk for asset ID {}. Checked {} and {}. Proceeding with DB deletion.",
-             asset_id, full_path_if_enabled.display(), full_path_if_disabled.display());
-         None
-    };
-
-    // --- 4. Delete Folder from Filesystem ---
-    if let Some(path) = path_to_delete {
-         println!("[delete_asset] Deleting folder: {}", path.display());
-         fs::remove_dir_all(&path)
-            .map_err(|e| format!("Failed to delete mod folder '{}': {}", path.display(), e))?;
-         println!("[delete_asset] Folder deleted successfully.");
+    {
+        let mut conn = db_state.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+        tx.execute("INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)", params![SETTINGS_KEY_MODS_FOLDER, change.mods_folder_path])
+            .map_err(|e| e.to_string())?;
+        let profile_id = get_active_profile_id(&tx).map_err(|e| e.to_string())?;
+        tx.execute("UPDATE profiles SET mods_folder_path = ?1 WHERE id = ?2", params![change.mods_folder_path, profile_id])
+            .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| format!("Failed to commit mods folder change: {}", e))?;
+    }
+    app_handle.state::<CachedConfigState>().invalidate_mods_base_path();
+    if watcher_was_running {
+        *watcher_guard = watch_mods_folder_if_present(app_handle, &new_base);
     }
 
-    // --- 5. Delete from Database ---
-    println!("[delete_asset] Deleting asset ID {} from database.", asset_id);
-    let changes = conn.execute("DELETE FROM assets WHERE id = ?1", params![asset_id])
-        .map_err(|e| format!("Failed to delete asset ID {} from database: {}", asset_id, e))?;
+    println!("[change_mods_folder] Mods folder is now {}", change.mods_folder_path);
+    Ok(change)
+}
 
-     if changes == 0 {
-         // This shouldn't happen if get_asset_location_info succeeded, but good to log.
-         eprintln!("[delete_asset] Warning: Database delete affected 0 rows for asset ID {}.", asset_id);
-     } else {
-         println!("[delete_asset] Database entry deleted successfully.");
-     }
+// Canonical form of a path that may not exist yet: its closest existing ancestor is resolved and
+// the missing components are appended as given
+fn canonicalize_existing_ancestor(path: &Path) -> io::Result<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        missing.push(existing.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no existing parent folder"))?);
+        existing = existing.parent().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no existing parent folder"))?;
+    }
+    let mut canonical = existing.canonicalize()?;
+    canonical.extend(missing.iter().rev());
+    Ok(canonical)
+}
 
-    println!("[delete_asset] Asset ID {} deleted successfully. END", asset_id);
-    Ok(())
+// Puts mods unstaged for a folder move back into staging. Failures leave plain folders, which
+// still work, so they are only logged.
+fn restage_asset_folders(base_mods_path: &Path, clean_relative_paths: &[&str]) {
+    for clean_relative_path in clean_relative_paths {
+        if let Err(e) = restage_asset_folder(base_mods_path, clean_relative_path) {
+            eprintln!("[change_mods_folder] Failed to re-stage '{}' in {}: {}", clean_relative_path, base_mods_path.display(), e);
+        }
+    }
 }
 
 #[command]
-async fn read_binary_file(path: String) -> Result<Vec<u8>, String> {
-    println!("[read_binary_file] Reading path: {}", path);
-    // Keep the original path for potential error reporting
-    let path_for_error = path.clone(); // Clone the path *before* it's moved
+async fn select_directory() -> CmdResult<Option<PathBuf>> { // Removed AppHandle
+    // FIX: Remove AppHandle from new(), use blocking dialog directly
+    let result = dialog::blocking::FileDialogBuilder::new()
+        .set_title("Select Mods Folder")
+        .pick_folder();
 
-    read_binary(PathBuf::from(path)) // 'path' is moved here
-        .map_err(|e| {
-            // Use the cloned path 'path_for_error' in the error message
-            eprintln!("[read_binary_file] Error reading file '{}': {}", path_for_error, e);
-            format!("Failed to read file: {}", e)
-        })
+    match result {
+        Some(path) => Ok(Some(path)),
+        None => Ok(None), // User cancelled
+    }
 }
 
 #[command]
-async fn select_archive_file() -> CmdResult<Option<PathBuf>> {
-    println!("[select_archive_file] Opening file dialog...");
-    let result = dialog::blocking::FileDialogBuilder::new()
-        .set_title("Select Mod Archive")
-        // --- Update Filter ---
-        .add_filter("Archives", &["zip", "7z", "rar"])
-        .add_filter("All Files", &["*"])
+async fn select_file() -> CmdResult<Option<PathBuf>> { // Removed AppHandle
+    // FIX: Use add_filter instead of dialog::Filter struct
+    let result = dialog::blocking::FileDialogBuilder::new() // FIX: Remove AppHandle
+        .set_title("Select Quick Launch Executable")
+        .add_filter("Executable", &["exe", "bat", "cmd", "sh", "app"]) // FIX: Use add_filter
+        .add_filter("All Files", &["*"]) // FIX: Use add_filter
         .pick_file();
 
     match result {
-        Some(path) => {
-            println!("[select_archive_file] File selected: {}", path.display());
-            Ok(Some(path))
-        },
-        None => {
-            println!("[select_archive_file] Dialog cancelled.");
-            Ok(None)
-        }, // User cancelled
+        Some(path) => Ok(Some(path)),
+        None => Ok(None), // User cancelled
     }
 }
 
+#[cfg(target_os = "windows")]
 #[command]
-fn analyze_archive(
-    file_path_str: String,
-    // *** ADDED: Inject DB State ***
-    db_state: State<DbState>
-) -> CmdResult<ArchiveAnalysisResult> {
-    println!("[analyze_archive] Analyzing: {}", file_path_str);
-    let file_path = PathBuf::from(&file_path_str);
-    if !file_path.is_file() { return Err(format!("Archive file not found: {}", file_path.display())); }
+fn launch_executable_elevated(path: String) -> Result<(), String> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::Shell::ShellExecuteW;
+    use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
 
-    let extension = file_path.extension().and_then(|os| os.to_str()).map(|s| s.to_lowercase());
-    println!("[analyze_archive] Detected extension: {:?}", extension);
+    println!("Attempting elevated launch for: {}", path);
 
-    let mut entries = Vec::new();
-    let mut ini_contents: HashMap<String, String> = HashMap::new();
-    let preview_candidates = ["preview.png", "icon.png", "thumbnail.png", "preview.jpg", "icon.jpg", "thumbnail.jpg"];
+    // Convert the path and verb to Windows wide strings (UTF-16)
+    let path_wide: Vec<u16> = std::ffi::OsStr::new(&path)
+        .encode_wide()
+        .chain(std::iter::once(0)) // Null-terminate
+        .collect();
+    let operation_wide: Vec<u16> = std::ffi::OsStr::new("runas") // "runas" verb requests elevation
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
 
-    // --- Fetch Deduction Maps ---
-    let maps = {
-        // Use a block to limit the scope of the lock guard
-        let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-        let conn = &*conn_guard; // Dereference the guard
-        fetch_deduction_maps(conn)
-             .map_err(|e| format!("Analyze: Failed to fetch deduction maps: {}", e))?
+    // Call ShellExecuteW to request elevation
+    // HINSTANCE returned is actually an integer value; > 32 indicates success.
+    let result = unsafe {
+        ShellExecuteW(
+            Some(HWND::default()),               // Pass as Option<HWND>
+            PCWSTR(operation_wide.as_ptr()),     // Operation: "runas"
+            PCWSTR(path_wide.as_ptr()),          // File path
+            None,                                // No parameters
+            None,                                // Default working directory
+            SW_SHOWNORMAL,                       // Show command normal state
+        )
     };
-    println!("[analyze_archive] Deduction maps loaded.");
-    // --- End Fetch ---
 
-    match extension.as_deref() {
-        Some("zip") => {
-            println!("[analyze_archive] Processing as ZIP...");
-            let file = fs::File::open(&file_path)
-                .map_err(|e| format!("Failed to open zip file {}: {}", file_path.display(), e))?;
-            let mut archive = ZipArchive::new(file)
-                .map_err(|e| format!("Failed to read zip archive {}: {}", file_path.display(), e))?;
+    // --- FIX 1: Explicitly cast result.0 to isize BEFORE comparison ---
+    let result_value = result.0 as isize;
 
-            for i in 0..archive.len() {
-                let mut file_entry = archive.by_index(i)
-                     .map_err(|e| format!("Failed to read zip entry #{}: {}", i, e))?;
-                let path_str_opt = file_entry.enclosed_name().map(|p| p.to_string_lossy().replace("\\", "/"));
-                if path_str_opt.is_none() { continue; }
-                // --- FIX: Just clone the String if needed, or use directly ---
-                let path_str = path_str_opt.unwrap().to_string(); // Use to_string() to ensure it's owned String
-                let is_dir = file_entry.is_dir();
+    if result_value > 32 { // Compare the casted value
+        println!("Elevated launch initiated successfully via ShellExecuteW.");
+        Ok(())
+    } else {
+        // result.0 contains the error code as an isize, cast to i32 if needed elsewhere
+        let error_code = result_value as i32; // Cast for error reporting
+        let error_message = format!(
+            "Failed to request elevated launch for '{}'. ShellExecuteW error code: {}",
+            path, error_code
+        );
+        eprintln!("{}", error_message);
 
-                if !is_dir && path_str.to_lowercase().ends_with(".ini") {
-                    let mut content = String::new();
-                    if file_entry.read_to_string(&mut content).is_ok() {
-                        ini_contents.insert(path_str.clone(), content);
-                    }
-                }
-                entries.push(ArchiveEntry { path: path_str, is_dir, is_likely_mod_root: false });
-            }
+        // --- FIX 2: Cast the ERROR_CANCELLED constant to i32 for comparison ---
+        if error_code == windows::Win32::Foundation::ERROR_CANCELLED.0 as i32 {
+             Err("Operation cancelled by user.".to_string())
+        } else {
+             Err(error_message)
         }
-        Some("7z") => {
-            println!("[analyze_archive] Processing as 7z...");
-            // --- FIX: Use Password::empty() ---
-            let mut archive = sevenz_rust::SevenZReader::open(&file_path_str, Password::empty())
-                .map_err(|e| format!("Failed to open/read 7z archive {}: {}", file_path.display(), e))?;
-
-             // --- FIX: Use for_each_entries ---
-             archive.for_each_entries(|entry, reader| {
-                let path_str = entry.name().replace("\\", "/");
-                let is_dir = entry.is_directory();
+    }
+}
 
-                if !is_dir && path_str.to_lowercase().ends_with(".ini") {
-                     let mut content_bytes = Vec::new();
-                     let mut buffer = [0u8; 4096];
-                     loop {
-                        let bytes_read = reader.read(&mut buffer)?;
-                        if bytes_read == 0 { break; }
-                        content_bytes.extend_from_slice(&buffer[..bytes_read]);
-                    }
-                     let content = String::from_utf8_lossy(&content_bytes).to_string();
-                     ini_contents.insert(path_str.clone(), content);
-                }
-                entries.push(ArchiveEntry { path: path_str, is_dir, is_likely_mod_root: false });
-                Ok(true) // Continue processing entries
-             })
-             // --- Map the specific error type from the closure if needed ---
-             .map_err(|e: sevenz_rust::Error| format!("Error iterating 7z entries: {}", e))?;
+// Checks the saved quick-launch path without running it. With `check_d3dx_ini`, also looks for
+// the d3dx.ini that 3DMigoto loaders keep next to the executable.
+#[command]
+fn validate_launch_target(check_d3dx_ini: Option<bool>, db_state: State<DbState>) -> CmdResult<LaunchTargetStatus> {
+    let conn = db_state.conn()?;
+    let path = get_setting_value(&conn, SETTINGS_KEY_QUICK_LAUNCH).map_err(|e| e.to_string())?
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty());
+    drop(conn);
+
+    let mut status = LaunchTargetStatus {
+        path: path.clone(), exists: false, is_file: false, is_executable: false, d3dx_ini_found: None, problems: Vec::new(),
+    };
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            status.problems.push("No launch target is configured.".to_string());
+            return Ok(status);
         }
-        Some("rar") => {
-            println!("[analyze_archive] Processing as RAR...");
-            let mut list_archive = Archive::new(&file_path_str)
-                .open_for_listing()
-                .map_err(|e| e.to_string())?;
-
-            let mut header_infos = Vec::new();
-            // Iterate through headers
-            for entry_result in &mut list_archive { // Keep iterating with &mut
-                match entry_result {
-                    Ok(header) => {
-                        let path_str = header.filename.to_string_lossy().replace("\\", "/").to_string();
-                        let is_dir = header.is_directory();
-                        // --- FIX 1: Clone path_str for the first push ---
-                        header_infos.push((path_str.clone(), is_dir, header.filename.clone()));
-                        // --- End Fix 1 ---
-                        entries.push(ArchiveEntry { path: path_str, is_dir, is_likely_mod_root: false });
-                    }
-                    Err(e) => {
-                        eprintln!("[analyze_archive] Warning: Skipping RAR entry due to header read error: {}", e);
-                        // --- FIX 2: Remove force_heal call ---
-                        // list_archive.force_heal(); // Cannot call this here
-                        // --- End Fix 2 ---
-                        // The loop will continue to the next entry if possible,
-                        // or stop if the error was fatal for the iterator.
-                    }
-                }
-            }
-            // `list_archive` borrow ends here
-
-            // --- Rest of the RAR logic (re-opening for INI reading) remains the same ---
-            let ini_files_to_read: Vec<(String, PathBuf)> = header_infos.iter()
-               .filter(|(path, is_dir, _)| !*is_dir && path.to_lowercase().ends_with(".ini"))
-               .map(|(path, _, original_filename)| (path.clone(), original_filename.clone()))
-               .collect();
+    };
 
-            if !ini_files_to_read.is_empty() {
-               let mut processing_archive = Archive::new(&file_path_str).open_for_processing()
-                    .map_err(|e| e.to_string())?;
-               let mut read_count = 0;
-               loop {
-                   match processing_archive.read_header().map_err(|e| e.to_string())? {
-                       Some(header_state) => {
-                           let current_filename = header_state.entry().filename.clone();
-                           let path_str = current_filename.to_string_lossy().replace("\\", "/").to_string();
-                           if let Some(pos) = ini_files_to_read.iter().position(|(_, fname)| fname == &current_filename) {
-                               match header_state.read() {
-                                   Ok((bytes, next_state)) => {
-                                       ini_contents.insert(path_str, String::from_utf8_lossy(&bytes).to_string());
-                                       processing_archive = next_state;
-                                       read_count += 1;
-                                       if read_count == ini_files_to_read.len() { break; }
-                                   }
-                                   Err(e) => { return Err(format!("Error reading content of RAR INI '{}': {}", path_str, e)); }
-                               }
-                           } else {
-                               processing_archive = header_state.skip().map_err(|e| e.to_string())?;
-                           }
-                       }
-                       None => break,
-                   }
-               }
-            }
-        }
-        _ => {
-            return Err(format!("Unsupported archive type: {:?}", extension));
+    status.exists = path.exists();
+    status.is_file = path.is_file();
+    if !status.exists {
+        status.problems.push(format!("'{}' does not exist.", path.display()));
+    } else if !status.is_file {
+        status.problems.push(format!("'{}' is not a file.", path.display()));
+    } else {
+        status.is_executable = is_executable_file(&path);
+        if !status.is_executable {
+            status.problems.push(format!("'{}' is not an executable.", path.display()));
         }
     }
-    println!("[analyze_archive] Pass 1: Found {} entries. Found {} INI files.", entries.len(), ini_contents.len());
-
-    entries.sort_unstable_by(|a, b| a.path.cmp(&b.path));
 
-    // ... (Pass 2: Find roots) ...
-    let mut likely_root_indices = HashSet::new();
-    for (ini_index, ini_entry) in entries.iter().enumerate() {
-        if !ini_entry.is_dir && ini_entry.path.to_lowercase().ends_with(".ini") {
-            let parent_path_obj = Path::new(&ini_entry.path).parent();
-            if let Some(parent_path_ref) = parent_path_obj {
-                let parent_path_str_norm = parent_path_ref.to_string_lossy().replace("\\", "/");
-                if parent_path_str_norm.is_empty() { continue; }
-                let found_parent = entries.iter().position(|dir_entry| {
-                    if !dir_entry.is_dir { return false; }
-                    let dir_entry_path_norm = dir_entry.path.strip_suffix('/').unwrap_or(&dir_entry.path);
-                    dir_entry_path_norm == parent_path_str_norm
-                });
-                if let Some(parent_index) = found_parent {
-                    likely_root_indices.insert(parent_index);
-                }
-            }
+    if check_d3dx_ini.unwrap_or(false) {
+        let found = path.parent().map_or(false, |dir| dir.join("d3dx.ini").is_file());
+        if !found {
+            status.problems.push("No d3dx.ini found next to the executable.".to_string());
         }
+        status.d3dx_ini_found = Some(found);
     }
-    // ... (Pass 3: Find previews) ...
-     let mut root_to_preview_map: HashMap<usize, String> = HashMap::new();
-     for root_index in likely_root_indices.iter() {
-          if let Some(root_entry) = entries.get(*root_index) {
-              let root_prefix = if root_entry.path.ends_with('/') { root_entry.path.clone() } else { format!("{}/", root_entry.path) };
-              for candidate in preview_candidates.iter() {
-                  let potential_preview_path = format!("{}{}", root_prefix, candidate);
-                  if entries.iter().any(|e| !e.is_dir && e.path.eq_ignore_ascii_case(&potential_preview_path)) {
-                      root_to_preview_map.insert(*root_index, potential_preview_path);
-                      break;
-                  }
-              }
-          }
-     }
-    // ... (Pass 4: Deduction) ...
-    let mut deduced_mod_name: Option<String> = None;
-    let mut deduced_author: Option<String> = None;
-    // Initialize final deduced slugs
-    let mut final_deduced_category_slug: Option<String> = None;
-    let mut final_deduced_entity_slug: Option<String> = None;
-    // Raw hints extracted from INI
-    let mut raw_ini_type_found: Option<String> = None;
-    let mut raw_ini_target_found: Option<String> = None;
-    // Preview path detected within archive
-    let mut detected_preview_internal_path : Option<String> = None;
-    let mut first_likely_root_processed = false;
+    println!("[validate_launch_target] {:?}", status);
+    Ok(status)
+}
 
-    // --- 1. Deduce from INI in First Likely Root ---
-    println!("[analyze_archive] Starting Pass 4: Deduction...");
-    for (index, entry) in entries.iter_mut().enumerate() {
-        if likely_root_indices.contains(&index) {
-            entry.is_likely_mod_root = true; // Mark the entry
-            println!("[analyze_archive] Found likely root: {}", entry.path);
-            if !first_likely_root_processed {
-                first_likely_root_processed = true;
-                let root_prefix = if entry.path.ends_with('/') { entry.path.clone() } else { format!("{}/", entry.path) };
-                // Find the first INI file *directly* inside this root
-                if let Some((_ini_path, ini_content)) = ini_contents.iter().find(|(p, _)| p.starts_with(&root_prefix) && p.trim_start_matches(&root_prefix).find('/') == None) {
-                    println!("[analyze_archive] Found INI in root {}: {}", root_prefix, _ini_path);
-                    if let Ok(ini) = Ini::load_from_str(ini_content) {
-                        // --- Temporary storage for extracted hints ---
-                        let mut extracted_target: Option<String> = None;
-                        let mut extracted_type: Option<String> = None;
-                        // ---
-                        for section_name in ["Mod", "Settings", "Info", "General"] {
-                            if let Some(section) = ini.section(Some(section_name)) {
-                                // Extract Name, Author
-                                let name_val = section.get("Name").or_else(|| section.get("ModName"));
-                                // Use the INI name if found, otherwise keep the initial filename guess
-                                if let Some(name) = name_val {
-                                    let cleaned_ini_name = MOD_NAME_CLEANUP_REGEX.replace_all(name, "").trim().to_string();
-                                    if !cleaned_ini_name.is_empty() {
-                                        deduced_mod_name = Some(cleaned_ini_name);
-                                    }
-                                }
-                                let author_val = section.get("Author");
-                                if author_val.is_some() { deduced_author = author_val.map(String::from); }
+#[cfg(target_os = "windows")]
+fn is_executable_file(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str())
+        .map_or(false, |ext| ["exe", "bat", "cmd", "com"].iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
 
-                                // Extract Raw Hints
-                                let target_val = section.get("Target").or_else(|| section.get("Entity")).or_else(|| section.get("Character"));
-                                if target_val.is_some() { extracted_target = target_val.map(|s| s.trim().to_string()); }
-                                let type_val = section.get("Type").or_else(|| section.get("Category"));
-                                if type_val.is_some() { extracted_type = type_val.map(|s| s.trim().to_string()); }
-                            }
-                        }
-                        // Log extracted hints and assign to outer scope
-                        println!("[analyze_archive] INI Extracted Hints: Target='{:?}', Type='{:?}'", extracted_target, extracted_type);
-                        raw_ini_target_found = extracted_target;
-                        raw_ini_type_found = extracted_type;
-                    } else {
-                        eprintln!("[analyze_archive] Warning: Failed to parse INI content from {}", _ini_path);
-                    }
-                } else {
-                    println!("[analyze_archive] No INI found directly in root: {}", root_prefix);
-                }
+#[cfg(not(target_os = "windows"))]
+fn is_executable_file(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).map_or(false, |metadata| metadata.permissions().mode() & 0o111 != 0)
+}
 
-                // --- Try matching INI Target Hint (USE HELPER) ---
-                if final_deduced_entity_slug.is_none() { // Only run if not already found
-                    if let Some(target_hint) = &raw_ini_target_found {
-                        println!("[analyze_archive] Trying INI target hint matching...");
-                        // Use the reusable helper function
-                        if let Some(slug) = find_entity_slug_from_hint(target_hint, &maps) {
-                            final_deduced_entity_slug = Some(slug);
-                            println!("[analyze_archive]   -> Found entity via INI target hint: '{}' -> {}", target_hint, final_deduced_entity_slug.as_ref().unwrap());
-                        }
-                    } else {
-                        println!("[analyze_archive] No INI target hint found.");
-                    }
-                }
+#[command]
+async fn launch_executable(path: String, _app_handle: AppHandle) -> CmdResult<()> { // app_handle might not be needed now
+    println!("Attempting to launch (non-elevated) via Command::new: {}", path);
 
-                // --- Try matching INI Type Hint (Category) ---
-                if final_deduced_category_slug.is_none() { // Only run if not already found
-                    if let Some(type_hint) = &raw_ini_type_found {
-                        let lower_type_hint = type_hint.to_lowercase();
-                        println!("[analyze_archive] Trying INI type hint: '{}' (lowercase: '{}')", type_hint, lower_type_hint);
+    // FIX: Use Command::new for launching executables
+    let cmd = Command::new(path) // Use the path directly as the command
+        // .args([]) // Add arguments if needed later
+        .spawn(); // Spawn the process
 
-                        // Prio 1: Exact slug
-                        if maps.category_slug_to_id.contains_key(type_hint) {
-                            final_deduced_category_slug = Some(type_hint.clone());
-                            println!("[analyze_archive]   -> Matched category via INI exact slug: {}", type_hint);
-                        }
-                        // Prio 2: Exact lowercase name -> original slug
-                        else if let Some(slug) = maps.lowercase_category_name_to_slug.get(&lower_type_hint) {
-                            final_deduced_category_slug = Some(slug.clone());
-                            println!("[analyze_archive]   -> Matched category via INI exact lowercase name: {} -> {}", lower_type_hint, slug);
-                        }
-                        // Prio 3: Known name starts with hint
-                        else {
-                            for (cat_name_lower, cat_slug) in &maps.lowercase_category_name_to_slug {
-                                if cat_name_lower.starts_with(&lower_type_hint) {
-                                    final_deduced_category_slug = Some(cat_slug.clone());
-                                    println!("[analyze_archive]   -> Matched category via INI name prefix: '{}' starts with '{}' -> {}", cat_name_lower, lower_type_hint, cat_slug);
-                                    break;
-                                }
-                            }
-                        }
-                        // Prio 4: Known name contains hint
-                        if final_deduced_category_slug.is_none() {
-                            for (cat_name_lower, cat_slug) in &maps.lowercase_category_name_to_slug {
-                                if lower_type_hint.len() > 2 && cat_name_lower.contains(&lower_type_hint) {
-                                    final_deduced_category_slug = Some(cat_slug.clone());
-                                    println!("[analyze_archive]   -> Matched category via INI name contains: '{}' contains '{}' -> {}", cat_name_lower, lower_type_hint, cat_slug);
-                                    break;
-                                }
-                            }
-                        }
-                        if final_deduced_category_slug.is_none() {
-                            println!("[analyze_archive]   -> No category match found from INI type hint.");
-                        }
-                    } else {
-                        println!("[analyze_archive] No INI type hint found.");
+    match cmd {
+        Ok((mut rx, _child)) => {
+            // You can optionally read stdout/stderr here if needed
+             while let Some(event) = rx.recv().await {
+                 match event {
+                    tauri::api::process::CommandEvent::Stdout(line) => {
+                        println!("Launcher stdout: {}", line);
+                    }
+                    tauri::api::process::CommandEvent::Stderr(line) => {
+                        eprintln!("Launcher stderr: {}", line);
+                    }
+                    tauri::api::process::CommandEvent::Error(e) => {
+                         eprintln!("Launcher error event: {}", e);
+                         // If we get the elevation error here, we could suggest the elevated launch
+                         if e.contains("os error 740") {
+                             return Err(format!("Failed to launch: The application requires administrator privileges. Try the 'Launch as Admin' button if available, or run GMM as administrator (not recommended). Original error: {}", e));
+                         }
+                         // Decide if other errors constitute a failure
+                         // return Err(format!("Launcher process event error: {}", e));
                     }
+                     tauri::api::process::CommandEvent::Terminated(payload) => {
+                        println!("Launcher terminated: {:?}", payload);
+                        if let Some(code) = payload.code {
+                             if code != 0 {
+                                println!("Launcher exited with non-zero code: {}", code);
+                                // Optionally return error based on exit code
+                                // return Err(format!("Launcher exited with code {}", code));
+                             }
+                         } else {
+                             println!("Launcher terminated without exit code (possibly killed).");
+                         }
+                         // Process terminated, break the loop
+                         break;
+                     }
+                    _ => {} // Ignore other events
                 }
+             }
+             println!("Launcher process finished or detached.");
+             Ok(()) // Assume success if spawn worked and process finished/detached
+        }
+        Err(e) => {
+             eprintln!("Failed to spawn launcher: {}", e);
+             // Check for the specific error here too
+             if e.to_string().contains("os error 740") {
+                 Err(format!("Failed to launch: The application requires administrator privileges. Try running GMM as administrator (not recommended). Error: {}", e))
+             } else {
+                  Err(format!("Failed to spawn executable: {}", e)) // Convert error to string
+             }
+        }
+    }
+}
+
+
+// == Core Commands (Return CmdResult<T>) ==
+
+#[command]
+fn get_categories(db_state: State<DbState>) -> CmdResult<Vec<Category>> {
+    let conn = db_state.conn()?;
+    let mut stmt = conn.prepare("SELECT id, name, slug FROM categories ORDER BY name")
+        .map_err(|e| e.to_string())?; // Convert error
+    let category_iter = stmt.query_map([], |row| {
+        Ok(Category {
+            id: row.get(0)?, name: row.get(1)?, slug: row.get(2)?,
+        })
+    }).map_err(|e| e.to_string())?; // Convert error
+    category_iter.collect::<SqlResult<Vec<Category>>>().map_err(|e| e.to_string()) // Convert error
+}
+
+// Categories with their asset totals for the active profile, from the stored enabled state.
+// Only assets with no stored state are checked on disk, unless `verify_on_disk` re-checks all.
+#[command]
+fn get_categories_with_counts(verify_on_disk: Option<bool>, db_state: State<DbState>, config_state: State<CachedConfigState>) -> CmdResult<Vec<CategoryWithCounts>> {
+    let base_mods_path = cached_mods_base_path(&config_state, &db_state).ok();
+    let conn = db_state.conn()?;
+    if let Some(base_mods_path) = &base_mods_path {
+        match reconcile_asset_enabled_states(&conn, base_mods_path, !verify_on_disk.unwrap_or(false)) {
+            Ok(summary) if summary.updated > 0 => println!("[get_categories_with_counts] Updated enabled state for {} assets", summary.updated),
+            Ok(_) => {}
+            Err(e) => eprintln!("[get_categories_with_counts] Failed to resolve enabled states: {}", e),
+        }
+    }
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name, c.slug, COUNT(a.id), COALESCE(SUM(a.is_enabled = 1), 0)
+         FROM categories c
+         LEFT JOIN entities e ON e.category_id = c.id
+         LEFT JOIN active_assets a ON a.entity_id = e.id
+         GROUP BY c.id
+         ORDER BY c.name"
+    ).map_err(|e| format!("Failed to prepare category count query: {}", e))?;
+    let categories = stmt.query_map([], |row| Ok(CategoryWithCounts {
+        id: row.get(0)?, name: row.get(1)?, slug: row.get(2)?, total_mods: row.get(3)?, enabled_mods: row.get(4)?,
+    })).map_err(|e| format!("Failed to query category counts: {}", e))?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| format!("Failed to read category counts: {}", e))?;
+    Ok(categories)
+}
+
+// Adds a user-defined category (with its "-other" entity), e.g. for games without bundled
+// definitions. The slug is derived from the name like create_entity does.
+#[command]
+fn create_category(name: String, db_state: State<DbState>, config_state: State<CachedConfigState>) -> CmdResult<Category> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Category name cannot be empty".to_string());
+    }
+    let mut conn = db_state.conn()?;
+    let name_taken: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM categories WHERE name = ?1)", params![name], |row| row.get(0))
+        .map_err(|e| format!("Failed to check category name: {}", e))?;
+    if name_taken {
+        return Err(format!("A category named '{}' already exists", name));
+    }
+
+    let base_slug = match slugify_entity_name(&name) {
+        s if s.is_empty() => "category".to_string(),
+        s => s,
+    };
+    let mut slug = base_slug.clone();
+    let mut suffix = 2;
+    loop {
+        // Entity slugs are checked too: "<slug>-other" must be free for the fallback entity
+        let taken: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM categories WHERE slug = ?1) OR EXISTS(SELECT 1 FROM entities WHERE slug = ?1 || ?2)",
+            params![slug, OTHER_ENTITY_SUFFIX], |row| row.get(0),
+        ).map_err(|e| format!("Failed to check category slug: {}", e))?;
+        if !taken && !slug.ends_with(OTHER_ENTITY_SUFFIX) { break; }
+        slug = format!("{}-{}", base_slug, suffix);
+        suffix += 1;
+    }
+
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    tx.execute("INSERT INTO categories (name, slug) VALUES (?1, ?2)", params![name, slug])
+        .map_err(|e| format!("Failed to create category: {}", e))?;
+    let id = tx.last_insert_rowid();
+    insert_other_entity(&tx, id, &slug).map_err(|e| format!("Failed to create '{}{}' entity: {}", slug, OTHER_ENTITY_SUFFIX, e))?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    config_state.invalidate_deduction_maps();
+
+    println!("[create_category] Created category '{}' ({})", name, slug);
+    Ok(Category { id, name, slug })
+}
+
+// Deletes a category and its "-other" entity. Refuses while it has any other entity or any
+// mod (in any profile) still belongs to it.
+#[command]
+fn delete_category(category_slug: String, db_state: State<DbState>, config_state: State<CachedConfigState>) -> CmdResult<()> {
+    let mut conn = db_state.conn()?;
+    let category_id: i64 = conn.query_row("SELECT id FROM categories WHERE slug = ?1", params![category_slug], |row| row.get(0))
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Category '{}' not found", category_slug),
+            _ => e.to_string(),
+        })?;
+
+    let other_slug = format!("{}{}", category_slug, OTHER_ENTITY_SUFFIX);
+    let entity_count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM entities WHERE category_id = ?1 AND slug != ?2",
+        params![category_id, other_slug], |row| row.get(0),
+    ).map_err(|e| format!("Failed to count entities: {}", e))?;
+    if entity_count > 0 {
+        return Err(format!("Category '{}' still has {} entities. Delete them first.", category_slug, entity_count));
+    }
+    // All profiles, not just the active one: their assets would be left without an entity
+    let asset_count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM assets a JOIN entities e ON a.entity_id = e.id WHERE e.category_id = ?1",
+        params![category_id], |row| row.get(0),
+    ).map_err(|e| format!("Failed to count mods: {}", e))?;
+    if asset_count > 0 {
+        return Err(format!("Category '{}' still has {} mods. Move or delete them first.", category_slug, asset_count));
+    }
+
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    tx.execute("DELETE FROM deduction_rules WHERE entity_slug IN (SELECT slug FROM entities WHERE category_id = ?1)", params![category_id])
+        .map_err(|e| format!("Failed to delete deduction rules: {}", e))?;
+    tx.execute("DELETE FROM entities WHERE category_id = ?1", params![category_id])
+        .map_err(|e| format!("Failed to delete '{}' entity: {}", other_slug, e))?;
+    tx.execute("DELETE FROM categories WHERE id = ?1", params![category_id])
+        .map_err(|e| format!("Failed to delete category: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    config_state.invalidate_deduction_maps();
+
+    println!("[delete_category] Deleted category '{}'", category_slug);
+    Ok(())
+}
+
+#[command]
+fn get_category_entities(category_slug: String, db_state: State<DbState>) -> CmdResult<Vec<Entity>> {
+    let conn = db_state.conn()?;
+     let category_id: i64 = conn.query_row(
+        "SELECT id FROM categories WHERE slug = ?1",
+        params![category_slug],
+        |row| row.get(0),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Category '{}' not found", category_slug),
+        _ => e.to_string(),
+    })?;
+
+     // Fetch id, name, slug - ORDER BY to put 'Other' first
+     let mut stmt = conn.prepare(
+        "SELECT id, name, slug
+         FROM entities
+         WHERE category_id = ?1
+         ORDER BY
+            CASE WHEN slug LIKE '%-other' THEN 0 ELSE 1 END ASC,
+            name ASC"
+    ).map_err(|e| e.to_string())?; // Corrected SQL query
+
+    let entity_iter = stmt.query_map(params![category_id], |row| {
+        Ok(Entity {
+            id: row.get(0)?,
+            category_id: category_id,
+            name: row.get(1)?,
+            slug: row.get(2)?,
+            description: None,
+            details: None,
+            base_image: None,
+            mod_count: 0,
+            enabled_mod_count: None,
+            recent_mod_count: None,
+            favorite_mod_count: None,
+        })
+    }).map_err(|e| e.to_string())?;
+    entity_iter.collect::<SqlResult<Vec<Entity>>>().map_err(|e| e.to_string())
+}
+
+#[command]
+fn get_entities_by_category(category_slug: String, db_state: State<DbState>) -> CmdResult<Vec<Entity>> {
+    let conn = db_state.conn()?;
+     let category_id: i64 = conn.query_row(
+        "SELECT id FROM categories WHERE slug = ?1",
+        params![category_slug],
+        |row| row.get(0),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Category '{}' not found", category_slug),
+        _ => e.to_string(),
+    })?;
+
+     // Fetch full entity details - ORDER BY to put 'Other' first
+     let mut stmt = conn.prepare(
+        "SELECT e.id, e.category_id, e.name, e.slug, e.description, e.details, e.base_image, COUNT(a.id) as mod_count
+         FROM entities e LEFT JOIN active_assets a ON e.id = a.entity_id
+         WHERE e.category_id = ?1
+         GROUP BY e.id
+         ORDER BY
+            CASE WHEN e.slug LIKE '%-other' THEN 0 ELSE 1 END ASC,
+            e.name ASC" // Corrected SQL query
+    ).map_err(|e| e.to_string())?;
+
+    let entity_iter = stmt.query_map(params![category_id], |row| {
+        Ok(Entity {
+            id: row.get(0)?, category_id: row.get(1)?, name: row.get(2)?,
+            slug: row.get(3)?, description: row.get(4)?, details: row.get(5)?,
+            base_image: row.get(6)?, mod_count: row.get(7)?,
+            enabled_mod_count: None,
+            recent_mod_count: None,
+            favorite_mod_count: None
+        })
+    }).map_err(|e| e.to_string())?;
+    entity_iter.collect::<SqlResult<Vec<Entity>>>().map_err(|e| e.to_string())
+}
+
+// Lowercase ASCII letters/digits separated by single '-', e.g. "Hu Tao (Alt)" -> "hu-tao-alt"
+fn slugify_entity_name(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+// Adds a user-defined entity (custom character, weapon, ...) to a category.
+// The slug is derived from the name; "-2", "-3", ... is appended if it is already taken.
+#[command]
+fn create_entity(
+    category_slug: String,
+    name: String,
+    description: Option<String>,
+    base_image: Option<String>,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>
+) -> CmdResult<Entity> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Entity name cannot be empty".to_string());
+    }
+    let conn = db_state.conn()?;
+    let category_id: i64 = conn.query_row(
+        "SELECT id FROM categories WHERE slug = ?1",
+        params![category_slug],
+        |row| row.get(0),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Category '{}' not found", category_slug),
+        _ => e.to_string(),
+    })?;
+
+    let base_slug = match slugify_entity_name(&name) {
+        s if s.is_empty() => format!("{}-custom", category_slug),
+        s => s,
+    };
+    let mut slug = base_slug.clone();
+    let mut suffix = 2;
+    loop {
+        let taken: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM entities WHERE slug = ?1)", params![slug], |row| row.get(0))
+            .map_err(|e| format!("Failed to check entity slug: {}", e))?;
+        // Never hand out a slug that looks like a category's fallback entity
+        if !taken && !slug.ends_with(OTHER_ENTITY_SUFFIX) { break; }
+        slug = format!("{}-{}", base_slug, suffix);
+        suffix += 1;
+    }
+
+    let description = description.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+    let base_image = base_image.map(|b| b.trim().to_string()).filter(|b| !b.is_empty());
+    conn.execute(
+        "INSERT INTO entities (category_id, name, slug, description, details, base_image) VALUES (?1, ?2, ?3, ?4, '{}', ?5)",
+        params![category_id, name, slug, description, base_image],
+    ).map_err(|e| format!("Failed to create entity: {}", e))?;
+    let id = conn.last_insert_rowid();
+    // The next scan/import must be able to deduce the new entity
+    config_state.invalidate_deduction_maps();
+
+    println!("[create_entity] Created entity '{}' ({}) in category '{}'", name, slug, category_slug);
+    Ok(Entity {
+        id, category_id, name, slug, description,
+        details: Some("{}".to_string()),
+        base_image,
+        mod_count: 0,
+        enabled_mod_count: None,
+        recent_mod_count: None,
+        favorite_mod_count: None,
+    })
+}
+
+// Deletes an entity. If mods still reference it, this fails unless `relocate_to_other` is set,
+// in which case the active profile's mods are moved to the category's "-other" entity first.
+// Mods of other profiles are never moved (their folders may not exist on disk), so they block deletion.
+#[command]
+fn delete_entity(
+    entity_slug: String,
+    relocate_to_other: Option<bool>,
+    force: Option<bool>,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>,
+    op_log: State<OperationLogState>
+) -> CmdResult<()> {
+    if entity_slug.ends_with(OTHER_ENTITY_SUFFIX) {
+        return Err(format!("'{}' is a category fallback entity and cannot be deleted.", entity_slug));
+    }
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    let mut conn_guard = db_state.conn()?;
+    let (entity_id, category_slug): (i64, String) = conn_guard.query_row(
+        "SELECT e.id, c.slug FROM entities e JOIN categories c ON e.category_id = c.id WHERE e.slug = ?1",
+        params![entity_slug],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Entity '{}' not found.", entity_slug),
+        _ => format!("DB Error getting entity: {}", e)
+    })?;
+
+    let (total_assets, active_asset_ids): (i64, Vec<i64>) = {
+        let total = conn_guard.query_row("SELECT COUNT(*) FROM assets WHERE entity_id = ?1", params![entity_id], |row| row.get(0))
+            .map_err(|e| format!("Failed to count assets: {}", e))?;
+        let mut stmt = conn_guard.prepare("SELECT id FROM active_assets WHERE entity_id = ?1 ORDER BY id")
+            .map_err(|e| e.to_string())?;
+        let ids = stmt.query_map(params![entity_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<i64>>>()
+            .map_err(|e| e.to_string())?;
+        (total, ids)
+    };
+    if total_assets > active_asset_ids.len() as i64 {
+        return Err(format!(
+            "Entity '{}' still has {} mod(s) in other profiles. Move or delete them there first.",
+            entity_slug, total_assets - active_asset_ids.len() as i64
+        ));
+    }
+    if !active_asset_ids.is_empty() && !relocate_to_other.unwrap_or(false) {
+        return Err(format!(
+            "Entity '{}' still has {} mod(s). Move or delete them first, or relocate them to '{}{}'.",
+            entity_slug, active_asset_ids.len(), category_slug, OTHER_ENTITY_SUFFIX
+        ));
+    }
+    if !active_asset_ids.is_empty() {
+        ensure_not_safe_mode(&conn_guard).map_err(|e| e.to_string())?;
+        if !force.unwrap_or(false) {
+            ensure_assets_unlocked(&conn_guard, &active_asset_ids).map_err(|e| e.to_string())?;
+        }
+    }
+
+    let other_slug = format!("{}{}", category_slug, OTHER_ENTITY_SUFFIX);
+    let other_entity_id: Option<i64> = conn_guard.query_row("SELECT id FROM entities WHERE slug = ?1", params![other_slug], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("DB Error getting fallback entity: {}", e))?;
+    if other_entity_id.is_none() && !active_asset_ids.is_empty() {
+        return Err(format!("Fallback entity '{}' not found; cannot relocate mods.", other_slug));
+    }
+
+    let tx = conn_guard.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut moves: Vec<Operation> = Vec::new();
+
+    for asset_id in &active_asset_ids {
+        let result = get_asset_location_info(&tx, *asset_id).map_err(|e| e.to_string())
+            .and_then(|info| {
+                let other_id = other_entity_id.ok_or_else(|| format!("Fallback entity '{}' not found", other_slug))?;
+                let plan = plan_asset_relocation(&base_mods_path, &info, &other_slug, &category_slug)?;
+                move_asset_folder(&tx, &base_mods_path, &info, &plan, other_id)
+            });
+        match result {
+            Ok(operation) => moves.push(operation),
+            Err(e) => {
+                undo_asset_moves(&moves);
+                return Err(format!("Failed to relocate asset {}, nothing was deleted: {}", asset_id, e));
+            }
+        }
+    }
+
+    let cleanup = (|| -> SqlResult<()> {
+        // Trashed mods keep a valid entity so they can still be restored
+        if let Some(other_id) = other_entity_id {
+            tx.execute("UPDATE trashed_assets SET entity_id = ?1 WHERE entity_id = ?2", params![other_id, entity_id])?;
+        }
+        tx.execute("DELETE FROM deduction_rules WHERE entity_slug = ?1", params![entity_slug])?;
+        tx.execute("DELETE FROM entities WHERE id = ?1", params![entity_id])?;
+        Ok(())
+    })();
+    if let Err(e) = cleanup.and_then(|_| tx.commit()) {
+        undo_asset_moves(&moves);
+        return Err(format!("Failed to delete entity '{}', folders were moved back: {}", entity_slug, e));
+    }
+    for operation in moves {
+        record_operation(&op_log, operation);
+    }
+    config_state.invalidate_deduction_maps();
+
+    println!("[delete_entity] Deleted entity '{}' ({} mod(s) moved to '{}')", entity_slug, active_asset_ids.len(), other_slug);
+    Ok(())
+}
+
+// Moves every mod of one entity into another, e.g. after a definitions mix-up. Folder names
+// already taken in the target get a "_2", "_3", ... suffix. Either every mod moves or none do.
+#[command]
+fn merge_entity(
+    source_entity_slug: String,
+    target_entity_slug: String,
+    delete_source: Option<bool>,
+    force: Option<bool>,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>,
+    op_log: State<OperationLogState>
+) -> CmdResult<MergeEntityResult> {
+    println!("[merge_entity] Merging '{}' into '{}'", source_entity_slug, target_entity_slug);
+    if source_entity_slug == target_entity_slug {
+        return Err("Source and target entity are the same.".to_string());
+    }
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    let mut conn_guard = db_state.conn()?;
+    ensure_not_safe_mode(&conn_guard).map_err(|e| e.to_string())?;
+    let lookup_entity = |slug: &str| conn_guard.query_row(
+        "SELECT e.id, c.slug FROM entities e JOIN categories c ON e.category_id = c.id WHERE e.slug = ?1",
+        params![slug],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Entity '{}' not found.", slug),
+        _ => format!("DB Error getting entity: {}", e)
+    });
+    let (source_entity_id, _) = lookup_entity(&source_entity_slug)?;
+    let (target_entity_id, target_category_slug) = lookup_entity(&target_entity_slug)?;
+
+    let asset_ids: Vec<i64> = {
+        let mut stmt = conn_guard.prepare("SELECT id FROM active_assets WHERE entity_id = ?1 ORDER BY id")
+            .map_err(|e| e.to_string())?;
+        stmt.query_map(params![source_entity_id], |row| row.get(0))
+            .and_then(|rows| rows.collect::<SqlResult<Vec<i64>>>())
+            .map_err(|e| e.to_string())?
+    };
+    if !force.unwrap_or(false) {
+        ensure_assets_unlocked(&conn_guard, &asset_ids).map_err(|e| e.to_string())?;
+    }
+
+    let tx = conn_guard.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut result = MergeEntityResult { moved: Vec::new(), renamed: Vec::new(), source_deleted: false };
+    let mut moves: Vec<Operation> = Vec::new();
+    let target_dir = PathBuf::from(&target_category_slug).join(&target_entity_slug);
+
+    for asset_id in &asset_ids {
+        let step = get_asset_location_info(&tx, *asset_id).map_err(|e| e.to_string())
+            .and_then(|info| {
+                let (current_full_path, planned_full_path, planned_relative_path) =
+                    relocation_paths(&base_mods_path, &info, &target_entity_slug, &target_category_slug)?;
+                let was_disabled = current_full_path.file_name()
+                    .map_or(false, |name| is_disabled_folder_name(&name.to_string_lossy()));
+                let base_name = Path::new(&planned_relative_path).file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .ok_or_else(|| "Cannot get mod folder name".to_string())?;
+
+                // Pick the first name free both on disk (either state) and in the DB
+                let mut new_full_path = planned_full_path;
+                let mut new_relative_path = planned_relative_path.clone();
+                let mut suffix = 1;
+                loop {
+                    let name_taken = tx.query_row(
+                        "SELECT EXISTS(SELECT 1 FROM assets WHERE folder_name = ?1)", params![new_relative_path], |row| row.get::<_, bool>(0),
+                    ).map_err(|e| e.to_string())?;
+                    if !name_taken && !asset_move_target_taken(&base_mods_path, &new_full_path, &new_relative_path) {
+                        break;
+                    }
+                    suffix += 1;
+                    let candidate = format!("{}_{}", base_name, suffix);
+                    new_relative_path = target_dir.join(&candidate).to_string_lossy().replace("\\", "/");
+                    let on_disk_name = if was_disabled { disabled_folder_name(&candidate) } else { candidate };
+                    new_full_path = base_mods_path.join(&target_dir).join(on_disk_name);
+                }
+
+                unstage_for_relocation(&base_mods_path, &info)?;
+                let plan = (current_full_path, new_full_path, new_relative_path);
+                let operation = move_asset_folder(&tx, &base_mods_path, &info, &plan, target_entity_id)?;
+                let new_relative_path = plan.2;
+                let rename = (new_relative_path != planned_relative_path).then_some(AssetFolderRename {
+                    asset_id: *asset_id, old_folder_name: info.clean_relative_path, new_folder_name: new_relative_path,
+                });
+                Ok((operation, rename))
+            });
+        match step {
+            Ok((operation, rename)) => {
+                moves.push(operation);
+                result.moved.push(*asset_id);
+                result.renamed.extend(rename);
+            }
+            Err(e) => {
+                undo_asset_moves(&moves);
+                return Err(format!("Failed to move asset {}, nothing was merged: {}", asset_id, e));
+            }
+        }
+    }
+
+    // The source only goes away when nothing references it any more, not even another profile
+    let wants_delete = delete_source.unwrap_or(false) && !source_entity_slug.ends_with(OTHER_ENTITY_SUFFIX);
+    let finish = (|| -> SqlResult<bool> {
+        tx.execute("UPDATE trashed_assets SET entity_id = ?1 WHERE entity_id = ?2", params![target_entity_id, source_entity_id])?;
+        if !wants_delete {
+            return Ok(false);
+        }
+        let remaining: i64 = tx.query_row("SELECT COUNT(*) FROM assets WHERE entity_id = ?1", params![source_entity_id], |row| row.get(0))?;
+        if remaining > 0 {
+            println!("[merge_entity] Keeping '{}': {} mod(s) remain in other profiles", source_entity_slug, remaining);
+            return Ok(false);
+        }
+        // Rules that matched the old entity should now land on the merged one
+        tx.execute("UPDATE deduction_rules SET entity_slug = ?1 WHERE entity_slug = ?2", params![target_entity_slug, source_entity_slug])?;
+        tx.execute("DELETE FROM entities WHERE id = ?1", params![source_entity_id])?;
+        Ok(true)
+    })();
+    match finish.and_then(|deleted| tx.commit().map(|_| deleted)) {
+        Ok(deleted) => result.source_deleted = deleted,
+        Err(e) => {
+            undo_asset_moves(&moves);
+            return Err(format!("Failed to merge '{}' into '{}', folders were moved back: {}", source_entity_slug, target_entity_slug, e));
+        }
+    }
+    for operation in moves {
+        record_operation(&op_log, operation);
+    }
+    if result.source_deleted {
+        config_state.invalidate_deduction_maps();
+    }
+
+    println!("[merge_entity] Moved {} mod(s) ({} renamed) into '{}', source deleted: {}",
+        result.moved.len(), result.renamed.len(), target_entity_slug, result.source_deleted);
+    Ok(result)
+}
+
+// Copies an image into the app data dir and makes it the entity's base image.
+// Returns the stored image's absolute path.
+#[command]
+fn set_entity_image(entity_slug: String, source_path: String, db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<String> {
+    let source = PathBuf::from(source_path.trim());
+    if !source.is_file() {
+        return Err(format!("Image file not found: {}", source.display()));
+    }
+    let extension = source.extension().and_then(OsStr::to_str).map(|ext| ext.to_lowercase())
+        .filter(|ext| ENTITY_IMAGE_EXTENSIONS.contains(&ext.as_str()))
+        .ok_or_else(|| format!("Unsupported image type: {}", source.display()))?;
+
+    let conn = db_state.conn()?;
+    let previous_image: Option<String> = conn.query_row("SELECT base_image FROM entities WHERE slug = ?1", params![entity_slug], |row| row.get(0))
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Entity '{}' not found", entity_slug),
+            _ => e.to_string(),
+        })?;
+
+    let images_dir = get_app_data_dir(&app_handle).map_err(|e| e.to_string())?.join(ENTITY_IMAGES_FOLDER_NAME);
+    fs::create_dir_all(&images_dir).map_err(|e| format!("Failed to create entity image folder: {}", e))?;
+    // A fresh name each time, so the webview doesn't keep showing a cached older image
+    let stored_name = format!("{}_{}.{}", entity_slug, unix_timestamp_millis(), extension);
+    let stored_path = images_dir.join(&stored_name);
+    fs::copy(&source, &stored_path).map_err(|e| format!("Failed to copy image to '{}': {}", stored_path.display(), e))?;
+
+    if let Err(e) = conn.execute("UPDATE entities SET base_image = ?1 WHERE slug = ?2", params![stored_name, entity_slug]) {
+        fs::remove_file(&stored_path).ok();
+        return Err(format!("Failed to update entity image: {}", e));
+    }
+    // Drop the previously stored custom image; bundled definition images are not in this folder
+    if let Some(previous) = previous_image.filter(|name| !name.is_empty() && !name.contains(['/', '\\'])) {
+        let previous_path = images_dir.join(previous);
+        if previous_path.is_file() {
+            fs::remove_file(&previous_path).unwrap_or_else(|e| eprintln!("[set_entity_image] Failed to remove old image '{}': {}", previous_path.display(), e));
+        }
+    }
+
+    println!("[set_entity_image] Entity '{}' now uses '{}'", entity_slug, stored_name);
+    Ok(stored_path.to_string_lossy().into_owned())
+}
+
+// Resolves an entity's base image for display: the absolute path of an image set with
+// set_entity_image, else the bundled definition image URL, else the default placeholder URL.
+#[command]
+fn get_entity_image_path(entity_slug: String, db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<String> {
+    let base_image: Option<String> = {
+        let conn = db_state.conn()?;
+        conn.query_row("SELECT base_image FROM entities WHERE slug = ?1", params![entity_slug], |row| row.get(0))
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Entity '{}' not found", entity_slug),
+                _ => e.to_string(),
+            })?
+    };
+    let base_image = match base_image.filter(|name| !name.trim().is_empty()) {
+        Some(name) => name,
+        None => return Ok(DEFAULT_ENTITY_IMAGE_URL.to_string()),
+    };
+    if !base_image.contains(['/', '\\']) {
+        let stored_path = get_app_data_dir(&app_handle).map_err(|e| e.to_string())?.join(ENTITY_IMAGES_FOLDER_NAME).join(&base_image);
+        if stored_path.is_file() {
+            return Ok(stored_path.to_string_lossy().into_owned());
+        }
+    }
+    Ok(format!("{}/{}", BUNDLED_ENTITY_IMAGES_URL, base_image))
+}
+
+
+#[command]
+fn get_entity_details(entity_slug: String, db_state: State<DbState>) -> CmdResult<Entity> {
+    println!("[get_entity_details] Starting for entity: {}", entity_slug);
+    
+    // PART 1: Get base entity info with a brief lock
+    let entity_info = {
+        let conn_guard = db_state.conn()?;
+        let conn = &*conn_guard; // Dereference the guard
+        
+        let mut stmt = conn.prepare(
+            "SELECT e.id, e.category_id, e.name, e.slug, e.description, e.details, e.base_image, COUNT(a.id) as mod_count
+             FROM entities e LEFT JOIN active_assets a ON e.id = a.entity_id
+             WHERE e.slug = ?1 GROUP BY e.id"
+        ).map_err(|e| format!("[get_entity_details] DB prepare error: {}", e))?;
+        
+        // Get basic entity details first
+        stmt.query_row(params![entity_slug], |row| {
+             Ok(Entity {
+                id: row.get(0)?, 
+                category_id: row.get(1)?, 
+                name: row.get(2)?,
+                slug: row.get(3)?, 
+                description: row.get(4)?, 
+                details: row.get(5)?,
+                base_image: row.get(6)?, 
+                mod_count: row.get(7)?,
+                enabled_mod_count: None,  // Will be populated later
+                recent_mod_count: None,   // Will be populated later
+                favorite_mod_count: None  // Will be populated later
+            })
+        }).map_err(|e| match e { 
+            rusqlite::Error::QueryReturnedNoRows => format!("Entity '{}' not found", entity_slug),
+            _ => format!("[get_entity_details] DB row error: {}", e),
+        })?
+    }; // conn_guard is released here
+    
+    // Create a mutable copy we'll update with the additional counts
+    let mut entity = entity_info;
+    
+    // PART 2: Get folder paths from DB with a separate brief lock
+    let asset_folder_paths: Vec<String> = {
+        let conn_guard = db_state.conn()?;
+        let conn = &*conn_guard;
+        
+        // Prepare statement and collect all folder paths while holding lock
+        let mut stmt = conn.prepare("SELECT folder_name FROM active_assets WHERE entity_id = ?1")
+            .map_err(|e| format!("[get_entity_details] Error preparing folder query: {}", e))?;
+            
+        let folder_iter = stmt.query_map(params![entity.id], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("[get_entity_details] Error executing folder query: {}", e))?;
+            
+        // Collect all paths into a Vec to release the lock sooner
+        let mut paths = Vec::new();
+        for result in folder_iter {
+            match result {
+                Ok(path) => paths.push(path.replace("\\", "/")),
+                Err(e) => println!("[get_entity_details] Warning: Error fetching path: {}", e),
+            }
+        }
+        paths
+    }; // conn_guard is released here
+    
+    // PART 3: Get mods base path (uses a lock internally, so call outside of any lock section)
+    let base_mods_path = match get_mods_base_path_from_settings(&db_state) {
+        Ok(path) => path,
+        Err(e) => {
+            println!("[get_entity_details] Warning: Error getting base mods path: {}", e);
+            // We'll proceed with empty counts since we can't check the disk
+            entity.enabled_mod_count = Some(0);
+            entity.recent_mod_count = Some(0);
+            entity.favorite_mod_count = Some(0);
+            return Ok(entity);
+        }
+    };
+    
+    // PART 4: Count enabled mods by checking disk paths (NO DB LOCK NEEDED)
+    let mut enabled_count = 0;
+    for clean_relative_path_str in &asset_folder_paths {
+        let clean_relative_path = PathBuf::from(clean_relative_path_str);
+        let filename_osstr = match clean_relative_path.file_name() {
+            Some(name) => name,
+            None => continue, // Skip if we can't get filename
+        };
+        
+        let filename_str = filename_osstr.to_string_lossy();
+        if filename_str.is_empty() { continue; }
+        
+        // Check only enabled state path
+        let full_path_if_enabled = base_mods_path.join(&clean_relative_path);
+        if full_path_if_enabled.is_dir() {
+            enabled_count += 1;
+        }
+    }
+    entity.enabled_mod_count = Some(enabled_count);
+    
+    // PART 5: Get recent mod count and favorite counts with a final lock
+    {
+        let conn_guard = db_state.conn()?;
+        let conn = &*conn_guard;
+        
+        // Count recent mods (approximation using ID sorting, assuming higher IDs are more recent)
+        if entity.mod_count > 0 {
+            match conn.query_row(
+                "SELECT COUNT(*) FROM active_assets 
+                 WHERE entity_id = ?1 
+                 AND id > (SELECT MAX(id) - (COUNT(*) / 4) FROM active_assets WHERE entity_id = ?1)",
+                params![entity.id],
+                |row| row.get::<_, i32>(0),
+            ) {
+                Ok(count) => {
+                    entity.recent_mod_count = Some(count);
+                },
+                Err(e) => {
+                    println!("[get_entity_details] Warning: Error counting recent mods: {}", e);
+                    entity.recent_mod_count = Some(0);
+                }
+            }
+        } else {
+            entity.recent_mod_count = Some(0);
+        }
+        
+        // Count mods in favorite presets
+        match conn.query_row(
+            "SELECT COUNT(DISTINCT a.id) FROM active_assets a
+             JOIN preset_assets pa ON a.id = pa.asset_id
+             JOIN active_presets p ON pa.preset_id = p.id
+             WHERE a.entity_id = ?1 AND p.is_favorite = 1",
+            params![entity.id],
+            |row| row.get::<_, i32>(0),
+        ) {
+            Ok(count) => {
+                entity.favorite_mod_count = Some(count);
+            },
+            Err(e) => {
+                println!("[get_entity_details] Warning: Error counting mods in favorite presets: {}", e);
+                entity.favorite_mod_count = Some(0);
+            }
+        }
+    } // Final conn_guard is released here
+    
+    println!("[get_entity_details] Completed for entity: {}", entity_slug);
+    Ok(entity)
+}
+
+// Returns every asset of the entity with a disk check per row; prefer get_assets_for_entity_paged
+// for large entities such as the "-other" buckets.
+#[command]
+fn get_assets_for_entity(entity_slug: String, db_state: State<DbState>, config_state: State<CachedConfigState>, _app_handle: AppHandle) -> CmdResult<Vec<Asset>> {
+    let base_mods_path = cached_mods_base_path(&config_state, &db_state)
+                             .map_err(|e| format!("[get_assets_for_entity {}] Error getting base mods path: {}", entity_slug, e))?;
+
+    let conn_guard = db_state.conn()?;
+    let conn = &*conn_guard;
+
+    // --- Entity ID Lookup ---
+    let entity_id: i64 = conn.query_row(
+        "SELECT id FROM entities WHERE slug = ?1",
+        params![entity_slug],
+        |row| row.get(0),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("[get_assets_for_entity {}] Entity not found for assets lookup", entity_slug),
+        _ => format!("[get_assets_for_entity {}] DB Error getting entity ID: {}", entity_slug, e),
+    })?;
+
+    // --- Tags for all assets of this entity ---
+    let mut tags_by_asset = fetch_tags_for_entity_assets(conn, entity_id)
+        .map_err(|e| format!("[get_assets_for_entity {}] DB Error fetching asset tags: {}", entity_slug, e))?;
+
+    // --- Prepare Statement ---
+    let mut stmt = conn.prepare(
+        "SELECT id, entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url, is_favorite, is_pinned, is_locked, created_at, updated_at, last_enabled_at
+         FROM active_assets WHERE entity_id = ?1 ORDER BY sort_order, name"
+    ).map_err(|e| format!("[get_assets_for_entity {}] DB Error preparing asset statement: {}", entity_slug, e))?;
+
+    // --- Query Rows ---
+    let asset_rows_result = stmt.query_map(params![entity_id], |row| {
+        let folder_name_raw: String = row.get(4)?;
+        Ok(Asset {
+            id: row.get(0)?,
+            entity_id: row.get(1)?,
+            name: row.get(2)?,
+            description: row.get(3)?,
+            // Store the CLEAN relative path from DB directly for now
+            folder_name: folder_name_raw.replace("\\", "/"),
+            image_filename: row.get(5)?,
+            author: row.get(6)?,
+            category_tag: row.get(7)?,
+            is_enabled: false, // Default, will be determined below
+            tags: tags_by_asset.remove(&row.get::<_, i64>(0)?).unwrap_or_default(),
+            notes: row.get(8)?,
+            source_url: row.get(9)?,
+            is_favorite: row.get(10)?,
+            is_pinned: row.get(11)?,
+            is_locked: row.get(12)?,
+            created_at: row.get(13)?,
+            updated_at: row.get(14)?,
+            last_enabled_at: row.get(15)?,
+        })
+    });
+
+    let mut assets_to_return = Vec::new();
+
+    match asset_rows_result {
+        Ok(asset_iter) => {
+             for (index, asset_result) in asset_iter.enumerate() {
+                 match asset_result {
+                     Ok(mut asset_from_db) => {
+                         // `asset_from_db.folder_name` holds the CLEAN relative path from DB; it becomes the
+                         // path as found on disk. Mods missing in every state are skipped.
+                         let clean_relative_path = asset_from_db.folder_name.replace("\\", "/");
+                         if let Some((is_enabled, current_relative_path)) = resolve_asset_disk_state(&base_mods_path, &clean_relative_path) {
+                             asset_from_db.is_enabled = is_enabled;
+                             asset_from_db.folder_name = current_relative_path;
+                             assets_to_return.push(asset_from_db);
+                         }
+                     }
+                     Err(e) => {
+                         eprintln!("[get_assets_for_entity {}] Error processing asset row index {}: {}", entity_slug, index, e);
+                     }
+                 }
+             }
+        }
+        Err(e) => {
+             let err_msg = format!("[get_assets_for_entity {}] DB Error preparing asset iterator: {}", entity_slug, e);
+             return Err(err_msg);
+        }
+    }
+
+    Ok(assets_to_return)
+}
+
+#[derive(Serialize, Debug)]
+struct EntityPageAsset {
+    #[serde(flatten)]
+    asset: Asset,
+    image_path: Option<String>, // Absolute path, None if the asset has no image on disk
+}
+
+#[derive(Serialize, Debug)]
+struct EntityPage {
+    entity: Entity,
+    assets: Vec<EntityPageAsset>,
+}
+
+// Entity details, its assets (enabled state resolved) and their image paths in one call,
+// so opening an entity doesn't need an invoke per card.
+#[command]
+fn get_entity_page(entity_slug: String, db_state: State<DbState>, config_state: State<CachedConfigState>, app_handle: AppHandle) -> CmdResult<EntityPage> {
+    let entity = get_entity_details(entity_slug.clone(), db_state.clone())?;
+    let assets = get_assets_for_entity(entity_slug, db_state.clone(), config_state.clone(), app_handle)?;
+    let base_mods_path = cached_mods_base_path(&config_state, &db_state).map_err(|e| e.to_string())?;
+
+    // folder_name is the on-disk path here, so no second enabled/disabled lookup is needed
+    let assets = assets.into_iter()
+        .map(|asset| {
+            let image_path = asset.image_filename.as_deref()
+                .filter(|name| !name.is_empty())
+                .map(|name| base_mods_path.join(&asset.folder_name).join(name))
+                .filter(|path| path.is_file())
+                .map(|path| path.to_string_lossy().into_owned());
+            EntityPageAsset { asset, image_path }
+        })
+        .collect();
+    Ok(EntityPage { entity, assets })
+}
+
+// One page of an entity's assets, ordered by `sort`: "manual" (default), "name", "name_desc",
+// "newest" or "size". Only the rows in the page are checked on disk; assets whose folder is
+// missing are left out of the page but still counted in `total`.
+#[command]
+fn get_assets_for_entity_paged(
+    entity_slug: String,
+    offset: i64,
+    limit: i64,
+    sort: Option<String>,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>
+) -> CmdResult<AssetPage> {
+    let order_by = match sort.as_deref().unwrap_or("manual") {
+        "manual" => "sort_order, name",
+        "name" => "name COLLATE NOCASE, id",
+        "name_desc" => "name COLLATE NOCASE DESC, id DESC",
+        "newest" => "id DESC",
+        "size" => "size_bytes DESC, name COLLATE NOCASE",
+        other => return Err(format!("Unknown sort '{}'. Use manual, name, name_desc, newest or size.", other)),
+    };
+    let offset = offset.max(0);
+    let limit = limit.clamp(1, 500);
+    let base_mods_path = cached_mods_base_path(&config_state, &db_state)
+        .map_err(|e| format!("[get_assets_for_entity_paged {}] Error getting base mods path: {}", entity_slug, e))?;
+    let conn = db_state.conn()?;
+
+    let entity_id: i64 = conn.query_row("SELECT id FROM entities WHERE slug = ?1", params![entity_slug], |row| row.get(0))
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("[get_assets_for_entity_paged {}] Entity not found for assets lookup", entity_slug),
+            _ => format!("[get_assets_for_entity_paged {}] DB Error getting entity ID: {}", entity_slug, e),
+        })?;
+    let total: i64 = conn.query_row("SELECT COUNT(*) FROM active_assets WHERE entity_id = ?1", params![entity_id], |row| row.get(0))
+        .map_err(|e| format!("[get_assets_for_entity_paged {}] DB Error counting assets: {}", entity_slug, e))?;
+    let mut tags_by_asset = fetch_tags_for_entity_assets(&conn, entity_id)
+        .map_err(|e| format!("[get_assets_for_entity_paged {}] DB Error fetching asset tags: {}", entity_slug, e))?;
+
+    let sql = format!(
+        "SELECT id, entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url, is_favorite, is_pinned, is_locked, created_at, updated_at, last_enabled_at
+         FROM active_assets WHERE entity_id = ?1 ORDER BY {} LIMIT ?2 OFFSET ?3",
+        order_by
+    );
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| format!("[get_assets_for_entity_paged {}] DB Error preparing asset statement: {}", entity_slug, e))?;
+    let rows = stmt.query_map(params![entity_id, limit, offset], |row| Ok(Asset {
+        id: row.get(0)?,
+        entity_id: row.get(1)?,
+        name: row.get(2)?,
+        description: row.get(3)?,
+        folder_name: row.get::<_, String>(4)?.replace("\\", "/"),
+        image_filename: row.get(5)?,
+        author: row.get(6)?,
+        category_tag: row.get(7)?,
+        is_enabled: false, // Determined from disk below
+        tags: Vec::new(),
+        notes: row.get(8)?,
+        source_url: row.get(9)?,
+        is_favorite: row.get(10)?,
+        is_pinned: row.get(11)?,
+        is_locked: row.get(12)?,
+        created_at: row.get(13)?,
+        updated_at: row.get(14)?,
+        last_enabled_at: row.get(15)?,
+    })).map_err(|e| format!("[get_assets_for_entity_paged {}] DB Error querying assets: {}", entity_slug, e))?
+        .collect::<SqlResult<Vec<Asset>>>()
+        .map_err(|e| format!("[get_assets_for_entity_paged {}] DB Error reading asset rows: {}", entity_slug, e))?;
+
+    let mut assets = Vec::with_capacity(rows.len());
+    for mut asset in rows {
+        // Like get_assets_for_entity, folder_name becomes the path as found on disk
+        if let Some((is_enabled, current_relative_path)) = resolve_asset_disk_state(&base_mods_path, &asset.folder_name) {
+            asset.is_enabled = is_enabled;
+            asset.folder_name = current_relative_path;
+            asset.tags = tags_by_asset.remove(&asset.id).unwrap_or_default();
+            assets.push(asset);
+        }
+    }
+    Ok(AssetPage { assets, total, offset, limit })
+}
+
+// asset_id -> tag names for every asset of an entity
+fn fetch_tags_for_entity_assets(conn: &Connection, entity_id: i64) -> SqlResult<HashMap<i64, Vec<String>>> {
+    let mut stmt = conn.prepare(
+        "SELECT at.asset_id, t.name FROM asset_tags at
+         JOIN tags t ON at.tag_id = t.id
+         JOIN assets a ON at.asset_id = a.id
+         WHERE a.entity_id = ?1 ORDER BY t.name COLLATE NOCASE"
+    )?;
+    let rows = stmt.query_map(params![entity_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+    let mut tags_by_asset: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (asset_id, tag) = row?;
+        tags_by_asset.entry(asset_id).or_default().push(tag);
+    }
+    Ok(tags_by_asset)
+}
+
+#[command]
+fn add_tag_to_asset(asset_id: i64, tag: String, db_state: State<DbState>) -> CmdResult<()> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err("Tag cannot be empty".to_string());
+    }
+    let conn = db_state.conn()?;
+    // Tag names are unique case-insensitively, so an existing "4k" is reused for "4K"
+    conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", params![tag])
+        .map_err(|e| format!("Failed to create tag '{}': {}", tag, e))?;
+    let tag_id: i64 = conn.query_row("SELECT id FROM tags WHERE name = ?1", params![tag], |row| row.get(0))
+        .map_err(|e| format!("Failed to look up tag '{}': {}", tag, e))?;
+    conn.execute("INSERT OR IGNORE INTO asset_tags (asset_id, tag_id) VALUES (?1, ?2)", params![asset_id, tag_id])
+        .map_err(|e| format!("Failed to tag asset {}: {}", asset_id, e))?;
+    println!("[add_tag_to_asset] Tagged asset {} with '{}'", asset_id, tag);
+    Ok(())
+}
+
+#[command]
+fn remove_tag_from_asset(asset_id: i64, tag: String, db_state: State<DbState>) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    conn.execute(
+        "DELETE FROM asset_tags WHERE asset_id = ?1 AND tag_id IN (SELECT id FROM tags WHERE name = ?2)",
+        params![asset_id, tag.trim()],
+    ).map_err(|e| format!("Failed to remove tag '{}' from asset {}: {}", tag, asset_id, e))?;
+    println!("[remove_tag_from_asset] Removed '{}' from asset {}", tag, asset_id);
+    Ok(())
+}
+
+#[command]
+fn get_tags_for_asset(asset_id: i64, db_state: State<DbState>) -> CmdResult<Vec<String>> {
+    let conn = db_state.conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT t.name FROM asset_tags at JOIN tags t ON at.tag_id = t.id WHERE at.asset_id = ?1 ORDER BY t.name COLLATE NOCASE"
+    ).map_err(|e| e.to_string())?;
+    let tags = stmt.query_map(params![asset_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<SqlResult<Vec<String>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(tags)
+}
+
+#[command]
+fn get_all_tags(db_state: State<DbState>) -> CmdResult<Vec<String>> {
+    let conn = db_state.conn()?;
+    let mut stmt = conn.prepare("SELECT name FROM tags ORDER BY name COLLATE NOCASE").map_err(|e| e.to_string())?;
+    let tags = stmt.query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<SqlResult<Vec<String>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(tags)
+}
+
+#[command]
+fn delete_tag(tag: String, db_state: State<DbState>) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    // asset_tags rows go with it via ON DELETE CASCADE
+    let deleted = conn.execute("DELETE FROM tags WHERE name = ?1", params![tag.trim()])
+        .map_err(|e| format!("Failed to delete tag '{}': {}", tag, e))?;
+    if deleted == 0 {
+        return Err(format!("Tag '{}' not found", tag));
+    }
+    println!("[delete_tag] Deleted tag '{}'", tag);
+    Ok(())
+}
+
+// Author match for the retag commands: trimmed and case-insensitive on both sides
+const AUTHOR_MATCH_CLAUSE: &str = "LOWER(TRIM(a.author)) = LOWER(?1)";
+
+#[command]
+fn get_assets_by_author(author: String, db_state: State<DbState>) -> CmdResult<Vec<AssetSearchResult>> {
+    let author = author.trim().to_string();
+    if author.is_empty() {
+        return Err("Author cannot be empty".to_string());
+    }
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let rows = {
+        let conn = db_state.conn()?;
+        let sql = format!("{} WHERE {} ORDER BY c.name, e.name, a.sort_order, a.name", ASSET_WITH_SLUGS_SELECT, AUTHOR_MATCH_CLAUSE);
+        let mut stmt = conn.prepare(&sql).map_err(|e| format!("Failed to prepare author query: {}", e))?;
+        stmt.query_map(params![author], map_asset_with_slugs_row)
+            .map_err(|e| format!("Failed to query assets by author: {}", e))
+            .and_then(|rows| rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read assets by author: {}", e)))?
+    }; // Lock released before disk checks
+    Ok(resolve_asset_results_on_disk(&base_mods_path, rows))
+}
+
+// Adds and removes tags on every asset by `author` in one transaction.
+// Returns how many assets actually gained or lost a tag.
+#[command]
+fn retag_by_author(author: String, add_tags: Vec<String>, remove_tags: Vec<String>, db_state: State<DbState>) -> CmdResult<usize> {
+    let author = author.trim().to_string();
+    if author.is_empty() {
+        return Err("Author cannot be empty".to_string());
+    }
+    let add_tags: Vec<&str> = add_tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()).collect();
+    let remove_tags: Vec<&str> = remove_tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()).collect();
+    if add_tags.is_empty() && remove_tags.is_empty() {
+        return Err("No tags to add or remove".to_string());
+    }
+
+    let mut conn = db_state.conn()?;
+    let asset_ids: Vec<i64> = {
+        let sql = format!("SELECT a.id FROM active_assets a WHERE {}", AUTHOR_MATCH_CLAUSE);
+        let mut stmt = conn.prepare(&sql).map_err(|e| format!("Failed to prepare author query: {}", e))?;
+        stmt.query_map(params![author], |row| row.get(0))
+            .map_err(|e| format!("Failed to query assets by author: {}", e))
+            .and_then(|rows| rows.collect::<SqlResult<Vec<i64>>>().map_err(|e| format!("Failed to read assets by author: {}", e)))?
+    };
+    if asset_ids.is_empty() {
+        println!("[retag_by_author] No assets by '{}'", author);
+        return Ok(0);
+    }
+
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut affected: HashSet<i64> = HashSet::new();
+    for tag in &add_tags {
+        // Tag names are unique case-insensitively, so an existing "4k" is reused for "4K"
+        tx.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", params![tag])
+            .map_err(|e| format!("Failed to create tag '{}': {}", tag, e))?;
+        let tag_id: i64 = tx.query_row("SELECT id FROM tags WHERE name = ?1", params![tag], |row| row.get(0))
+            .map_err(|e| format!("Failed to look up tag '{}': {}", tag, e))?;
+        for asset_id in &asset_ids {
+            let inserted = tx.execute("INSERT OR IGNORE INTO asset_tags (asset_id, tag_id) VALUES (?1, ?2)", params![asset_id, tag_id])
+                .map_err(|e| format!("Failed to tag asset {}: {}", asset_id, e))?;
+            if inserted > 0 { affected.insert(*asset_id); }
+        }
+    }
+    for tag in &remove_tags {
+        for asset_id in &asset_ids {
+            let removed = tx.execute(
+                "DELETE FROM asset_tags WHERE asset_id = ?1 AND tag_id IN (SELECT id FROM tags WHERE name = ?2)",
+                params![asset_id, tag],
+            ).map_err(|e| format!("Failed to remove tag '{}' from asset {}: {}", tag, asset_id, e))?;
+            if removed > 0 { affected.insert(*asset_id); }
+        }
+    }
+    tx.commit().map_err(|e| format!("Failed to commit tag changes: {}", e))?;
+
+    println!("[retag_by_author] '{}': {} of {} assets changed (+{:?} -{:?})", author, affected.len(), asset_ids.len(), add_tags, remove_tags);
+    Ok(affected.len())
+}
+
+// --- Custom deduction rules (consulted by deduce_mod_info_v2 on the next scan) ---
+#[command]
+fn add_deduction_rule(
+    pattern: String,
+    is_regex: bool,
+    entity_slug: String,
+    priority: Option<i64>,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>
+) -> CmdResult<DeductionRule> {
+    let pattern = pattern.trim().to_string();
+    if pattern.is_empty() {
+        return Err("Pattern cannot be empty".to_string());
+    }
+    DeductionPattern::compile(&pattern, is_regex)?;
+
+    let conn = db_state.conn()?;
+    let entity_exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM entities WHERE slug = ?1)", params![entity_slug], |row| row.get(0))
+        .map_err(|e| format!("Failed to look up entity '{}': {}", entity_slug, e))?;
+    if !entity_exists {
+        return Err(format!("Entity '{}' not found", entity_slug));
+    }
+    // New rules go last unless a priority is given
+    let priority = match priority {
+        Some(p) => p,
+        None => conn.query_row("SELECT COALESCE(MAX(priority), 0) + 1 FROM deduction_rules", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to compute rule priority: {}", e))?,
+    };
+    conn.execute(
+        "INSERT INTO deduction_rules (pattern, is_regex, entity_slug, priority) VALUES (?1, ?2, ?3, ?4)",
+        params![pattern, is_regex, entity_slug, priority],
+    ).map_err(|e| format!("Failed to add deduction rule: {}", e))?;
+    let id = conn.last_insert_rowid();
+    config_state.invalidate_deduction_maps();
+    println!("[add_deduction_rule] Added rule {} ('{}' -> {}, priority {})", id, pattern, entity_slug, priority);
+    Ok(DeductionRule { id, pattern, is_regex, entity_slug, priority })
+}
+
+#[command]
+fn list_deduction_rules(db_state: State<DbState>) -> CmdResult<Vec<DeductionRule>> {
+    let conn = db_state.conn()?;
+    let mut stmt = conn.prepare("SELECT id, pattern, is_regex, entity_slug, priority FROM deduction_rules ORDER BY priority, id")
+        .map_err(|e| e.to_string())?;
+    let rules = stmt.query_map([], |row| Ok(DeductionRule {
+        id: row.get(0)?,
+        pattern: row.get(1)?,
+        is_regex: row.get(2)?,
+        entity_slug: row.get(3)?,
+        priority: row.get(4)?,
+    }))
+        .map_err(|e| e.to_string())?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rules)
+}
+
+#[command]
+fn delete_deduction_rule(rule_id: i64, db_state: State<DbState>, config_state: State<CachedConfigState>) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    let deleted = conn.execute("DELETE FROM deduction_rules WHERE id = ?1", params![rule_id])
+        .map_err(|e| format!("Failed to delete deduction rule {}: {}", rule_id, e))?;
+    if deleted == 0 {
+        return Err(format!("Deduction rule {} not found", rule_id));
+    }
+    config_state.invalidate_deduction_maps();
+    println!("[delete_deduction_rule] Deleted rule {}", rule_id);
+    Ok(())
+}
+
+// Asset columns plus entity/category slugs, shared by search and the favorites panel
+const ASSET_WITH_SLUGS_SELECT: &str = "SELECT a.id, a.entity_id, a.name, a.description, a.folder_name, a.image_filename, a.author, a.category_tag, e.slug, c.slug, a.notes, a.source_url, a.is_favorite, a.is_pinned, a.is_locked, a.created_at, a.updated_at, a.last_enabled_at
+     FROM active_assets a JOIN entities e ON a.entity_id = e.id JOIN categories c ON e.category_id = c.id";
+
+fn map_asset_with_slugs_row(row: &rusqlite::Row) -> SqlResult<AssetSearchResult> {
+    Ok(AssetSearchResult {
+        asset: Asset {
+            id: row.get(0)?,
+            entity_id: row.get(1)?,
+            name: row.get(2)?,
+            description: row.get(3)?,
+            folder_name: row.get::<_, String>(4)?.replace("\\", "/"),
+            image_filename: row.get(5)?,
+            author: row.get(6)?,
+            category_tag: row.get(7)?,
+            is_enabled: false, // Determined from disk by resolve_asset_results_on_disk
+            tags: Vec::new(),
+            notes: row.get(10)?,
+            source_url: row.get(11)?,
+            is_favorite: row.get(12)?,
+            is_pinned: row.get(13)?,
+            is_locked: row.get(14)?,
+            created_at: row.get(15)?,
+            updated_at: row.get(16)?,
+            last_enabled_at: row.get(17)?,
+        },
+        entity_slug: row.get(8)?,
+        category_slug: row.get(9)?,
+    })
+}
+
+// Resolves enabled state and on-disk folder name, skipping assets missing from disk
+fn resolve_asset_results_on_disk(base_mods_path: &Path, rows: Vec<AssetSearchResult>) -> Vec<AssetSearchResult> {
+    rows.into_iter()
+        .filter_map(|mut result| {
+            let (is_enabled, disk_path) = resolve_asset_disk_state(base_mods_path, &result.asset.folder_name)?;
+            result.asset.is_enabled = is_enabled;
+            result.asset.folder_name = disk_path;
+            Some(result)
+        })
+        .collect()
+}
+
+#[command]
+fn toggle_asset_favorite(asset_id: i64, is_favorite: bool, db_state: State<DbState>) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    let changes = conn.execute("UPDATE assets SET is_favorite = ?1 WHERE id = ?2", params![is_favorite, asset_id])
+        .map_err(|e| format!("Failed to update favorite status: {}", e))?;
+    if changes == 0 {
+        return Err(format!("Asset {} not found", asset_id));
+    }
+    Ok(())
+}
+
+#[command]
+fn get_favorite_assets(db_state: State<DbState>) -> CmdResult<Vec<AssetSearchResult>> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let rows = {
+        let conn = db_state.conn()?;
+        let sql = format!("{} WHERE a.is_favorite = 1 ORDER BY c.name, e.name, a.sort_order, a.name", ASSET_WITH_SLUGS_SELECT);
+        let mut stmt = conn.prepare(&sql).map_err(|e| format!("Failed to prepare favorites query: {}", e))?;
+        stmt.query_map([], map_asset_with_slugs_row)
+            .map_err(|e| format!("Failed to query favorites: {}", e))
+            .and_then(|rows| rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read favorites: {}", e)))?
+    }; // Lock released before disk checks
+    Ok(resolve_asset_results_on_disk(&base_mods_path, rows))
+}
+
+// Most recently added assets (by created_at), newest first
+#[command]
+fn get_recent_assets(limit: i64, db_state: State<DbState>) -> CmdResult<Vec<AssetSearchResult>> {
+    query_assets_by_timestamp(&db_state, "created_at", limit)
+}
+
+// Most recently edited or toggled assets (by updated_at), newest first
+#[command]
+fn get_recently_modified(limit: i64, db_state: State<DbState>) -> CmdResult<Vec<AssetSearchResult>> {
+    query_assets_by_timestamp(&db_state, "updated_at", limit)
+}
+
+// Mods never enabled first (oldest imports first), then the longest unused; for library cleanup
+#[command]
+fn get_least_used_assets(limit: i64, db_state: State<DbState>) -> CmdResult<Vec<AssetSearchResult>> {
+    let limit = limit.clamp(1, 500);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let rows = {
+        let conn = db_state.conn()?;
+        let sql = format!("{} ORDER BY a.last_enabled_at IS NOT NULL, a.last_enabled_at ASC, a.created_at ASC, a.id ASC LIMIT ?1", ASSET_WITH_SLUGS_SELECT);
+        let mut stmt = conn.prepare(&sql).map_err(|e| format!("Failed to prepare least used query: {}", e))?;
+        stmt.query_map(params![limit], map_asset_with_slugs_row)
+            .map_err(|e| format!("Failed to query least used assets: {}", e))
+            .and_then(|rows| rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read least used assets: {}", e)))?
+    }; // Lock released before disk checks
+    Ok(resolve_asset_results_on_disk(&base_mods_path, rows))
+}
+
+// `column` is always one of the fixed timestamp column names above, never user input
+fn query_assets_by_timestamp(db_state: &DbState, column: &str, limit: i64) -> CmdResult<Vec<AssetSearchResult>> {
+    let limit = limit.clamp(1, 500);
+    let base_mods_path = get_mods_base_path_from_settings(db_state).map_err(|e| e.to_string())?;
+    let rows = {
+        let conn = db_state.conn()?;
+        let sql = format!("{} WHERE a.{col} IS NOT NULL ORDER BY a.{col} DESC, a.id DESC LIMIT ?1", ASSET_WITH_SLUGS_SELECT, col = column);
+        let mut stmt = conn.prepare(&sql).map_err(|e| format!("Failed to prepare recent assets query: {}", e))?;
+        stmt.query_map(params![limit], map_asset_with_slugs_row)
+            .map_err(|e| format!("Failed to query recent assets: {}", e))
+            .and_then(|rows| rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read recent assets: {}", e)))?
+    }; // Lock released before disk checks
+    Ok(resolve_asset_results_on_disk(&base_mods_path, rows))
+}
+
+// Pinned assets keep their current state when randomize_enabled runs
+#[command]
+fn toggle_asset_pinned(asset_id: i64, is_pinned: bool, db_state: State<DbState>) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    let changes = conn.execute("UPDATE assets SET is_pinned = ?1 WHERE id = ?2", params![is_pinned, asset_id])
+        .map_err(|e| format!("Failed to update pinned flag: {}", e))?;
+    if changes == 0 {
+        return Err(format!("Asset with ID {} not found.", asset_id));
+    }
+    println!("[toggle_asset_pinned] Asset {} pinned: {}", asset_id, is_pinned);
+    Ok(())
+}
+
+// Sets (or with None / a blank tag, clears) category_tag on many assets without touching
+// anything else. Returns how many assets were updated; unknown IDs are ignored.
+#[command]
+fn set_category_tags(asset_ids: Vec<i64>, tag: Option<String>, db_state: State<DbState>) -> CmdResult<usize> {
+    let tag = tag.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    let mut conn = db_state.conn()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut updated = 0;
+    {
+        let mut stmt = tx.prepare("UPDATE assets SET category_tag = ?1, updated_at = CAST(strftime('%s','now') AS INTEGER) WHERE id = ?2")
+            .map_err(|e| format!("Failed to prepare category tag update: {}", e))?;
+        for asset_id in &asset_ids {
+            updated += stmt.execute(params![tag, asset_id])
+                .map_err(|e| format!("Failed to update category tag of asset {}: {}", asset_id, e))?;
+        }
+    }
+    tx.commit().map_err(|e| format!("Failed to commit category tags: {}", e))?;
+    println!("[set_category_tags] Set category tag {:?} on {} of {} assets", tag, updated, asset_ids.len());
+    Ok(updated)
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct ExclusiveGroup {
+    name: String,
+    enforced: bool,
+    asset_ids: Vec<i64>, // Members in the active profile
+}
+
+#[command]
+fn get_exclusive_groups(db_state: State<DbState>) -> CmdResult<Vec<ExclusiveGroup>> {
+    let conn = db_state.conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT g.name, g.enforced, a.id FROM exclusive_groups g
+         LEFT JOIN active_assets a ON a.exclusive_group = g.name
+         ORDER BY g.name, a.id"
+    ).map_err(|e| format!("Failed to prepare exclusive group query: {}", e))?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?, row.get::<_, Option<i64>>(2)?)))
+        .map_err(|e| format!("Failed to query exclusive groups: {}", e))?;
+
+    let mut groups: Vec<ExclusiveGroup> = Vec::new();
+    for row in rows {
+        let (name, enforced, asset_id) = row.map_err(|e| format!("Failed to read exclusive group row: {}", e))?;
+        if groups.last().map_or(true, |group| group.name != name) {
+            groups.push(ExclusiveGroup { name, enforced, asset_ids: Vec::new() });
+        }
+        if let (Some(group), Some(asset_id)) = (groups.last_mut(), asset_id) {
+            group.asset_ids.push(asset_id);
+        }
+    }
+    Ok(groups)
+}
+
+// Moves assets into an exclusive group, or with None / a blank name out of their group.
+// New groups start unenforced; groups left without members are dropped.
+#[command]
+fn set_exclusive_group(asset_ids: Vec<i64>, group_name: Option<String>, db_state: State<DbState>) -> CmdResult<usize> {
+    let group_name = group_name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty());
+    let mut conn = db_state.conn()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    if let Some(name) = &group_name {
+        tx.execute("INSERT OR IGNORE INTO exclusive_groups (name) VALUES (?1)", params![name])
+            .map_err(|e| format!("Failed to create exclusive group '{}': {}", name, e))?;
+    }
+    let mut updated = 0;
+    {
+        let mut stmt = tx.prepare("UPDATE assets SET exclusive_group = ?1 WHERE id = ?2")
+            .map_err(|e| format!("Failed to prepare exclusive group update: {}", e))?;
+        for asset_id in &asset_ids {
+            updated += stmt.execute(params![group_name, asset_id])
+                .map_err(|e| format!("Failed to update exclusive group of asset {}: {}", asset_id, e))?;
+        }
+    }
+    tx.execute("DELETE FROM exclusive_groups WHERE name NOT IN (SELECT exclusive_group FROM assets WHERE exclusive_group IS NOT NULL)", [])
+        .map_err(|e| format!("Failed to drop empty exclusive groups: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit exclusive group: {}", e))?;
+    println!("[set_exclusive_group] Set exclusive group {:?} on {} of {} assets", group_name, updated, asset_ids.len());
+    Ok(updated)
+}
+
+#[command]
+fn set_exclusive_group_enforced(group_name: String, enforced: bool, db_state: State<DbState>) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    let changes = conn.execute("UPDATE exclusive_groups SET enforced = ?1 WHERE name = ?2", params![enforced, group_name.trim()])
+        .map_err(|e| format!("Failed to update exclusive group: {}", e))?;
+    if changes == 0 {
+        return Err(format!("Exclusive group '{}' not found.", group_name.trim()));
+    }
+    println!("[set_exclusive_group_enforced] Group '{}' enforced: {}", group_name.trim(), enforced);
+    Ok(())
+}
+
+// Called before enabling an asset: disables the other enabled members of its group if that
+// group is enforced. A locked member fails the enable (unless forced) before anything is renamed.
+fn disable_exclusive_group_members(base_mods_path: &Path, asset_id: i64, force: bool, db_state: &DbState, op_log: &OperationLogState) -> CmdResult<()> {
+    let members: Vec<(i64, String, String, bool)> = {
+        let conn = db_state.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT a.id, a.name, a.folder_name, a.is_locked FROM active_assets a
+             JOIN exclusive_groups g ON g.name = a.exclusive_group AND g.enforced = 1
+             WHERE a.exclusive_group = (SELECT exclusive_group FROM assets WHERE id = ?1) AND a.id != ?1"
+        ).map_err(|e| format!("Failed to prepare exclusive group query: {}", e))?;
+        let rows = stmt.query_map(params![asset_id], |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?.replace("\\", "/"), row.get(3)?)))
+            .map_err(|e| format!("Failed to query exclusive group members: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read exclusive group members: {}", e))?
+    };
+    let enabled_members: Vec<_> = members.into_iter()
+        .filter(|(_, _, clean_relative_path, _)| matches!(resolve_asset_disk_state(base_mods_path, clean_relative_path), Some((true, _))))
+        .collect();
+    if !force {
+        if let Some((member_id, member_name, _, _)) = enabled_members.iter().find(|(_, _, _, is_locked)| *is_locked) {
+            return Err(format!("Cannot enable this mod: '{}' (ID {}) in the same exclusive group is locked. Unlock it first or pass force.", member_name, member_id));
+        }
+    }
+
+    let mut toggled = Vec::new();
+    let mut result = Ok(());
+    for (member_id, member_name, clean_relative_path, _) in enabled_members {
+        if let Err(e) = set_asset_enabled_on_disk(base_mods_path, &clean_relative_path, false) {
+            result = Err(format!("Failed to disable '{}' of the same exclusive group: {}", member_name, e));
+            break;
+        }
+        println!("[disable_exclusive_group_members] Disabled '{}' (ID {}) for asset {}", member_name, member_id, asset_id);
+        record_operation(op_log, Operation::StagedToggle { asset_id: member_id, clean_relative_path, was_enabled: true });
+        toggled.push((member_id, false));
+    }
+    record_asset_toggles(db_state, &toggled);
+    result
+}
+
+// Everything a single or bulk toggle needs besides the asset itself
+struct AssetToggleContext<'a> {
+    base_mods_path: &'a Path,
+    db_state: &'a DbState,
+    op_log: &'a OperationLogState,
+    app_handle: &'a AppHandle,
+    force: bool,
+    backup_inis: bool,
+}
+
+impl<'a> AssetToggleContext<'a> {
+    fn new(base_mods_path: &'a Path, db_state: &'a DbState, op_log: &'a OperationLogState, app_handle: &'a AppHandle, force: bool) -> CmdResult<Self> {
+        let backup_inis = is_ini_backup_on_toggle(&*db_state.conn()?);
+        Ok(AssetToggleContext { base_mods_path, db_state, op_log, app_handle, force, backup_inis })
+    }
+
+    // Puts one asset into `desired_enabled`: checks its lock, backs up its INIs if configured,
+    // disables the rest of its enforced exclusive group when enabling, then renames or
+    // (un)links it and records the undo entry. Returns false if it was already in that state.
+    // The caller stores the new state with record_asset_toggles.
+    fn set_enabled(&self, asset_id: i64, asset_name: &str, clean_relative_path: &str, desired_enabled: bool) -> CmdResult<bool> {
+        let (current_enabled, current_relative_path) = resolve_asset_disk_state(self.base_mods_path, clean_relative_path)
+            .ok_or_else(|| format!("Mod folder of '{}' not found on disk (path: '{}'). Did the folder get moved or deleted?", asset_name, clean_relative_path))?;
+        if current_enabled == desired_enabled {
+            return Ok(false);
+        }
+        if !self.force {
+            ensure_asset_unlocked(&*self.db_state.conn()?, asset_id).map_err(|e| e.to_string())?;
+        }
+        if self.backup_inis {
+            create_ini_backup(self.app_handle, asset_id, &self.base_mods_path.join(&current_relative_path))
+                .map_err(|e| format!("Failed to back up INI files of '{}' before toggling: {}", asset_name, e))?;
+        }
+        if desired_enabled {
+            disable_exclusive_group_members(self.base_mods_path, asset_id, self.force, self.db_state, self.op_log)?;
+        }
+
+        let is_staged = CACHED_CONFIG.is_symlink_enable_mode() || current_relative_path == staged_relative_path(clean_relative_path);
+        set_asset_enabled_on_disk(self.base_mods_path, clean_relative_path, desired_enabled)
+            .map_err(|e| format!("Failed to toggle mod '{}': {}", asset_name, e))?;
+        let new_relative_path = resolve_asset_disk_state(self.base_mods_path, clean_relative_path).map(|(_, path)| path);
+        let operation = match new_relative_path {
+            Some(new_relative_path) if !is_staged => Operation::Toggle {
+                asset_id,
+                from_path: self.base_mods_path.join(&current_relative_path),
+                to_path: self.base_mods_path.join(new_relative_path),
+            },
+            _ => Operation::StagedToggle { asset_id, clean_relative_path: clean_relative_path.to_string(), was_enabled: current_enabled },
+        };
+        record_operation(self.op_log, operation);
+        Ok(true)
+    }
+}
+
+// Locked assets are not toggled, randomized or deleted unless the command is called with force
+#[command]
+fn set_asset_locked(asset_id: i64, locked: bool, db_state: State<DbState>) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    let changes = conn.execute("UPDATE assets SET is_locked = ?1 WHERE id = ?2", params![locked, asset_id])
+        .map_err(|e| format!("Failed to update locked flag: {}", e))?;
+    if changes == 0 {
+        return Err(format!("Asset with ID {} not found.", asset_id));
+    }
+    println!("[set_asset_locked] Asset {} locked: {}", asset_id, locked);
+    Ok(())
+}
+
+fn ensure_asset_unlocked(conn: &Connection, asset_id: i64) -> Result<(), AppError> {
+    let is_locked: bool = conn.query_row("SELECT is_locked FROM assets WHERE id = ?1", params![asset_id], |row| row.get(0))
+        .optional()?
+        .unwrap_or(false);
+    if is_locked {
+        return Err(AppError::ModOperation(format!("Asset {} is locked. Unlock it first or pass force.", asset_id)));
+    }
+    Ok(())
+}
+
+// Bulk moves of a whole entity refuse as soon as one of its mods is locked
+fn ensure_assets_unlocked(conn: &Connection, asset_ids: &[i64]) -> Result<(), AppError> {
+    for asset_id in asset_ids {
+        ensure_asset_unlocked(conn, *asset_id)?;
+    }
+    Ok(())
+}
+
+fn load_locked_asset_ids(conn: &Connection) -> SqlResult<HashSet<i64>> {
+    let mut stmt = conn.prepare("SELECT id FROM active_assets WHERE is_locked = 1")?;
+    let ids = stmt.query_map([], |row| row.get(0))?.collect::<SqlResult<HashSet<i64>>>()?;
+    Ok(ids)
+}
+
+fn locked_asset_failure(asset_id: i64) -> BulkToggleFailure {
+    BulkToggleFailure { asset_id, error: "Asset is locked".to_string() }
+}
+
+// Stores the new enabled state of renamed assets and bumps their updated_at. Failures are
+// only logged; the renames already happened and reconcile_enabled_states can fix the drift.
+fn record_asset_toggles(db_state: &DbState, toggles: &[(i64, bool)]) {
+    if toggles.is_empty() {
+        return;
+    }
+    let conn = match db_state.conn() {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("[record_asset_toggles] {}, skipping state update for {} assets", e, toggles.len());
+            return;
+        }
+    };
+    let result = conn.prepare(
+        "UPDATE assets SET is_enabled = ?1, updated_at = CAST(strftime('%s','now') AS INTEGER),
+             last_enabled_at = CASE WHEN ?1 THEN CAST(strftime('%s','now') AS INTEGER) ELSE last_enabled_at END
+         WHERE id = ?2"
+    )
+        .and_then(|mut stmt| toggles.iter().try_for_each(|(id, enabled)| stmt.execute(params![enabled, id]).map(|_| ())));
+    if let Err(e) = result {
+        eprintln!("[record_asset_toggles] Failed to store enabled state: {}", e);
+    }
+}
+
+// Enabled state implied by a mod folder's on-disk name
+fn is_enabled_folder_path(path: &Path) -> bool {
+    path.file_name().map_or(true, |name| !is_disabled_folder_name(&name.to_string_lossy()))
+}
+
+// Compares assets.is_enabled with the disk for the active profile, fixing rows that drifted.
+// With `only_unknown` just the rows without a stored state are checked.
+fn reconcile_asset_enabled_states(conn: &Connection, base_mods_path: &Path, only_unknown: bool) -> Result<ReconcileEnabledSummary, AppError> {
+    let sql = if only_unknown {
+        "SELECT id, folder_name, is_enabled FROM active_assets WHERE is_enabled IS NULL"
+    } else {
+        "SELECT id, folder_name, is_enabled FROM active_assets"
+    };
+    let rows: Vec<(i64, String, Option<bool>)> = conn.prepare(sql)?
+        .query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?.replace("\\", "/"), row.get(2)?)))?
+        .collect::<SqlResult<Vec<_>>>()?;
+
+    let mut summary = ReconcileEnabledSummary { checked: rows.len(), updated: 0, missing: 0 };
+    let mut update_stmt = conn.prepare("UPDATE assets SET is_enabled = ?1 WHERE id = ?2")?;
+    for (asset_id, clean_relative_path, stored) in rows {
+        let on_disk = resolve_asset_disk_state(base_mods_path, &clean_relative_path).map(|(is_enabled, _)| is_enabled);
+        if on_disk.is_none() {
+            summary.missing += 1;
+        }
+        if on_disk != stored {
+            update_stmt.execute(params![on_disk, asset_id])?;
+            summary.updated += 1;
+        }
+    }
+    Ok(summary)
+}
+
+// Walks every asset of the active profile on disk to correct stored enabled states, e.g. after
+// folders were renamed outside the app.
+#[command]
+fn reconcile_enabled_states(db_state: State<DbState>) -> CmdResult<ReconcileEnabledSummary> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let conn = db_state.conn()?;
+    let summary = reconcile_asset_enabled_states(&conn, &base_mods_path, false).map_err(|e| e.to_string())?;
+    println!("[reconcile_enabled_states] Checked {} assets: {} updated, {} missing on disk", summary.checked, summary.updated, summary.missing);
+    Ok(summary)
+}
+
+// Enables one random asset per entity and disables the rest, for one entity slug or "all".
+// Pinned assets are never renamed; an entity with an enabled pinned asset just gets its other
+// assets disabled. Locked assets are treated as pinned unless `force` is set.
+// Assets missing from disk are ignored.
+// (id, name, clean path, pinned, enabled)
+type RandomizeCandidate = (i64, String, String, bool, bool);
+
+#[command]
+async fn randomize_enabled(
+    scope: String,
+    force: Option<bool>,
+    operation_id: Option<String>,
+    db_state: State<'_, DbState>,
+    op_log: State<'_, OperationLogState>,
+    app_handle: AppHandle
+) -> CmdResult<BulkToggleSummary> {
+    println!("[randomize_enabled] Randomizing scope '{}'", scope);
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    // (asset_id, entity_id, name, clean folder_name, is_pinned); locked counts as pinned unless forced
+    let honor_locks = !force.unwrap_or(false);
+    let assets: Vec<(i64, i64, String, String, bool)> = {
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+        let map_row = |row: &rusqlite::Row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get::<_, String>(3)?.replace("\\", "/"),
+            row.get::<_, bool>(4)? || (honor_locks && row.get::<_, bool>(5)?)));
+        let rows = if scope == "all" {
+            let mut stmt = conn.prepare("SELECT id, entity_id, name, folder_name, is_pinned, is_locked FROM active_assets ORDER BY entity_id, id")
+                .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+            stmt.query_map([], map_row).and_then(|rows| rows.collect::<SqlResult<Vec<_>>>())
+        } else {
+            let mut stmt = conn.prepare("SELECT a.id, a.entity_id, a.name, a.folder_name, a.is_pinned, a.is_locked FROM active_assets a JOIN entities e ON a.entity_id = e.id WHERE e.slug = ?1 ORDER BY a.id")
+                .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+            stmt.query_map(params![scope], map_row).and_then(|rows| rows.collect::<SqlResult<Vec<_>>>())
+        };
+        rows.map_err(|e| format!("Failed to query assets: {}", e))?
+    }; // Lock released before renaming
+    if assets.is_empty() {
+        return Err(format!("No mods found for scope '{}'.", scope));
+    }
+
+    // Decide the target state of every unpinned asset, entity by entity
+    let mut by_entity: BTreeMap<i64, Vec<RandomizeCandidate>> = BTreeMap::new();
+    for (asset_id, entity_id, name, clean_relative_path, is_pinned) in assets {
+        if let Some((is_enabled, _)) = resolve_asset_disk_state(&base_mods_path, &clean_relative_path) {
+            by_entity.entry(entity_id).or_default().push((asset_id, name, clean_relative_path, is_pinned, is_enabled));
+        }
+    }
+    let mut rng = rand::thread_rng();
+    let mut plan: Vec<(i64, String, String, bool)> = Vec::new(); // (id, name, path, desired_enabled)
+    for entity_assets in by_entity.values() {
+        let pinned_enabled = entity_assets.iter().any(|(_, _, _, pinned, enabled)| *pinned && *enabled);
+        let candidates: Vec<&RandomizeCandidate> = entity_assets.iter().filter(|a| !a.3).collect();
+        let chosen_id = if pinned_enabled { None } else { candidates.choose(&mut rng).map(|a| a.0) };
+        for (asset_id, name, path, _, _) in candidates {
+            plan.push((*asset_id, name.clone(), path.clone(), Some(*asset_id) == chosen_id));
+        }
+    }
+
+    let toggle = AssetToggleContext::new(&base_mods_path, &db_state, &op_log, &app_handle, !honor_locks)?;
+    let total = plan.len();
+    app_handle.emit_all(RANDOMIZE_START_EVENT, total).ok();
+    let mut summary = BulkToggleSummary { succeeded: Vec::new(), failed: Vec::new() };
+    let mut toggles: Vec<(i64, bool)> = Vec::new();
+    // Disable before enabling so an entity never has two mods enabled mid-way
+    plan.sort_by_key(|(_, _, _, desired_enabled)| *desired_enabled);
+    for (index, (asset_id, name, clean_relative_path, desired_enabled)) in plan.iter().enumerate() {
+        app_handle.emit_all(RANDOMIZE_PROGRESS_EVENT, &ApplyProgress {
+            operation_id: operation_id.clone(),
+            processed: index + 1,
+            total,
+            current_asset_id: Some(*asset_id),
+            message: format!("Processing: {} ({}/{})", name, index + 1, total),
+        }).ok();
+        match toggle.set_enabled(*asset_id, name, clean_relative_path, *desired_enabled) {
+            Ok(true) => {
+                summary.succeeded.push(*asset_id);
+                toggles.push((*asset_id, *desired_enabled));
+            }
+            Ok(false) => {} // Already in the drawn state
+            Err(e) => {
+                eprintln!("[randomize_enabled] Failed for asset '{}' (ID {}): {}", name, asset_id, e);
+                summary.failed.push(BulkToggleFailure { asset_id: *asset_id, error: e });
+            }
+        }
+    }
+
+    record_asset_toggles(&db_state, &toggles);
+
+    let summary_msg = format!("Randomized {} entities: {} mods updated, {} failed.", by_entity.len(), summary.succeeded.len(), summary.failed.len());
+    println!("[randomize_enabled] {}", summary_msg);
+    app_handle.emit_all(RANDOMIZE_COMPLETE_EVENT, &summary_msg).ok();
+    Ok(summary)
+}
+
+#[command]
+fn search_assets(query: String, limit: Option<i64>, db_state: State<DbState>) -> CmdResult<Vec<AssetSearchResult>> {
+    let trimmed_query = query.trim();
+    if trimmed_query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.unwrap_or(50).clamp(1, 500);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let conn = db_state.conn()?;
+
+    // --- FTS5 (prefix match on every term) ---
+    let fts_available: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'assets_fts')",
+        [], |row| row.get(0),
+    ).unwrap_or(false);
+    let mut rows: Vec<AssetSearchResult> = Vec::new();
+    if fts_available {
+        let fts_query = trimmed_query.split_whitespace()
+            .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let sql = format!("{} JOIN assets_fts f ON f.rowid = a.id WHERE assets_fts MATCH ?1 ORDER BY f.rank LIMIT ?2", ASSET_WITH_SLUGS_SELECT);
+        match conn.prepare(&sql).and_then(|mut stmt| {
+            stmt.query_map(params![fts_query, limit], map_asset_with_slugs_row)?.collect::<SqlResult<Vec<_>>>()
+        }) {
+            Ok(found) => rows = found,
+            Err(e) => eprintln!("[search_assets] FTS query failed, falling back to LIKE: {}", e),
+        }
+    }
+
+    // --- LIKE fallback (also covers substring matches FTS tokens miss, e.g. CJK text) ---
+    if rows.is_empty() {
+        let pattern = format!("%{}%", trimmed_query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let sql = format!(
+            "{} WHERE a.name LIKE ?1 ESCAPE '\\' OR a.description LIKE ?1 ESCAPE '\\' OR a.author LIKE ?1 ESCAPE '\\' OR a.category_tag LIKE ?1 ESCAPE '\\' ORDER BY a.name LIMIT ?2",
+            ASSET_WITH_SLUGS_SELECT
+        );
+        let mut stmt = conn.prepare(&sql).map_err(|e| format!("Failed to prepare search query: {}", e))?;
+        rows = stmt.query_map(params![pattern, limit], map_asset_with_slugs_row)
+            .map_err(|e| format!("Failed to run search query: {}", e))?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| format!("Failed to read search results: {}", e))?;
+    }
+    drop(conn);
+
+    let results = resolve_asset_results_on_disk(&base_mods_path, rows);
+
+    println!("[search_assets] Query '{}' returned {} results", trimmed_query, results.len());
+    Ok(results)
+}
+
+#[command]
+fn toggle_asset_enabled(entity_slug: String, asset: Asset, force: Option<bool>, db_state: State<DbState>, op_log: State<OperationLogState>, app_handle: AppHandle) -> CmdResult<bool> {
+    // Note: asset.folder_name passed from frontend is the CURRENT name on disk.
+    // We use the asset.id to get the CLEAN relative path from DB for robust path construction.
+    println!("[toggle_asset_enabled] Toggling asset: ID={}, Name={}, UI Folder='{}', UI Enabled State={}", asset.id, asset.name, asset.folder_name, asset.is_enabled);
+    {
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+    }
+
+    // Get BASE mods path
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    // Fetch the CLEAN STORED relative path from DB using asset ID
+    let clean_relative_path_from_db_str = {
+         let conn = db_state.conn()?;
+         conn.query_row::<String, _, _>(
+            "SELECT folder_name FROM assets WHERE id = ?1", // Expecting clean path here
+            params![asset.id],
+            |row| row.get(0),
+         ).map_err(|e| format!("Failed to get relative path from DB for asset ID {}: {}", asset.id, e))?
+    };
+     // Ensure forward slashes for PathBuf consistency
+     let clean_relative_path_from_db_str = clean_relative_path_from_db_str.replace("\\", "/");
+     println!("[toggle_asset_enabled] Clean relative path from DB: '{}'", clean_relative_path_from_db_str);
+
+    let (current_enabled, _) = resolve_asset_disk_state(&base_mods_path, &clean_relative_path_from_db_str)
+        .ok_or_else(|| format!("Cannot toggle mod '{}': Folder not found for DB path '{}'. Did the folder get moved or deleted?", asset.name, clean_relative_path_from_db_str))?;
+    let toggle = AssetToggleContext::new(&base_mods_path, &db_state, &op_log, &app_handle, force.unwrap_or(false))?;
+    toggle.set_enabled(asset.id, &asset.name, &clean_relative_path_from_db_str, !current_enabled)?;
+    record_asset_toggles(&db_state, &[(asset.id, !current_enabled)]);
+
+    println!("[toggle_asset_enabled] Switched '{}' in entity '{}'. New state: {}", asset.name, entity_slug, !current_enabled);
+    Ok(!current_enabled)
+}
+
+
+#[command]
+async fn set_assets_enabled(
+    asset_ids: Vec<i64>,
+    enabled: bool,
+    force: Option<bool>,
+    operation_id: Option<String>,
+    db_state: State<'_, DbState>,
+    op_log: State<'_, OperationLogState>,
+    app_handle: AppHandle
+) -> CmdResult<BulkToggleSummary> {
+    println!("[set_assets_enabled] Setting {} assets to enabled={}", asset_ids.len(), enabled);
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    // --- Fetch clean paths and names for all requested assets ---
+    let asset_rows: HashMap<i64, (String, String, bool)> = {
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare("SELECT folder_name, name, is_locked FROM assets WHERE id = ?1")
+            .map_err(|e| format!("Failed to prepare asset lookup: {}", e))?;
+        let mut rows = HashMap::new();
+        for asset_id in &asset_ids {
+            if let Some(row) = stmt.query_row(params![asset_id], |row| Ok((row.get::<_, String>(0)?.replace("\\", "/"), row.get::<_, String>(1)?, row.get::<_, bool>(2)?)))
+                .optional()
+                .map_err(|e| format!("Failed to look up asset {}: {}", asset_id, e))? {
+                rows.insert(*asset_id, row);
+            }
+        }
+        rows
+    }; // Lock released before renaming
+
+    let toggle = AssetToggleContext::new(&base_mods_path, &db_state, &op_log, &app_handle, force.unwrap_or(false))?;
+    let total = asset_ids.len();
+    app_handle.emit_all(BULK_TOGGLE_START_EVENT, total).ok();
+
+    let mut summary = BulkToggleSummary { succeeded: Vec::new(), failed: Vec::new() };
+    for (index, asset_id) in asset_ids.iter().enumerate() {
+        let (clean_relative_path, asset_name, is_locked) = match asset_rows.get(asset_id) {
+            Some(row) => row,
+            None => {
+                summary.failed.push(BulkToggleFailure { asset_id: *asset_id, error: "Asset not found in database".to_string() });
+                continue;
+            }
+        };
+        // Only an actual state change counts as touching a locked asset
+        let would_change = matches!(resolve_asset_disk_state(&base_mods_path, clean_relative_path), Some((is_enabled, _)) if is_enabled != enabled);
+        if *is_locked && would_change && !force.unwrap_or(false) {
+            summary.failed.push(locked_asset_failure(*asset_id));
+            continue;
+        }
+
+        app_handle.emit_all(BULK_TOGGLE_PROGRESS_EVENT, &ApplyProgress {
+            operation_id: operation_id.clone(),
+            processed: index + 1,
+            total,
+            current_asset_id: Some(*asset_id),
+            message: format!("Processing: {} ({}/{})", asset_name, index + 1, total),
+        }).ok();
+
+        match toggle.set_enabled(*asset_id, asset_name, clean_relative_path, enabled) {
+            Ok(_) => summary.succeeded.push(*asset_id),
+            Err(e) => {
+                eprintln!("[set_assets_enabled] Failed for asset '{}' (ID {}): {}", asset_name, asset_id, e);
+                summary.failed.push(BulkToggleFailure { asset_id: *asset_id, error: e });
+            }
+        }
+    }
+
+    let toggles: Vec<(i64, bool)> = summary.succeeded.iter().map(|id| (*id, enabled)).collect();
+    record_asset_toggles(&db_state, &toggles);
+
+    let summary_msg = format!("Updated {} mods, {} failed.", summary.succeeded.len(), summary.failed.len());
+    println!("[set_assets_enabled] {}", summary_msg);
+    app_handle.emit_all(BULK_TOGGLE_COMPLETE_EVENT, &summary_msg).ok();
+    Ok(summary)
+}
+
+// Disables every enabled mod, first saving the current state as a hidden auto-snapshot
+// preset so it can be restored with apply_preset.
+#[command]
+async fn disable_all_mods(
+    force: Option<bool>,
+    operation_id: Option<String>,
+    db_state: State<'_, DbState>,
+    op_log: State<'_, OperationLogState>,
+    app_handle: AppHandle
+) -> CmdResult<DisableAllSummary> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let snapshot = {
+        let mut conn = db_state.conn()?;
+        // Checked here too so no snapshot preset is left behind
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+        let name = format!("{}{}", AUTO_SNAPSHOT_PRESET_PREFIX, unix_timestamp_millis());
+        tx.execute("INSERT INTO presets (name) VALUES (?1)", params![name])
+            .map_err(|e| format!("Failed to create snapshot preset: {}", e))?;
+        let preset_id = tx.last_insert_rowid();
+        save_current_state_to_preset(&tx, &base_mods_path, preset_id)?;
+        let pruned_count = tx.execute(
+            "DELETE FROM presets WHERE id IN (
+                 SELECT id FROM active_presets WHERE SUBSTR(name, 1, LENGTH(?1)) = ?1 ORDER BY id DESC LIMIT -1 OFFSET ?2
+             )",
+            params![AUTO_SNAPSHOT_PRESET_PREFIX, AUTO_SNAPSHOT_KEEP_COUNT],
+        ).map_err(|e| format!("Failed to prune old snapshot presets: {}", e))?;
+        tx.commit().map_err(|e| format!("Failed to commit snapshot preset: {}", e))?;
+        if pruned_count > 0 {
+            println!("[disable_all_mods] Pruned {} old snapshot presets", pruned_count);
+        }
+        println!("[disable_all_mods] Saved snapshot preset '{}' (ID {})", name, preset_id);
+        Preset { id: preset_id, name, is_favorite: false }
+    };
+
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let toggle = AssetToggleContext::new(&base_mods_path, &db_state, &op_log, &app_handle, force.unwrap_or(false))?;
+    let result = set_all_assets_enabled(&toggle, false, operation_id)?;
+    Ok(DisableAllSummary { snapshot, result })
+}
+
+#[command]
+async fn enable_all_mods(
+    force: Option<bool>,
+    operation_id: Option<String>,
+    db_state: State<'_, DbState>,
+    op_log: State<'_, OperationLogState>,
+    app_handle: AppHandle
+) -> CmdResult<BulkToggleSummary> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let toggle = AssetToggleContext::new(&base_mods_path, &db_state, &op_log, &app_handle, force.unwrap_or(false))?;
+    set_all_assets_enabled(&toggle, true, operation_id.unwrap_or_else(new_operation_id))
+}
+
+// Renames every active asset not already in the desired state, emitting bulk://toggle events.
+// Assets missing on disk are left out; locked ones are reported as failed unless `force` is set.
+fn set_all_assets_enabled(toggle: &AssetToggleContext, enabled: bool, operation_id: String) -> CmdResult<BulkToggleSummary> {
+    let (db_state, app_handle, base_mods_path, force) = (toggle.db_state, toggle.app_handle, toggle.base_mods_path, toggle.force);
+    let assets: Vec<(i64, String, String, bool)> = {
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare("SELECT id, folder_name, name, is_locked FROM active_assets ORDER BY id")
+            .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?.replace("\\", "/"), row.get(2)?, row.get(3)?)))
+            .map_err(|e| format!("Failed to query assets: {}", e))
+            .and_then(|rows| rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e)))?
+    }; // Lock released before renaming
+
+    let mut summary = BulkToggleSummary { succeeded: Vec::new(), failed: Vec::new() };
+    let to_change: Vec<(i64, String, String)> = assets.into_iter()
+        .filter(|(_, clean_relative_path, _, _)| {
+            matches!(resolve_asset_disk_state(base_mods_path, clean_relative_path), Some((is_enabled, _)) if is_enabled != enabled)
+        })
+        .filter_map(|(asset_id, clean_relative_path, asset_name, is_locked)| {
+            if is_locked && !force {
+                summary.failed.push(locked_asset_failure(asset_id));
+                None
+            } else {
+                Some((asset_id, clean_relative_path, asset_name))
+            }
+        })
+        .collect();
+    println!("[set_all_assets_enabled] Setting {} mods to enabled={}", to_change.len(), enabled);
+
+    let total = to_change.len();
+    app_handle.emit_all(BULK_TOGGLE_START_EVENT, total).ok();
+    for (index, (asset_id, clean_relative_path, asset_name)) in to_change.iter().enumerate() {
+        app_handle.emit_all(BULK_TOGGLE_PROGRESS_EVENT, &ApplyProgress {
+            operation_id: operation_id.clone(),
+            processed: index + 1,
+            total,
+            current_asset_id: Some(*asset_id),
+            message: format!("Processing: {} ({}/{})", asset_name, index + 1, total),
+        }).ok();
+        match toggle.set_enabled(*asset_id, asset_name, clean_relative_path, enabled) {
+            Ok(_) => summary.succeeded.push(*asset_id),
+            Err(e) => {
+                eprintln!("[set_all_assets_enabled] Failed for asset '{}' (ID {}): {}", asset_name, asset_id, e);
+                summary.failed.push(BulkToggleFailure { asset_id: *asset_id, error: e });
+            }
+        }
+    }
+
+    let toggles: Vec<(i64, bool)> = summary.succeeded.iter().map(|id| (*id, enabled)).collect();
+    record_asset_toggles(db_state, &toggles);
+
+    let summary_msg = format!("{} {} mods, {} failed.", if enabled { "Enabled" } else { "Disabled" }, summary.succeeded.len(), summary.failed.len());
+    println!("[set_all_assets_enabled] {}", summary_msg);
+    app_handle.emit_all(BULK_TOGGLE_COMPLETE_EVENT, &summary_msg).ok();
+    Ok(summary)
+}
+
+// Lists an asset's variants with their enabled state read from disk
+#[command]
+fn get_asset_variants(asset_id: i64, db_state: State<DbState>) -> CmdResult<Vec<AssetVariant>> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let conn = db_state.conn()?;
+    load_asset_variants(&conn, &base_mods_path, asset_id).map_err(|e| e.to_string())
+}
+
+fn load_asset_variants(conn: &Connection, base_mods_path: &Path, asset_id: i64) -> Result<Vec<AssetVariant>, AppError> {
+    let location = get_asset_location_info(conn, asset_id)?;
+    let (_, current_relative_path) = resolve_asset_disk_state(base_mods_path, &location.clean_relative_path)
+        .ok_or_else(|| AppError::NotFound(format!("Mod folder not found on disk (path: '{}')", location.clean_relative_path)))?;
+    let asset_folder = base_mods_path.join(current_relative_path);
+
+    let mut stmt = conn.prepare("SELECT id, name, folder_name FROM asset_variants WHERE asset_id = ?1 ORDER BY name COLLATE NOCASE")?;
+    let variants = stmt.query_map(params![asset_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+        .collect::<SqlResult<Vec<_>>>()?
+        .into_iter()
+        .map(|(id, name, folder_name)| AssetVariant {
+            id,
+            asset_id,
+            name,
+            is_enabled: asset_folder.join(&folder_name).is_dir(),
+            folder_name,
+        })
+        .collect();
+    Ok(variants)
+}
+
+// Enables the chosen variant's folder and disables its siblings (DISABLED_ prefix, as for assets)
+#[command]
+fn set_active_variant(asset_id: i64, variant_id: i64, force: Option<bool>, db_state: State<DbState>) -> CmdResult<Vec<AssetVariant>> {
+    println!("[set_active_variant] Asset {}: activating variant {}", asset_id, variant_id);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let conn = db_state.conn()?;
+    ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+    if !force.unwrap_or(false) {
+        ensure_asset_unlocked(&conn, asset_id).map_err(|e| e.to_string())?;
+    }
+
+    let variants = load_asset_variants(&conn, &base_mods_path, asset_id).map_err(|e| e.to_string())?;
+    if !variants.iter().any(|v| v.id == variant_id) {
+        return Err(format!("Variant {} does not belong to asset {}", variant_id, asset_id));
+    }
+    let location = get_asset_location_info(&conn, asset_id).map_err(|e| e.to_string())?;
+    let (_, current_relative_path) = resolve_asset_disk_state(&base_mods_path, &location.clean_relative_path)
+        .ok_or_else(|| format!("Mod folder not found on disk (path: '{}')", location.clean_relative_path))?;
+    let asset_folder = base_mods_path.join(current_relative_path);
+
+    // Disable siblings first so two variants are never enabled at the same time
+    let (chosen, siblings): (Vec<&AssetVariant>, Vec<&AssetVariant>) = variants.iter().partition(|v| v.id == variant_id);
+    for variant in siblings.into_iter().chain(chosen) {
+        set_folder_enabled_by_rename(&asset_folder, &variant.folder_name, variant.id == variant_id)
+            .map_err(|e| format!("Failed to switch variant '{}': {}", variant.name, e))?;
+    }
+
+    load_asset_variants(&conn, &base_mods_path, asset_id).map_err(|e| e.to_string())
+}
+
+#[command]
+fn get_asset_image_path(
+    asset_id: i64,
+    db_state: State<DbState>
+) -> CmdResult<String> {
+    // --- Data needed from DB ---
+    let base_mods_path_str: String;
+    let clean_relative_path_str: String;
+    let image_filename: String;
+
+    // --- Acquire lock *only* for DB reads ---
+    { // Scope for the pooled connection
+        println!("[get_asset_image_path ID: {}] Acquiring DB lock...", asset_id);
+        let conn_guard = db_state.conn().map_err(|e| format!("[get_asset_image_path ID: {}] {}", asset_id, e))?;
+        let conn = &*conn_guard;
+
+        // 1. Get base mods path from settings
+        base_mods_path_str = get_setting_value(conn, SETTINGS_KEY_MODS_FOLDER)
+            .map_err(|e| format!("[get_asset_image_path ID: {}] DB Error getting base path: {}", asset_id, e))?
+            .ok_or_else(|| format!("[get_asset_image_path ID: {}] Mods folder path not set", asset_id))?;
+
+        // 2. Fetch asset info (clean path and image filename) using asset_id
+        let (fetched_path, fetched_image_opt): (String, Option<String>) = conn.query_row(
+            "SELECT folder_name, image_filename FROM assets WHERE id = ?1",
+            params![asset_id],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("[get_asset_image_path ID: {}] Asset not found.", asset_id),
+            _ => format!("[get_asset_image_path ID: {}] DB Error getting asset info: {}", asset_id, e),
+        })?;
+
+        clean_relative_path_str = fetched_path.replace("\\", "/"); // Normalize path separators immediately
+        image_filename = match fetched_image_opt {
+             Some(name) if !name.is_empty() => name,
+             _ => {
+                 // If no image filename in DB, we can stop early. Release lock implicitly.
+                 return Err(format!("[get_asset_image_path ID: {}] Asset does not have an associated image filename.", asset_id));
+             }
+        };
+
+        println!("[get_asset_image_path ID: {}] Releasing DB lock.", asset_id);
+        // `conn_guard` is dropped here, returning the connection to the pool
+    }
+    // --- Lock is released ---
+
+    // --- Filesystem operations (No DB lock needed) ---
+    println!("[get_asset_image_path ID: {}] Performing filesystem checks...", asset_id);
+    let base_mods_path = PathBuf::from(base_mods_path_str);
+    // 3. Determine current folder path (enabled, disabled or staged)
+    let current_mod_folder_path = match resolve_asset_disk_state(&base_mods_path, &clean_relative_path_str) {
+        Some((is_enabled, current_relative_path)) => {
+            let path = base_mods_path.join(current_relative_path);
+            println!("[get_asset_image_path ID: {}] Found {} path: {}", asset_id, if is_enabled { "enabled" } else { "disabled" }, path.display());
+            path
+        }
+        None => {
+            // Folder not found. This isn't necessarily an error for *this* function,
+            // but we can't construct the image path. Return an error.
+            println!("[get_asset_image_path ID: {}] Mod folder not found on disk.", asset_id);
+            return Err(format!("Mod folder for asset ID {} not found on disk (clean path '{}').", asset_id, clean_relative_path_str));
+        }
+    };
+
+    // 4. Construct the FULL path to the image file within the found folder
+    let image_full_path = current_mod_folder_path.join(&image_filename);
+    println!("[get_asset_image_path ID: {}] Checking image file: {}", asset_id, image_full_path.display());
+
+    // 5. Check if the image file *itself* exists
+    if !image_full_path.is_file() {
+        println!("[get_asset_image_path ID: {}] Image file does not exist.", asset_id);
+        return Err(format!("Image file '{}' not found in mod folder '{}'.", image_filename, current_mod_folder_path.display()));
+    }
+
+    // Return the absolute path string for the frontend
+    println!("[get_asset_image_path ID: {}] Success, returning path: {}", asset_id, image_full_path.display());
+    Ok(image_full_path.to_string_lossy().into_owned())
+}
+
+// Returns a downscaled copy of the asset's preview image, generated on first request and cached
+// under the app data dir. Ok(None) means the asset has no preview image to thumbnail.
+#[command]
+async fn get_asset_thumbnail(
+    asset_id: i64,
+    max_dim: Option<u32>,
+    db_state: State<'_, DbState>,
+    app_handle: AppHandle
+) -> CmdResult<Option<String>> {
+    let max_dim = max_dim.unwrap_or(DEFAULT_THUMBNAIL_MAX_DIM).clamp(16, 2048);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    let (clean_relative_path, image_filename): (String, Option<String>) = {
+        let conn = db_state.conn()?;
+        conn.query_row(
+            "SELECT folder_name, image_filename FROM assets WHERE id = ?1",
+            params![asset_id],
+            |row| Ok((row.get::<_, String>(0)?.replace("\\", "/"), row.get(1)?)),
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Asset ID {} not found.", asset_id),
+            _ => format!("DB Error getting asset info: {}", e),
+        })?
+    }; // Lock released before touching the filesystem
+
+    let image_filename = match image_filename {
+        Some(name) if !name.is_empty() => name,
+        _ => return Ok(None),
+    };
+    let source_path = match resolve_asset_disk_state(&base_mods_path, &clean_relative_path) {
+        Some((_, current_relative_path)) => base_mods_path.join(current_relative_path).join(&image_filename),
+        None => return Ok(None),
+    };
+    let source_metadata = match fs::metadata(&source_path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return Ok(None),
+    };
+
+    // Key on source path + mtime + size so an edited or replaced preview gets a fresh thumbnail
+    let source_mtime = source_metadata.modified().ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    std::hash::Hash::hash(&source_path, &mut hasher);
+    std::hash::Hash::hash(&source_mtime, &mut hasher);
+    std::hash::Hash::hash(&source_metadata.len(), &mut hasher);
+    std::hash::Hash::hash(&max_dim, &mut hasher);
+    let cache_key = std::hash::Hasher::finish(&hasher);
+
+    let cache_dir = get_app_data_dir(&app_handle).map_err(|e| e.to_string())?.join(THUMBNAIL_CACHE_FOLDER_NAME);
+    let asset_prefix = format!("{}_", asset_id);
+    let thumbnail_path = cache_dir.join(format!("{}{:016x}.jpg", asset_prefix, cache_key));
+    if thumbnail_path.is_file() {
+        return Ok(Some(thumbnail_path.to_string_lossy().into_owned()));
+    }
+
+    let task = async_runtime::spawn_blocking(move || -> CmdResult<String> {
+        fs::create_dir_all(&cache_dir).map_err(|e| format!("Failed to create thumbnail cache dir: {}", e))?;
+        let source_image = image::open(&source_path)
+            .map_err(|e| format!("Failed to decode preview image '{}': {}", source_path.display(), e))?;
+        let thumbnail = if source_image.width() > max_dim || source_image.height() > max_dim {
+            source_image.thumbnail(max_dim, max_dim)
+        } else {
+            source_image
+        };
+        // JPEG has no alpha channel, flatten to RGB first
+        image::DynamicImage::ImageRgb8(thumbnail.to_rgb8())
+            .save_with_format(&thumbnail_path, image::ImageFormat::Jpeg)
+            .map_err(|e| format!("Failed to write thumbnail '{}': {}", thumbnail_path.display(), e))?;
+
+        // Drop thumbnails generated from older versions of this asset's preview
+        if let Ok(entries) = fs::read_dir(&cache_dir) {
+            for entry in entries.flatten() {
+                let entry_path = entry.path();
+                let is_stale = entry_path != thumbnail_path
+                    && entry.file_name().to_string_lossy().starts_with(&asset_prefix);
+                if is_stale {
+                    fs::remove_file(&entry_path).ok();
+                }
+            }
+        }
+        println!("[get_asset_thumbnail ID: {}] Generated thumbnail: {}", asset_id, thumbnail_path.display());
+        Ok(thumbnail_path.to_string_lossy().into_owned())
+    });
+
+    match task.await {
+        Ok(Ok(path)) => Ok(Some(path)),
+        Ok(Err(e)) => {
+            // A corrupt or unsupported preview shouldn't break the grid, the frontend shows its placeholder
+            eprintln!("[get_asset_thumbnail ID: {}] {}", asset_id, e);
+            Ok(None)
+        }
+        Err(e) => Err(format!("Thumbnail task failed: {}", e)),
+    }
+}
+
+#[command]
+fn open_mods_folder(_app_handle: AppHandle, db_state: State<DbState>) -> CmdResult<()> { // Mark app_handle unused
+    let mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    println!("Opening mods folder: {}", mods_path.display());
+
+    if !mods_path.exists() || !mods_path.is_dir() { // Check it's a directory
+        eprintln!("Configured mods folder does not exist or is not a directory: {}", mods_path.display());
+        return Err(format!("Configured mods folder does not exist or is not a directory: {}", mods_path.display()));
+    }
+
+    open_folder_in_file_manager(&mods_path)
+}
+
+// Helper to open a directory in the OS file manager (explorer / open / xdg-open)
+fn open_folder_in_file_manager(folder_path: &Path) -> CmdResult<()> {
+    let command_name;
+    let arg; // Variable to hold the single argument string
+
+    // Determine OS-specific command and prepare the argument
+    if cfg!(target_os = "windows") {
+        command_name = "explorer";
+        // Windows explorer doesn't always handle forward slashes well, especially in UNC paths, canonicalize might help sometimes
+        // Or just ensure it's a string representation
+         arg = folder_path.to_string_lossy().to_string();
+    } else if cfg!(target_os = "macos") {
+        command_name = "open";
+         arg = folder_path.to_str().ok_or("Invalid path string for macOS")?.to_string();
+    } else { // Assume Linux/Unix-like
+        command_name = "xdg-open";
+         arg = folder_path.to_str().ok_or("Invalid path string for Linux")?.to_string();
+    }
+
+    println!("Executing: {} \"{}\"", command_name, arg); // Log with quotes for clarity
+
+    // FIX: Use .args() with a slice containing the single argument
+    match Command::new(command_name).args(&[arg]).spawn() {
+        Ok((_, _child)) => {
+             println!("File explorer command spawned successfully.");
+             Ok(())
+        },
+        Err(e) => {
+             eprintln!("Failed to spawn file explorer command '{}': {}", command_name, e);
+             Err(format!("Failed to open folder using '{}': {}", command_name, e))
+        }
+    }
+}
+
+// Opens the file manager with `item_path` selected where the platform supports it
+// (explorer /select, and open -R); elsewhere opens the containing folder.
+fn reveal_in_file_manager(item_path: &Path) -> CmdResult<()> {
+    let (command_name, args) = if cfg!(target_os = "windows") {
+        // explorer needs backslashes for /select to work
+        ("explorer", vec!["/select,".to_string(), item_path.to_string_lossy().replace("/", "\\")])
+    } else if cfg!(target_os = "macos") {
+        ("open", vec!["-R".to_string(), item_path.to_str().ok_or("Invalid path string for macOS")?.to_string()])
+    } else {
+        let parent = item_path.parent().ok_or("Cannot determine containing folder")?;
+        return open_folder_in_file_manager(parent);
+    };
+
+    println!("Executing: {} {:?}", command_name, args);
+    match Command::new(command_name).args(&args).spawn() {
+        Ok((_, _child)) => Ok(()),
+        Err(e) => {
+             eprintln!("Failed to spawn file explorer command '{}': {}", command_name, e);
+             Err(format!("Failed to open folder using '{}': {}", command_name, e))
+        }
+    }
+}
+
+#[command]
+fn get_app_data_path(app_handle: AppHandle) -> CmdResult<String> {
+    // Lets users locate the SQLite DB and config for support/backups
+    let data_dir = get_app_data_dir(&app_handle).map_err(|e| e.to_string())?;
+    Ok(data_dir.to_string_lossy().into_owned())
+}
+
+#[command]
+fn reveal_app_data_folder(app_handle: AppHandle) -> CmdResult<()> {
+    let data_dir = get_app_data_dir(&app_handle).map_err(|e| e.to_string())?;
+    println!("Opening app data folder: {}", data_dir.display());
+
+    if !data_dir.is_dir() {
+        eprintln!("App data folder does not exist: {}", data_dir.display());
+        return Err(format!("App data folder does not exist: {}", data_dir.display()));
+    }
+
+    open_folder_in_file_manager(&data_dir)
+}
+
+#[command]
+async fn scan_mods_directory(
+    force_full: bool,
+    dry_run: bool,
+    min_found_ratio: Option<f64>,
+    force_prune: Option<bool>,
+    prune_missing: Option<bool>,
+    operation_id: Option<String>,
+    app_handle: AppHandle
+) -> CmdResult<()> {
+    let options = ScanOptions {
+        subpath: None,
+        force_full,
+        dry_run,
+        prune_guard: PruneGuard {
+            prune_missing: prune_missing.unwrap_or(false),
+            ..PruneGuard::from_options(min_found_ratio, force_prune)
+        },
+    };
+    let config_handle = app_handle.clone();
+    let db_state = config_handle.state::<DbState>();
+    let cancel_flag = config_handle.state::<CancellationState>().scan.clone();
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let result = run_mods_scan(&db_state, app_handle, options, cancel_flag, operation_id).await;
+    // Rebuild deduction maps on next use in case entities changed since they were cached
+    config_handle.state::<CachedConfigState>().invalidate_deduction_maps();
+    result
+}
+
+#[command]
+async fn scan_subpath(
+    relative_path: String,
+    operation_id: Option<String>,
+    db_state: State<'_, DbState>,
+    cancel_state: State<'_, CancellationState>,
+    app_handle: AppHandle
+) -> CmdResult<()> {
+    // Targeted rescans are small, so always re-deduce everything in scope
+    let config_handle = app_handle.clone();
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let options = ScanOptions { subpath: Some(relative_path), force_full: true, dry_run: false, prune_guard: PruneGuard::default() };
+    let result = run_mods_scan(&db_state, app_handle, options, cancel_state.scan.clone(), operation_id).await;
+    config_handle.state::<CachedConfigState>().invalidate_deduction_maps();
+    result
+}
+
+// Meant to be called once by the frontend after launch. Runs an incremental scan if the autoscan
+// setting is on and the mods folder changed since the last full scan; returns whether it scanned.
+// A missing mods folder (unmounted drive) is skipped outright, and the scan keeps the default
+// prune guard, so an autoscan can't wipe the library.
+#[command]
+async fn maybe_autoscan(
+    operation_id: Option<String>,
+    db_state: State<'_, DbState>,
+    cancel_state: State<'_, CancellationState>,
+    app_handle: AppHandle
+) -> CmdResult<bool> {
+    let last_scan_at = {
+        let conn = db_state.conn()?;
+        if !matches!(get_setting_value(&conn, SETTINGS_KEY_AUTOSCAN), Ok(Some(value)) if value == "true") {
+            return Ok(false);
+        }
+        get_setting_value(&conn, SETTINGS_KEY_LAST_SCAN_COMPLETED_AT).ok().flatten().and_then(|value| value.parse::<i64>().ok())
+    };
+    let base_mods_path = match get_mods_base_path_from_settings(&db_state) {
+        Ok(path) if path.is_dir() => path,
+        Ok(path) => {
+            println!("[maybe_autoscan] Mods folder {} not found (unmounted drive?), skipping autoscan.", path.display());
+            return Ok(false);
+        }
+        Err(_) => return Ok(false),
+    };
+
+    let folder_mtime = mods_folder_mtime_secs(&base_mods_path);
+    if let (Some(last_scan_at), Some(folder_mtime)) = (last_scan_at, folder_mtime) {
+        if folder_mtime < last_scan_at {
+            println!("[maybe_autoscan] Mods folder unchanged since the last scan, skipping autoscan.");
+            return Ok(false);
+        }
+    }
+
+    println!("[maybe_autoscan] Mods folder changed (mtime {:?}, last scan {:?}), scanning.", folder_mtime, last_scan_at);
+    let config_handle = app_handle.clone();
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let options = ScanOptions { subpath: None, force_full: false, dry_run: false, prune_guard: PruneGuard::default() };
+    let result = run_mods_scan(&db_state, app_handle, options, cancel_state.scan.clone(), operation_id).await;
+    config_handle.state::<CachedConfigState>().invalidate_deduction_maps();
+    result.map(|_| true)
+}
+
+// Asks a running scan to stop. Folders already written stay in the DB; nothing is pruned.
+#[command]
+fn cancel_scan(cancel_state: State<CancellationState>) -> CmdResult<()> {
+    println!("[cancel_scan] Cancellation requested");
+    cancel_state.scan.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+// What a run_mods_scan covers and how it treats the DB
+struct ScanOptions {
+    // Only this folder is walked, and pruning is limited to assets whose stored path lies underneath it
+    subpath: Option<String>,
+    // Unless set, folders whose mtime matches `assets.last_scanned_mtime` skip deduction
+    force_full: bool,
+    // Nothing is renamed on disk and all DB writes are rolled back; the would-be additions and
+    // prunes are emitted as a scan://preview event instead
+    dry_run: bool,
+    // Protects against mass pruning when the mods drive is missing or unmounted
+    prune_guard: PruneGuard,
+}
+
+// Shared scan implementation, see ScanOptions.
+// Setting `cancel_flag` stops the walk and deduction early; a cancelled scan never prunes.
+async fn run_mods_scan(
+    db_state: &DbState,
+    app_handle: AppHandle,
+    options: ScanOptions,
+    cancel_flag: Arc<AtomicBool>,
+    operation_id: String
+) -> CmdResult<()> {
+    let ScanOptions { subpath, force_full, dry_run, prune_guard } = options;
+    cancel_flag.store(false, Ordering::SeqCst);
+    // Recorded for maybe_autoscan; folders changed while the scan runs still count as newer
+    let scan_started_at = unix_timestamp_millis() / 1000;
+    println!("Starting robust mod directory scan with pruning (force_full: {}, dry_run: {})...", force_full, dry_run);
+    let base_mods_path = get_mods_base_path_from_settings(db_state).map_err(|e| e.to_string())?;
+    println!("Scanning base path: {}", base_mods_path.display());
+
+    if !base_mods_path.is_dir() {
+        let err_msg = format!("Mods directory path is not a valid directory: {}", base_mods_path.display());
+        emit_operation_result(&app_handle, SCAN_ERROR_EVENT, &operation_id, err_msg.clone());
+        return Err(err_msg);
+    }
+
+    // --- Resolve scan root and prune scope ---
+    // prune_scope holds the CLEAN relative prefix (as stored in assets.folder_name) for subpath scans
+    let (scan_root, prune_scope): (PathBuf, Option<String>) = match &subpath {
+        None => (base_mods_path.clone(), None),
+        Some(rel) => {
+            let rel_clean = rel.replace("\\", "/").trim_matches('/').to_string();
+            let rel_path = PathBuf::from(&rel_clean);
+            if rel_clean.is_empty() || rel_path.is_absolute()
+                || rel_path.components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+                let err_msg = format!("Invalid scan subpath: '{}'", rel);
+                emit_operation_result(&app_handle, SCAN_ERROR_EVENT, &operation_id, err_msg.clone());
+                return Err(err_msg);
+            }
+            let root = base_mods_path.join(&rel_path);
+            if !root.is_dir() {
+                let err_msg = format!("Scan subpath is not a valid directory: {}", root.display());
+                emit_operation_result(&app_handle, SCAN_ERROR_EVENT, &operation_id, err_msg.clone());
+                return Err(err_msg);
+            }
+            // A disabled mod folder is stored under its clean name
+            let scope = clean_folder_relative_path(&rel_clean);
+            println!("Restricting scan to subpath: {} (prune scope '{}')", root.display(), scope);
+            (root, Some(scope))
+        }
+    };
+    // The base folder itself is never a mod, but a targeted subpath may be one
+    let walk_min_depth = if prune_scope.is_some() { 0 } else { 1 };
+
+    // --- Preparation ---
+    let (deduction_maps, safe_mode, hash_during_scan) = {
+        let conn_guard = db_state.conn()?;
+        let conn = &*conn_guard;
+        let maps = cached_deduction_maps(&app_handle.state::<CachedConfigState>(), conn).map_err(|e| format!("Failed to pre-fetch deduction maps: {}", e))?;
+        (maps, is_safe_mode(conn), is_scan_hashing_enabled(conn))
+    };
+    // "DISABLED" without the underscore is only repaired under the default DISABLED_ convention
+    let fix_legacy_disabled_names = CACHED_CONFIG.disable_convention() == DisableConvention::default();
+    println!("[Scan Prep] Deduction maps loaded.");
+
+    let db_pool = db_state.0.clone();
+    let base_mods_path_clone = base_mods_path.clone();
+    let app_handle_clone = app_handle.clone();
+    let maps_clone = deduction_maps.clone();
+
+    println!("[Scan Prep] Calculating total potential mod folders...");
+    let potential_mod_folders_for_count: Vec<PathBuf> = WalkDir::new(&scan_root)
+        .min_depth(walk_min_depth)
+        .into_iter()
+        .filter_map(|e| e.ok().filter(|entry| entry.file_type().is_dir()))
+        .filter(|e| {
+             // Temporary check for rename condition as well for count (might be slightly inaccurate if rename fails later)
+             let path = e.path();
+             let filename = path.file_name().unwrap_or_default().to_string_lossy();
+             // Check for INI OR if it needs renaming (so it's counted)
+             has_ini_file(&path.to_path_buf())
+                 || (fix_legacy_disabled_names && filename.starts_with("DISABLED") && !is_disabled_folder_name(&filename))
+         })
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    let total_to_process = potential_mod_folders_for_count.len();
+    println!("[Scan Prep] Found {} potential mod folders for progress total (includes folders needing rename).", total_to_process);
+
+    app_handle.emit_all(SCAN_PROGRESS_EVENT, ScanProgress {
+            operation_id: operation_id.clone(),
+            processed: 0, total: total_to_process, current_path: None, message: "Starting scan...".to_string()
+        }).unwrap_or_else(|e| eprintln!("Failed to emit initial scan progress: {}", e));
+
+
+    // --- Process folders and collect FOUND asset IDs in a blocking task ---
+    let result_operation_id = operation_id.clone();
+    let scan_task = async_runtime::spawn_blocking(move || {
+        // Borrow a pooled connection for the blocking task
+        let conn = db_pool.get().map_err(|e| format!("Failed to get DB connection in scan task: {}", e))?;
+
+        // --- Fetch ALL asset IDs and their CLEAN relative paths from DB first ---
+        let mut initial_db_assets = HashMap::<i64, String>::new(); // asset_id -> clean_relative_path
+        let mut known_mtimes = HashMap::<String, (i64, i64)>::new(); // clean_relative_path -> (asset_id, last_scanned_mtime)
+        { // Scope for the statement
+            let mut stmt = conn.prepare("SELECT id, folder_name, last_scanned_mtime FROM active_assets")
+                .map_err(|e| format!("Failed to prepare asset fetch statement: {}", e))?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<i64>>(2)?)));
+             let row_iter = rows.map_err(|e| format!("Error creating asset query iterator: {}", e))?;
+            for row_result in row_iter {
+                 match row_result {
+                     Ok((id, folder_name, last_mtime)) => {
+                         let clean_path = folder_name.replace("\\", "/");
+                         if let Some(mtime) = last_mtime {
+                             known_mtimes.insert(clean_path.clone(), (id, mtime));
+                         }
+                         // Subpath scans must never prune assets outside their scope
+                         if let Some(scope) = &prune_scope {
+                             if clean_path != *scope && !clean_path.starts_with(&format!("{}/", scope)) {
+                                 continue;
+                             }
+                         }
+                         initial_db_assets.insert(id, clean_path);
+                     }
+                     Err(e) => {
+                          eprintln!("[Scan Task Prep] Error fetching asset row from DB: {}", e);
+                     }
+                 }
+            }
+        }
+        println!("[Scan Task Prep] Fetched {} assets from DB initially.", initial_db_assets.len());
+
+        // Dry runs do the normal inserts/updates so lookups behave the same; the transaction
+        // rolls back when dropped, so every early return discards them as well
+        let mut preview = ScanPreview::default();
+        let mut dry_run_tx = if dry_run {
+            Some(conn.unchecked_transaction().map_err(|e| format!("Failed to start dry-run transaction: {}", e))?)
+        } else {
+            None
+        };
+
+        let mut processed_count = 0; // Counts folders *identified* as mods and processed
+        let mut mods_added_count = 0;
+        let mut mods_updated_count = 0;
+        let mut errors_count = 0;
+        let mut processed_mod_paths = HashSet::new(); // Track processed paths to avoid duplicates if structure is odd
+        let mut found_asset_ids = HashSet::<i64>::new(); // Track IDs found on disk
+        let mut renamed_count = 0; // Count renamed folders
+        let mut skipped_unchanged_count = 0; // Mod folders whose mtime matched the stored value
+
+        // --- Phase 1: walk the tree serially (renames, mod folder detection, incremental skips) ---
+        // Deduction is the expensive part, so folders that need it are only collected here.
+        let mut pending_folders: Vec<(PathBuf, Option<i64>)> = Vec::new();
+        let mut walker = WalkDir::new(&scan_root).min_depth(walk_min_depth).into_iter();
+
+        let mut cancelled = false;
+        while let Some(entry_result) = walker.next() {
+            if cancel_flag.load(Ordering::Relaxed) {
+                cancelled = true;
+                break;
+            }
+            match entry_result {
+                Ok(entry) => {
+                    let current_path = entry.path().to_path_buf();
+                    let is_directory = entry.file_type().is_dir(); // Check type once
+
+                    if is_directory && !processed_mod_paths.contains(&current_path) {
+                        // --- START: Check for DISABLED without underscore and rename ---
+                        let filename_osstr = current_path.file_name().unwrap_or_default();
+                        let filename_str = filename_osstr.to_string_lossy();
+
+                        let needs_rename = fix_legacy_disabled_names && filename_str.starts_with("DISABLED") && !is_disabled_folder_name(&filename_str);
+                        let mut current_path_for_processing = current_path.clone(); // Path to use for has_ini and processing
+
+                        if needs_rename && dry_run {
+                            // Leave the folder alone; it is processed under its current name below
+                            preview.would_rename.push(current_path.display().to_string());
+                        } else if needs_rename && safe_mode {
+                            println!("[Scan Task - Rename] Safe mode active, leaving '{}' as is.", current_path.display());
+                        } else if needs_rename {
+                            let new_filename = disabled_folder_name(filename_str.strip_prefix("DISABLED").unwrap_or(&filename_str));
+                            if let Some(parent_path) = current_path.parent() {
+                                let new_path = parent_path.join(&new_filename);
+                                println!("[Scan Task - Rename] Found incorrect prefix: '{}'. Renaming to '{}'", current_path.display(), new_path.display());
+
+                                // Emit progress before rename attempt
+                                app_handle_clone.emit_all(SCAN_PROGRESS_EVENT, ScanProgress {
+                                     operation_id: operation_id.clone(),
+                                     processed: processed_count, // Don't increment processed count for rename yet
+                                     total: total_to_process,
+                                     current_path: Some(current_path.display().to_string()),
+                                     message: format!("Renaming: {}", filename_str)
+                                }).unwrap_or_else(|e| eprintln!("Failed to emit rename progress: {}", e));
+
+                                match fs::rename(&current_path, &new_path) {
+                                    Ok(_) => {
+                                        println!("[Scan Task - Rename] Successfully renamed.");
+                                        current_path_for_processing = new_path; // Use the NEW path for further processing
+                                        renamed_count += 1;
+                                    }
+                                    Err(e) => {
+                                        eprintln!("[Scan Task - Rename] ERROR: Failed to rename folder '{}': {}. Skipping folder.", current_path.display(), e);
+                                        errors_count += 1;
+                                        // Don't process this folder if rename failed
+                                        walker.skip_current_dir(); // Skip children as well
+                                        continue; // Move to the next entry in WalkDir
+                                    }
+                                }
+                            } else {
+                                eprintln!("[Scan Task - Rename] ERROR: Cannot get parent path for '{}'. Skipping rename and folder.", current_path.display());
+                                errors_count += 1;
+                                walker.skip_current_dir(); // Skip children
+                                continue; // Move to the next entry
+                            }
+                        }
+                        // --- END: Rename Check ---
+
+                        // Now check if the (potentially renamed) folder has an INI file
+                        if has_ini_file(&current_path_for_processing) {
+                            // This is a mod folder (or was successfully renamed to be treated as one)
+                            processed_mod_paths.insert(current_path_for_processing.clone()); // Add the path we actually processed
+                            walker.skip_current_dir(); // Skip children of a mod folder
+
+                            // --- Incremental: skip deduction if folder is unchanged since the last scan ---
+                            let current_mtime = folder_mtime_secs(&current_path_for_processing);
+                            if !force_full {
+                                if let (Some(mtime), Some((rel, _))) = (current_mtime, mods_relative_path(&base_mods_path_clone, &current_path_for_processing)) {
+                                    let rel_str = rel.to_string_lossy().replace("\\", "/");
+                                    let clean_rel = clean_folder_relative_path(&rel_str);
+                                    if let Some((asset_id, stored_mtime)) = known_mtimes.get(&clean_rel) {
+                                        if *stored_mtime == mtime {
+                                            found_asset_ids.insert(*asset_id);
+                                            // Cheap (one directory listing) and picks up variants for assets scanned before they were tracked
+                                            if let Err(e) = sync_asset_variants(&conn, *asset_id, &find_variant_folders(&current_path_for_processing)) {
+                                                eprintln!("[Scan Task] Failed to update variants for asset {}: {}", asset_id, e);
+                                            }
+                                            skipped_unchanged_count += 1;
+                                            processed_count += 1;
+                                            continue;
+                                        }
+                                    }
+                                }
+                            }
+
+                            pending_folders.push((current_path_for_processing, current_mtime));
+                        }
+                        // If it's a directory but doesn't have an INI (and wasn't renamed+processed),
+                        // we just let WalkDir continue into its children.
+                    }
+                    // If it's not a directory, or already processed, ignore.
+                }
+                Err(e) => {
+                     eprintln!("[Scan Task] Error accessing path during scan: {}", e);
+                     errors_count += 1;
+                }
+            }
+        }
+
+        // --- Phase 2: deduce in parallel, write to the DB serially in walk order ---
+        // Workers only read the filesystem and the shared maps; results come back over a channel
+        // tagged with their walk index and are buffered until the next expected index arrives, so
+        // inserts, found ids and progress events happen in the same order as a serial scan.
+        if cancelled {
+            pending_folders.clear();
+        }
+        println!("[Scan Task] Deducing {} mod folders in parallel...", pending_folders.len());
+        let (result_tx, result_rx) = std::sync::mpsc::channel::<(usize, Option<DeducedInfo>, Option<FolderMeasurement>)>();
+        std::thread::scope(|scope| -> Result<(), String> {
+            let folders = &pending_folders;
+            let base_ref = &base_mods_path_clone;
+            let maps_ref = &maps_clone;
+            let cancel_ref = &cancel_flag;
+            scope.spawn(move || {
+                folders.par_iter().enumerate().for_each_with(result_tx, |tx, (index, (folder_path, _))| {
+                    if cancel_ref.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    // Send only fails once the receiver is gone (DB error or cancel), nothing left to do then.
+                    let deduced = deduce_mod_info_v2(folder_path, base_ref, maps_ref);
+                    // With scan hashing off, changed folders are left for compute_missing_hashes
+                    let measurement = if hash_during_scan { Some(measure_folder(folder_path)) } else { None };
+                    let _ = tx.send((index, deduced, measurement));
+                });
+            });
+
+            let mut reorder_buffer: BTreeMap<usize, (Option<DeducedInfo>, Option<FolderMeasurement>)> = BTreeMap::new();
+            let mut next_index = 0;
+
+            for (index, deduced_result, measurement) in result_rx {
+                if cancel_flag.load(Ordering::Relaxed) {
+                    cancelled = true;
+                    break; // Dropping the receiver lets the remaining workers finish without sending
+                }
+                reorder_buffer.insert(index, (deduced_result, measurement));
+
+                while let Some((deduced_result, measurement)) = reorder_buffer.remove(&next_index) {
+                    let (folder_size, content_hash) = measurement.unzip();
+                    let (current_path_for_processing, current_mtime) = &pending_folders[next_index];
+                    next_index += 1;
+                    processed_count += 1;
+                    let path_display = current_path_for_processing.display().to_string();
+                    let folder_name_only = current_path_for_processing.file_name().unwrap_or_default().to_string_lossy();
+
+                    // Emit progress for actual mod processing
+                    app_handle_clone.emit_all(SCAN_PROGRESS_EVENT, ScanProgress {
+                        operation_id: operation_id.clone(),
+                        processed: processed_count,
+                        total: total_to_process,
+                        current_path: Some(path_display.clone()),
+                        message: format!("Processing: {}", folder_name_only)
+                    }).unwrap_or_else(|e| eprintln!("Failed to emit scan progress: {}", e));
+
+                    // --- Start Original Deduction/DB Logic (using current_path_for_processing) ---
+                    match deduced_result {
+                        Some(deduced) => {
+                            println!("[Scan Task] Deduced slug for '{}': {}", path_display, deduced.entity_slug);
+                            let target_entity_id_result: Option<i64> = maps_clone.entity_slug_to_id.get(&deduced.entity_slug).copied();
+
+                            if let Some(target_entity_id) = target_entity_id_result {
+                                println!("[Scan Task] Found entity ID {} for slug '{}'", target_entity_id, deduced.entity_slug);
+
+                                let (relative_path_buf, is_staged) = match mods_relative_path(&base_mods_path_clone, current_path_for_processing) {
+                                    Some(relative) => relative,
+                                    None => {
+                                        eprintln!("[Scan Task] Error: Could not strip base path prefix from '{}'. Skipping.", path_display);
+                                        errors_count += 1;
+                                        continue; // Skip only this mod folder deduction/DB part
+                                    }
+                                };
+
+                                let filename_osstr = relative_path_buf.file_name().unwrap_or_default();
+                                let filename_str = filename_osstr.to_string_lossy();
+                                // --- Critical: Ensure stripping the CORRECT prefix after potential rename ---
+                                // (a dry run may still see the un-renamed "DISABLED" form)
+                                let clean_filename = strip_disabled_marker(&filename_str)
+                                    .or_else(|| if dry_run && fix_legacy_disabled_names { filename_str.strip_prefix("DISABLED") } else { None })
+                                    .unwrap_or(&filename_str);
+                                // ---
+                                let relative_parent_path = relative_path_buf.parent();
+                                let relative_path_to_store = match relative_parent_path {
+                                    Some(parent) if parent.as_os_str().len() > 0 => parent.join(clean_filename).to_string_lossy().to_string(),
+                                    _ => clean_filename.to_string(),
+                                };
+                                let relative_path_to_store = relative_path_to_store.replace("\\", "/");
+                                let is_enabled_on_disk = if is_staged {
+                                    is_folder_link(&base_mods_path_clone.join(&relative_path_to_store))
+                                } else {
+                                    clean_filename == &*filename_str
+                                };
+                                println!("[Scan Task] Calculated DB path: '{}'", relative_path_to_store);
+
+                                let existing_db_asset_id: Option<i64> = conn.query_row(
+                                    "SELECT id FROM active_assets WHERE entity_id = ?1 AND folder_name = ?2",
+                                    params![target_entity_id, relative_path_to_store],
+                                    |row| row.get(0),
+                                ).optional().map_err(|e| format!("DB error checking for existing asset '{}': {}", relative_path_to_store, e))?;
+
+                                if let Some(asset_id) = existing_db_asset_id {
+                                    println!("[Scan Task] Asset already in DB (ID: {}), path '{}'. Marking as found.", asset_id, relative_path_to_store);
+                                    found_asset_ids.insert(asset_id);
+                                    if let Err(e) = conn.execute(
+                                        // Unmeasured folders keep their stored size/hash unless the mtime moved on
+                                        "UPDATE assets SET size_bytes = COALESCE(?2, CASE WHEN last_scanned_mtime IS ?1 THEN size_bytes END), \
+                                         content_hash = COALESCE(?3, CASE WHEN last_scanned_mtime IS ?1 THEN content_hash END), \
+                                         last_scanned_mtime = ?1, version = COALESCE(?4, version), is_enabled = ?5 WHERE id = ?6",
+                                        params![current_mtime, folder_size, content_hash, deduced.version, is_enabled_on_disk, asset_id],
+                                    ) {
+                                        eprintln!("[Scan Task] Failed to store scan mtime for asset {}: {}", asset_id, e);
+                                    }
+                                    if let Err(e) = sync_asset_variants(&conn, asset_id, &find_variant_folders(current_path_for_processing)) {
+                                        eprintln!("[Scan Task] Failed to update variants for asset {}: {}", asset_id, e);
+                                    }
+                                    // mods_updated_count += 1; // Optional update logic here
+                                } else {
+                                    println!("[Scan Task] Inserting new asset: EntityID={}, Name='{}', Path='{}'", target_entity_id, deduced.mod_name, relative_path_to_store);
+                                    let insert_result = conn.execute(
+                                        "INSERT INTO assets (entity_id, name, description, folder_name, image_filename, author, category_tag, last_scanned_mtime, size_bytes, content_hash, version, is_enabled) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                                        params![
+                                            target_entity_id,
+                                            deduced.mod_name,
+                                            deduced.description,
+                                            relative_path_to_store,
+                                            deduced.image_filename,
+                                            deduced.author,
+                                            deduced.mod_type_tag,
+                                            current_mtime,
+                                            folder_size,
+                                            content_hash,
+                                            deduced.version,
+                                            is_enabled_on_disk
+                                        ]
+                                    );
+
+                                    match insert_result {
+                                        Ok(changes) => {
+                                            if changes > 0 {
+                                                mods_added_count += 1;
+                                                let new_id = conn.last_insert_rowid();
+                                                found_asset_ids.insert(new_id);
+                                                if let Err(e) = sync_asset_variants(&conn, new_id, &find_variant_folders(current_path_for_processing)) {
+                                                    eprintln!("[Scan Task] Failed to store variants for asset {}: {}", new_id, e);
+                                                }
+                                                if dry_run {
+                                                    preview.would_add.push(ScanPreviewEntry {
+                                                        asset_id: None,
+                                                        name: deduced.mod_name.clone(),
+                                                        folder_name: relative_path_to_store.clone(),
+                                                        entity_slug: deduced.entity_slug.clone(),
+                                                    });
+                                                }
+                                                println!("[Scan Task]   -> Insert successful (New ID: {})", new_id);
+                                            } else {
+                                                eprintln!("[Scan Task]   -> Insert reported 0 changes for '{}'.", relative_path_to_store);
+                                                errors_count += 1;
+                                            }
+                                        }
+                                        Err(e) => {
+                                            if e.to_string().contains("UNIQUE constraint failed") && e.to_string().contains("assets.folder_name") {
+                                                eprintln!("[Scan Task]   -> Insert failed due to UNIQUE constraint on folder_name '{}'. Asset might exist under a different entity or needs pruning. Skipping insert.", relative_path_to_store);
+                                                // Maybe don't count as error if pruning will fix it?
+                                            } else {
+                                                eprintln!("[Scan Task]   -> DB error inserting new asset '{}': {}", relative_path_to_store, e);
+                                                errors_count += 1;
+                                            }
+                                        }
+                                    }
+                                }
+                            } else {
+                                eprintln!("[Scan Task] CRITICAL ERROR: Deduced slug '{}' for path '{}' does NOT exist in the entity map! Skipping mod. Check DB initialization and deduction logic.", deduced.entity_slug, path_display);
+                                errors_count += 1;
+                            }
+                        }
+                        None => {
+                            eprintln!("[Scan Task] Error: Failed to deduce mod info for path '{}'", path_display);
+                            errors_count += 1;
+                        }
+                    }
+                    // --- End Original Deduction/DB Logic ---
+                }
+            }
+            Ok(())
+        })?;
+
+        if cancelled {
+            let cancel_msg = format!(
+                "Scan cancelled: {}. Processed {} of {} mod folders ({} added); nothing was pruned.",
+                AppError::UserCancelled, processed_count, total_to_process, mods_added_count
+            );
+            println!("[Scan Task] {}", cancel_msg);
+            return Err(cancel_msg);
+        }
+
+        if !force_full {
+            let skip_msg = format!(
+                "Processed {} changed or new mod folders, skipped {} unchanged.",
+                processed_count - skipped_unchanged_count, skipped_unchanged_count
+            );
+            println!("[Scan Task] {}", skip_msg);
+            app_handle_clone.emit_all(SCAN_PROGRESS_EVENT, ScanProgress {
+                operation_id: operation_id.clone(),
+                processed: processed_count, total: total_to_process, current_path: None, message: skip_msg
+            }).unwrap_or_else(|e| eprintln!("Failed to emit scan progress: {}", e));
+        }
+
+        // --- Pruning Logic (Remains the same) ---
+        let mut mods_to_prune_ids = Vec::new();
+        for (asset_id, _clean_path) in initial_db_assets.iter() {
+            if !found_asset_ids.contains(asset_id) {
+                 mods_to_prune_ids.push(*asset_id);
+            }
+        }
+
+        // --- Prune guard: an unmounted or emptied drive looks exactly like "every mod was deleted" ---
+        let mut prune_skipped_reason: Option<String> = None;
+        if !mods_to_prune_ids.is_empty() && !prune_guard.force_prune {
+            let known_count = initial_db_assets.len();
+            let found_known_count = known_count - mods_to_prune_ids.len();
+            if processed_count == 0 {
+                let err_msg = format!(
+                    "No mod folders were found under '{}' but the database has {} mods. Possible unmounted drive or wrong mods folder; nothing was pruned.",
+                    scan_root.display(), known_count
+                );
+                eprintln!("[Scan Task Pruning] {}", err_msg);
+                return Err(err_msg);
+            }
+            let found_ratio = found_known_count as f64 / known_count as f64;
+            if found_ratio < prune_guard.min_found_ratio {
+                let reason = format!(
+                    "Pruning skipped: only {} of {} known mods were found on disk ({:.0}% < {:.0}%). Rescan with pruning forced if they were really removed.",
+                    found_known_count, known_count, found_ratio * 100.0, prune_guard.min_found_ratio * 100.0
+                );
+                println!("[Scan Task Pruning] {}", reason);
+                prune_skipped_reason = Some(reason);
+                mods_to_prune_ids.clear();
+            }
+        }
+        // Safe mode never prunes; a dry run only reports, so it is left alone
+        if safe_mode && !dry_run && !mods_to_prune_ids.is_empty() {
+            let reason = format!("Pruning skipped: {}", AppError::ModOperation("safe mode active".to_string()));
+            println!("[Scan Task Pruning] {}", reason);
+            prune_skipped_reason = Some(reason);
+            mods_to_prune_ids.clear();
+        }
+        // Missing mods are only reported unless the caller asked for them to be removed
+        if !prune_guard.prune_missing && !dry_run && !mods_to_prune_ids.is_empty() {
+            let reason = format!(
+                "{} mods are missing from disk and were kept; review them in the prune list to remove them.",
+                mods_to_prune_ids.len()
+            );
+            println!("[Scan Task Pruning] {}", reason);
+            prune_skipped_reason = Some(reason);
+            mods_to_prune_ids.clear();
+        }
+        let prune_count = mods_to_prune_ids.len();
+        let mut pruned_count = 0;
+        let mut pruning_errors_count = 0;
+
+        if dry_run {
+            {
+                let mut stmt = conn.prepare("SELECT a.name, a.folder_name, e.slug FROM assets a JOIN entities e ON a.entity_id = e.id WHERE a.id = ?1")
+                    .map_err(|e| format!("Failed to prepare prune preview query: {}", e))?;
+                for asset_id in &mods_to_prune_ids {
+                    match stmt.query_row(params![asset_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))) {
+                        Ok((name, folder_name, entity_slug)) => preview.would_prune.push(ScanPreviewEntry {
+                            asset_id: Some(*asset_id), name, folder_name: folder_name.replace("\\", "/"), entity_slug,
+                        }),
+                        Err(e) => eprintln!("[Scan Task Dry Run] Failed to read asset {} for preview: {}", asset_id, e),
+                    }
+                }
+            }
+            if let Some(tx) = dry_run_tx.take() {
+                tx.rollback().map_err(|e| format!("Failed to roll back dry-run transaction: {}", e))?;
+            }
+            pruned_count = prune_count;
+            println!("[Scan Task Dry Run] Would add {}, prune {}, rename {}. Nothing was written.",
+                preview.would_add.len(), preview.would_prune.len(), preview.would_rename.len());
+            app_handle_clone.emit_all(SCAN_PREVIEW_EVENT, &preview).unwrap_or_else(|e| eprintln!("Failed to emit scan preview: {}", e));
+        } else if !mods_to_prune_ids.is_empty() {
+            println!("[Scan Task Pruning] Found {} mods in DB missing from disk. Pruning...", prune_count);
+            emit_operation_result(&app_handle_clone, PRUNING_START_EVENT, &operation_id, format!("Pruning {} missing mods...", prune_count));
+
+             let ids_to_delete_sql: Vec<Box<dyn rusqlite::ToSql>> = mods_to_prune_ids
+                .into_iter()
+                .map(|id| Box::new(id) as Box<dyn rusqlite::ToSql>)
+                .collect();
+
+            if !ids_to_delete_sql.is_empty() {
+                let placeholders = ids_to_delete_sql.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+                let sql = format!("DELETE FROM assets WHERE id IN ({})", placeholders);
+
+                emit_operation_result(&app_handle_clone, PRUNING_PROGRESS_EVENT, &operation_id, format!("Deleting {} entries...", ids_to_delete_sql.len()));
+
+                let delete_result = conn.execute(&sql, rusqlite::params_from_iter(ids_to_delete_sql))
+                                        .map_err(|e| format!("DB error during pruning: {}", e));
+
+                match delete_result {
+                    Ok(count) => {
+                         pruned_count = count;
+                         println!("[Scan Task Pruning] Successfully pruned {} asset entries.", pruned_count);
+                         emit_operation_result(&app_handle_clone, PRUNING_COMPLETE_EVENT, &operation_id, format!("Pruned {} missing mods.", pruned_count));
+                    },
+                    Err(e) => {
+                        eprintln!("[Scan Task Pruning] {}", e);
+                         pruning_errors_count += 1;
+                         emit_operation_result(&app_handle_clone, PRUNING_ERROR_EVENT, &operation_id, e);
+                    }
+                }
+            } else {
+                 println!("[Scan Task Pruning] No valid IDs to prune after conversion.");
+                 emit_operation_result(&app_handle_clone, PRUNING_COMPLETE_EVENT, &operation_id, "Pruned 0 missing mods.");
+            }
+        } else {
+             println!("[Scan Task Pruning] No missing mods found. Skipping pruning.");
+        }
+        // --- End Pruning Logic ---
+
+        let total_errors = errors_count + pruning_errors_count;
+        // Return renamed_count as well
+        if dry_run {
+            renamed_count = preview.would_rename.len();
+        }
+        Ok::<_, String>((processed_count, mods_added_count, mods_updated_count, total_errors, pruned_count, renamed_count, skipped_unchanged_count, prune_skipped_reason))
+    });
+
+    // --- Handle Task Result ---
+     match scan_task.await {
+         Ok(Ok((processed, added, _updated, errors, pruned, renamed, skipped, prune_skipped_reason))) => { // Add renamed here
+             let rename_msg = if renamed > 0 { format!(" Renamed {} incorrectly prefixed folders.", renamed) } else { "".to_string() };
+             let skipped_msg = if skipped > 0 { format!(" Skipped {} unchanged folders.", skipped) } else { "".to_string() };
+             let summary = if dry_run {
+                 format!(
+                     "Dry run complete, nothing was changed. Processed {} mod folders.{} Would add {} new mods, prune {} missing mods and rename {} folders. {} errors occurred.",
+                     processed, skipped_msg, added, pruned, renamed, errors
+                 )
+             } else {
+                 format!(
+                     "Scan complete. Processed {} mod folders.{} Added {} new mods. Pruned {} missing mods.{} {} errors occurred.",
+                     processed, skipped_msg, added, pruned, rename_msg, errors
+                 )
+             };
+             let summary = match prune_skipped_reason {
+                 Some(reason) => format!("{} {}", summary, reason),
+                 None => summary,
+             };
+             println!("{}", summary);
+             if !dry_run && subpath.is_none() {
+                 if let Err(e) = db_state.conn().and_then(|conn| conn.execute(
+                     "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+                     params![SETTINGS_KEY_LAST_SCAN_COMPLETED_AT, scan_started_at.to_string()],
+                 ).map_err(|e| e.to_string())) {
+                     eprintln!("Failed to record scan completion time: {}", e);
+                 }
+             }
+             emit_operation_result(&app_handle, SCAN_COMPLETE_EVENT, &result_operation_id, summary);
+             Ok(())
+         }
+         Ok(Err(e)) => {
+             eprintln!("Scan task failed internally: {}", e);
+              emit_operation_result(&app_handle, SCAN_ERROR_EVENT, &result_operation_id, e.clone());
+             Err(e)
+         }
+         Err(e) => {
+             let err_msg = format!("Scan task panicked or failed to join: {}", e);
+             eprintln!("{}", err_msg);
+             emit_operation_result(&app_handle, SCAN_ERROR_EVENT, &result_operation_id, err_msg.clone());
+             Err(err_msg)
+         }
+     }
+}
+
+#[command]
+fn get_total_asset_count(db_state: State<DbState>) -> CmdResult<i64> {
+    let conn = db_state.conn()?;
+    conn.query_row("SELECT COUNT(*) FROM active_assets", [], |row| row.get(0))
+        .map_err(|e| e.to_string())
+}
+
+// Accepts only absolute http(s) URLs with a plausible host; returns the trimmed URL
+fn validate_source_url(url: &str) -> CmdResult<String> {
+    let url = url.trim();
+    let invalid = || format!("Invalid source URL '{}': expected an http:// or https:// link.", url);
+    let lower = url.to_lowercase();
+    let rest = lower.strip_prefix("https://").or_else(|| lower.strip_prefix("http://")).ok_or_else(invalid)?;
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(invalid());
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    let host = host.split(':').next().unwrap_or("");
+    let host_ok = !host.is_empty()
+        && !host.starts_with('.') && !host.ends_with('.') && !host.contains("..")
+        && (host.contains('.') || host == "localhost")
+        && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    if !host_ok {
+        return Err(invalid());
+    }
+    Ok(url.to_string())
+}
+
+// Moves several assets to one entity, using the same folder move + DB update as update_asset_info.
+// Assets whose destination folder already exists, and locked assets unless `force` is set, are
+// skipped; other failures are reported per asset.
+// DB updates share one transaction; if it cannot be committed, the folders are moved back.
+#[command]
+fn relocate_assets(
+    asset_ids: Vec<i64>,
+    target_entity_slug: String,
+    force: Option<bool>,
+    db_state: State<DbState>,
+    op_log: State<OperationLogState>
+) -> CmdResult<RelocateAssetsSummary> {
+    println!("[relocate_assets] Relocating {} assets to entity '{}'", asset_ids.len(), target_entity_slug);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    let mut conn_guard = db_state.conn()?;
+    ensure_not_safe_mode(&conn_guard).map_err(|e| e.to_string())?;
+    let (target_entity_id, target_category_slug): (i64, String) = conn_guard.query_row(
+        "SELECT e.id, c.slug FROM entities e JOIN categories c ON e.category_id = c.id WHERE e.slug = ?1",
+        params![target_entity_slug],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Target entity '{}' not found.", target_entity_slug),
+        _ => format!("DB Error getting target entity info: {}", e)
+    })?;
+
+    let tx = conn_guard.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut summary = RelocateAssetsSummary { relocated: Vec::new(), skipped: Vec::new(), failed: Vec::new() };
+    let mut moves: Vec<Operation> = Vec::new();
+
+    for asset_id in &asset_ids {
+        let current_info = match get_asset_location_info(&tx, *asset_id) {
+            Ok(info) => info,
+            Err(e) => {
+                summary.failed.push(BulkToggleFailure { asset_id: *asset_id, error: e.to_string() });
+                continue;
+            }
+        };
+        if current_info.entity_slug == target_entity_slug {
+            summary.skipped.push(BulkToggleFailure { asset_id: *asset_id, error: "Asset already belongs to the target entity".to_string() });
+            continue;
+        }
+        if !force.unwrap_or(false) && ensure_asset_unlocked(&tx, *asset_id).is_err() {
+            summary.skipped.push(locked_asset_failure(*asset_id));
+            continue;
+        }
+
+        let plan = match relocation_paths(&base_mods_path, &current_info, &target_entity_slug, &target_category_slug) {
+            Ok(plan) => plan,
+            Err(e) => {
+                summary.failed.push(BulkToggleFailure { asset_id: *asset_id, error: e });
+                continue;
+            }
+        };
+        if asset_move_target_taken(&base_mods_path, &plan.1, &plan.2) {
+            summary.skipped.push(BulkToggleFailure { asset_id: *asset_id, error: format!("Target path '{}' already exists", plan.1.display()) });
+            continue;
+        }
+        if let Err(e) = unstage_for_relocation(&base_mods_path, &current_info) {
+            summary.failed.push(BulkToggleFailure { asset_id: *asset_id, error: e });
+            continue;
+        }
+
+        match move_asset_folder(&tx, &base_mods_path, &current_info, &plan, target_entity_id) {
+            Ok(operation) => {
+                moves.push(operation);
+                summary.relocated.push(*asset_id);
+            }
+            Err(e) => {
+                eprintln!("[relocate_assets] Failed to move asset {}: {}", asset_id, e);
+                summary.failed.push(BulkToggleFailure { asset_id: *asset_id, error: e });
+            }
+        }
+    }
+
+    if let Err(e) = tx.commit() {
+        undo_asset_moves(&moves);
+        return Err(format!("Failed to commit relocation, folders were moved back: {}", e));
+    }
+    for operation in moves {
+        record_operation(&op_log, operation);
+    }
+
+    println!("[relocate_assets] Relocated {}, skipped {}, failed {}.", summary.relocated.len(), summary.skipped.len(), summary.failed.len());
+    Ok(summary)
+}
+
+// Re-runs deduction on one asset's current folder (e.g. after adding a rule or a custom entity)
+// and, if it now resolves to a different existing entity, moves it there like update_asset_info.
+#[command]
+fn rededuce_asset(
+    asset_id: i64,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>,
+    op_log: State<OperationLogState>
+) -> CmdResult<RededuceResult> {
+    let base_mods_path = cached_mods_base_path(&config_state, &db_state).map_err(|e| e.to_string())?;
+    let conn_guard = db_state.conn()?;
+    let conn = &*conn_guard;
+    ensure_not_safe_mode(conn).map_err(|e| e.to_string())?;
+
+    let current_info = get_asset_location_info(conn, asset_id).map_err(|e| e.to_string())?;
+    let (_, current_relative_path) = resolve_asset_disk_state(&base_mods_path, &current_info.clean_relative_path)
+        .ok_or_else(|| format!("Mod folder for asset {} not found on disk.", asset_id))?;
+    let maps = cached_deduction_maps(&config_state, conn).map_err(|e| e.to_string())?;
+    let deduced = deduce_mod_info_v2(&base_mods_path.join(&current_relative_path), &base_mods_path, &maps)
+        .ok_or_else(|| format!("Could not deduce info for asset {}.", asset_id))?;
+
+    let mut result = RededuceResult {
+        asset_id,
+        old_entity_slug: current_info.entity_slug.clone(),
+        new_entity_slug: current_info.entity_slug.clone(),
+        moved: false,
+    };
+    if deduced.entity_slug == current_info.entity_slug {
+        println!("[rededuce_asset] Asset {} still resolves to '{}'", asset_id, current_info.entity_slug);
+        return Ok(result);
+    }
+    // Deduction may fall back to a slug with no entity row (e.g. unknown-other); keep the asset where it is then
+    let target: Option<(i64, String)> = conn.query_row(
+        "SELECT e.id, c.slug FROM entities e JOIN categories c ON e.category_id = c.id WHERE e.slug = ?1",
+        params![deduced.entity_slug],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().map_err(|e| format!("DB Error getting new target entity info: {}", e))?;
+    let (new_entity_id, new_category_slug) = match target {
+        Some(target) => target,
+        None => {
+            println!("[rededuce_asset] Asset {} resolved to unknown entity '{}', not moving", asset_id, deduced.entity_slug);
+            return Ok(result);
+        }
+    };
+
+    let plan = plan_asset_relocation(&base_mods_path, &current_info, &deduced.entity_slug, &new_category_slug)?;
+    let operation = move_asset_folder(conn, &base_mods_path, &current_info, &plan, new_entity_id)
+        .map_err(|e| format!("Cannot relocate asset {}: {}", asset_id, e))?;
+    record_operation(&op_log, operation);
+
+    println!("[rededuce_asset] Moved asset {} from '{}' to '{}'", asset_id, result.old_entity_slug, deduced.entity_slug);
+    result.new_entity_slug = deduced.entity_slug;
+    result.moved = true;
+    Ok(result)
+}
+
+// Renames every mod folder of an entity from a template ({name}, {author}, {entity}, {id}).
+// Disabled folders keep their DISABLED_ prefix and colliding names get a numeric suffix.
+// Locked mods keep their folder name unless `force` is set.
+// All-or-nothing: if any rename or DB update fails, earlier renames are moved back and the
+// transaction is rolled back.
+#[command]
+fn normalize_asset_folder_names(
+    entity_slug: String,
+    pattern: String,
+    force: Option<bool>,
+    db_state: State<DbState>,
+    op_log: State<OperationLogState>
+) -> CmdResult<Vec<AssetFolderRename>> {
+    if !pattern.contains('{') {
+        return Err("Pattern must contain at least one placeholder, e.g. {author}_{name}.".to_string());
+    }
+    println!("[normalize_asset_folder_names] Entity '{}', pattern '{}'", entity_slug, pattern);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    let mut conn_guard = db_state.conn()?;
+    ensure_not_safe_mode(&conn_guard).map_err(|e| e.to_string())?;
+    let (entity_id, entity_name): (i64, String) = conn_guard.query_row(
+        "SELECT id, name FROM entities WHERE slug = ?1", params![entity_slug], |row| Ok((row.get(0)?, row.get(1)?))
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Entity '{}' not found.", entity_slug),
+        _ => format!("DB Error getting entity: {}", e)
+    })?;
+    let assets: Vec<(i64, String, Option<String>, String, bool)> = {
+        let mut stmt = conn_guard.prepare("SELECT id, name, author, folder_name, is_locked FROM active_assets WHERE entity_id = ?1 ORDER BY id")
+            .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map(params![entity_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get::<_, String>(3)?.replace("\\", "/"), row.get(4)?)))
+            .map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
+    };
+
+    // Names already used next to each folder (clean, lowercase); an asset may keep its own name
+    let mut taken_names: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+
+    // (asset_id, current full path, new full path, old clean relative path, new clean relative path)
+    let mut plan: Vec<(i64, PathBuf, PathBuf, String, String)> = Vec::new();
+    for (asset_id, name, author, clean_relative_path, is_locked) in &assets {
+        // A locked folder still counts as a taken name, it is listed from disk below
+        if *is_locked && !force.unwrap_or(false) {
+            continue;
+        }
+        unstage_asset_folder(&base_mods_path, clean_relative_path)
+            .map_err(|e| format!("Failed to move '{}' out of staging: {}", name, e))?;
+        let (is_enabled, current_relative_path) = match resolve_asset_disk_state(&base_mods_path, clean_relative_path) {
+            Some(state) => state,
+            None => return Err(format!("Mod folder for '{}' not found on disk (path: '{}').", name, clean_relative_path)),
+        };
+        let relative_parent = Path::new(clean_relative_path).parent().map(Path::to_path_buf).unwrap_or_default();
+        let taken = taken_names.entry(relative_parent.clone()).or_insert_with(|| {
+            fs::read_dir(base_mods_path.join(&relative_parent)).into_iter().flatten()
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .map(|n| strip_disabled_marker(&n).map(String::from).unwrap_or(n).to_lowercase())
+                .collect()
+        });
+
+        let rendered = pattern
+            .replace("{name}", name)
+            .replace("{author}", author.as_deref().unwrap_or("Unknown"))
+            .replace("{entity}", &entity_name)
+            .replace("{id}", &asset_id.to_string());
+        let base_name = sanitize_folder_name(&rendered);
+        let mut new_name = base_name.clone();
+        let own_name = Path::new(clean_relative_path).file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+        let mut suffix = 2;
+        while new_name.to_lowercase() != own_name && taken.contains(&new_name.to_lowercase()) {
+            new_name = format!("{}_{}", base_name, suffix);
+            suffix += 1;
+        }
+        taken.insert(new_name.to_lowercase());
+
+        let new_clean_relative_path = relative_parent.join(&new_name).to_string_lossy().replace("\\", "/");
+        if new_clean_relative_path == *clean_relative_path { continue; }
+        let new_disk_name = if is_enabled { new_name.clone() } else { disabled_folder_name(&new_name) };
+        plan.push((*asset_id, base_mods_path.join(&current_relative_path), base_mods_path.join(&relative_parent).join(new_disk_name),
+                   clean_relative_path.clone(), new_clean_relative_path));
+    }
+
+    let tx = conn_guard.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let undo_renames = |done: &[(PathBuf, PathBuf)]| {
+        for (from_path, to_path) in done.iter().rev() {
+            if let Err(e) = fs::rename(to_path, from_path) {
+                eprintln!("[normalize_asset_folder_names] Failed to move '{}' back: {}", to_path.display(), e);
+            }
+        }
+    };
+    for (asset_id, from_path, to_path, _, new_clean_relative_path) in &plan {
+        let result = fs::rename(from_path, to_path)
+            .map_err(|e| format!("Failed to rename '{}' to '{}': {}", from_path.display(), to_path.display(), e))
+            .and_then(|_| {
+                done.push((from_path.clone(), to_path.clone()));
+                tx.execute("UPDATE assets SET folder_name = ?1 WHERE id = ?2", params![new_clean_relative_path, asset_id])
+                    .map(|_| ())
+                    .map_err(|e| format!("DB update failed for asset {}: {}", asset_id, e))
+            });
+        if let Err(e) = result {
+            undo_renames(&done);
+            return Err(format!("{}. No folders were renamed.", e)); // tx rolls back on drop
+        }
+    }
+    if let Err(e) = tx.commit() {
+        undo_renames(&done);
+        return Err(format!("Failed to commit folder renames, folders were moved back: {}", e));
+    }
+
+    let mut renames = Vec::new();
+    for (asset_id, from_path, to_path, old_clean_relative_path, new_clean_relative_path) in plan {
+        record_operation(&op_log, Operation::Relocate {
+            asset_id, from_path, to_path, old_entity_id: entity_id, old_relative_path: old_clean_relative_path.clone(),
+        });
+        renames.push(AssetFolderRename { asset_id, old_folder_name: old_clean_relative_path, new_folder_name: new_clean_relative_path });
+    }
+    println!("[normalize_asset_folder_names] Renamed {} of {} folders.", renames.len(), assets.len());
+    Ok(renames)
+}
+
+// Turns arbitrary text into a folder name that is valid on Windows: reserved characters and
+// whitespace become '_', runs of '_' collapse, and leading/trailing '_' and '.' are trimmed.
+fn sanitize_folder_name(raw: &str) -> String {
+    let replaced: String = raw.chars()
+        .map(|c| if c.is_control() || c.is_whitespace() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') { '_' } else { c })
+        .collect();
+    let mut collapsed = String::with_capacity(replaced.len());
+    for c in replaced.chars() {
+        if c == '_' && collapsed.ends_with('_') { continue; }
+        collapsed.push(c);
+    }
+    let trimmed = collapsed.trim_matches(|c| c == '_' || c == '.');
+    let without_prefix = strip_disabled_marker(trimmed).unwrap_or(trimmed);
+    if without_prefix.is_empty() { "mod".to_string() } else { without_prefix.to_string() }
+}
+
+// The max dimension to re-encode previews to, or None to store them as-is (the default).
+fn preview_normalize_max_dim(normalize_preview: Option<bool>, max_dimension: Option<u32>) -> Option<u32> {
+    normalize_preview.unwrap_or(false).then(|| max_dimension.unwrap_or(DEFAULT_PREVIEW_MAX_DIM).clamp(64, 8192))
+}
+
+// Decodes any supported image format and writes it as PNG, downscaled to fit max_dim.
+fn write_normalized_preview(data: &[u8], target_path: &Path, max_dim: u32) -> Result<(), String> {
+    let source_image = image::load_from_memory(data).map_err(|e| format!("Failed to decode preview image: {}", e))?;
+    let preview = if source_image.width() > max_dim || source_image.height() > max_dim {
+        source_image.thumbnail(max_dim, max_dim)
+    } else {
+        source_image
+    };
+    preview.save_with_format(target_path, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to write preview '{}': {}", target_path.display(), e))
+}
+
+// Stores preview bytes as the mod's preview.png, re-encoding them when max_dim is set.
+fn write_preview_data(data: &[u8], target_path: &Path, max_dim: Option<u32>) -> Result<(), String> {
+    match max_dim {
+        Some(max_dim) => write_normalized_preview(data, target_path, max_dim),
+        None => fs::write(target_path, data).map_err(|e| format!("Failed to write preview '{}': {}", target_path.display(), e)),
+    }
+}
+
+// Same as write_preview_data for an image file; without normalization this is a plain copy.
+fn write_preview_file(source_path: &Path, target_path: &Path, max_dim: Option<u32>) -> Result<(), String> {
+    match max_dim {
+        Some(max_dim) => {
+            let data = fs::read(source_path).map_err(|e| format!("Failed to read '{}': {}", source_path.display(), e))?;
+            write_normalized_preview(&data, target_path, max_dim)
+        }
+        None => fs::copy(source_path, target_path).map(|_| ())
+            .map_err(|e| format!("Failed to copy '{}' to '{}': {}", source_path.display(), target_path.display(), e)),
+    }
+}
+
+// Copies a folder tree; symlinks inside the mod are not followed. Returns the bytes copied.
+fn copy_dir_recursive(source: &Path, destination: &Path) -> io::Result<u64> {
+    let mut copied_bytes = 0;
+    for entry in WalkDir::new(source).follow_links(false) {
+        let entry = entry.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let relative = entry.path().strip_prefix(source).map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let target = destination.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_file() {
+            copied_bytes += fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(copied_bytes)
+}
+
+// Forks a mod into a new folder next to the original. The copy starts disabled so it never
+// clashes with the original until the user switches over.
+#[command]
+fn duplicate_asset(asset_id: i64, new_name: String, db_state: State<DbState>, config_state: State<CachedConfigState>) -> CmdResult<Asset> {
+    println!("[duplicate_asset] Duplicating asset {} as '{}'", asset_id, new_name);
+    let new_name = new_name.trim().to_string();
+    if new_name.is_empty() {
+        return Err("New mod name cannot be empty.".to_string());
+    }
+    let base_mods_path = cached_mods_base_path(&config_state, &db_state).map_err(|e| e.to_string())?;
+    let mut conn = db_state.conn()?;
+    ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+
+    let (entity_id, folder_name, description, image_filename, author, category_tag, notes, source_url, version) = conn.query_row(
+        "SELECT entity_id, folder_name, description, image_filename, author, category_tag, notes, source_url, version FROM active_assets WHERE id = ?1",
+        params![asset_id],
+        |row| Ok((
+            row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?, row.get::<_, Option<String>>(3)?, row.get::<_, Option<String>>(4)?,
+            row.get::<_, Option<String>>(5)?, row.get::<_, Option<String>>(6)?, row.get::<_, Option<String>>(7)?, row.get::<_, Option<String>>(8)?,
+        )),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Asset with ID {} not found.", asset_id),
+        _ => format!("Failed to read asset {}: {}", asset_id, e),
+    })?;
+    let clean_relative_path = folder_name.replace("\\", "/");
+    let (_, source_relative_path) = resolve_asset_disk_state(&base_mods_path, &clean_relative_path)
+        .ok_or_else(|| format!("Mod folder for '{}' not found on disk.", clean_relative_path))?;
+
+    // The copy lives in the same parent folder, so it stays with the same entity on rescan
+    let new_folder_name = sanitize_folder_name(&new_name);
+    let parent = Path::new(&clean_relative_path).parent().map(Path::to_path_buf).unwrap_or_default();
+    let new_clean_relative_path = parent.join(&new_folder_name).to_string_lossy().replace("\\", "/");
+    let new_disabled_path = base_mods_path.join(&parent).join(disabled_folder_name(&new_folder_name));
+
+    let name_taken: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM active_assets WHERE folder_name = ?1)", params![new_clean_relative_path], |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+    if name_taken || resolve_asset_disk_state(&base_mods_path, &new_clean_relative_path).is_some() || new_disabled_path.exists() {
+        return Err(format!("A mod folder named '{}' already exists.", new_clean_relative_path));
+    }
+
+    let source_path = base_mods_path.join(&source_relative_path);
+    if let Err(e) = copy_dir_recursive(&source_path, &new_disabled_path) {
+        let _ = fs::remove_dir_all(&new_disabled_path); // Don't leave a half-copied mod behind
+        return Err(format!("Failed to copy '{}' to '{}': {}", source_path.display(), new_disabled_path.display(), e));
+    }
+
+    let insert_result = (|| -> SqlResult<i64> {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute(
+            "INSERT INTO assets (entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url, version, is_enabled) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0)",
+            params![entity_id, new_name, description, new_clean_relative_path, image_filename, author, category_tag, notes, source_url, version],
+        )?;
+        let new_asset_id = tx.last_insert_rowid();
+        tx.execute("INSERT OR IGNORE INTO asset_tags (asset_id, tag_id) SELECT ?1, tag_id FROM asset_tags WHERE asset_id = ?2", params![new_asset_id, asset_id])?;
+        tx.commit()?;
+        Ok(new_asset_id)
+    })();
+    let new_asset_id = match insert_result {
+        Ok(id) => id,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&new_disabled_path);
+            return Err(format!("Failed to add duplicate of asset {} to the DB: {}", asset_id, e));
+        }
+    };
+
+    let asset = load_asset(&conn, new_asset_id, false)
+        .map_err(|e| format!("Failed to read back duplicated asset {}: {}", new_asset_id, e))?;
+
+    println!("[duplicate_asset] Created asset {} at '{}'", new_asset_id, new_clean_relative_path);
+    Ok(asset)
+}
+
+// Reads one asset row with its tags. `is_enabled` comes from the caller, which just put the
+// folder into a known state.
+fn load_asset(conn: &Connection, asset_id: i64, is_enabled: bool) -> SqlResult<Asset> {
+    let asset = conn.query_row(
+        "SELECT id, entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url, is_favorite, is_pinned, is_locked, created_at, updated_at, last_enabled_at
+         FROM active_assets WHERE id = ?1",
+        params![asset_id],
+        |row| Ok(Asset {
+            id: row.get(0)?,
+            entity_id: row.get(1)?,
+            name: row.get(2)?,
+            description: row.get(3)?,
+            folder_name: row.get::<_, String>(4)?.replace("\\", "/"),
+            image_filename: row.get(5)?,
+            author: row.get(6)?,
+            category_tag: row.get(7)?,
+            is_enabled,
+            tags: Vec::new(),
+            notes: row.get(8)?,
+            source_url: row.get(9)?,
+            is_favorite: row.get(10)?,
+            is_pinned: row.get(11)?,
+            is_locked: row.get(12)?,
+            created_at: row.get(13)?,
+            updated_at: row.get(14)?,
+            last_enabled_at: row.get(15)?,
+        }),
+    )?;
+    let tags = conn.prepare("SELECT t.name FROM asset_tags at JOIN tags t ON at.tag_id = t.id WHERE at.asset_id = ?1 ORDER BY t.name COLLATE NOCASE")?
+        .query_map(params![asset_id], |row| row.get(0))?
+        .collect::<SqlResult<Vec<String>>>()?;
+    Ok(Asset { tags, ..asset })
+}
+
+#[command]
+fn update_asset_info(
+    asset_id: i64,
+    name: String,
+    description: Option<String>,
+    author: Option<String>,
+    category_tag: Option<String>,
+    notes: Option<String>,
+    source_url: Option<String>,
+    selected_image_absolute_path: Option<String>,
+    image_data: Option<Vec<u8>>,
+    new_target_entity_slug: Option<String>,
+    normalize_preview: Option<bool>, // Re-encode the new preview as PNG capped at max_dimension
+    max_dimension: Option<u32>,
+    db_state: State<DbState>,
+    op_log: State<OperationLogState>
+) -> CmdResult<()> { // Returns Result<(), String>
+    println!("[update_asset_info] Start for asset ID: {}. Relocate to: {:?}. Image Data Provided: {}",
+        asset_id, new_target_entity_slug, image_data.is_some());
+
+    // Validate before touching anything on disk
+    let notes = notes.filter(|n| !n.trim().is_empty());
+    let source_url = match source_url.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        Some(url) => Some(validate_source_url(url)?),
+        None => None,
+    };
+
+    let mut conn_guard = db_state.conn()?;
+
+    // --- 1. Get Current Asset Location Info ---
+    let current_info = get_asset_location_info(&conn_guard, asset_id)
+        .map_err(|e| format!("Failed get current asset info: {}", e))?;
+    println!("[update_asset_info] Current Info: {:?}", current_info);
+
+    let base_mods_path = PathBuf::from(
+        get_setting_value(&conn_guard, SETTINGS_KEY_MODS_FOLDER)
+           .map_err(|e|e.to_string())?
+           .ok_or_else(|| "Mods folder path not set".to_string())?
+    );
+    println!("[update_asset_info] Base mods path: {}", base_mods_path.display());
+
+    // The move, preview write and row update succeed or fail together: on any error the folder is
+    // moved back and the transaction rolls back on drop
+    let tx = conn_guard.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // --- 2. Relocation Logic ---
+    let needs_relocation = new_target_entity_slug.is_some() && new_target_entity_slug.as_deref() != Some(&current_info.entity_slug);
+    let mut final_entity_id = current_info.entity_id;
+    let mut final_relative_path_str = current_info.clean_relative_path.clone();
+    let mut relocation: Option<(Operation, PathBuf)> = None; // (undo entry, new full path on disk)
+
+    if needs_relocation {
+        ensure_not_safe_mode(&tx).map_err(|e| e.to_string())?;
+        let target_slug = new_target_entity_slug.as_ref().unwrap();
+        let (new_entity_id, new_category_slug): (i64, String) = tx.query_row(
+            "SELECT e.id, c.slug FROM entities e JOIN categories c ON e.category_id = c.id WHERE e.slug = ?1",
+            params![target_slug],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).map_err(|e| format!("DB Error getting new target entity info: {}", e))?;
+
+        let plan = plan_asset_relocation(&base_mods_path, &current_info, target_slug, &new_category_slug)?;
+        println!("[update_asset_info] Moving mod folder from '{}' to '{}' (DB path '{}')", plan.0.display(), plan.1.display(), plan.2);
+        let operation = move_asset_folder(&tx, &base_mods_path, &current_info, &plan, new_entity_id)
+            .map_err(|e| format!("Cannot relocate asset {}: {}", asset_id, e))?;
+        println!("[update_asset_info] Successfully moved mod folder.");
+
+        final_entity_id = new_entity_id;
+        final_relative_path_str = plan.2;
+        relocation = Some((operation, plan.1));
+    }
+
+    let update_result = (|| -> CmdResult<()> {
+        // --- 4. Handle Image Saving (Handles Paste > File Path > Existing) ---
+
+        // Determine the mod folder path ON DISK where the image should be saved
+        // This uses the path *after* potential relocation if it happened.
+        let mod_folder_on_disk = if let Some((_, relocated_path)) = &relocation {
+            relocated_path.clone()
+        } else {
+            // If no relocation, determine current path (enabled, disabled or staged) based on current_info
+            match resolve_asset_disk_state(&base_mods_path, &current_info.clean_relative_path) {
+                Some((_, current_relative_path)) => base_mods_path.join(current_relative_path),
+                None => return Err(format!("Mod folder not found on disk for '{}' in any state.", current_info.clean_relative_path)),
+            }
+        };
+        println!("[update_asset_info] Confirmed mod path on disk for image: {}", mod_folder_on_disk.display());
+
+        // Ensure the target directory exists (it should, but double-check)
+        if !mod_folder_on_disk.is_dir() {
+            // This might happen if the folder got deleted between checks, try creating it.
+            println!("[update_asset_info] Warning: Target mod folder {} does not exist, attempting to create.", mod_folder_on_disk.display());
+            fs::create_dir_all(&mod_folder_on_disk).map_err(|e| e.to_string())?;
+        }
+
+        let image_filename_to_save: Option<String>;
+
+        // --- Priority 1: Handle pasted/provided image data ---
+        if let Some(data) = image_data {
+            println!("[update_asset_info] Handling provided image data ({} bytes)", data.len());
+            let target_image_path = mod_folder_on_disk.join(TARGET_IMAGE_FILENAME);
+            write_preview_data(&data, &target_image_path, preview_normalize_max_dim(normalize_preview, max_dimension))
+                .map_err(|e| format!("Failed to save pasted image data: {}", e))?;
+            println!("[update_asset_info] Image data written successfully.");
+            image_filename_to_save = Some(TARGET_IMAGE_FILENAME.to_string());
+        }
+        // --- Priority 2: Handle selected file path (only if no data was provided) ---
+        else if let Some(source_path_str) = selected_image_absolute_path {
+            println!("[update_asset_info] Handling selected image file path: {}", source_path_str);
+            let source_path = PathBuf::from(&source_path_str);
+            if !source_path.is_file() { return Err(format!("Selected image file does not exist: {}", source_path.display())); }
+            let target_image_path = mod_folder_on_disk.join(TARGET_IMAGE_FILENAME);
+            write_preview_file(&source_path, &target_image_path, preview_normalize_max_dim(normalize_preview, max_dimension))
+                 .map_err(|e| format!("Failed to save selected image: {}", e))?;
+            println!("[update_asset_info] Image file copied successfully.");
+            image_filename_to_save = Some(TARGET_IMAGE_FILENAME.to_string());
+        }
+        // --- Priority 3: No new image provided, fetch existing filename from DB ---
+        else {
+             println!("[update_asset_info] No new image data or path provided. Fetching existing filename.");
+             // Query existing filename. Ok if it doesn't exist (returns None)
+             image_filename_to_save = tx.query_row::<Option<String>, _, _>(
+                "SELECT image_filename FROM assets WHERE id=?1",
+                 params![asset_id],
+                 |r|r.get(0)
+             ).optional().map_err(|e| format!("DB error fetching existing image name: {}", e))?.flatten(); // flatten Option<Option<String>>
+        }
+        println!("[update_asset_info] Image handling complete. Filename to save in DB: {:?}", image_filename_to_save);
+
+
+        // --- 5. Update Database ---
+        println!("[update_asset_info] Attempting DB update for asset ID {}...", asset_id);
+        let changes = tx.execute(
+            "UPDATE assets SET name = ?1, description = ?2, author = ?3, category_tag = ?4, image_filename = ?5, entity_id = ?6, folder_name = ?7, notes = ?8, source_url = ?9, updated_at = CAST(strftime('%s','now') AS INTEGER) WHERE id = ?10",
+            params![
+                name, // Use name from arguments
+                description,
+                author,
+                category_tag,
+                image_filename_to_save, // Use the determined filename
+                final_entity_id,        // Use potentially updated entity ID
+                final_relative_path_str, // Use potentially updated relative path (for DB only)
+                notes,
+                source_url,
+                asset_id
+            ]
+        ).map_err(|e| format!("Failed update asset info in DB for ID {}: {}", asset_id, e))?;
+
+        println!("[update_asset_info] DB update executed. Changes: {}", changes);
+        if changes == 0 { eprintln!("[update_asset_info] Warning: DB update affected 0 rows for asset ID {}.", asset_id); }
+
+        tx.commit().map_err(|e| format!("Failed to commit asset update: {}", e))
+    })();
+
+    if let Err(e) = update_result {
+        if let Some((operation, _)) = relocation {
+            undo_asset_moves(&[operation]);
+        }
+        return Err(e);
+    }
+    if let Some((operation, _)) = relocation {
+        record_operation(&op_log, operation);
+    }
+
+    println!("[update_asset_info] Asset ID {} updated successfully. END", asset_id);
+    Ok(())
+}
+
+#[command]
+fn delete_asset(asset_id: i64, force: Option<bool>, db_state: State<DbState>, op_log: State<OperationLogState>, app_handle: AppHandle) -> CmdResult<()> {
+     println!("[delete_asset] Attempting to delete asset ID: {}", asset_id);
+
+    let conn_guard = db_state.conn()?;
+    let conn = &*conn_guard;
+    println!("[delete_asset] DB lock acquired.");
+    ensure_not_safe_mode(conn).map_err(|e| e.to_string())?;
+    if !force.unwrap_or(false) {
+        ensure_asset_unlocked(conn, asset_id).map_err(|e| e.to_string())?;
+    }
+
+    // --- 1. Get Asset Info ---
+    let asset_info = get_asset_location_info(conn, asset_id)
+        .map_err(|e| format!("Failed to get asset info for deletion: {}", e))?;
+    println!("[delete_asset] Asset info found: {:?}", asset_info);
+
+    // --- 2. Get Base Mods Path ---
+    let base_mods_path_str = get_setting_value(conn, SETTINGS_KEY_MODS_FOLDER)
+        .map_err(|e| format!("Failed to query mods folder setting: {}", e))?
+        .ok_or_else(|| "Mods folder path not set".to_string())?;
+    let base_mods_path = PathBuf::from(base_mods_path_str);
+
+    // --- 3. Determine Full Path on Disk (Check Enabled/Disabled) ---
+    // A staged mod (symlink mode) becomes a real folder first so the trash gets its contents
+    unstage_asset_folder(&base_mods_path, &asset_info.clean_relative_path)
+        .map_err(|e| format!("Failed to move mod out of staging before deleting: {}", e))?;
+     let relative_path_buf = PathBuf::from(&asset_info.clean_relative_path);
+     let filename_osstr = relative_path_buf.file_name().ok_or_else(|| format!("Could not extract filename from DB path: {}", asset_info.clean_relative_path))?;
+     let filename_str = filename_osstr.to_string_lossy();
+     let disabled_filename = disabled_folder_name(&filename_str);
+     let relative_parent_path = relative_path_buf.parent();
+
+     let full_path_if_enabled = base_mods_path.join(&relative_path_buf);
+     let full_path_if_disabled = match relative_parent_path {
+        Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_filename),
+        _ => base_mods_path.join(&disabled_filename),
+     };
+
+    let path_to_delete = if full_path_if_enabled.is_dir() {
+        Some(full_path_if_enabled)
+    } else if full_path_if_disabled.is_dir() {
+        Some(full_path_if_disabled)
+    } else {
+         // Folder not found, maybe already deleted? Log a warning but proceed to DB deletion.
+         eprintln!("[delete_asset] Warning: Mod folder not found on disk for asset ID {}. Checked {} and {}. Proceeding with DB deletion.",
+             asset_id, full_path_if_enabled.display(), full_path_if_disabled.display());
+         None
+    };
+
+    // --- 4. Snapshot DB state so the delete can be undone ---
+    let record = DeletedAssetRecord {
+        preset_states: {
+            let mut stmt = conn.prepare("SELECT preset_id, is_enabled FROM preset_assets WHERE asset_id = ?1").map_err(|e| e.to_string())?;
+            let rows = stmt.query_map(params![asset_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? == 1)))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<SqlResult<Vec<_>>>().map_err(|e| e.to_string())?
+        },
+        tag_ids: {
+            let mut stmt = conn.prepare("SELECT tag_id FROM asset_tags WHERE asset_id = ?1").map_err(|e| e.to_string())?;
+            let rows = stmt.query_map(params![asset_id], |row| row.get::<_, i64>(0)).map_err(|e| e.to_string())?;
+            rows.collect::<SqlResult<Vec<_>>>().map_err(|e| e.to_string())?
+        },
+        ..conn.query_row(
+            "SELECT id, entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url, is_favorite,
+                    is_locked, is_pinned, sort_order, version, content_hash, exclusive_group, created_at, profile_id
+             FROM assets WHERE id = ?1",
+            params![asset_id],
+            |row| Ok(DeletedAssetRecord {
+                id: row.get(0)?, entity_id: row.get(1)?, name: row.get(2)?, description: row.get(3)?,
+                folder_name: row.get(4)?, image_filename: row.get(5)?, author: row.get(6)?, category_tag: row.get(7)?,
+                notes: row.get(8)?, source_url: row.get(9)?, is_favorite: row.get(10)?,
+                is_locked: row.get(11)?, is_pinned: row.get(12)?, sort_order: row.get(13)?, version: row.get(14)?,
+                content_hash: row.get(15)?, exclusive_group: row.get(16)?, created_at: row.get(17)?, profile_id: row.get(18)?,
+                preset_states: Vec::new(), tag_ids: Vec::new(),
+            }),
+        ).map_err(|e| format!("Failed to read asset ID {} before deletion: {}", asset_id, e))?
+    };
+
+    // --- 5. Move Folder to Trash (copied over when the mods folder is on another drive) ---
+    if let Some(path) = path_to_delete {
+         let trash_dir = get_trash_dir(&app_handle).map_err(|e| e.to_string())?;
+         fs::create_dir_all(&trash_dir).map_err(|e| format!("Failed to create trash folder '{}': {}", trash_dir.display(), e))?;
+         let folder_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+         let trash_path = trash_dir.join(format!("{}_{}", unix_timestamp_millis(), folder_name));
+
+         println!("[delete_asset] Moving folder to trash: {} -> {}", path.display(), trash_path.display());
+         // Nothing is deleted permanently here; a mod that can't be trashed stays where it is
+         move_dir(&extended_length_path(&path), &extended_length_path(&trash_path))
+             .map_err(|e| format!("Failed to move mod folder '{}' to the trash, nothing was deleted: {}", path.display(), e))?;
+         println!("[delete_asset] Folder moved to trash.");
+         // Remember where it came from so it can be restored later, even after a restart
+         let original_relative_path = path.strip_prefix(&base_mods_path)
+             .map(|p| p.to_string_lossy().replace("\\", "/"))
+             .unwrap_or_else(|_| record.folder_name.clone());
+         let trash_folder_name = trash_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+         conn.execute(
+             "INSERT INTO trashed_assets (trash_folder_name, original_relative_path, entity_id, name, description, folder_name, image_filename, author, category_tag, deleted_at, notes, source_url)
+              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+             params![trash_folder_name, original_relative_path, record.entity_id, record.name, record.description, record.folder_name,
+                     record.image_filename, record.author, record.category_tag, (unix_timestamp_millis() / 1000) as i64,
+                     record.notes, record.source_url],
+         ).map_err(|e| {
+             if let Err(move_error) = move_dir(&extended_length_path(&trash_path), &extended_length_path(&path)) {
+                 eprintln!("[delete_asset] Failed to move '{}' back out of the trash: {}", trash_path.display(), move_error);
+             }
+             format!("Failed to record the trashed mod, nothing was deleted: {}", e)
+         })?;
+         record_operation(&op_log, Operation::Delete { record: Box::new(record), original_path: path, trash_path });
+    }
+
+    // --- 6. Delete from Database ---
+    println!("[delete_asset] Deleting asset ID {} from database.", asset_id);
+    let changes = conn.execute("DELETE FROM assets WHERE id = ?1", params![asset_id])
+        .map_err(|e| format!("Failed to delete asset ID {} from database: {}", asset_id, e))?;
+
+     if changes == 0 {
+         // This shouldn't happen if get_asset_location_info succeeded, but good to log.
+         eprintln!("[delete_asset] Warning: Database delete affected 0 rows for asset ID {}.", asset_id);
+     } else {
+         println!("[delete_asset] Database entry deleted successfully.");
+     }
+    remove_ini_backups(&app_handle, asset_id);
+
+    println!("[delete_asset] Asset ID {} deleted successfully. END", asset_id);
+    Ok(())
+}
+
+#[command]
+fn undo_last_operation(db_state: State<DbState>, op_log: State<OperationLogState>) -> CmdResult<String> {
+    {
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+    }
+    let operation = op_log.0.lock().map_err(|_| "Operation log lock poisoned".to_string())?
+        .pop()
+        .ok_or_else(|| "Nothing to undo.".to_string())?;
+    println!("[undo_last_operation] Undoing: {:?}", operation);
+
+    // Puts the entry back when the undo fails before touching the disk, so it can be retried
+    let keep_entry = |e: String| -> String {
+        record_operation(&op_log, operation.clone());
+        e
+    };
+
+    // Moves `current` back to `original`, refusing to overwrite anything
+    let move_back = |current: &Path, original: &Path| -> CmdResult<()> {
+        if !current.exists() {
+            return Err(format!("Cannot undo: '{}' no longer exists.", current.display()));
+        }
+        if original.exists() {
+            return Err(format!("Cannot undo: '{}' already exists.", original.display()));
+        }
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        move_dir(&extended_length_path(current), &extended_length_path(original))
+            .map_err(|e| format!("Failed to move '{}' back to '{}': {}", current.display(), original.display(), e))
+    };
+
+    match &operation {
+        Operation::Toggle { asset_id, from_path, to_path } => {
+            move_back(to_path, from_path).map_err(keep_entry)?;
+            record_asset_toggles(&db_state, &[(*asset_id, is_enabled_folder_path(from_path))]);
+            Ok(format!("Reverted enable/disable of asset {}.", asset_id))
+        }
+        Operation::StagedToggle { asset_id, clean_relative_path, was_enabled } => {
+            let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| keep_entry(e.to_string()))?;
+            set_asset_enabled_on_disk(&base_mods_path, clean_relative_path, *was_enabled)
+                .map_err(|e| keep_entry(format!("Cannot undo: {}", e)))?;
+            record_asset_toggles(&db_state, &[(*asset_id, *was_enabled)]);
+            Ok(format!("Reverted enable/disable of asset {}.", asset_id))
+        }
+        Operation::Relocate { asset_id, from_path, to_path, old_entity_id, old_relative_path } => {
+            move_back(to_path, from_path).map_err(keep_entry)?;
+            let conn = db_state.conn()?;
+            conn.execute(
+                "UPDATE assets SET entity_id = ?1, folder_name = ?2 WHERE id = ?3",
+                params![old_entity_id, old_relative_path, asset_id],
+            ).map_err(|e| format!("Folder moved back, but failed to restore DB entry for asset {}: {}", asset_id, e))?;
+            Ok(format!("Moved asset {} back to its previous location.", asset_id))
+        }
+        Operation::Delete { record, original_path, trash_path } => {
+            move_back(trash_path, original_path).map_err(keep_entry)?;
+            let mut conn = db_state.conn()?;
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+            tx.execute(
+                "INSERT INTO assets (id, entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url, is_favorite, is_enabled,
+                                     is_locked, is_pinned, sort_order, version, content_hash, exclusive_group, created_at, profile_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+                params![record.id, record.entity_id, record.name, record.description, record.folder_name, record.image_filename, record.author, record.category_tag,
+                        record.notes, record.source_url, record.is_favorite, is_enabled_folder_path(original_path),
+                        record.is_locked, record.is_pinned, record.sort_order, record.version, record.content_hash, record.exclusive_group,
+                        record.created_at, record.profile_id],
+            ).map_err(|e| format!("Folder restored, but failed to re-add asset '{}' to the DB (a rescan will pick it up): {}", record.name, e))?;
+            for (preset_id, is_enabled) in &record.preset_states {
+                tx.execute("INSERT OR IGNORE INTO preset_assets (preset_id, asset_id, is_enabled) VALUES (?1, ?2, ?3)", params![preset_id, record.id, is_enabled])
+                    .map_err(|e| e.to_string())?;
+            }
+            for tag_id in &record.tag_ids {
+                tx.execute("INSERT OR IGNORE INTO asset_tags (asset_id, tag_id) VALUES (?1, ?2)", params![record.id, tag_id])
+                    .map_err(|e| e.to_string())?;
+            }
+            let trash_folder_name = trash_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            tx.execute("DELETE FROM trashed_assets WHERE trash_folder_name = ?1", params![trash_folder_name])
+                .map_err(|e| e.to_string())?;
+            tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+            Ok(format!("Restored deleted asset '{}'.", record.name))
+        }
+    }
+}
+
+#[command]
+fn get_trash_contents(db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<Vec<TrashedAsset>> {
+    let trash_dir = get_trash_dir(&app_handle).map_err(|e| e.to_string())?;
+    let conn = db_state.conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, original_relative_path, trash_folder_name, deleted_at FROM active_trashed_assets ORDER BY deleted_at DESC"
+    ).map_err(|e| e.to_string())?;
+    let entries = stmt.query_map([], |row| Ok(TrashedAsset {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        original_relative_path: row.get(2)?,
+        trash_folder_name: row.get(3)?,
+        deleted_at: row.get(4)?,
+    })).map_err(|e| e.to_string())?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    // Only report what is actually recoverable
+    Ok(entries.into_iter().filter(|t| trash_dir.join(&t.trash_folder_name).is_dir()).collect())
+}
+
+#[command]
+fn restore_trashed_asset(trash_id: i64, db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<i64> {
+    println!("[restore_trashed_asset] Restoring trash entry {}", trash_id);
+    let trash_dir = get_trash_dir(&app_handle).map_err(|e| e.to_string())?;
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let mut conn = db_state.conn()?;
+    ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+
+    let (trash_folder_name, original_relative_path, entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url) = conn.query_row(
+        "SELECT trash_folder_name, original_relative_path, entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url
+         FROM active_trashed_assets WHERE id = ?1",
+        params![trash_id],
+        |row| Ok((
+            row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, String>(3)?, row.get::<_, Option<String>>(4)?, row.get::<_, String>(5)?,
+            row.get::<_, Option<String>>(6)?, row.get::<_, Option<String>>(7)?, row.get::<_, Option<String>>(8)?, row.get::<_, Option<String>>(9)?, row.get::<_, Option<String>>(10)?,
+        )),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Trash entry {} not found.", trash_id),
+        _ => format!("Failed to read trash entry {}: {}", trash_id, e),
+    })?;
+
+    let trash_path = trash_dir.join(&trash_folder_name);
+    let restore_path = base_mods_path.join(&original_relative_path);
+    if !trash_path.is_dir() {
+        return Err(format!("Trashed folder '{}' no longer exists.", trash_path.display()));
+    }
+    if restore_path.exists() {
+        return Err(format!("Cannot restore: '{}' already exists.", restore_path.display()));
+    }
+    if let Some(parent) = restore_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    move_dir(&extended_length_path(&trash_path), &extended_length_path(&restore_path))
+        .map_err(|e| format!("Failed to move '{}' back to '{}': {}", trash_path.display(), restore_path.display(), e))?;
+
+    // The original entity may have been removed since; leave the folder for a rescan in that case
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let entity_exists: bool = tx.query_row("SELECT EXISTS(SELECT 1 FROM entities WHERE id = ?1)", params![entity_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if !entity_exists {
+        return Err(format!("Folder restored to '{}', but its entity no longer exists. Run a scan to re-import it.", restore_path.display()));
+    }
+    tx.execute(
+        "INSERT INTO assets (entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url, is_enabled) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url, is_enabled_folder_path(&restore_path)],
+    ).map_err(|e| format!("Folder restored, but failed to re-add '{}' to the DB (a rescan will pick it up): {}", name, e))?;
+    let new_asset_id = tx.last_insert_rowid();
+    tx.execute("DELETE FROM trashed_assets WHERE id = ?1", params![trash_id]).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    println!("[restore_trashed_asset] Restored '{}' as asset ID {}", name, new_asset_id);
+    Ok(new_asset_id)
+}
+
+#[command]
+fn empty_trash(db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<usize> {
+    let trash_dir = get_trash_dir(&app_handle).map_err(|e| e.to_string())?;
+    let conn = db_state.conn()?;
+    let entries: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare("SELECT id, trash_folder_name FROM active_trashed_assets").map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(|e| e.to_string())?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| e.to_string())?
+    };
+
+    let mut removed_count = 0;
+    let mut errors = Vec::new();
+    for (trash_id, trash_folder_name) in entries {
+        let trash_path = trash_dir.join(&trash_folder_name);
+        if trash_path.exists() {
+            if let Err(e) = fs::remove_dir_all(&trash_path) {
+                errors.push(format!("Failed to delete '{}': {}", trash_path.display(), e));
+                continue; // Keep the record so it can be retried
+            }
+            removed_count += 1;
+        }
+        conn.execute("DELETE FROM trashed_assets WHERE id = ?1", params![trash_id]).map_err(|e| e.to_string())?;
+    }
+    match prune_orphaned_ini_backups(&app_handle, &conn) {
+        Ok(pruned_count) if pruned_count > 0 => println!("[empty_trash] Deleted INI backups of {} removed mods", pruned_count),
+        Ok(_) => {}
+        Err(e) => eprintln!("[empty_trash] Failed to prune INI backups: {}", e),
+    }
+
+    println!("[empty_trash] Permanently deleted {} trashed mods, {} errors", removed_count, errors.len());
+    if errors.is_empty() { Ok(removed_count) } else { Err(errors.join("\n")) }
+}
+
+#[command]
+async fn read_binary_file(path: String) -> Result<Vec<u8>, String> {
+    println!("[read_binary_file] Reading path: {}", path);
+    // Keep the original path for potential error reporting
+    let path_for_error = path.clone(); // Clone the path *before* it's moved
+
+    read_binary(PathBuf::from(path)) // 'path' is moved here
+        .map_err(|e| {
+            // Use the cloned path 'path_for_error' in the error message
+            eprintln!("[read_binary_file] Error reading file '{}': {}", path_for_error, e);
+            format!("Failed to read file: {}", e)
+        })
+}
+
+#[command]
+async fn select_archive_file() -> CmdResult<Option<PathBuf>> {
+    println!("[select_archive_file] Opening file dialog...");
+    let result = dialog::blocking::FileDialogBuilder::new()
+        .set_title("Select Mod Archive")
+        // --- Update Filter ---
+        .add_filter("Archives", SUPPORTED_ARCHIVE_EXTENSIONS)
+        .add_filter("All Files", &["*"])
+        .pick_file();
+
+    match result {
+        Some(path) => {
+            println!("[select_archive_file] File selected: {}", path.display());
+            Ok(Some(path))
+        },
+        None => {
+            println!("[select_archive_file] Dialog cancelled.");
+            Ok(None)
+        }, // User cancelled
+    }
+}
+
+// Picks a folder and lists the supported archives directly inside it (not recursive), sorted by name
+#[command]
+async fn select_archive_folder() -> CmdResult<Option<Vec<PathBuf>>> {
+    let folder = match dialog::blocking::FileDialogBuilder::new()
+        .set_title("Select Folder With Mod Archives")
+        .pick_folder() {
+        Some(path) => path,
+        None => return Ok(None), // User cancelled
+    };
+    let mut archives: Vec<PathBuf> = fs::read_dir(&folder)
+        .map_err(|e| format!("Failed to read folder {}: {}", folder.display(), e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| path.extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(false, |ext| SUPPORTED_ARCHIVE_EXTENSIONS.contains(&ext.to_lowercase().as_str())))
+        .collect();
+    archives.sort();
+    println!("[select_archive_folder] Found {} archives in {}", archives.len(), folder.display());
+    Ok(Some(archives))
+}
+
+// A split archive volume. The last volume of a split zip keeps the .zip name, so its
+// name.z01 sibling is checked too. RAR volumes are read through unrar, which follows them.
+fn is_multipart_archive(path: &Path) -> bool {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    MULTIPART_ARCHIVE_REGEX.is_match(&file_name)
+        || (path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("zip")) && path.with_extension("z01").is_file())
+}
+
+fn ensure_archive_readable(path: &Path, extension: Option<&str>) -> Result<(), String> {
+    if extension != Some("rar") && is_multipart_archive(path) {
+        return Err(AppError::UnsupportedArchive(format!(
+            "'{}' is one volume of a multi-part archive. Join the parts into a single archive before importing.",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        )).to_string());
+    }
+    Ok(())
+}
+
+fn sevenz_password(password: Option<&str>) -> Password {
+    password.map(Password::from).unwrap_or_else(Password::empty)
+}
+
+fn open_rar<'a>(path: &'a str, password: Option<&'a str>) -> Archive<'a> {
+    match password {
+        Some(pw) => Archive::with_password(path, pw),
+        None => Archive::new(path),
+    }
+}
+
+fn is_zip_password_error(e: &ZipError) -> bool {
+    matches!(e, ZipError::UnsupportedArchive(msg) if *msg == ZipError::PASSWORD_REQUIRED)
+}
+
+#[derive(Debug, Error)]
+enum ZipEntryError {
+    #[error("This archive is password-protected. Enter its password to continue.")]
+    MissingPassword,
+    #[error("Wrong password for this archive.")]
+    BadPassword,
+    #[error("Failed to read zip entry #{0}: {1}")]
+    Read(usize, ZipError),
+}
+
+// Opens a zip entry for reading, decrypting it when a password is given
+fn open_zip_entry<'a>(archive: &'a mut ZipArchive<fs::File>, index: usize, password: Option<&str>) -> Result<zip::read::ZipFile<'a>, ZipEntryError> {
+    match password {
+        Some(pw) => archive.by_index_decrypt(index, pw.as_bytes())
+            .map_err(|e| ZipEntryError::Read(index, e))?
+            .map_err(|_| ZipEntryError::BadPassword),
+        None => archive.by_index(index).map_err(|e| {
+            if is_zip_password_error(&e) {
+                ZipEntryError::MissingPassword
+            } else {
+                ZipEntryError::Read(index, e)
+            }
+        }),
+    }
+}
+
+fn sevenz_error_message(context: &str, e: sevenz_rust::Error) -> String {
+    match e {
+        sevenz_rust::Error::PasswordRequired => "This archive is password-protected. Enter its password to continue.".to_string(),
+        sevenz_rust::Error::MaybeBadPassword(_) => "Wrong password for this archive.".to_string(),
+        e => format!("{}: {}", context, e),
+    }
+}
+
+fn rar_error_message(e: unrar::error::UnrarError) -> String {
+    match e.code {
+        unrar::error::Code::MissingPassword => "This archive is password-protected. Enter its password to continue.".to_string(),
+        unrar::error::Code::BadPassword => "Wrong password for this archive.".to_string(),
+        _ => e.to_string(),
+    }
+}
+
+// Reads every file entry to the end so the decoders check their CRCs (zip and rar store one
+// per entry, 7z per entry or per solid block). Password problems are returned as errors since
+// they say nothing about integrity.
+fn verify_archive_file(path: &Path, password: Option<&str>) -> Result<ArchiveVerifyResult, String> {
+    let extension = path.extension().and_then(|os| os.to_str()).map(|s| s.to_lowercase());
+    ensure_archive_readable(path, extension.as_deref())?;
+    let path_str = path.to_string_lossy().to_string();
+    let mut entries_checked = 0;
+    let mut corrupt_entries: Vec<String> = Vec::new();
+
+    match extension.as_deref() {
+        Some("zip") => {
+            let file = fs::File::open(path).map_err(|e| format!("Failed to open zip: {}", e))?;
+            match ZipArchive::new(file) {
+                Ok(mut archive) => {
+                    for i in 0..archive.len() {
+                        let mut entry = match open_zip_entry(&mut archive, i, password) {
+                            Ok(entry) => entry,
+                            Err(e @ (ZipEntryError::MissingPassword | ZipEntryError::BadPassword)) => return Err(e.to_string()),
+                            Err(e) => { corrupt_entries.push(format!("#{}: {}", i, e)); continue; }
+                        };
+                        if entry.is_dir() { continue; }
+                        entries_checked += 1;
+                        if let Err(e) = io::copy(&mut entry, &mut io::sink()) {
+                            corrupt_entries.push(format!("{}: {}", entry.name(), e));
+                        }
+                    }
+                }
+                Err(e) => corrupt_entries.push(format!("<archive>: {}", e)),
+            }
+        }
+        Some("7z") => {
+            match sevenz_rust::SevenZReader::open(&path_str, sevenz_password(password)) {
+                Ok(mut archive) => {
+                    let iterate_result = archive.for_each_entries(|entry, reader| {
+                        if entry.is_directory() { return Ok(true); }
+                        entries_checked += 1;
+                        if let Err(e) = io::copy(reader, &mut io::sink()) {
+                            corrupt_entries.push(format!("{}: {}", entry.name(), e));
+                        }
+                        Ok(true)
+                    });
+                    match iterate_result {
+                        Ok(()) => {}
+                        Err(e @ (sevenz_rust::Error::PasswordRequired | sevenz_rust::Error::MaybeBadPassword(_))) => {
+                            return Err(sevenz_error_message("", e));
+                        }
+                        Err(e) => corrupt_entries.push(format!("<archive>: {}", e)),
+                    }
+                }
+                Err(e @ (sevenz_rust::Error::PasswordRequired | sevenz_rust::Error::MaybeBadPassword(_))) => return Err(sevenz_error_message("", e)),
+                Err(e) => corrupt_entries.push(format!("<archive>: {}", e)),
+            }
+        }
+        Some("rar") => {
+            match open_rar(&path_str, password).open_for_processing() {
+                Ok(mut archive) => loop {
+                    match archive.read_header() {
+                        Ok(Some(header_state)) => {
+                            let entry_name = header_state.entry().filename.to_string_lossy().replace("\\", "/");
+                            if header_state.entry().is_directory() {
+                                archive = header_state.skip().map_err(rar_error_message)?;
+                                continue;
+                            }
+                            entries_checked += 1;
+                            match header_state.test() {
+                                Ok(next) => archive = next,
+                                Err(e) if matches!(e.code, unrar::error::Code::MissingPassword | unrar::error::Code::BadPassword) => {
+                                    return Err(rar_error_message(e));
+                                }
+                                Err(e) => {
+                                    // The archive handle is consumed, so later entries can't be checked
+                                    corrupt_entries.push(format!("{}: {}", entry_name, e));
+                                    break;
+                                }
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(e) => { corrupt_entries.push(format!("<archive>: {}", e)); break; }
+                    }
+                },
+                Err(e) if matches!(e.code, unrar::error::Code::MissingPassword | unrar::error::Code::BadPassword) => return Err(rar_error_message(e)),
+                Err(e) => corrupt_entries.push(format!("<archive>: {}", e)),
+            }
+        }
+        _ => return Err(format!("Unsupported archive type for verification: {:?}", extension)),
+    }
+
+    Ok(ArchiveVerifyResult { intact: corrupt_entries.is_empty(), entries_checked, corrupt_entries })
+}
+
+// Reads the whole archive, so it runs on the blocking pool like extraction does
+async fn run_archive_verify(archive_path: PathBuf, password: Option<String>) -> CmdResult<ArchiveVerifyResult> {
+    async_runtime::spawn_blocking(move || verify_archive_file(&archive_path, password.as_deref())).await
+        .map_err(|e| format!("Archive verification task failed: {}", e))?
+}
+
+#[command]
+async fn verify_archive(path: String, password: Option<String>) -> CmdResult<ArchiveVerifyResult> {
+    println!("[verify_archive] Verifying {}", path);
+    let archive_path = PathBuf::from(&path);
+    if !archive_path.is_file() { return Err(format!("Archive file not found: {}", archive_path.display())); }
+    let result = run_archive_verify(archive_path, password).await?;
+    println!("[verify_archive] {} entries checked, {} corrupt", result.entries_checked, result.corrupt_entries.len());
+    Ok(result)
+}
+
+#[command]
+fn analyze_archive(
+    file_path_str: String,
+    password: Option<String>,
+    // *** ADDED: Inject DB State ***
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>
+) -> CmdResult<ArchiveAnalysisResult> {
+    // --- Fetch Deduction Maps ---
+    let maps = {
+        // Use a block to limit the scope of the lock guard
+        let conn_guard = db_state.conn()?;
+        let conn = &*conn_guard; // Dereference the guard
+        cached_deduction_maps(&config_state, conn)
+             .map_err(|e| format!("Analyze: Failed to fetch deduction maps: {}", e))?
+    };
+    println!("[analyze_archive] Deduction maps loaded.");
+    // --- End Fetch ---
+    analyze_archive_file(file_path_str, &maps, password.as_deref())
+}
+
+// Analyzes several archives with one set of deduction maps. Archives that fail to analyze are
+// reported in `failed` with their error.
+#[command]
+fn batch_analyze_archives(
+    paths: Vec<String>,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>
+) -> CmdResult<BatchArchiveAnalysis> {
+    println!("[batch_analyze_archives] Analyzing {} archives", paths.len());
+    let maps = {
+        let conn = db_state.conn()?;
+        cached_deduction_maps(&config_state, &conn)
+            .map_err(|e| format!("Analyze: Failed to fetch deduction maps: {}", e))?
+    };
+    let mut analysis = BatchArchiveAnalysis { results: Vec::with_capacity(paths.len()), failed: Vec::new() };
+    for path in paths {
+        match analyze_archive_file(path.clone(), &maps, None) {
+            Ok(result) => analysis.results.push(result),
+            Err(e) => {
+                eprintln!("[batch_analyze_archives] Failed to analyze '{}': {}", path, e);
+                analysis.failed.push(ArchiveAnalysisFailure { file_path: path, error: e });
+            }
+        }
+    }
+    println!("[batch_analyze_archives] Analyzed {} archives, {} failed", analysis.results.len(), analysis.failed.len());
+    Ok(analysis)
+}
+
+// Deduces entity, name, author etc. for a loose mod folder, to pre-fill the import_folder form
+#[command]
+fn analyze_folder(source_path: String, db_state: State<DbState>, config_state: State<CachedConfigState>) -> CmdResult<DeducedInfo> {
+    let source = PathBuf::from(source_path.trim());
+    if !source.is_dir() { return Err(format!("Folder not found: {}", source.display())); }
+    let (maps, base_mods_path) = {
+        let conn = db_state.conn()?;
+        let maps = cached_deduction_maps(&config_state, &conn).map_err(|e| e.to_string())?;
+        let base = get_setting_value(&conn, SETTINGS_KEY_MODS_FOLDER).map_err(|e| e.to_string())?.map(PathBuf::from).unwrap_or_default();
+        (maps, base)
+    };
+    deduce_mod_info_v2(&source, &base_mods_path, &maps)
+        .ok_or_else(|| format!("Could not deduce mod info for '{}'.", source.display()))
+}
+
+fn analyze_archive_file(file_path_str: String, maps: &DeductionMaps, password: Option<&str>) -> CmdResult<ArchiveAnalysisResult> {
+    println!("[analyze_archive] Analyzing: {}", file_path_str);
+    let file_path = PathBuf::from(&file_path_str);
+    if !file_path.is_file() { return Err(format!("Archive file not found: {}", file_path.display())); }
+
+    let extension = file_path.extension().and_then(|os| os.to_str()).map(|s| s.to_lowercase());
+    println!("[analyze_archive] Detected extension: {:?}", extension);
+
+    let mut entries = Vec::new();
+    let mut ini_contents: HashMap<String, String> = HashMap::new();
+    let preview_candidates = CACHED_CONFIG.preview_candidates();
+    let multipart = is_multipart_archive(&file_path);
+    let mut encrypted = false;
+
+    match extension.as_deref() {
+        _ if multipart && extension.as_deref() != Some("rar") => {
+            // Neither the zip nor the 7z reader can follow volumes; report it instead of failing
+            println!("[analyze_archive] Multi-part volume, contents not listed.");
+        }
+        Some("zip") => {
+            println!("[analyze_archive] Processing as ZIP...");
+            let file = fs::File::open(&file_path)
+                .map_err(|e| format!("Failed to open zip file {}: {}", file_path.display(), e))?;
+            let mut archive = ZipArchive::new(file)
+                .map_err(|e| format!("Failed to read zip archive {}: {}", file_path.display(), e))?;
+
+            for i in 0..archive.len() {
+                let entry_encrypted = archive.by_index(i).err().map_or(false, |e| is_zip_password_error(&e));
+                encrypted |= entry_encrypted;
+                let (path_str_opt, is_dir) = {
+                    let file_entry = archive.by_index_raw(i)
+                         .map_err(|e| format!("Failed to read zip entry #{}: {}", i, e))?;
+                    (file_entry.enclosed_name().map(|p| p.to_string_lossy().replace("\\", "/")), file_entry.is_dir())
+                };
+                if path_str_opt.is_none() { continue; }
+                // --- FIX: Just clone the String if needed, or use directly ---
+                let path_str = path_str_opt.unwrap().to_string(); // Use to_string() to ensure it's owned String
+
+                // Encrypted INIs can only be read with the password
+                if !is_dir && path_str.to_lowercase().ends_with(".ini") && (!entry_encrypted || password.is_some()) {
+                    let mut file_entry = open_zip_entry(&mut archive, i, password).map_err(|e| e.to_string())?;
+                    let mut content = String::new();
+                    if file_entry.read_to_string(&mut content).is_ok() {
+                        ini_contents.insert(path_str.clone(), content);
+                    }
+                }
+                entries.push(ArchiveEntry { path: path_str, is_dir, is_likely_mod_root: false });
+            }
+        }
+        Some("7z") => {
+            println!("[analyze_archive] Processing as 7z...");
+            let mut archive = match sevenz_rust::SevenZReader::open(&file_path_str, sevenz_password(password)) {
+                Ok(archive) => Some(archive),
+                // Encrypted headers: not even the file list is readable without the password
+                Err(sevenz_rust::Error::PasswordRequired) => {
+                    encrypted = true;
+                    None
+                }
+                Err(e) => return Err(sevenz_error_message(&format!("Failed to open/read 7z archive {}", file_path.display()), e)),
+            };
+
+            if let Some(archive) = archive.as_mut() {
+                 encrypted |= archive.archive().folders.iter().any(|folder| {
+                     folder.coders.iter().any(|coder| coder.decompression_method_id() == sevenz_rust::SevenZMethod::ID_AES256SHA256)
+                 });
+                 // --- FIX: Use for_each_entries ---
+                 let iteration = archive.for_each_entries(|entry, reader| {
+                    let path_str = entry.name().replace("\\", "/");
+                    let is_dir = entry.is_directory();
+
+                    if !is_dir && path_str.to_lowercase().ends_with(".ini") {
+                         let mut content_bytes = Vec::new();
+                         let mut buffer = [0u8; 4096];
+                         loop {
+                            let bytes_read = reader.read(&mut buffer)?;
+                            if bytes_read == 0 { break; }
+                            content_bytes.extend_from_slice(&buffer[..bytes_read]);
+                        }
+                         let content = String::from_utf8_lossy(&content_bytes).to_string();
+                         ini_contents.insert(path_str.clone(), content);
+                    }
+                    entries.push(ArchiveEntry { path: path_str, is_dir, is_likely_mod_root: false });
+                    Ok(true) // Continue processing entries
+                 });
+                 match iteration {
+                     Ok(()) => {}
+                     // Only the contents are encrypted: list the names from the header instead
+                     Err(sevenz_rust::Error::PasswordRequired) => {
+                         encrypted = true;
+                         ini_contents.clear();
+                         entries = archive.archive().files.iter()
+                             .map(|entry| ArchiveEntry { path: entry.name().replace("\\", "/"), is_dir: entry.is_directory(), is_likely_mod_root: false })
+                             .collect();
+                     }
+                     Err(e) => return Err(sevenz_error_message("Error iterating 7z entries", e)),
+                 }
+            }
+        }
+        Some("rar") => {
+            println!("[analyze_archive] Processing as RAR...");
+            let mut header_infos = Vec::new();
+            match open_rar(&file_path_str, password).open_for_listing() {
+                // Encrypted headers: nothing can be listed without the password
+                Err(e) if e.code == unrar::error::Code::MissingPassword => encrypted = true,
+                Err(e) => return Err(rar_error_message(e)),
+                Ok(mut list_archive) => {
+                    // Iterate through headers
+                    for entry_result in &mut list_archive { // Keep iterating with &mut
+                        match entry_result {
+                            Ok(header) => {
+                                encrypted |= header.is_encrypted();
+                                let path_str = header.filename.to_string_lossy().replace("\\", "/").to_string();
+                                let is_dir = header.is_directory();
+                                // --- FIX 1: Clone path_str for the first push ---
+                                header_infos.push((path_str.clone(), is_dir, header.filename.clone()));
+                                // --- End Fix 1 ---
+                                entries.push(ArchiveEntry { path: path_str, is_dir, is_likely_mod_root: false });
+                            }
+                            Err(e) => {
+                                eprintln!("[analyze_archive] Warning: Skipping RAR entry due to header read error: {}", e);
+                                // --- FIX 2: Remove force_heal call ---
+                                // list_archive.force_heal(); // Cannot call this here
+                                // --- End Fix 2 ---
+                                // The loop will continue to the next entry if possible,
+                                // or stop if the error was fatal for the iterator.
+                            }
+                        }
+                    }
+                    // `list_archive` borrow ends here
+                }
+            }
+
+            // --- Rest of the RAR logic (re-opening for INI reading) remains the same ---
+            let ini_files_to_read: Vec<(String, PathBuf)> = header_infos.iter()
+               .filter(|(path, is_dir, _)| !*is_dir && path.to_lowercase().ends_with(".ini"))
+               .map(|(path, _, original_filename)| (path.clone(), original_filename.clone()))
+               .collect();
+
+            if !ini_files_to_read.is_empty() && (!encrypted || password.is_some()) {
+               let mut processing_archive = open_rar(&file_path_str, password).open_for_processing()
+                    .map_err(rar_error_message)?;
+               let mut read_count = 0;
+               loop {
+                   match processing_archive.read_header().map_err(|e| e.to_string())? {
+                       Some(header_state) => {
+                           let current_filename = header_state.entry().filename.clone();
+                           let path_str = current_filename.to_string_lossy().replace("\\", "/").to_string();
+                           if let Some(pos) = ini_files_to_read.iter().position(|(_, fname)| fname == &current_filename) {
+                               match header_state.read() {
+                                   Ok((bytes, next_state)) => {
+                                       ini_contents.insert(path_str, String::from_utf8_lossy(&bytes).to_string());
+                                       processing_archive = next_state;
+                                       read_count += 1;
+                                       if read_count == ini_files_to_read.len() { break; }
+                                   }
+                                   Err(e) => { return Err(format!("Error reading content of RAR INI '{}': {}", path_str, rar_error_message(e))); }
+                               }
+                           } else {
+                               processing_archive = header_state.skip().map_err(|e| e.to_string())?;
+                           }
+                       }
+                       None => break,
+                   }
+               }
+            }
+        }
+        _ => {
+            return Err(format!("Unsupported archive type: {:?}", extension));
+        }
+    }
+    println!("[analyze_archive] Pass 1: Found {} entries. Found {} INI files.", entries.len(), ini_contents.len());
+
+    entries.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+    // ... (Pass 2: Find roots) ...
+    let mut likely_root_indices = HashSet::new();
+    for (ini_index, ini_entry) in entries.iter().enumerate() {
+        if !ini_entry.is_dir && ini_entry.path.to_lowercase().ends_with(".ini") {
+            let parent_path_obj = Path::new(&ini_entry.path).parent();
+            if let Some(parent_path_ref) = parent_path_obj {
+                let parent_path_str_norm = parent_path_ref.to_string_lossy().replace("\\", "/");
+                if parent_path_str_norm.is_empty() { continue; }
+                let found_parent = entries.iter().position(|dir_entry| {
+                    if !dir_entry.is_dir { return false; }
+                    let dir_entry_path_norm = dir_entry.path.strip_suffix('/').unwrap_or(&dir_entry.path);
+                    dir_entry_path_norm == parent_path_str_norm
+                });
+                if let Some(parent_index) = found_parent {
+                    likely_root_indices.insert(parent_index);
+                }
+            }
+        }
+    }
+    // ... (Pass 3: Find previews) ...
+     let mut root_to_preview_map: HashMap<usize, String> = HashMap::new();
+     for root_index in likely_root_indices.iter() {
+          if let Some(root_entry) = entries.get(*root_index) {
+              let root_prefix = if root_entry.path.ends_with('/') { root_entry.path.clone() } else { format!("{}/", root_entry.path) };
+              for candidate in preview_candidates.iter() {
+                  let potential_preview_path = format!("{}{}", root_prefix, candidate);
+                  if let Some(preview_entry) = entries.iter().find(|e| !e.is_dir && e.path.eq_ignore_ascii_case(&potential_preview_path)) {
+                      root_to_preview_map.insert(*root_index, preview_entry.path.clone());
+                      break;
+                  }
+              }
+          }
+     }
+    // ... (Pass 4: Deduction) ...
+    let mut deduced_mod_name: Option<String> = None;
+    let mut deduced_author: Option<String> = None;
+    let mut deduced_version: Option<String> = None;
+    // Initialize final deduced slugs
+    let mut final_deduced_category_slug: Option<String> = None;
+    let mut final_deduced_entity_slug: Option<String> = None;
+    // Raw hints extracted from INI
+    let mut raw_ini_type_found: Option<String> = None;
+    let mut raw_ini_target_found: Option<String> = None;
+    // Preview path detected within archive
+    let mut detected_preview_internal_path : Option<String> = None;
+    let mut first_likely_root_processed = false;
+
+    // --- 1. Deduce from INI in First Likely Root ---
+    println!("[analyze_archive] Starting Pass 4: Deduction...");
+    for (index, entry) in entries.iter_mut().enumerate() {
+        if likely_root_indices.contains(&index) {
+            entry.is_likely_mod_root = true; // Mark the entry
+            println!("[analyze_archive] Found likely root: {}", entry.path);
+            if !first_likely_root_processed {
+                first_likely_root_processed = true;
+                let root_prefix = if entry.path.ends_with('/') { entry.path.clone() } else { format!("{}/", entry.path) };
+                // Find the first INI file *directly* inside this root
+                if let Some((_ini_path, ini_content)) = ini_contents.iter().find(|(p, _)| p.starts_with(&root_prefix) && p.trim_start_matches(&root_prefix).find('/') == None) {
+                    println!("[analyze_archive] Found INI in root {}: {}", root_prefix, _ini_path);
+                    if let Ok(ini) = Ini::load_from_str(ini_content) {
+                        // --- Temporary storage for extracted hints ---
+                        let mut extracted_target: Option<String> = None;
+                        let mut extracted_type: Option<String> = None;
+                        // ---
+                        for section_name in ["Mod", "Settings", "Info", "General"] {
+                            if let Some(section) = ini.section(Some(section_name)) {
+                                // Extract Name, Author
+                                let name_val = section.get("Name").or_else(|| section.get("ModName"));
+                                // Use the INI name if found, otherwise keep the initial filename guess
+                                if let Some(name) = name_val {
+                                    let cleaned_ini_name = MOD_NAME_CLEANUP_REGEX.replace_all(name, "").trim().to_string();
+                                    if !cleaned_ini_name.is_empty() {
+                                        deduced_mod_name = Some(cleaned_ini_name);
+                                    }
+                                }
+                                let author_val = section.get("Author");
+                                if author_val.is_some() { deduced_author = author_val.map(String::from); }
+                                let version_val = section.get("Version").or_else(|| section.get("ModVersion"));
+                                if version_val.is_some() { deduced_version = version_val.map(|s| s.trim().to_string()); }
+
+                                // Extract Raw Hints
+                                let target_val = section.get("Target").or_else(|| section.get("Entity")).or_else(|| section.get("Character"));
+                                if target_val.is_some() { extracted_target = target_val.map(|s| s.trim().to_string()); }
+                                let type_val = section.get("Type").or_else(|| section.get("Category"));
+                                if type_val.is_some() { extracted_type = type_val.map(|s| s.trim().to_string()); }
+                            }
+                        }
+                        // Log extracted hints and assign to outer scope
+                        println!("[analyze_archive] INI Extracted Hints: Target='{:?}', Type='{:?}'", extracted_target, extracted_type);
+                        raw_ini_target_found = extracted_target;
+                        raw_ini_type_found = extracted_type;
+                    } else {
+                        eprintln!("[analyze_archive] Warning: Failed to parse INI content from {}", _ini_path);
+                    }
+                } else {
+                    println!("[analyze_archive] No INI found directly in root: {}", root_prefix);
+                }
+
+                // --- Try matching INI Target Hint (USE HELPER) ---
+                if final_deduced_entity_slug.is_none() { // Only run if not already found
+                    if let Some(target_hint) = &raw_ini_target_found {
+                        println!("[analyze_archive] Trying INI target hint matching...");
+                        // Use the reusable helper function
+                        if let Some(slug) = find_entity_slug_from_hint(target_hint, maps) {
+                            final_deduced_entity_slug = Some(slug);
+                            println!("[analyze_archive]   -> Found entity via INI target hint: '{}' -> {}", target_hint, final_deduced_entity_slug.as_ref().unwrap());
+                        }
+                    } else {
+                        println!("[analyze_archive] No INI target hint found.");
+                    }
+                }
+
+                // --- Try matching INI Type Hint (Category) ---
+                if final_deduced_category_slug.is_none() { // Only run if not already found
+                    if let Some(type_hint) = &raw_ini_type_found {
+                        let lower_type_hint = type_hint.to_lowercase();
+                        println!("[analyze_archive] Trying INI type hint: '{}' (lowercase: '{}')", type_hint, lower_type_hint);
+
+                        // Prio 1: Exact slug
+                        if maps.category_slug_to_id.contains_key(type_hint) {
+                            final_deduced_category_slug = Some(type_hint.clone());
+                            println!("[analyze_archive]   -> Matched category via INI exact slug: {}", type_hint);
+                        }
+                        // Prio 2: Exact lowercase name -> original slug
+                        else if let Some(slug) = maps.lowercase_category_name_to_slug.get(&lower_type_hint) {
+                            final_deduced_category_slug = Some(slug.clone());
+                            println!("[analyze_archive]   -> Matched category via INI exact lowercase name: {} -> {}", lower_type_hint, slug);
+                        }
+                        // Prio 3: Known name starts with hint
+                        else {
+                            for (cat_name_lower, cat_slug) in &maps.lowercase_category_name_to_slug {
+                                if cat_name_lower.starts_with(&lower_type_hint) {
+                                    final_deduced_category_slug = Some(cat_slug.clone());
+                                    println!("[analyze_archive]   -> Matched category via INI name prefix: '{}' starts with '{}' -> {}", cat_name_lower, lower_type_hint, cat_slug);
+                                    break;
+                                }
+                            }
+                        }
+                        // Prio 4: Known name contains hint
+                        if final_deduced_category_slug.is_none() {
+                            for (cat_name_lower, cat_slug) in &maps.lowercase_category_name_to_slug {
+                                if lower_type_hint.len() > 2 && cat_name_lower.contains(&lower_type_hint) {
+                                    final_deduced_category_slug = Some(cat_slug.clone());
+                                    println!("[analyze_archive]   -> Matched category via INI name contains: '{}' contains '{}' -> {}", cat_name_lower, lower_type_hint, cat_slug);
+                                    break;
+                                }
+                            }
+                        }
+                        if final_deduced_category_slug.is_none() {
+                            println!("[analyze_archive]   -> No category match found from INI type hint.");
+                        }
+                    } else {
+                        println!("[analyze_archive] No INI type hint found.");
+                    }
+                }
+
+                // Use detected preview if available for this root
+                if let Some(preview_path) = root_to_preview_map.get(&index) {
+                    detected_preview_internal_path = Some(preview_path.clone());
+                    println!("[analyze_archive] Detected preview for this root: {}", preview_path);
+                }
+
+                // --- Break after processing the first root's INI ---
+                println!("[analyze_archive] Finished processing first likely root INI.");
+                break;
+            }
+        }
+    }
+    // --- End INI Deduction ---
+
+
+    // --- 2. Deduce from Internal Filenames ---
+    if final_deduced_entity_slug.is_none() {
+        println!("[analyze_archive] Trying internal filename matching...");
+        let mut file_match_found = false;
+        // Iterate through ALL file entries in the archive
+        for entry in &entries {
+            if !entry.is_dir {
+                // Get filename stem (without extension)
+                let filename = entry.path.split('/').last().unwrap_or(&entry.path); // Get last component
+                if let Some(stem) = Path::new(filename).file_stem().and_then(OsStr::to_str) {
+                    if !stem.is_empty() {
+                        // Use the helper to check if the stem matches an entity
+                        if let Some(slug) = find_entity_slug_from_hint(stem, maps) {
+                            final_deduced_entity_slug = Some(slug);
+                            println!("[analyze_archive]   -> Found entity via internal filename stem: '{}' -> {}", stem, final_deduced_entity_slug.as_ref().unwrap());
+                            file_match_found = true;
+                            break; // Found a match from a file, stop searching files
+                        }
+                    }
+                }
+            }
+        }
+        if !file_match_found {
+            println!("[analyze_archive]   -> No entity match found from internal filenames.");
+        }
+    } else {
+        println!("[analyze_archive] Skipping internal filename check (entity already found).")
+    }
+    // --- End Internal Filename Deduction ---
+
+
+    // --- 3. Deduce from Archive Filename (USE HELPER - Lower Priority) ---
+    if final_deduced_entity_slug.is_none() || final_deduced_category_slug.is_none() {
+        println!("[analyze_archive] Attempting deduction from archive filename...");
+        if let Some(stem) = file_path.file_stem().and_then(OsStr::to_str) {
+            // Try matching stem against Entities (USE HELPER)
+            if final_deduced_entity_slug.is_none() {
+                println!("[analyze_archive] Trying archive filename stem for Entity: '{}'", stem);
+                if let Some(slug) = find_entity_slug_from_hint(stem, maps) {
+                    final_deduced_entity_slug = Some(slug);
+                    println!("[analyze_archive]   -> Found entity via filename.");
+                } else {
+                    println!("[analyze_archive]   -> No entity match found from filename.");
+                }
+            }
+
+            // Try matching stem against Categories
+            if final_deduced_category_slug.is_none() {
+                let cleaned_stem = clean_and_extract_name(stem);
+                println!("[analyze_archive] Trying archive filename stem for Category: '{}' (cleaned lowercase: '{}')", stem, cleaned_stem);
+
+                // Prio 1: Exact slug (original stem)
+                if maps.category_slug_to_id.contains_key(stem) {
+                    final_deduced_category_slug = Some(stem.to_string());
+                    println!("[analyze_archive]   -> Matched category from filename via exact slug: {}", stem);
+                }
+                // Prio 2: Exact lowercase cleaned name match -> original slug
+                else if let Some(slug) = maps.lowercase_category_name_to_slug.get(&cleaned_stem) {
+                    final_deduced_category_slug = Some(slug.clone());
+                    println!("[analyze_archive]   -> Matched category from filename via exact cleaned name: {} -> {}", cleaned_stem, slug);
+                }
+                // Prio 3: Known name contains cleaned stem part
+                else {
+                    let stem_words: Vec<&str> = cleaned_stem.split_whitespace().filter(|w| w.len() > 2).collect();
+                    if !stem_words.is_empty() {
+                        'cat_loop: for (cat_name_lower, cat_slug) in &maps.lowercase_category_name_to_slug {
+                            for word in &stem_words {
+                                if cat_name_lower.contains(word) {
+                                    final_deduced_category_slug = Some(cat_slug.clone());
+                                    println!("[analyze_archive]   -> Matched category from filename via word contains: '{}' contains '{}' -> {}", cat_name_lower, word, cat_slug);
+                                    break 'cat_loop;
+                                }
+                            }
+                        }
+                    }
+                }
+                if final_deduced_category_slug.is_none() {
+                    println!("[analyze_archive]   -> No category match found from filename.");
+                }
+            }
+        } else {
+            println!("[analyze_archive] Could not get filename stem.");
+        }
+    } else {
+        println!("[analyze_archive] Skipping filename deduction (already found entity and category).");
+    }
+    // --- End Filename Deduction ---
+
+
+    // Nothing matched at all: suggest the fallback entity, like a scan would
+    if final_deduced_entity_slug.is_none() && final_deduced_category_slug.is_none() {
+        final_deduced_entity_slug = Some(maps.fallback_entity_slug.clone());
+        println!("[analyze_archive] No entity or category match, suggesting fallback entity '{}'", maps.fallback_entity_slug);
+    }
+
+    // --- 4. Final Category Lookup (If needed) ---
+    if final_deduced_entity_slug.is_some() && final_deduced_category_slug.is_none() {
+        let entity_slug = final_deduced_entity_slug.as_ref().unwrap();
+        println!("[analyze_archive] Entity slug '{}' found, but category slug is missing. Looking up category...", entity_slug);
+        if let Some(cat_slug) = maps.entity_slug_to_category_slug.get(entity_slug) {
+            final_deduced_category_slug = Some(cat_slug.clone());
+            println!("[analyze_archive]   -> Found category slug '{}' from entity map.", cat_slug);
+        } else {
+            eprintln!("[analyze_archive]   -> Warning: Could not find category slug for deduced entity slug '{}' in maps!", entity_slug);
+        }
+    }
+    // --- End Final Category Lookup ---
+
+
+    // --- Fallback name deduction & final cleanup ---
+    // Use cleaned archive name if INI name wasn't found or was empty after cleaning
+    if deduced_mod_name.is_none() || deduced_mod_name.as_deref() == Some("") {
+        deduced_mod_name = file_path.file_stem()
+            .and_then(OsStr::to_str)
+            .map(|s| clean_and_extract_name(s)); // Use cleaner here too
+        println!("[analyze_archive] Used archive filename for deduced name: {:?}", deduced_mod_name);
+    }
+    // Final cleanup on whatever name we ended up with
+    if let Some(name) = &deduced_mod_name {
+        let cleaned = clean_and_extract_name(name); // Use cleaner
+        if !cleaned.is_empty() {
+            // If cleaning didn't result in empty, use the cleaned version
+            deduced_mod_name = Some(cleaned);
+        } else {
+            // If cleaning resulted in empty, revert to original file stem as last resort
+            deduced_mod_name = file_path.file_stem().and_then(OsStr::to_str).map(String::from);
+            println!("[analyze_archive] Warning: Name cleanup resulted in empty string, using raw file stem: {:?}", deduced_mod_name);
+        }
+    }
+
+
+    // --- Final Log ---
+    println!("[analyze_archive] Final Deductions: Name={:?}, Author={:?}, Category={:?}, Entity={:?}, Preview={:?}, RawINI Target={:?}, RawINI Type={:?}",
+        deduced_mod_name, deduced_author, final_deduced_category_slug, final_deduced_entity_slug, detected_preview_internal_path, raw_ini_target_found, raw_ini_type_found);
+
+    // --- Return Result ---
+    Ok(ArchiveAnalysisResult {
+        file_path: file_path_str,
+        entries,
+        deduced_mod_name,
+        deduced_author,
+        deduced_version,
+        deduced_category_slug: final_deduced_category_slug,
+        deduced_entity_slug: final_deduced_entity_slug,
+        raw_ini_type: raw_ini_type_found,
+        raw_ini_target: raw_ini_target_found,
+        detected_preview_internal_path,
+        encrypted,
+        multipart,
+    })
+}
+
+#[command]
+fn read_archive_file_content(archive_path_str: String, internal_file_path: String, password: Option<String>) -> CmdResult<Vec<u8>> {
+    println!("[read_archive_file_content] Reading '{}' from archive '{}'", internal_file_path, archive_path_str);
+    let archive_path = PathBuf::from(&archive_path_str);
+    if !archive_path.is_file() { return Err(format!("Archive file not found: {}", archive_path.display())); }
+
+    let extension = archive_path.extension().and_then(|os| os.to_str()).map(|s| s.to_lowercase());
+    let internal_path_normalized = internal_file_path.replace("\\", "/");
+
+    match extension.as_deref() {
+        Some("zip") => {
+            let file = fs::File::open(&archive_path).map_err(|e| format!("Zip Read: Failed open: {}", e))?;
+            let mut archive = ZipArchive::new(file).map_err(|e| format!("Zip Read: Failed read archive: {}", e))?;
+
+            let entry = match password.as_deref() {
+                Some(pw) => match archive.by_name_decrypt(&internal_path_normalized, pw.as_bytes()) {
+                    Ok(Ok(file_in_zip)) => Ok(file_in_zip),
+                    Ok(Err(_)) => return Err("Wrong password for this archive.".to_string()),
+                    Err(e) => Err(e),
+                },
+                None => archive.by_name(&internal_path_normalized),
+            };
+            match entry {
+                Ok(mut file_in_zip) => {
+                    let mut buffer = Vec::with_capacity(file_in_zip.size() as usize);
+                    match file_in_zip.read_to_end(&mut buffer) {
+                        Ok(_) => Ok(buffer), // Successful read
+                        Err(e) => Err(format!("Zip Read: Failed read content: {}", e)),
+                    }
+                },
+                Err(ZipError::FileNotFound) => Err(format!("Zip Read: Internal file '{}' not found.", internal_file_path)),
+                Err(e) if is_zip_password_error(&e) => Err("This archive is password-protected. Enter its password to continue.".to_string()),
+                Err(e) => Err(format!("Zip Read: Error accessing internal file '{}': {}", internal_file_path, e)),
+            }
+        }
+        Some("7z") => {
+            // --- 7z logic remains the same as previously corrected ---
+            let mut found_content: Option<Vec<u8>> = None;
+            let mut found_error: Option<String> = None;
+            let mut archive = sevenz_rust::SevenZReader::open(&archive_path_str, sevenz_password(password.as_deref()))
+                .map_err(|e| sevenz_error_message("7z Read: Failed open", e))?;
+
+            archive.for_each_entries(|entry, reader| {
+                if found_content.is_some() || found_error.is_some() { return Ok(false); }
+                let entry_name_normalized = entry.name().replace("\\", "/");
+                if entry_name_normalized == internal_path_normalized {
+                    let mut content_bytes = Vec::new();
+                    let mut buffer = [0u8; 4096];
+                    let read_result: Result<(), io::Error> = (|| { // Use closure for ? propagation
+                        loop {
+                            let bytes_read = reader.read(&mut buffer)?;
+                            if bytes_read == 0 { break; }
+                            content_bytes.extend_from_slice(&buffer[..bytes_read]);
+                        }
+                        Ok(())
+                    })(); // Immediately invoke
+
+                    match read_result {
+                        Ok(()) => found_content = Some(content_bytes),
+                        Err(e) => found_error = Some(format!("7z Read: Error reading content '{}': {}", internal_file_path, e)),
+                    }
+                    return Ok(false); // Stop processing after finding (or failing to read) the file
+                }
+                Ok(true)
+            })
+            .map_err(|e: sevenz_rust::Error| sevenz_error_message("7z Read: Error iterating entries", e))?;
+
+            if let Some(content) = found_content { Ok(content) }
+            else if let Some(err) = found_error { Err(err) }
+            else { Err(format!("7z Read: Internal file '{}' not found.", internal_file_path)) }
+        }
+        Some("rar") => {
+            let mut archive = open_rar(&archive_path_str, password.as_deref())
+                .open_for_processing() // Need Process mode to read content
+                .map_err(rar_error_message)?;
+            let mut found_content: Option<Vec<u8>> = None;
+
+            loop {
+                match archive.read_header() {
+                    Ok(Some(header_state)) => { // Returns OpenArchive<..., CursorBeforeFile>
+                        let entry_filename = &header_state.entry().filename; // Access header via entry()
+                        let entry_name_normalized = entry_filename.to_string_lossy().replace("\\", "/");
+
+                        if entry_name_normalized == internal_path_normalized {
+                            // Found the file, process it using read()
+                            match header_state.read() {
+                                Ok((bytes, _next_archive_state)) => { // Successfully read
+                                    found_content = Some(bytes);
+                                    break; // Found and read, exit loop
+                                }
+                                Err(e) => { // Error during reading
+                                    return Err(format!("Rar Read: Error reading content '{}': {}", internal_file_path, rar_error_message(e)));
+                                }
+                            }
+                        } else {
+                            // Not the file we want, skip it
+                            archive = header_state.skip().map_err(|e| e.to_string())?; // Skip and update archive state
+                        }
+                    }
+                    Ok(None) => break, // End of archive, file not found
+                    Err(e) => return Err(format!("Rar Read: Error reading header: {}", e)),
+                }
+            }
+            found_content.ok_or_else(|| format!("Rar Read: Internal file '{}' not found.", internal_file_path))
+        }
+        _ => Err(format!("Unsupported archive type for reading: {:?}", extension)),
+    }
+}
+
+fn archive_preview_temp_dir() -> PathBuf {
+    std::env::temp_dir().join(ARCHIVE_PREVIEW_TEMP_FOLDER_NAME)
+}
+
+// Removes extracted previews older than ARCHIVE_PREVIEW_MAX_AGE_SECS (or all of them)
+fn prune_archive_preview_temp_files(remove_all: bool) {
+    let temp_dir = archive_preview_temp_dir();
+    if !temp_dir.is_dir() { return; }
+    if remove_all {
+        if let Err(e) = fs::remove_dir_all(&temp_dir) {
+            eprintln!("[prune_archive_preview_temp_files] Failed to remove {}: {}", temp_dir.display(), e);
+        }
+        return;
+    }
+    let max_age = std::time::Duration::from_secs(ARCHIVE_PREVIEW_MAX_AGE_SECS);
+    let Ok(read_dir) = fs::read_dir(&temp_dir) else { return; };
+    for entry in read_dir.filter_map(|e| e.ok()) {
+        let is_stale = entry.metadata().ok()
+            .and_then(|meta| meta.modified().ok())
+            .and_then(|modified| modified.elapsed().ok())
+            .map_or(false, |age| age > max_age);
+        if is_stale {
+            if let Err(e) = fs::remove_file(entry.path()) {
+                eprintln!("[prune_archive_preview_temp_files] Failed to remove {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
+
+// Streams a single archive entry into dest_path without buffering it in memory.
+// Returns the number of bytes written.
+fn extract_archive_entry_to_file(archive_path: &Path, internal_file_path: &str, password: Option<&str>, dest_path: &Path) -> Result<u64, String> {
+    let extension = archive_path.extension().and_then(|os| os.to_str()).map(|s| s.to_lowercase());
+    let internal_path_normalized = internal_file_path.replace("\\", "/");
+    let archive_path_str = archive_path.to_string_lossy().to_string();
+
+    match extension.as_deref() {
+        Some("zip") => {
+            let file = fs::File::open(archive_path).map_err(|e| format!("Zip Read: Failed open: {}", e))?;
+            let mut archive = ZipArchive::new(file).map_err(|e| format!("Zip Read: Failed read archive: {}", e))?;
+            let entry = match password {
+                Some(pw) => match archive.by_name_decrypt(&internal_path_normalized, pw.as_bytes()) {
+                    Ok(Ok(file_in_zip)) => Ok(file_in_zip),
+                    Ok(Err(_)) => return Err("Wrong password for this archive.".to_string()),
+                    Err(e) => Err(e),
+                },
+                None => archive.by_name(&internal_path_normalized),
+            };
+            let mut file_in_zip = match entry {
+                Ok(file_in_zip) => file_in_zip,
+                Err(ZipError::FileNotFound) => return Err(format!("Zip Read: Internal file '{}' not found.", internal_file_path)),
+                Err(e) if is_zip_password_error(&e) => return Err("This archive is password-protected. Enter its password to continue.".to_string()),
+                Err(e) => return Err(format!("Zip Read: Error accessing internal file '{}': {}", internal_file_path, e)),
+            };
+            let mut outfile = File::create(dest_path).map_err(|e| format!("Failed to create {}: {}", dest_path.display(), e))?;
+            io::copy(&mut file_in_zip, &mut outfile).map_err(|e| format!("Zip Read: Failed read content: {}", e))
+        }
+        Some("7z") => {
+            let mut written: Option<u64> = None;
+            let mut found_error: Option<String> = None;
+            let mut archive = sevenz_rust::SevenZReader::open(&archive_path_str, sevenz_password(password))
+                .map_err(|e| sevenz_error_message("7z Read: Failed open", e))?;
+
+            archive.for_each_entries(|entry, reader| {
+                if written.is_some() || found_error.is_some() { return Ok(false); }
+                if entry.name().replace("\\", "/") != internal_path_normalized { return Ok(true); }
+                let copy_result = File::create(dest_path).and_then(|mut outfile| io::copy(reader, &mut outfile));
+                match copy_result {
+                    Ok(bytes) => written = Some(bytes),
+                    Err(e) => found_error = Some(format!("7z Read: Error reading content '{}': {}", internal_file_path, e)),
+                }
+                Ok(false)
+            })
+            .map_err(|e: sevenz_rust::Error| sevenz_error_message("7z Read: Error iterating entries", e))?;
+
+            if let Some(bytes) = written { Ok(bytes) }
+            else if let Some(err) = found_error { Err(err) }
+            else { Err(format!("7z Read: Internal file '{}' not found.", internal_file_path)) }
+        }
+        Some("rar") => {
+            let mut archive = open_rar(&archive_path_str, password)
+                .open_for_processing()
+                .map_err(rar_error_message)?;
+            loop {
+                match archive.read_header() {
+                    Ok(Some(header_state)) => {
+                        let entry_name_normalized = header_state.entry().filename.to_string_lossy().replace("\\", "/");
+                        if entry_name_normalized == internal_path_normalized {
+                            header_state.extract_to(dest_path)
+                                .map_err(|e| format!("Rar Read: Error reading content '{}': {}", internal_file_path, rar_error_message(e)))?;
+                            return fs::metadata(dest_path).map(|meta| meta.len()).map_err(|e| e.to_string());
+                        }
+                        archive = header_state.skip().map_err(|e| e.to_string())?;
+                    }
+                    Ok(None) => break,
+                    Err(e) => return Err(format!("Rar Read: Error reading header: {}", e)),
+                }
+            }
+            Err(format!("Rar Read: Internal file '{}' not found.", internal_file_path))
+        }
+        _ => Err(format!("Unsupported archive type for reading: {:?}", extension)),
+    }
+}
+
+// Extracts one image from an archive to a temp file and returns its path, so the webview can
+// load it through the asset protocol instead of receiving the bytes over IPC.
+#[command]
+async fn extract_archive_preview(archive_path_str: String, internal_file_path: String, password: Option<String>) -> CmdResult<String> {
+    println!("[extract_archive_preview] Extracting '{}' from archive '{}'", internal_file_path, archive_path_str);
+    let archive_path = PathBuf::from(&archive_path_str);
+    if !archive_path.is_file() { return Err(format!("Archive file not found: {}", archive_path.display())); }
+
+    let image_ext = Path::new(&internal_file_path.replace("\\", "/")).extension()
+        .and_then(|os| os.to_str())
+        .map(|s| s.to_lowercase())
+        .filter(|ext| ENTITY_IMAGE_EXTENSIONS.contains(&ext.as_str()))
+        .ok_or_else(|| format!("'{}' is not a supported image file.", internal_file_path))?;
+
+    prune_archive_preview_temp_files(false);
+    let temp_dir = archive_preview_temp_dir();
+    fs::create_dir_all(&temp_dir).map_err(|e| format!("Failed to create temp folder {}: {}", temp_dir.display(), e))?;
+    let dest_path = temp_dir.join(format!("{}_{}.{}", unix_timestamp_millis(), rand::random::<u32>(), image_ext));
+
+    match extract_archive_entry_to_file(&archive_path, &internal_file_path, password.as_deref(), &dest_path) {
+        Ok(bytes) => {
+            println!("[extract_archive_preview] Wrote {} bytes to {}", bytes, dest_path.display());
+            Ok(dest_path.to_string_lossy().to_string())
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&dest_path);
+            Err(e)
+        }
+    }
+}
+
+// Maps an archive entry onto its path relative to the import destination.
+// Returns None for entries outside the selected root (or the root entry itself).
+fn archive_entry_dest_relative(internal_path_obj: &Path, prefix_path: &Path, extract_all: bool) -> Option<PathBuf> {
+    let relative_path = if extract_all {
+        internal_path_obj.to_path_buf()
+    } else {
+        internal_path_obj.strip_prefix(prefix_path).ok()?.to_path_buf()
+    };
+    if relative_path.as_os_str().is_empty() { None } else { Some(relative_path) }
+}
+
+// Guards against Zip Slip: the entry must be a plain relative path, and the nearest existing
+// ancestor of the output path must canonicalize to somewhere inside the destination folder
+// (which catches symlinked directories).
+fn checked_extraction_path(dest_root: &Path, relative_path: &Path) -> Result<PathBuf, AppError> {
+    let escapes = || AppError::Archive(format!("Entry '{}' resolves outside the destination folder; import rejected", relative_path.display()));
+    if relative_path.components().any(|c| !matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir)) {
+        return Err(escapes());
+    }
+    let outpath = dest_root.join(relative_path);
+    let dest_canonical = dest_root.canonicalize()?;
+    let existing_ancestor = outpath.ancestors().find(|p| p.exists()).unwrap_or(dest_root);
+    if !existing_ancestor.canonicalize()?.starts_with(&dest_canonical) {
+        return Err(escapes());
+    }
+    Ok(extended_length_path(&outpath))
+}
+
+// Checks every entry import_archive will extract against `dest_root` before anything is written,
+// so a crafted archive is rejected without leaving a partial import behind. Returns the number of
+// file entries, used as the import://progress total.
+fn check_archive_entries_to_extract(archive_path: &Path, extension: Option<&str>, prefix_path: &Path, extract_all: bool, dest_root: &Path, password: Option<&str>) -> Result<usize, String> {
+    let archive_path_str = archive_path.to_string_lossy().to_string();
+    // (internal path, is directory) for every entry
+    let entries: Vec<(PathBuf, bool)> = match extension {
+        Some("zip") => {
+            let file = fs::File::open(archive_path).map_err(|e| format!("Zip Count: Failed open: {}", e))?;
+            let mut archive = ZipArchive::new(file).map_err(|e| format!("Zip Count: Failed read archive: {}", e))?;
+            let mut entries = Vec::with_capacity(archive.len());
+            for i in 0..archive.len() {
+                let file_in_zip = archive.by_index_raw(i).map_err(|e| format!("Zip Count: Failed read entry #{}: {}", i, e))?;
+                match file_in_zip.enclosed_name() {
+                    Some(p) => entries.push((p.to_path_buf(), file_in_zip.is_dir())),
+                    None => return Err(AppError::Archive(format!("Entry '{}' resolves outside the destination folder; import rejected", file_in_zip.name())).to_string()),
+                }
+            }
+            entries
+        }
+        Some("7z") => {
+            let archive = sevenz_rust::SevenZReader::open(&archive_path_str, sevenz_password(password))
+                .map_err(|e| sevenz_error_message("7z Count: Failed open", e))?;
+            archive.archive().files.iter()
+                .map(|entry| (PathBuf::from(entry.name().replace("\\", "/")), entry.is_directory()))
+                .collect()
+        }
+        Some("rar") => {
+            let list_archive = open_rar(&archive_path_str, password).open_for_listing()
+                .map_err(rar_error_message)?;
+            list_archive
+                .filter_map(|entry_result| entry_result.ok())
+                .map(|header| (PathBuf::from(header.filename.to_string_lossy().replace("\\", "/")), header.is_directory()))
+                .collect()
+        }
+        _ => return Err(format!("Unsupported archive type for extraction: {:?}", extension)),
+    };
+
+    let mut count = 0;
+    for (internal_path, is_dir) in entries {
+        let Some(relative_path) = archive_entry_dest_relative(&internal_path, prefix_path, extract_all) else { continue };
+        checked_extraction_path(dest_root, &relative_path).map_err(|e| e.to_string())?;
+        if !is_dir { count += 1; }
+    }
+    Ok(count)
+}
+
+// Extracts the selected root of an archive into `final_mod_dest_path`, emitting import://progress
+// after every extracted file. Returns the number of files extracted.
+fn extract_archive_to_folder(
+    archive_path: &Path,
+    selected_internal_root: &str,
+    final_mod_dest_path: &Path,
+    password: Option<&str>,
+    operation_id: &str,
+    app_handle: &AppHandle
+) -> Result<usize, String> {
+    let archive_path_str = archive_path.to_string_lossy().to_string();
+    let extension = archive_path.extension().and_then(|os| os.to_str()).map(|s| s.to_lowercase());
+    // Normalize and prepare the prefix path IF a root was selected
+    let prefix_to_extract_norm = selected_internal_root.replace("\\", "/");
+    let prefix_to_extract = prefix_to_extract_norm.strip_suffix('/').unwrap_or(&prefix_to_extract_norm);
+    let prefix_path = Path::new(prefix_to_extract);
+    let extract_all = prefix_to_extract.is_empty(); // Flag to determine if extracting all
+    println!("[import_archive] Extract All Mode: {}", extract_all);
+    ensure_archive_readable(archive_path, extension.as_deref())?;
+
+    let total_files = check_archive_entries_to_extract(archive_path, extension.as_deref(), prefix_path, extract_all, final_mod_dest_path, password)?;
+    println!("[import_archive] {} files to extract.", total_files);
+    app_handle.emit_all(IMPORT_PROGRESS_EVENT, ImportProgress {
+        operation_id: operation_id.to_string(),
+        processed: 0, total: total_files, current_file: None, message: "Starting extraction...".to_string()
+    }).unwrap_or_else(|e| eprintln!("Failed to emit initial import progress: {}", e));
+
+    let mut files_extracted_count = 0;
+    let emit_file_progress = |processed: usize, relative_path: &Path| {
+        let current_file = relative_path.to_string_lossy().replace("\\", "/");
+        app_handle.emit_all(IMPORT_PROGRESS_EVENT, ImportProgress {
+            operation_id: operation_id.to_string(),
+            processed,
+            total: total_files,
+            message: format!("Extracting {}", current_file),
+            current_file: Some(current_file),
+        }).unwrap_or_else(|e| eprintln!("Failed to emit import progress: {}", e));
+    };
+
+    match extension.as_deref() {
+        Some("zip") => {
+             let file = fs::File::open(archive_path).map_err(|e| format!("Zip Extract: Failed open: {}", e))?;
+             let mut archive = ZipArchive::new(file).map_err(|e| format!("Zip Extract: Failed read archive: {}", e))?;
+             for i in 0..archive.len() {
+                  let mut file_in_zip = open_zip_entry(&mut archive, i, password).map_err(|e| format!("Zip Extract: {}", e))?;
+                  let internal_path_obj = match file_in_zip.enclosed_name() {
+                      Some(p) => p.to_path_buf(),
+                      None => return Err(AppError::Archive(format!("Entry '{}' resolves outside the destination folder; import rejected", file_in_zip.name())).to_string()),
+                  };
+                  let relative_path_to_dest_obj = match archive_entry_dest_relative(&internal_path_obj, prefix_path, extract_all) {
+                      Some(p) => p,
+                      None => continue,
+                  };
+                  let outpath = checked_extraction_path(final_mod_dest_path, &relative_path_to_dest_obj).map_err(|e| e.to_string())?;
+
+                  if file_in_zip.is_dir() {
+                      fs::create_dir_all(&outpath).map_err(|e| io_error_message("Zip Extract: Failed create dir", &outpath, &e))?;
+                  } else {
+                      if let Some(p) = outpath.parent() { if !p.exists() { fs::create_dir_all(p).map_err(|e| io_error_message("Zip Extract: Failed create parent", p, &e))?; } }
+                      let mut outfile = fs::File::create(&outpath).map_err(|e| io_error_message("Zip Extract: Failed create file", &outpath, &e))?;
+                      std::io::copy(&mut file_in_zip, &mut outfile).map_err(|e| format!("Zip Extract: Failed copy content '{}': {}", outpath.display(), e))?;
+                      files_extracted_count += 1;
+                      emit_file_progress(files_extracted_count, &relative_path_to_dest_obj);
+                  }
+             }
+        }
+        Some("7z") => {
+            let mut archive = sevenz_rust::SevenZReader::open(&archive_path_str, sevenz_password(password))
+                .map_err(|e| sevenz_error_message("7z Extract: Failed open", e))?;
+             archive.for_each_entries(|entry, reader| {
+                 let internal_path_obj = PathBuf::from(entry.name().replace("\\", "/"));
+                 let relative_path_to_dest_obj = match archive_entry_dest_relative(&internal_path_obj, prefix_path, extract_all) {
+                     Some(p) => p,
+                     None => return Ok(true), // Skip to next
+                 };
+                 let outpath = checked_extraction_path(final_mod_dest_path, &relative_path_to_dest_obj)
+                     .map_err(|e| sevenz_rust::Error::other(e.to_string()))?;
+
+                 if entry.is_directory() {
+                    fs::create_dir_all(&outpath)?;
+                 } else {
+                    if let Some(p) = outpath.parent() { if !p.exists() { fs::create_dir_all(&p)?; }}
+                    let mut outfile = fs::File::create(&outpath)?;
+                    let mut buffer = [0u8; 4096];
+                    loop {
+                        let bytes_read = reader.read(&mut buffer)?;
+                        if bytes_read == 0 { break; }
+                        outfile.write_all(&buffer[..bytes_read])?;
+                    }
+                    files_extracted_count += 1;
+                    emit_file_progress(files_extracted_count, &relative_path_to_dest_obj);
+                 }
+                 Ok(true) // Continue to next entry
+             })
+             .map_err(|e: sevenz_rust::Error| sevenz_error_message("7z Extract: Error processing entries", e))?;
+        }
+        Some("rar") => {
+            let mut archive = open_rar(&archive_path_str, password).open_for_processing()
+                .map_err(rar_error_message)?;
+            loop {
+                match archive.read_header().map_err(|e| e.to_string())? {
+                    Some(header_state) => {
+                        let internal_path_obj = PathBuf::from(header_state.entry().filename.to_string_lossy().replace("\\", "/"));
+                        let relative_path_to_dest_obj = match archive_entry_dest_relative(&internal_path_obj, prefix_path, extract_all) {
+                            Some(p) => p,
+                            None => {
+                                archive = header_state.skip().map_err(|e| e.to_string())?;
+                                continue; // Skip to next
+                            }
+                        };
+                        let outpath = checked_extraction_path(final_mod_dest_path, &relative_path_to_dest_obj).map_err(|e| e.to_string())?;
+
+                        if header_state.entry().is_directory() {
+                            fs::create_dir_all(&outpath).map_err(|e| io_error_message("Rar Extract: Failed create dir", &outpath, &e))?;
+                            archive = header_state.skip().map_err(|e| e.to_string())?;
+                        } else {
+                            if let Some(p) = outpath.parent() { if !p.exists() { fs::create_dir_all(p).map_err(|e| io_error_message("Rar Extract: Failed create parent", p, &e))?; }}
+                            archive = header_state.extract_to(&outpath).map_err(rar_error_message)?;
+                            files_extracted_count += 1;
+                            emit_file_progress(files_extracted_count, &relative_path_to_dest_obj);
+                        }
+                    }
+                    None => break, // End of archive
+                }
+            }
+        }
+        _ => return Err(format!("Unsupported archive type for extraction: {:?}", extension)),
+    }
+    Ok(files_extracted_count)
+}
+
+// What the imported mod is and where it goes; shared by import_archive, batch_import_archives
+// and import_folder
+#[derive(Deserialize, Debug, Clone)]
+struct ImportOptions {
+    target_entity_slug: String, // import_folder deduces it when empty
+    mod_name: String, // import_folder deduces it when empty
+    description: Option<String>,
+    author: Option<String>,
+    category_tag: Option<String>,
+    image_data: Option<Vec<u8>>,
+    selected_preview_absolute_path: Option<String>,
+    preset_ids: Option<Vec<i64>>,
+    normalize_preview: Option<bool>, // Re-encode the chosen preview as PNG capped at max_dimension
+    max_dimension: Option<u32>,
+}
+
+// One archive import (import_archive, or an entry of batch_import_archives)
+#[derive(Deserialize, Debug, Clone)]
+struct ImportRequest {
+    archive_path: String,
+    #[serde(default)]
+    selected_internal_root: String, // Empty means "extract all"
+    preview_internal_path: Option<String>, // Image inside the archive to save as the preview
+    #[serde(default)]
+    overwrite_existing: bool,
+    password: Option<String>, // For encrypted archives
+    #[serde(default)]
+    verify: bool, // Check entry CRCs first and refuse corrupt archives
+    expected_sha256: Option<String>, // Published hash of the archive; the import aborts on mismatch
+    #[serde(flatten)]
+    options: ImportOptions,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct BatchImportFailure {
+    archive_path: String,
+    error: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct BatchImportSummary {
+    imported: Vec<ImportArchiveResult>,
+    failed: Vec<BatchImportFailure>, // Includes archives that need overwrite confirmation
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct ImportArchiveResult {
+    asset_id: Option<i64>,
+    message: String,
+    old_version: Option<String>,
+    new_version: Option<String>,
+    overwritten: bool,
+    // Set when the mod is already installed and the archive holds a newer version;
+    // nothing was changed and the import must be repeated with overwrite_existing.
+    requires_confirmation: bool,
+}
+
+// Reads the mod version from the same INI sections deduction uses for name/author.
+fn parse_ini_version(ini_content: &str) -> Option<String> {
+    let ini = Ini::load_from_str(ini_content).ok()?;
+    let mut version = None;
+    for section_name in ["Mod", "Settings", "Info", "General"] {
+        if let Some(section) = ini.section(Some(section_name)) {
+            if let Some(value) = section.get("Version").or_else(|| section.get("ModVersion")) {
+                version = Some(value.trim().to_string());
+            }
+        }
+    }
+    version.filter(|v| !v.is_empty())
+}
+
+// Compares version strings by their numeric parts ("1.10" > "1.9", "v2" > "1.5.3").
+// Returns None when either side has no numbers to compare.
+fn compare_mod_versions(a: &str, b: &str) -> Option<std::cmp::Ordering> {
+    let numeric_parts = |v: &str| -> Vec<u64> {
+        v.split(|c: char| !c.is_ascii_digit()).filter_map(|part| part.parse().ok()).collect()
+    };
+    let (parts_a, parts_b) = (numeric_parts(a), numeric_parts(b));
+    if parts_a.is_empty() || parts_b.is_empty() { return None; }
+    let len = parts_a.len().max(parts_b.len());
+    Some((0..len)
+        .map(|i| parts_a.get(i).unwrap_or(&0).cmp(parts_b.get(i).unwrap_or(&0)))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(std::cmp::Ordering::Equal))
+}
+
+// Deduces the version of the mod inside an archive from the first INI directly under the
+// selected root, without extracting anything.
+fn deduce_archive_root_version(archive_path: &Path, selected_internal_root: &str, password: Option<&str>) -> Option<String> {
+    let archive_path_str = archive_path.to_string_lossy().to_string();
+    let extension = archive_path.extension().and_then(|os| os.to_str()).map(|s| s.to_lowercase());
+    let prefix_norm = selected_internal_root.replace("\\", "/");
+    let prefix = prefix_norm.strip_suffix('/').unwrap_or(&prefix_norm);
+    let prefix_path = Path::new(prefix);
+    let extract_all = prefix.is_empty();
+
+    let file_names: Vec<String> = match extension.as_deref() {
+        Some("zip") => {
+            let file = fs::File::open(archive_path).ok()?;
+            let mut archive = ZipArchive::new(file).ok()?;
+            (0..archive.len())
+                .filter_map(|i| archive.by_index_raw(i).ok().filter(|f| !f.is_dir()).map(|f| f.name().replace("\\", "/")))
+                .collect()
+        }
+        Some("7z") => sevenz_rust::SevenZReader::open(&archive_path_str, sevenz_password(password)).ok()?
+            .archive().files.iter()
+            .filter(|entry| !entry.is_directory())
+            .map(|entry| entry.name().replace("\\", "/"))
+            .collect(),
+        Some("rar") => open_rar(&archive_path_str, password).open_for_listing().ok()?
+            .filter_map(|entry_result| entry_result.ok())
+            .filter(|header| !header.is_directory())
+            .map(|header| header.filename.to_string_lossy().replace("\\", "/"))
+            .collect(),
+        _ => return None,
+    };
+
+    let ini_name = file_names.into_iter().find(|name| {
+        archive_entry_dest_relative(Path::new(name), prefix_path, extract_all).map_or(false, |rel| {
+            rel.components().count() == 1 && rel.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("ini"))
+        })
+    })?;
+    let content = read_archive_file_content(archive_path_str, ini_name, password.map(String::from)).ok()?;
+    parse_ini_version(&String::from_utf8_lossy(&content))
+}
+
+// Undoes a failed import: removes the partially extracted folder and, when an existing
+// install was being overwritten, moves the previous version back into place.
+fn discard_failed_import(dest_path: &Path, backup_path: Option<&PathBuf>) {
+    fs::remove_dir_all(dest_path).ok();
+    if let Some(backup) = backup_path {
+        if let Err(e) = move_dir(&extended_length_path(backup), &extended_length_path(dest_path)) {
+            eprintln!("[import_archive] ERROR: Failed to restore previous version from '{}': {}", backup.display(), e);
+        }
+    }
+}
+
+#[command]
+async fn import_archive(
+    request: ImportRequest,
+    operation_id: Option<String>,
+    db_state: State<'_, DbState>,
+    app_handle: AppHandle
+) -> CmdResult<ImportArchiveResult> {
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let result = run_archive_import(request, &operation_id, &db_state, &app_handle).await;
+    match &result {
+        Ok(summary) if summary.requires_confirmation => {}
+        Ok(summary) => emit_operation_result(&app_handle, IMPORT_COMPLETE_EVENT, &operation_id, summary.message.clone()),
+        Err(err_msg) => emit_operation_result(&app_handle, IMPORT_ERROR_EVENT, &operation_id, err_msg.clone()),
+    }
+    result
+}
+
+// Lowercase hex SHA-256 of a file, read in chunks so large archives aren't loaded into memory
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+async fn verify_archive_sha256(archive_path_str: String, expected: String) -> CmdResult<()> {
+    let expected = expected.trim().to_ascii_lowercase();
+    if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("'{}' is not a valid SHA-256 hash", expected));
+    }
+    println!("[import_archive] Verifying SHA-256 of {}", archive_path_str);
+    let actual = async_runtime::spawn_blocking(move || sha256_file(Path::new(&archive_path_str))).await
+        .map_err(|e| format!("Hash task failed: {}", e))?
+        .map_err(|e| format!("Failed to hash archive: {}", e))?;
+    if actual != expected {
+        return Err(format!("Archive SHA-256 mismatch: expected {}, got {}", expected, actual));
+    }
+    println!("[import_archive] SHA-256 verified: {}", actual);
+    Ok(())
+}
+
+#[command]
+async fn hash_file(path: String) -> CmdResult<String> {
+    let file_path = PathBuf::from(&path);
+    if !file_path.is_file() {
+        return Err(format!("File not found: {}", path));
+    }
+    async_runtime::spawn_blocking(move || sha256_file(&file_path)).await
+        .map_err(|e| format!("Hash task failed: {}", e))?
+        .map_err(|e| format!("Failed to hash {}: {}", path, e))
+}
+
+// Moves a folder, falling back to copy + delete when a rename isn't possible (e.g. across drives)
+fn move_dir(source: &Path, destination: &Path) -> io::Result<()> {
+    if fs::rename(source, destination).is_ok() {
+        return Ok(());
+    }
+    copy_dir_recursive(source, destination)?;
+    fs::remove_dir_all(source)
+}
+
+// Brings an already-extracted mod folder under management: copies (or moves) it to
+// <category>/<entity>/<name> and adds the asset like import_archive does. Empty name/entity
+// and missing metadata are filled in from deduce_mod_info_v2.
+#[command]
+async fn import_folder(
+    source_path: String,
+    options: ImportOptions,
+    move_source: Option<bool>, // Move instead of copy; the source folder is gone afterwards
+    operation_id: Option<String>,
+    db_state: State<'_, DbState>,
+    config_state: State<'_, CachedConfigState>,
+    app_handle: AppHandle
+) -> CmdResult<Asset> {
+    let ImportOptions {
+        target_entity_slug, mod_name, description, author, category_tag, image_data,
+        selected_preview_absolute_path, preset_ids, normalize_preview, max_dimension,
+    } = options;
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let move_source = move_source.unwrap_or(false);
+    println!("[import_folder] Importing '{}' into '{}' (move: {})", source_path, target_entity_slug, move_source);
+    let source = PathBuf::from(source_path.trim());
+    if !source.is_dir() { return Err(format!("Folder not found: {}", source.display())); }
+
+    let (base_mods_path, target_category_slug, target_entity_slug, target_entity_id, deduced) = {
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+        let base_mods_path = get_setting_value(&conn, SETTINGS_KEY_MODS_FOLDER)
+            .map_err(|e| e.to_string())?
+            .map(PathBuf::from)
+            .ok_or_else(|| "Mods folder path not set".to_string())?;
+        let maps = cached_deduction_maps(&config_state, &conn).map_err(|e| e.to_string())?;
+        let deduced = deduce_mod_info_v2(&source, &base_mods_path, &maps);
+
+        let target_entity_slug = match target_entity_slug.trim() {
+            "" => deduced.as_ref().map(|d| d.entity_slug.clone()).unwrap_or(maps.fallback_entity_slug.clone()),
+            slug => slug.to_string(),
+        };
+        let (target_category_slug, target_entity_id): (String, i64) = conn.query_row(
+            "SELECT c.slug, e.id FROM entities e JOIN categories c ON e.category_id = c.id WHERE e.slug = ?1",
+            params![target_entity_slug], |row| Ok((row.get(0)?, row.get(1)?)),
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Target entity '{}' not found.", target_entity_slug),
+            _ => format!("DB Error get target entity: {}", e)
+        })?;
+        (base_mods_path, target_category_slug, target_entity_slug, target_entity_id, deduced)
+    };
+
+    let mod_name = match mod_name.trim() {
+        "" => deduced.as_ref().map(|d| d.mod_name.clone()).unwrap_or_default(),
+        name => name.to_string(),
+    };
+    if mod_name.is_empty() { return Err("Mod Name cannot be empty.".to_string()); }
+    let description = description.or_else(|| deduced.as_ref().and_then(|d| d.description.clone()));
+    let author = author.or_else(|| deduced.as_ref().and_then(|d| d.author.clone()));
+    let category_tag = category_tag.or_else(|| deduced.as_ref().and_then(|d| d.mod_type_tag.clone()));
+    let version = deduced.as_ref().and_then(|d| d.version.clone());
+
+    let target_mod_folder_name = mod_name.replace(" ", "_").replace(".", "_").replace("'", "").replace("\"", "");
+    if target_mod_folder_name.is_empty() { return Err("Mod Name results in invalid folder name.".to_string()); }
+    let relative_path_for_db_str = Path::new(&target_category_slug).join(&target_entity_slug).join(&target_mod_folder_name)
+        .to_string_lossy().replace("\\", "/");
+    let final_mod_dest_path = base_mods_path.join(&relative_path_for_db_str);
+
+    if resolve_asset_disk_state(&base_mods_path, &relative_path_for_db_str).is_some() {
+        return Err(format!("A mod folder already exists at '{}'.", relative_path_for_db_str));
+    }
+    if path_is_same_or_within(&final_mod_dest_path, &source) {
+        return Err(format!("Cannot import '{}' into itself.", source.display()));
+    }
+    {
+        let conn = db_state.conn()?;
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM active_assets WHERE folder_name = ?1)", params![relative_path_for_db_str], |row| row.get(0),
+        ).map_err(|e| e.to_string())?;
+        if exists {
+            return Err(format!("Database entry already exists for '{}'. Aborting.", relative_path_for_db_str));
+        }
+    }
+
+    // --- Copy / Move ---
+    let transfer_task = {
+        let source = source.clone();
+        let dest = final_mod_dest_path.clone();
+        async_runtime::spawn_blocking(move || -> Result<(), String> {
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed create dest directory '{}': {}", parent.display(), e))?;
+            }
+            let result = if move_source { move_dir(&source, &dest) } else { copy_dir_recursive(&source, &dest).map(|_| ()) };
+            result.map_err(|e| {
+                if !move_source { fs::remove_dir_all(&dest).ok(); }
+                format!("Failed to {} '{}' to '{}': {}", if move_source { "move" } else { "copy" }, source.display(), dest.display(), e)
+            })
+        })
+    };
+    transfer_task.await.map_err(|e| format!("Folder import task failed: {}", e))??;
+    // A moved folder can only be put back by moving it again
+    let undo_transfer = |dest: &Path| {
+        if move_source {
+            move_dir(dest, &source).ok();
+        } else {
+            fs::remove_dir_all(dest).ok();
+        }
+    };
+
+    // --- Handle Preview Image ---
+    let preview_max_dim = preview_normalize_max_dim(normalize_preview, max_dimension);
+    let target_image_path = final_mod_dest_path.join(TARGET_IMAGE_FILENAME);
+    let preview_result = if let Some(data) = image_data {
+        Some(write_preview_data(&data, &target_image_path, preview_max_dim))
+    } else {
+        selected_preview_absolute_path
+            .map(PathBuf::from)
+            .filter(|p| p.is_file())
+            .map(|p| write_preview_file(&p, &target_image_path, preview_max_dim))
+    };
+    let image_filename_for_db = match preview_result {
+        Some(Ok(())) => Some(TARGET_IMAGE_FILENAME.to_string()),
+        Some(Err(e)) => {
+            eprintln!("[import_folder] ERROR: Failed to save preview: {}. Falling back to the folder's own image.", e);
+            find_preview_image(&final_mod_dest_path)
+        }
+        None => find_preview_image(&final_mod_dest_path),
+    };
+
+    // --- Add to Database ---
+    let mut conn_guard = db_state.conn()?;
+    let insert_result = (|| -> SqlResult<i64> {
+        let tx = conn_guard.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute(
+            "INSERT INTO assets (entity_id, name, description, folder_name, image_filename, author, category_tag, version, is_enabled) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1)",
+            params![target_entity_id, mod_name, description, relative_path_for_db_str, image_filename_for_db, author, category_tag, version],
+        )?;
+        let asset_id = tx.last_insert_rowid();
+        for preset_id in preset_ids.unwrap_or_default() {
+            tx.execute("INSERT OR IGNORE INTO preset_assets (preset_id, asset_id, is_enabled) VALUES (?1, ?2, 1)", params![preset_id, asset_id])?;
+        }
+        tx.commit()?;
+        Ok(asset_id)
+    })();
+    let asset_id = insert_result.map_err(|e| {
+        undo_transfer(&final_mod_dest_path);
+        format!("Failed add imported mod to database: {}", e)
+    })?;
+    let asset = load_asset(&conn_guard, asset_id, true).map_err(|e| format!("Failed to read back imported asset {}: {}", asset_id, e))?;
+
+    let message = format!("Imported '{}' from folder.", mod_name);
+    println!("[import_folder] {} (asset ID {})", message, asset_id);
+    emit_operation_result(&app_handle, IMPORT_COMPLETE_EVENT, &operation_id, message);
+    Ok(asset)
+}
+
+// Imports archives one after another; a failure is recorded and the batch moves on.
+// Emits BATCH_IMPORT_PROGRESS_EVENT before each archive (per-file extraction progress still
+// comes through IMPORT_PROGRESS_EVENT).
+#[command]
+async fn batch_import_archives(
+    imports: Vec<ImportRequest>,
+    operation_id: Option<String>, // Shared by the batch events and each archive's extraction events
+    db_state: State<'_, DbState>,
+    app_handle: AppHandle
+) -> CmdResult<BatchImportSummary> {
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    let total = imports.len();
+    println!("[batch_import_archives] Importing {} archives", total);
+    let mut summary = BatchImportSummary { imported: Vec::new(), failed: Vec::new() };
+
+    for (index, request) in imports.into_iter().enumerate() {
+        app_handle.emit_all(BATCH_IMPORT_PROGRESS_EVENT, ApplyProgress {
+            operation_id: operation_id.clone(),
+            processed: index,
+            total,
+            current_asset_id: None,
+            message: format!("Importing {}", request.options.mod_name),
+        }).unwrap_or_else(|e| eprintln!("Failed to emit batch import progress: {}", e));
+
+        let archive_path = request.archive_path.clone();
+        let result = run_archive_import(request, &operation_id, &db_state, &app_handle).await;
+        match result {
+            Ok(imported) if imported.requires_confirmation => {
+                summary.failed.push(BatchImportFailure { archive_path, error: imported.message });
+            }
+            Ok(imported) => summary.imported.push(imported),
+            Err(e) => {
+                eprintln!("[batch_import_archives] Failed to import '{}': {}", archive_path, e);
+                summary.failed.push(BatchImportFailure { archive_path, error: e });
+            }
+        }
+    }
+
+    let message = format!("Imported {} of {} archives.", summary.imported.len(), total);
+    app_handle.emit_all(BATCH_IMPORT_PROGRESS_EVENT, ApplyProgress { operation_id: operation_id.clone(), processed: total, total, current_asset_id: None, message: message.clone() })
+        .unwrap_or_else(|e| eprintln!("Failed to emit batch import progress: {}", e));
+    emit_operation_result(&app_handle, IMPORT_COMPLETE_EVENT, &operation_id, message);
+    println!("[batch_import_archives] {} imported, {} failed", summary.imported.len(), summary.failed.len());
+    Ok(summary)
+}
+
+// Import implementation; the DB lock is only held before and after extraction, which runs
+// in a blocking task. If the mod is already installed, it is only replaced when the archive
+// holds a newer version and `overwrite_existing` is set.
+async fn run_archive_import(request: ImportRequest, operation_id: &str, db_state: &DbState, app_handle: &AppHandle) -> CmdResult<ImportArchiveResult> {
+    let ImportRequest {
+        archive_path: archive_path_str, selected_internal_root, preview_internal_path,
+        overwrite_existing, password, verify, expected_sha256, options,
+    } = request;
+    let ImportOptions {
+        mut target_entity_slug, mod_name, description, author, category_tag, image_data,
+        selected_preview_absolute_path, preset_ids, normalize_preview, max_dimension,
+    } = options;
+    // Some(..) re-encodes the chosen preview, None stores it as-is
+    let preview_max_dim = preview_normalize_max_dim(normalize_preview, max_dimension);
+    if let Some(expected) = expected_sha256.filter(|hash| !hash.trim().is_empty()) {
+        verify_archive_sha256(archive_path_str.clone(), expected).await?;
+    }
+
+    println!("[import_archive] Importing '{}', internal path '{}' for entity '{}'. Image Data Provided: {}. Add to presets: {:?}. Overwrite: {}",
+        archive_path_str,
+        if selected_internal_root.is_empty() { "(Extract All)" } else { &selected_internal_root }, // Indicate if extracting all
+        target_entity_slug,
+        image_data.is_some(),
+        preset_ids,
+        overwrite_existing);
+
+    // --- Basic Validation & Setup ---
+    if mod_name.trim().is_empty() { return Err("Mod Name cannot be empty.".to_string()); }
+    let archive_path = PathBuf::from(&archive_path_str);
+    if !archive_path.is_file() { return Err(format!("Archive file not found: {}", archive_path.display())); }
+    if verify {
+        let report = run_archive_verify(archive_path.clone(), password.clone()).await?;
+        if !report.intact {
+            return Err(AppError::Archive(format!(
+                "Archive is corrupt or incomplete ({} bad entries), not importing:\n{}",
+                report.corrupt_entries.len(), report.corrupt_entries.join("\n")
+            )).to_string());
+        }
+        println!("[import_archive] Verified {} entries", report.entries_checked);
+    }
+
+    let target_mod_folder_name = mod_name.trim().replace(" ", "_").replace(".", "_").replace("'", "").replace("\"", "");
+    if target_mod_folder_name.is_empty() { return Err("Mod Name results in invalid folder name.".to_string()); }
+
+    let (base_mods_path, target_category_slug, target_entity_id, relative_path_for_db_str, existing_asset) = {
+        let conn_guard = db_state.conn()?;
+        ensure_not_safe_mode(&conn_guard).map_err(|e| e.to_string())?;
+
+        let base_mods_path_str = get_setting_value(&conn_guard, SETTINGS_KEY_MODS_FOLDER)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Mods folder path not set".to_string())?;
+
+        // No entity picked: import into the fallback entity instead of refusing
+        if target_entity_slug.trim().is_empty() {
+            target_entity_slug = read_fallback_entity_slug(&conn_guard).map_err(|e| e.to_string())?;
+            println!("[import_archive] No target entity given, using fallback '{}'", target_entity_slug);
+        }
+
+        let (target_category_slug, target_entity_id): (String, i64) = conn_guard.query_row(
+            "SELECT c.slug, e.id FROM entities e JOIN categories c ON e.category_id = c.id WHERE e.slug = ?1",
+            params![target_entity_slug], |row| Ok((row.get(0)?, row.get(1)?)),
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Target entity '{}' not found.", target_entity_slug),
+            _ => format!("DB Error get target entity: {}", e)
+        })?;
+
+        let relative_path_for_db = Path::new(&target_category_slug).join(&target_entity_slug).join(&target_mod_folder_name);
+        let relative_path_for_db_str = relative_path_for_db.to_string_lossy().replace("\\", "/");
+
+        let existing_asset: Option<(i64, Option<String>)> = conn_guard.query_row(
+            "SELECT id, version FROM active_assets WHERE entity_id = ?1 AND folder_name = ?2",
+            params![target_entity_id, relative_path_for_db_str], |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional().map_err(|e| format!("DB error check existing import '{}': {}", relative_path_for_db_str, e))?;
+
+        (PathBuf::from(base_mods_path_str), target_category_slug, target_entity_id, relative_path_for_db_str, existing_asset)
+    };
+
+    let new_version = {
+        let archive_path = archive_path.clone();
+        let selected_internal_root = selected_internal_root.clone();
+        let password = password.clone();
+        async_runtime::spawn_blocking(move || deduce_archive_root_version(&archive_path, &selected_internal_root, password.as_deref())).await
+            .map_err(|e| format!("Version check task failed: {}", e))?
+    };
+    let old_version = existing_asset.as_ref().and_then(|(_, version)| version.clone());
+    println!("[import_archive] Archive version: {:?}, installed version: {:?}", new_version, old_version);
+
+    // --- Existing Install: only replaced by a newer version, after confirmation ---
+    let mut final_mod_dest_path = base_mods_path.join(&target_category_slug).join(&target_entity_slug).join(&target_mod_folder_name);
+    let mut backup_path: Option<PathBuf> = None;
+    if let Some((existing_id, _)) = &existing_asset {
+        let is_newer = match (&new_version, &old_version) {
+            (Some(incoming), Some(installed)) => compare_mod_versions(incoming, installed) == Some(std::cmp::Ordering::Greater),
+            _ => false,
+        };
+        if !is_newer {
+            return Err(format!("Database entry already exists for '{}' (installed version: {}, archive version: {}). Aborting.",
+                relative_path_for_db_str,
+                old_version.as_deref().unwrap_or("unknown"),
+                new_version.as_deref().unwrap_or("unknown")));
+        }
+        if !overwrite_existing {
+            return Ok(ImportArchiveResult {
+                asset_id: Some(*existing_id),
+                message: format!("'{}' is already installed (version {}). The archive contains version {}.",
+                    mod_name.trim(), old_version.as_deref().unwrap_or("unknown"), new_version.as_deref().unwrap_or("unknown")),
+                old_version,
+                new_version,
+                overwritten: false,
+                requires_confirmation: true,
+            });
+        }
+
+        // Extract over the folder's current (possibly disabled) location so its state is kept,
+        // moving the previous version out of the mods folder until the new one is in the database.
+        if let Some((_, current_relative_path)) = resolve_asset_disk_state(&base_mods_path, &relative_path_for_db_str) {
+            final_mod_dest_path = base_mods_path.join(&current_relative_path);
+            let backup_dir = get_app_data_dir(app_handle).map_err(|e| e.to_string())?.join(OVERWRITE_BACKUP_FOLDER_NAME);
+            create_dir_all_long_path(&backup_dir).map_err(|e| io_error_message("Failed to create", &backup_dir, &e))?;
+            let backup = backup_dir.join(format!("{}_{}", unix_timestamp_millis(), final_mod_dest_path.file_name().unwrap_or_default().to_string_lossy()));
+            move_dir(&extended_length_path(&final_mod_dest_path), &extended_length_path(&backup))
+                .map_err(|e| format!("Failed to move previous version aside: {}", e))?;
+            println!("[import_archive] Previous version moved to '{}'", backup.display());
+            backup_path = Some(backup);
+        }
+    }
+
+    create_dir_all_long_path(&final_mod_dest_path).map_err(|e| {
+        discard_failed_import(&final_mod_dest_path, backup_path.as_ref());
+        io_error_message("Failed create dest directory", &final_mod_dest_path, &e)
+    })?;
+    println!("[import_archive] Target destination folder created/ensured: {}", final_mod_dest_path.display());
+
+    // --- Extraction Logic ---
+    println!("[import_archive] Starting extraction...");
+    let extraction_task = {
+        let dest_path = final_mod_dest_path.clone();
+        let app_handle = app_handle.clone();
+        let password = password.clone();
+        let operation_id = operation_id.to_string();
+        async_runtime::spawn_blocking(move || extract_archive_to_folder(&archive_path, &selected_internal_root, &dest_path, password.as_deref(), &operation_id, &app_handle))
+    };
+    let extraction_result = extraction_task.await
+        .map_err(|e| format!("Extraction task failed: {}", e))
+        .and_then(|result| result);
+
+    // Handle extraction result
+    let files_extracted_count = extraction_result.map_err(|e| {
+         discard_failed_import(&final_mod_dest_path, backup_path.as_ref());
+         e
+    })?;
+    println!("[import_archive] Extracted {} files.", files_extracted_count);
+
+    // --- Handle Preview Image ---
+    // An image chosen from inside the archive takes precedence over an external file
+    let internal_preview_data = match preview_internal_path.filter(|p| !p.trim().is_empty()) {
+        Some(internal_path) if image_data.is_none() => {
+            let archive_path_str = archive_path_str.clone();
+            let read_result = async_runtime::spawn_blocking(move || read_archive_file_content(archive_path_str, internal_path, password)).await
+                .map_err(|e| format!("Preview read task failed: {}", e))
+                .and_then(|result| result);
+            match read_result {
+                Ok(data) => Some(data),
+                Err(e) => {
+                    eprintln!("[import_archive] ERROR: Failed to read preview from archive: {}. Falling back to other preview sources.", e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    let mut image_filename_for_db: Option<String> = None;
+    if let Some(data) = image_data.or(internal_preview_data) {
+        println!("[import_archive] Handling provided image data ({} bytes)", data.len());
+        let target_image_path = final_mod_dest_path.join(TARGET_IMAGE_FILENAME);
+        let write_target = target_image_path.clone();
+        let write_result = async_runtime::spawn_blocking(move || write_preview_data(&data, &write_target, preview_max_dim)).await
+            .map_err(|e| format!("Preview write task failed: {}", e))
+            .and_then(|result| result);
+        match write_result {
+            Ok(_) => {
+                println!("[import_archive] Image data written successfully to '{}'.", target_image_path.display());
+                image_filename_for_db = Some(TARGET_IMAGE_FILENAME.to_string());
+            }
+            Err(e) => {
+                eprintln!("[import_archive] ERROR: Failed to save pasted image data to '{}': {}. Preview will be missing.", target_image_path.display(), e);
+            }
+        }
+    }
+    else if let Some(user_preview_path_str) = selected_preview_absolute_path {
+        println!("[import_archive] Handling selected image file path: {}", user_preview_path_str);
+        let source_path = PathBuf::from(&user_preview_path_str);
+        if source_path.is_file() {
+            let target_image_path = final_mod_dest_path.join(TARGET_IMAGE_FILENAME);
+            let write_target = target_image_path.clone();
+            let write_result = async_runtime::spawn_blocking(move || write_preview_file(&source_path, &write_target, preview_max_dim)).await
+                .map_err(|e| format!("Preview write task failed: {}", e))
+                .and_then(|result| result);
+            match write_result {
+                Ok(_) => {
+                    println!("[import_archive] Image file copied successfully to '{}'.", target_image_path.display());
+                    image_filename_for_db = Some(TARGET_IMAGE_FILENAME.to_string());
+                }
+                Err(e) => {
+                    eprintln!("[import_archive] ERROR: Failed copy user preview to '{}': {}. Preview will be missing.", target_image_path.display(), e);
+                }
+            }
+        } else {
+             println!("[import_archive] Warning: Selected preview file '{}' not found, skipping.", user_preview_path_str);
+        }
+    }
+    else {
+        let potential_extracted_image_path = final_mod_dest_path.join(TARGET_IMAGE_FILENAME);
+        if potential_extracted_image_path.is_file() {
+            println!("[import_archive] Using extracted {} as preview.", TARGET_IMAGE_FILENAME);
+            image_filename_for_db = Some(TARGET_IMAGE_FILENAME.to_string());
+        } else {
+             println!("[import_archive] No pasted, selected, or extracted preview found.");
+        }
+    }
+    println!("[import_archive] Image handling complete. Filename to save in DB: {:?}", image_filename_for_db);
+
+    // --- Add to Database ---
+    // Any failure from here on removes the extracted folder and restores the previous version
+    let overwritten = existing_asset.is_some();
+    let db_result = (|| -> CmdResult<i64> {
+        let mut conn_guard = db_state.conn()?;
+        let tx = conn_guard.transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| format!("Failed start import transaction: {}", e))?;
+
+        let asset_id = if let Some((existing_id, _)) = existing_asset {
+            println!("[import_archive] Updating existing asset {} to version {:?}", existing_id, new_version);
+            tx.execute(
+                "UPDATE assets SET name = ?1, description = ?2, image_filename = ?3, author = ?4, category_tag = ?5, version = ?6 WHERE id = ?7",
+                params![mod_name.trim(), description, image_filename_for_db, author, category_tag, new_version, existing_id]
+            ).map_err(|e| format!("Failed update existing mod in database: {}", e))?;
+            existing_id
+        } else {
+            let check_existing: Option<i64> = tx.query_row(
+                "SELECT id FROM active_assets WHERE entity_id = ?1 AND folder_name = ?2",
+                params![target_entity_id, relative_path_for_db_str], |row| row.get(0)
+            ).optional().map_err(|e| format!("DB error check existing import '{}': {}", relative_path_for_db_str, e))?;
+
+            if check_existing.is_some() {
+                return Err(format!("Database entry already exists for '{}'. Aborting.", relative_path_for_db_str));
+            }
+
+            println!("[import_archive] Adding asset to DB: entity_id={}, name={}, path={}, image={:?}", target_entity_id, mod_name, relative_path_for_db_str, image_filename_for_db);
+            tx.execute(
+                "INSERT INTO assets (entity_id, name, description, folder_name, image_filename, author, category_tag, version, is_enabled) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1)",
+                params![
+                    target_entity_id, mod_name.trim(),
+                    description, relative_path_for_db_str,
+                    image_filename_for_db, author, category_tag, new_version
+                ]
+            ).map_err(|e| format!("Failed add imported mod to database: {}", e))?;
+
+            let new_asset_id = tx.last_insert_rowid();
+            println!("[import_archive] Asset inserted with ID: {}", new_asset_id);
+            new_asset_id
+        };
+
+        // --- Add to Presets ---
+        if let Some(ids) = preset_ids {
+            if !ids.is_empty() {
+                println!("[import_archive] Adding asset {} to presets: {:?}", asset_id, ids);
+                let mut insert_preset_stmt = tx.prepare_cached(
+                    "INSERT OR IGNORE INTO preset_assets (preset_id, asset_id, is_enabled) VALUES (?1, ?2, ?3)"
+                ).map_err(|e| format!("Failed prepare preset asset insert: {}", e))?;
+                for preset_id in ids {
+                     insert_preset_stmt.execute(params![preset_id, asset_id, 1]) // Default to enabled state 1 when importing
+                        .map_err(|e| format!("Failed insert asset {} into preset {}: {}", asset_id, preset_id, e))?;
+                }
+                println!("[import_archive] Finished adding asset {} to presets.", asset_id);
+            }
+        }
+
+        // --- Commit Transaction ---
+        tx.commit().map_err(|e| format!("Failed to commit import transaction: {}", e))?;
+        Ok(asset_id)
+    })();
+    let asset_id = db_result.inspect_err(|_| discard_failed_import(&final_mod_dest_path, backup_path.as_ref()))?;
+
+    if let Some(backup) = &backup_path {
+        if let Err(e) = fs::remove_dir_all(backup) {
+            eprintln!("[import_archive] Warning: Failed to remove previous version at '{}': {}", backup.display(), e);
+        }
+    }
+
+   println!("[import_archive] Import successful for '{}'", mod_name);
+   let message = if overwritten {
+       format!("Updated '{}' from version {} to {} ({} files).", mod_name.trim(),
+           old_version.as_deref().unwrap_or("unknown"), new_version.as_deref().unwrap_or("unknown"), files_extracted_count)
+   } else {
+       format!("Imported '{}' ({} files).", mod_name.trim(), files_extracted_count)
+   };
+   Ok(ImportArchiveResult { asset_id: Some(asset_id), message, old_version, new_version, overwritten, requires_confirmation: false })
+}
+
+#[command]
+fn create_preset(name: String, db_state: State<DbState>) -> CmdResult<Preset> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Preset name cannot be empty.".to_string());
+    }
+    println!("[create_preset] Attempting to create preset: '{}'", name);
+
+    let base_mods_path = get_mods_base_path_from_settings(&db_state)
+        .map_err(|e| format!("Cannot create preset: {}", e))?;
+
+    let conn_guard = db_state.conn()?;
+    let mut conn = conn_guard;
+
+    // Use a block scope for the transaction
+    let preset_id = { // Start block scope for tx
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        // Check if name exists
+        let existing_count: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM active_presets WHERE LOWER(name) = LOWER(?1)",
+            params![name],
+            |row| row.get(0),
+        ).map_err(|e| format!("DB error checking preset name: {}", e))?;
+
+        if existing_count > 0 {
+            // Rollback happens automatically when tx is dropped on error return
+            return Err(format!("Preset name '{}' already exists.", name));
+        }
+
+        // Insert new preset
+        tx.execute("INSERT INTO presets (name) VALUES (?1)", params![name])
+            .map_err(|e| format!("Failed to insert preset: {}", e))?;
+        let new_preset_id = tx.last_insert_rowid();
+        println!("[create_preset] Inserted preset with ID: {}", new_preset_id);
+
+        save_current_state_to_preset(&tx, &base_mods_path, new_preset_id)?;
+
+        // Commit the transaction
+        tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+        new_preset_id // Return the ID from the block
+    }; // End block scope for tx
+
+    println!("[create_preset] Preset '{}' created successfully.", name);
+
+    Ok(Preset { id: preset_id, name: name.to_string(), is_favorite: false })
+}
+
+// Stores the on-disk enabled state of every active asset in a preset.
+// Assets whose folder is missing on disk are skipped.
+fn save_current_state_to_preset(conn: &Connection, base_mods_path: &Path, preset_id: i64) -> CmdResult<()> {
+    let mut stmt = conn.prepare("SELECT id, folder_name FROM active_assets")
+        .map_err(|e| format!("Failed to prepare asset fetch: {}", e))?;
+    let assets = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?.replace("\\", "/"))))
+        .map_err(|e| format!("Error preparing asset iterator: {}", e))?
+        .collect::<SqlResult<Vec<(i64, String)>>>()
+        .map_err(|e| format!("Error fetching asset row: {}", e))?;
+
+    let mut insert_stmt = conn.prepare("INSERT INTO preset_assets (preset_id, asset_id, is_enabled) VALUES (?1, ?2, ?3)")
+        .map_err(|e| format!("Failed to prepare preset asset insert: {}", e))?;
+    for (asset_id, clean_relative_path) in assets {
+        let is_currently_enabled = match resolve_asset_disk_state(base_mods_path, &clean_relative_path) {
+            Some((is_enabled, _)) => is_enabled,
+            None => {
+                println!("[save_current_state_to_preset] Warning: Asset ID {} folder not found on disk (path: {}). Skipping.", asset_id, clean_relative_path);
+                continue;
+            }
+        };
+        insert_stmt.execute(params![preset_id, asset_id, is_currently_enabled as i64])
+            .map_err(|e| format!("Failed to save state for asset {}: {}", asset_id, e))?;
+    }
+    Ok(())
+}
+
+
+#[command]
+fn get_presets(include_auto: Option<bool>, db_state: State<DbState>) -> CmdResult<Vec<Preset>> {
+    let conn = db_state.conn()?;
+    let mut stmt = conn.prepare("SELECT id, name, is_favorite FROM active_presets WHERE ?1 OR SUBSTR(name, 1, LENGTH(?2)) != ?2 ORDER BY name ASC")
+        .map_err(|e| e.to_string())?;
+    let preset_iter = stmt.query_map(params![include_auto.unwrap_or(false), AUTO_SNAPSHOT_PRESET_PREFIX], |row| {
+        Ok(Preset {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            is_favorite: row.get::<_, i64>(2)? == 1,
+        })
+    }).map_err(|e| e.to_string())?;
+    preset_iter.collect::<SqlResult<Vec<Preset>>>().map_err(|e| e.to_string())
+}
+
+#[command]
+fn get_favorite_presets(db_state: State<DbState>) -> CmdResult<Vec<Preset>> {
+    let conn = db_state.conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, is_favorite FROM active_presets WHERE is_favorite = 1 ORDER BY name ASC LIMIT 3"
+    ).map_err(|e| e.to_string())?;
+    let preset_iter = stmt.query_map([], |row| {
+        Ok(Preset {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            is_favorite: row.get::<_, i64>(2)? == 1,
+        })
+    }).map_err(|e| e.to_string())?;
+    preset_iter.collect::<SqlResult<Vec<Preset>>>().map_err(|e| e.to_string())
+}
+
+// (asset_id, desired_is_enabled, clean_relative_path, asset_name) for every asset in a preset
+fn fetch_preset_asset_states(conn: &Connection, preset_id: i64) -> CmdResult<Vec<(i64, bool, String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT pa.asset_id, pa.is_enabled, a.folder_name, a.name
+         FROM preset_assets pa
+         JOIN active_assets a ON pa.asset_id = a.id
+         WHERE pa.preset_id = ?1"
+    ).map_err(|e| format!("Failed to prepare fetch for preset assets: {}", e))?;
+
+    let preset_assets_iter_result = stmt.query_map(params![preset_id], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,                   // asset_id
+            row.get::<_, i64>(1)? == 1,              // desired_is_enabled (bool)
+            row.get::<_, String>(2)?.replace("\\", "/"), // clean_relative_path
+            row.get::<_, String>(3)?,               // asset_name
+        ))
+    });
+
+    match preset_assets_iter_result {
+         Ok(iter) => iter.collect::<SqlResult<Vec<(i64, bool, String, String)>>>() // Include name
+                          .map_err(|e| format!("Failed to collect preset assets: {}", e)),
+         Err(e) => Err(format!("Error preparing preset asset iterator: {}", e)),
+    }
+}
+
+#[command]
+fn preview_preset_apply(preset_id: i64, db_state: State<DbState>) -> CmdResult<PresetApplyPreview> {
+    println!("[preview_preset_apply] Previewing preset ID: {}", preset_id);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state)
+        .map_err(|e| format!("Cannot preview preset: {}", e))?;
+
+    let preset_assets = {
+        let conn = db_state.conn()?;
+        fetch_preset_asset_states(&conn, preset_id)?
+    };
+
+    let mut preview = PresetApplyPreview { changes: Vec::new(), missing: Vec::new() };
+    for (asset_id, target_enabled, clean_relative_path, name) in preset_assets {
+        match resolve_asset_disk_state(&base_mods_path, &clean_relative_path) {
+            Some((current_enabled, _)) if current_enabled != target_enabled => {
+                preview.changes.push(PresetAssetChange { asset_id, name, current_enabled, target_enabled });
+            }
+            Some(_) => {} // Already in the target state
+            None => preview.missing.push(PresetMissingAsset { asset_id, name, folder_name: clean_relative_path }),
+        }
+    }
+
+    println!("[preview_preset_apply] {} changes, {} missing", preview.changes.len(), preview.missing.len());
+    Ok(preview)
+}
+
+#[command]
+fn diff_presets(preset_a: i64, preset_b: i64, db_state: State<DbState>) -> CmdResult<PresetDiff> {
+    println!("[diff_presets] Comparing presets {} and {}", preset_a, preset_b);
+    let conn = db_state.conn()?;
+    for preset_id in [preset_a, preset_b] {
+        ensure_preset_exists(&conn, preset_id)?;
+    }
+
+    // LEFT JOINs so rows left behind by deleted assets still show up
+    let mut stmt = conn.prepare(
+        "SELECT pa.asset_id, pa.is_enabled, a.name, e.slug
+         FROM preset_assets pa
+         LEFT JOIN assets a ON pa.asset_id = a.id
+         LEFT JOIN entities e ON a.entity_id = e.id
+         WHERE pa.preset_id = ?1"
+    ).map_err(|e| format!("Failed to prepare preset asset query: {}", e))?;
+    let mut load = |preset_id: i64| -> CmdResult<HashMap<i64, (bool, PresetDiffEntry)>> {
+        stmt.query_map(params![preset_id], |row| {
+            let asset_id: i64 = row.get(0)?;
+            let name: Option<String> = row.get(2)?;
+            Ok((asset_id, (row.get::<_, i64>(1)? == 1, PresetDiffEntry {
+                asset_id,
+                asset_deleted: name.is_none(),
+                name: name.unwrap_or_else(|| format!("Deleted mod #{}", asset_id)),
+                entity_slug: row.get(3)?,
+            })))
+        })
+            .map_err(|e| format!("Failed to query preset {}: {}", preset_id, e))?
+            .collect::<SqlResult<HashMap<_, _>>>()
+            .map_err(|e| format!("Failed to read preset {}: {}", preset_id, e))
+    };
+    let assets_a = load(preset_a)?;
+    let mut assets_b = load(preset_b)?;
+
+    let mut diff = PresetDiff { enabled_only_in_a: Vec::new(), enabled_only_in_b: Vec::new(), only_in_a: Vec::new(), only_in_b: Vec::new() };
+    for (asset_id, (enabled_in_a, entry)) in assets_a {
+        match assets_b.remove(&asset_id) {
+            Some((enabled_in_b, _)) if enabled_in_a && !enabled_in_b => diff.enabled_only_in_a.push(entry),
+            Some((enabled_in_b, _)) if !enabled_in_a && enabled_in_b => diff.enabled_only_in_b.push(entry),
+            Some(_) => {} // Same state in both
+            None => diff.only_in_a.push(entry),
+        }
+    }
+    diff.only_in_b.extend(assets_b.into_values().map(|(_, entry)| entry));
+
+    for entries in [&mut diff.enabled_only_in_a, &mut diff.enabled_only_in_b, &mut diff.only_in_a, &mut diff.only_in_b] {
+        entries.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    }
+
+    println!("[diff_presets] {} / {} state differences, {} / {} missing from the other preset",
+        diff.enabled_only_in_a.len(), diff.enabled_only_in_b.len(), diff.only_in_a.len(), diff.only_in_b.len());
+    Ok(diff)
+}
+
+// IDs of the presets whose snapshot matches what is enabled on disk right now. Only assets present
+// on disk are compared, so entries of deleted or missing mods are ignored; a mod the preset doesn't
+// list matches as long as it is disabled (applying the preset would leave it that way).
+#[command]
+async fn identify_current_preset(include_auto: Option<bool>, app_handle: AppHandle) -> CmdResult<Vec<i64>> {
+    run_disk_stat_task(app_handle, move |handle| current_preset_matches(&handle.state::<DbState>(), include_auto.unwrap_or(false))).await
+}
+
+fn current_preset_matches(db_state: &DbState, include_auto: bool) -> CmdResult<Vec<i64>> {
+    let base_mods_path = get_mods_base_path_from_settings(db_state).map_err(|e| e.to_string())?;
+    let conn = db_state.conn()?;
+
+    let assets: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare("SELECT id, folder_name FROM active_assets")
+            .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?.replace("\\", "/"))))
+            .map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
+    };
+    // Same detection as apply_preset
+    let mut on_disk = HashSet::new();
+    let mut enabled_now = HashSet::new();
+    for (asset_id, clean_relative_path) in &assets {
+        if let Some((is_enabled, _)) = resolve_asset_disk_state(&base_mods_path, clean_relative_path) {
+            on_disk.insert(*asset_id);
+            if is_enabled {
+                enabled_now.insert(*asset_id);
+            }
+        }
+    }
+
+    let mut preset_stmt = conn.prepare("SELECT id FROM active_presets WHERE ?1 OR SUBSTR(name, 1, LENGTH(?2)) != ?2 ORDER BY name ASC")
+        .map_err(|e| format!("Failed to prepare preset query: {}", e))?;
+    let preset_ids = preset_stmt.query_map(params![include_auto, AUTO_SNAPSHOT_PRESET_PREFIX], |row| row.get::<_, i64>(0))
+        .map_err(|e| format!("Failed to query presets: {}", e))?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| format!("Failed to read presets: {}", e))?;
+
+    let mut enabled_stmt = conn.prepare("SELECT asset_id FROM preset_assets WHERE preset_id = ?1 AND is_enabled = 1")
+        .map_err(|e| format!("Failed to prepare preset asset query: {}", e))?;
+    let mut matches = Vec::new();
+    for preset_id in preset_ids {
+        let enabled_in_preset = enabled_stmt.query_map(params![preset_id], |row| row.get::<_, i64>(0))
+            .map_err(|e| format!("Failed to query preset {}: {}", preset_id, e))?
+            .filter(|asset_id| !matches!(asset_id, Ok(id) if !on_disk.contains(id)))
+            .collect::<SqlResult<HashSet<_>>>()
+            .map_err(|e| format!("Failed to read preset {}: {}", preset_id, e))?;
+        if enabled_in_preset == enabled_now {
+            matches.push(preset_id);
+        }
+    }
+
+    println!("[identify_current_preset] {} enabled mods on disk, matching presets: {:?}", enabled_now.len(), matches);
+    Ok(matches)
+}
+
+fn ensure_preset_exists(conn: &Connection, preset_id: i64) -> CmdResult<()> {
+    let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM active_presets WHERE id = ?1)", params![preset_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to look up preset {}: {}", preset_id, e))?;
+    if !exists {
+        return Err(format!("Preset with ID {} not found", preset_id));
+    }
+    Ok(())
+}
+
+#[command]
+fn get_preset_asset_details(preset_id: i64, db_state: State<DbState>) -> CmdResult<PresetAssetDetails> {
+    let conn = db_state.conn()?;
+    ensure_preset_exists(&conn, preset_id)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT pa.asset_id, pa.is_enabled, a.name, a.folder_name, e.slug
+         FROM preset_assets pa
+         LEFT JOIN assets a ON pa.asset_id = a.id
+         LEFT JOIN entities e ON a.entity_id = e.id
+         WHERE pa.preset_id = ?1"
+    ).map_err(|e| format!("Failed to prepare preset asset query: {}", e))?;
+    let mut entries = stmt.query_map(params![preset_id], |row| {
+        let asset_id: i64 = row.get(0)?;
+        let name: Option<String> = row.get(2)?;
+        Ok(PresetAssetDetail {
+            asset_id,
+            is_enabled: row.get::<_, i64>(1)? == 1,
+            asset_deleted: name.is_none(),
+            name: name.unwrap_or_else(|| format!("Deleted mod #{}", asset_id)),
+            folder_name: row.get::<_, Option<String>>(3)?.map(|f| f.replace('\\', "/")),
+            entity_slug: row.get(4)?,
+        })
+    })
+        .map_err(|e| format!("Failed to query preset {}: {}", preset_id, e))?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| format!("Failed to read preset {}: {}", preset_id, e))?;
+    entries.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+
+    let deleted_count = entries.iter().filter(|entry| entry.asset_deleted).count();
+    println!("[get_preset_asset_details] Preset {}: {} entries, {} deleted", preset_id, entries.len(), deleted_count);
+    Ok(PresetAssetDetails { preset_id, entries, deleted_count })
+}
+
+// Drops entries whose asset no longer exists; returns how many were removed
+#[command]
+fn clean_preset(preset_id: i64, db_state: State<DbState>) -> CmdResult<usize> {
+    let conn = db_state.conn()?;
+    ensure_preset_exists(&conn, preset_id)?;
+    let removed = conn.execute(
+        "DELETE FROM preset_assets WHERE preset_id = ?1 AND asset_id NOT IN (SELECT id FROM assets)",
+        params![preset_id],
+    ).map_err(|e| format!("Failed to clean preset {}: {}", preset_id, e))?;
+    println!("[clean_preset] Removed {} stale entries from preset {}", removed, preset_id);
+    Ok(removed)
+}
+
+#[command]
+async fn apply_preset(
+    preset_id: i64,
+    force: Option<bool>,
+    operation_id: Option<String>,
+    db_state: State<'_, DbState>,
+    cancel_state: State<'_, CancellationState>,
+    app_handle: AppHandle
+) -> CmdResult<()> {
+    println!("[apply_preset] Applying preset ID: {}", preset_id);
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    cancel_state.preset_apply.store(false, Ordering::SeqCst);
+
+    // Clone app_handle for potential use in error emission later
+    let app_handle_clone = app_handle.clone();
+
+    // --- Get base path first ---
+    let base_mods_path = get_mods_base_path_from_settings(&db_state)
+        .map_err(|e| format!("Cannot apply preset: {}", e))?;
+
+    // --- Fetch preset assets ---
+    let (mut preset_assets_to_apply, locked_asset_ids) = { // Use block scope for connection lock
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+        let locked_asset_ids = if force.unwrap_or(false) {
+            HashSet::new()
+        } else {
+            load_locked_asset_ids(&conn).map_err(|e| format!("Failed to load locked assets: {}", e))?
+        };
+        (fetch_preset_asset_states(&conn, preset_id)?, locked_asset_ids)
+    }; // Connection lock released here
+    // All disables run before any enable, so a folder being switched off frees its name before
+    // another mod's folder is renamed onto it. The sort is stable, so DB order holds within each phase.
+    preset_assets_to_apply.sort_by_key(|(_, desired_is_enabled, _, _)| *desired_is_enabled);
+
+    let total_assets = preset_assets_to_apply.len();
+    println!("[apply_preset] Found {} assets in preset.", total_assets);
+
+    // --- Emit START event ---
+    app_handle.emit_all(PRESET_APPLY_START_EVENT, total_assets).ok();
+
+    let mut processed_count = 0;
+    let mut errors = Vec::new();
+    let mut toggles: Vec<(i64, bool)> = Vec::new();
+
+    for (asset_id, desired_is_enabled, clean_relative_path_str, asset_name) in preset_assets_to_apply {
+        if cancel_state.preset_apply.load(Ordering::Relaxed) {
+            // Mods already renamed keep their new state
+            let cancel_msg = format!("Preset application cancelled: {}. Processed {} of {} mods.", AppError::UserCancelled, processed_count, total_assets);
+            println!("[apply_preset] {}", cancel_msg);
+            emit_operation_result(&app_handle, PRESET_APPLY_ERROR_EVENT, &operation_id, cancel_msg.clone());
+            record_asset_toggles(&db_state, &toggles);
+            return Err(cancel_msg);
+        }
+        processed_count += 1;
+
+        // --- Emit PROGRESS event ---
+        let progress_message = format!("Processing: {} ({}/{})", asset_name, processed_count, total_assets);
+        app_handle.emit_all(PRESET_APPLY_PROGRESS_EVENT, &ApplyProgress {
+            operation_id: operation_id.clone(),
+            processed: processed_count,
+            total: total_assets,
+            current_asset_id: Some(asset_id),
+            message: progress_message.clone(),
+        }).ok();
+        println!("[apply_preset] {}", progress_message); // Also log to console
+
+        // --- Filesystem logic ---
+        let clean_relative_path = PathBuf::from(&clean_relative_path_str);
+        let filename_osstr = clean_relative_path.file_name().unwrap_or_default();
+        let filename_str = filename_osstr.to_string_lossy();
+        if filename_str.is_empty() {
+            let err_msg = format!("Skipping asset ID {}: Invalid folder name '{}'.", asset_id, clean_relative_path_str);
+            println!("[apply_preset] {}", err_msg);
+            errors.push(err_msg);
+            continue;
+        }
+
+        let current_is_enabled = match resolve_asset_disk_state(&base_mods_path, &clean_relative_path_str) {
+            Some((is_enabled, _)) => is_enabled,
+            None => {
+                let err_msg = format!("Skipping asset '{}' (ID {}): Folder not found on disk (path: '{}').", asset_name, asset_id, clean_relative_path_str);
+                println!("[apply_preset] {}", err_msg);
+                errors.push(err_msg);
+                continue;
+            }
+        };
+
+        if current_is_enabled != desired_is_enabled && locked_asset_ids.contains(&asset_id) {
+            let err_msg = format!("Skipping locked asset '{}' (ID {}).", asset_name, asset_id);
+            println!("[apply_preset] {}", err_msg);
+            errors.push(err_msg);
+        } else if current_is_enabled != desired_is_enabled {
+            // Renames, or links/unlinks in symlink mode
+            println!("[apply_preset] Switching '{}' (Desired Enabled: {})", clean_relative_path_str, desired_is_enabled);
+            match set_asset_enabled_on_disk(&base_mods_path, &clean_relative_path_str, desired_is_enabled) {
+                Ok(_) => toggles.push((asset_id, desired_is_enabled)),
+                Err(e) => {
+                     let err_msg = format!("Failed to rename asset '{}' (ID {}): {}", asset_name, asset_id, e);
+                     println!("[apply_preset] Error: {}", err_msg);
+                     errors.push(err_msg);
+                }
+            }
+        }
+        // Optional: Short delay for UI updates if needed
+        // tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+    } // End loop
+    record_asset_toggles(&db_state, &toggles);
+
+    println!("[apply_preset] Finished applying preset ID {}. Errors: {}", preset_id, errors.len());
+
+    if errors.is_empty() {
+        // --- Emit COMPLETE event ---
+        let summary = format!("Successfully applied preset ({} mods processed).", total_assets);
+        emit_operation_result(&app_handle, PRESET_APPLY_COMPLETE_EVENT, &operation_id, summary);
+        Ok(())
+    } else {
+        // --- Emit ERROR event ---
+        let combined_errors = errors.join("\n");
+        let error_summary = format!("Preset application completed with {} error(s).", errors.len());
+        // You might want to send the full errors separately or just the summary
+        emit_operation_result(&app_handle_clone, PRESET_APPLY_ERROR_EVENT, &operation_id, error_summary.clone());
+        Err(format!("{}\nDetails:\n{}", error_summary, combined_errors)) // Return error details too
+    }
+}
+
+
+// Asks a running apply_preset to stop before its next mod
+#[command]
+fn cancel_preset_apply(cancel_state: State<CancellationState>) -> CmdResult<()> {
+    println!("[cancel_preset_apply] Cancellation requested");
+    cancel_state.preset_apply.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+// (category, entity, name, author, clean folder_name, tags)
+type ExportedModRow = (String, String, String, Option<String>, String, Option<String>);
+
+// Writes the library as a Markdown table (grouped under category/entity headings) or as CSV.
+// Enabled state is read from disk; folders missing on disk are listed as "missing".
+#[command]
+fn export_mod_list(format: String, only_enabled: bool, dest_path: String, db_state: State<DbState>) -> CmdResult<usize> {
+    let format = format.trim().to_lowercase();
+    if format != "md" && format != "csv" {
+        return Err(format!("Unsupported export format '{}'. Use \"md\" or \"csv\".", format));
+    }
+    println!("[export_mod_list] Exporting {} list (only enabled: {}) to {}", format, only_enabled, dest_path);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    let rows: Vec<ExportedModRow> = {
+        let conn = db_state.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT c.name, e.name, a.name, a.author, a.folder_name,
+                    (SELECT GROUP_CONCAT(t.name, ', ') FROM asset_tags at JOIN tags t ON at.tag_id = t.id WHERE at.asset_id = a.id)
+             FROM active_assets a
+             JOIN entities e ON a.entity_id = e.id
+             JOIN categories c ON e.category_id = c.id
+             ORDER BY c.name COLLATE NOCASE, e.name COLLATE NOCASE, a.name COLLATE NOCASE"
+        ).map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((
+            row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get::<_, String>(4)?.replace("\\", "/"), row.get(5)?,
+        ))).map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
+    };
+
+    let mut output = String::new();
+    if format == "csv" {
+        output.push_str("category,entity,name,author,enabled,tags\n");
+    } else {
+        output.push_str("# Mod List\n");
+    }
+    let mut current_group: Option<(String, String)> = None;
+    let mut exported = 0;
+    for (category, entity, name, author, folder_name, tags) in rows {
+        let enabled = match resolve_asset_disk_state(&base_mods_path, &folder_name) {
+            Some((true, _)) => "yes",
+            Some((false, _)) => "no",
+            None => "missing",
+        };
+        if only_enabled && enabled != "yes" { continue; }
+        let author = author.unwrap_or_default();
+        let tags = tags.unwrap_or_default();
+
+        if format == "csv" {
+            let fields = [category.as_str(), entity.as_str(), name.as_str(), author.as_str(), enabled, tags.as_str()];
+            output.push_str(&fields.iter().map(|f| csv_escape(f)).collect::<Vec<_>>().join(","));
+            output.push('\n');
+        } else {
+            if current_group.as_ref().map(|(c, _)| c) != Some(&category) {
+                output.push_str(&format!("\n## {}\n", category));
+            }
+            if current_group.as_ref() != Some(&(category.clone(), entity.clone())) {
+                output.push_str(&format!("\n### {}\n\n| Name | Author | Enabled | Tags |\n| --- | --- | --- | --- |\n", entity));
+                current_group = Some((category.clone(), entity.clone()));
+            }
+            output.push_str(&format!("| {} | {} | {} | {} |\n",
+                markdown_cell_escape(&name), markdown_cell_escape(&author), enabled, markdown_cell_escape(&tags)));
+        }
+        exported += 1;
+    }
+
+    fs::write(&dest_path, output).map_err(|e| format!("Failed to write mod list '{}': {}", dest_path, e))?;
+    println!("[export_mod_list] Exported {} mods.", exported);
+    Ok(exported)
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn markdown_cell_escape(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+#[command]
+fn export_preset(preset_id: i64, dest_path: String, db_state: State<DbState>) -> CmdResult<()> {
+    println!("[export_preset] Exporting preset ID {} to {}", preset_id, dest_path);
+    let export = {
+        let conn = db_state.conn()?;
+        let name: String = conn.query_row("SELECT name FROM active_presets WHERE id = ?1", params![preset_id], |row| row.get(0))
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Preset with ID {} not found.", preset_id),
+                _ => format!("Failed to fetch preset: {}", e),
+            })?;
+        let mut stmt = conn.prepare(
+            "SELECT a.folder_name, pa.is_enabled FROM preset_assets pa JOIN active_assets a ON pa.asset_id = a.id
+             WHERE pa.preset_id = ?1 ORDER BY a.folder_name"
+        ).map_err(|e| format!("Failed to prepare preset asset fetch: {}", e))?;
+        let assets = stmt.query_map(params![preset_id], |row| Ok(PresetExportEntry {
+            folder_name: row.get::<_, String>(0)?.replace("\\", "/"),
+            is_enabled: row.get::<_, i64>(1)? == 1,
+        })).map_err(|e| format!("Failed to query preset assets: {}", e))?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| format!("Failed to read preset assets: {}", e))?;
+        let mut fav_stmt = conn.prepare("SELECT folder_name FROM active_assets WHERE is_favorite = 1 ORDER BY folder_name")
+            .map_err(|e| format!("Failed to prepare favorites fetch: {}", e))?;
+        let favorites = fav_stmt.query_map([], |row| Ok(row.get::<_, String>(0)?.replace("\\", "/")))
+            .map_err(|e| format!("Failed to query favorites: {}", e))?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| format!("Failed to read favorites: {}", e))?;
+        PresetExportFile { format_version: PRESET_EXPORT_FORMAT_VERSION, name, assets, favorites }
+    };
+
+    let json = serde_json::to_string_pretty(&export).map_err(|e| format!("Failed to serialize preset: {}", e))?;
+    fs::write(&dest_path, json).map_err(|e| format!("Failed to write preset file '{}': {}", dest_path, e))?;
+    println!("[export_preset] Exported {} asset states.", export.assets.len());
+    Ok(())
+}
+
+#[command]
+fn import_preset(src_path: String, db_state: State<DbState>) -> CmdResult<PresetImportResult> {
+    println!("[import_preset] Importing preset from {}", src_path);
+    let content = fs::read_to_string(&src_path).map_err(|e| format!("Failed to read preset file '{}': {}", src_path, e))?;
+    let import: PresetExportFile = serde_json::from_str(&content).map_err(|e| format!("Invalid preset file: {}", e))?;
+    if import.format_version > PRESET_EXPORT_FORMAT_VERSION {
+        return Err(format!("Preset file version {} is newer than supported version {}.", import.format_version, PRESET_EXPORT_FORMAT_VERSION));
+    }
+    let base_name = import.name.trim();
+    if base_name.is_empty() {
+        return Err("Preset file has an empty name.".to_string());
+    }
+
+    let mut conn = db_state.conn()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // Pick a free name if the preset already exists here
+    let mut preset_name = base_name.to_string();
+    let mut suffix = 1;
+    loop {
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM active_presets WHERE LOWER(name) = LOWER(?1))",
+            params![preset_name], |row| row.get(0),
+        ).map_err(|e| format!("DB error checking preset name: {}", e))?;
+        if !exists { break; }
+        suffix += 1;
+        preset_name = format!("{} ({})", base_name, suffix);
+    }
+
+    tx.execute("INSERT INTO presets (name) VALUES (?1)", params![preset_name])
+        .map_err(|e| format!("Failed to insert preset: {}", e))?;
+    let preset_id = tx.last_insert_rowid();
 
-                // Use detected preview if available for this root
-                if let Some(preview_path) = root_to_preview_map.get(&index) {
-                    detected_preview_internal_path = Some(preview_path.clone());
-                    println!("[analyze_archive] Detected preview for this root: {}", preview_path);
+    let mut warnings = Vec::new();
+    {
+        let mut find_stmt = tx.prepare("SELECT id FROM active_assets WHERE folder_name = ?1")
+            .map_err(|e| format!("Failed to prepare asset lookup: {}", e))?;
+        let mut insert_stmt = tx.prepare("INSERT OR REPLACE INTO preset_assets (preset_id, asset_id, is_enabled) VALUES (?1, ?2, ?3)")
+            .map_err(|e| format!("Failed to prepare preset asset insert: {}", e))?;
+        for entry in &import.assets {
+            let folder_name = entry.folder_name.replace("\\", "/");
+            let asset_id: Option<i64> = find_stmt.query_row(params![folder_name], |row| row.get(0))
+                .optional()
+                .map_err(|e| format!("Failed to look up asset '{}': {}", folder_name, e))?;
+            match asset_id {
+                Some(id) => {
+                    insert_stmt.execute(params![preset_id, id, entry.is_enabled])
+                        .map_err(|e| format!("Failed to save state for '{}': {}", folder_name, e))?;
                 }
+                None => warnings.push(format!("Skipped '{}': mod not found in library.", folder_name)),
+            }
+        }
 
-                // --- Break after processing the first root's INI ---
-                println!("[analyze_archive] Finished processing first likely root INI.");
-                break;
+        // Favorites only ever get added; existing favorites are left alone
+        let mut favorite_stmt = tx.prepare("UPDATE assets SET is_favorite = 1 WHERE id IN (SELECT id FROM active_assets WHERE folder_name = ?1)")
+            .map_err(|e| format!("Failed to prepare favorite update: {}", e))?;
+        for folder_name in &import.favorites {
+            let folder_name = folder_name.replace("\\", "/");
+            let changes = favorite_stmt.execute(params![folder_name])
+                .map_err(|e| format!("Failed to mark '{}' as favorite: {}", folder_name, e))?;
+            if changes == 0 {
+                warnings.push(format!("Favorite '{}' not found in library.", folder_name));
             }
         }
     }
-    // --- End INI Deduction ---
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
+    println!("[import_preset] Created preset '{}' (ID {}) with {} skipped entries.", preset_name, preset_id, warnings.len());
+    Ok(PresetImportResult {
+        preset: Preset { id: preset_id, name: preset_name, is_favorite: false },
+        warnings,
+    })
+}
 
-    // --- 2. Deduce from Internal Filenames ---
-    if final_deduced_entity_slug.is_none() {
-        println!("[analyze_archive] Trying internal filename matching...");
-        let mut file_match_found = false;
-        // Iterate through ALL file entries in the archive
-        for entry in &entries {
-            if !entry.is_dir {
-                // Get filename stem (without extension)
-                let filename = entry.path.split('/').last().unwrap_or(&entry.path); // Get last component
-                if let Some(stem) = Path::new(filename).file_stem().and_then(OsStr::to_str) {
-                    if !stem.is_empty() {
-                        // Use the helper to check if the stem matches an entity
-                        if let Some(slug) = find_entity_slug_from_hint(stem, &maps) {
-                            final_deduced_entity_slug = Some(slug);
-                            println!("[analyze_archive]   -> Found entity via internal filename stem: '{}' -> {}", stem, final_deduced_entity_slug.as_ref().unwrap());
-                            file_match_found = true;
-                            break; // Found a match from a file, stop searching files
-                        }
-                    }
-                }
+// Zips the selected mods into one shareable archive: each mod's current folder goes under its
+// clean (enabled-form) folder name, plus a MOD_PACK_MANIFEST_FILENAME describing them. Files are
+// streamed into the zip one at a time and export://progress is emitted per file. Returns the
+// number of mods written; missing mods fail the export rather than producing a partial pack.
+#[command]
+async fn export_assets_as_archive(asset_ids: Vec<i64>, dest_path: String, operation_id: Option<String>, app_handle: AppHandle) -> CmdResult<usize> {
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    async_runtime::spawn_blocking(move || write_mod_pack(&app_handle, &asset_ids, &PathBuf::from(&dest_path), &operation_id)).await
+        .map_err(|e| format!("Export task failed: {}", e))?
+}
+
+fn write_mod_pack(app_handle: &AppHandle, asset_ids: &[i64], dest: &Path, operation_id: &str) -> CmdResult<usize> {
+    if asset_ids.is_empty() {
+        return Err("No mods selected to export.".to_string());
+    }
+    if !dest.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("zip")) {
+        return Err(format!("Export destination must be a .zip file: {}", dest.display()));
+    }
+    let db_state = app_handle.state::<DbState>();
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    // (source folder on disk, manifest entry) in the requested order
+    let mut mods: Vec<(PathBuf, ModPackEntry)> = Vec::with_capacity(asset_ids.len());
+    {
+        let conn = db_state.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT a.folder_name, a.name, a.author, a.description, a.version, a.source_url, e.slug, e.name, c.slug
+             FROM active_assets a
+             JOIN entities e ON a.entity_id = e.id
+             JOIN categories c ON e.category_id = c.id
+             WHERE a.id = ?1"
+        ).map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let mut used_folders: HashSet<String> = HashSet::new();
+        for asset_id in asset_ids {
+            let (clean_relative_path, entry) = stmt.query_row(params![asset_id], |row| Ok((
+                row.get::<_, String>(0)?.replace("\\", "/"),
+                ModPackEntry {
+                    folder: String::new(),
+                    name: row.get(1)?, author: row.get(2)?, description: row.get(3)?, version: row.get(4)?, source_url: row.get(5)?,
+                    entity_slug: row.get(6)?, entity_name: row.get(7)?, category_slug: row.get(8)?,
+                },
+            ))).optional()
+                .map_err(|e| format!("Failed to read asset {}: {}", asset_id, e))?
+                .ok_or_else(|| format!("Asset with ID {} not found.", asset_id))?;
+            let (_, current_relative_path) = resolve_asset_disk_state(&base_mods_path, &clean_relative_path)
+                .ok_or_else(|| format!("Folder of '{}' not found on disk (path: '{}').", entry.name, clean_relative_path))?;
+
+            // Mods from different entities may share a folder name
+            let clean_name = clean_relative_path.rsplit('/').next().unwrap_or(&clean_relative_path).to_string();
+            let mut folder = clean_name.clone();
+            let mut suffix = 2;
+            while !used_folders.insert(folder.to_lowercase()) {
+                folder = format!("{} ({})", clean_name, suffix);
+                suffix += 1;
             }
+            mods.push((base_mods_path.join(current_relative_path), ModPackEntry { folder, ..entry }));
         }
-        if !file_match_found {
-            println!("[analyze_archive]   -> No entity match found from internal filenames.");
+    } // Connection released before reading mod files
+
+    if let Some(parent) = dest.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        if !parent.is_dir() {
+            return Err(format!("Destination folder not found: {}", parent.display()));
+        }
+        // Zipping a folder into itself would pick up the archive being written
+        if let Ok(dest_parent) = parent.canonicalize() {
+            if let Some((_, entry)) = mods.iter().find(|(source, _)| source.canonicalize().map_or(false, |source| dest_parent.starts_with(source))) {
+                return Err(format!("The archive can't be saved inside the folder of '{}'.", entry.name));
+            }
         }
-    } else {
-        println!("[analyze_archive] Skipping internal filename check (entity already found).")
     }
-    // --- End Internal Filename Deduction ---
 
+    let source_files: Vec<Vec<walkdir::DirEntry>> = mods.iter()
+        .map(|(source, _)| WalkDir::new(source).min_depth(1).sort_by_file_name().into_iter().filter_map(|e| e.ok()).collect())
+        .collect();
+    let total = source_files.iter().map(|entries| entries.iter().filter(|entry| entry.file_type().is_file()).count()).sum::<usize>();
+    println!("[export_assets_as_archive] Exporting {} mods ({} files) to {}", mods.len(), total, dest.display());
 
-    // --- 3. Deduce from Archive Filename (USE HELPER - Lower Priority) ---
-    if final_deduced_entity_slug.is_none() || final_deduced_category_slug.is_none() {
-        println!("[analyze_archive] Attempting deduction from archive filename...");
-        if let Some(stem) = file_path.file_stem().and_then(OsStr::to_str) {
-            // Try matching stem against Entities (USE HELPER)
-            if final_deduced_entity_slug.is_none() {
-                println!("[analyze_archive] Trying archive filename stem for Entity: '{}'", stem);
-                if let Some(slug) = find_entity_slug_from_hint(stem, &maps) {
-                    final_deduced_entity_slug = Some(slug);
-                    println!("[analyze_archive]   -> Found entity via filename.");
-                } else {
-                    println!("[analyze_archive]   -> No entity match found from filename.");
-                }
+    let file = fs::File::create(extended_length_path(dest)).map_err(|e| io_error_message("Failed to create", dest, &e))?;
+    let result = write_mod_pack_entries(app_handle, file, mods, source_files, total, operation_id);
+    if result.is_err() {
+        fs::remove_file(extended_length_path(dest)).ok(); // Don't leave a truncated archive behind
+    }
+    let written = result?;
+    println!("[export_assets_as_archive] Wrote {} mods to {}", written, dest.display());
+    Ok(written)
+}
+
+fn write_mod_pack_entries(
+    app_handle: &AppHandle,
+    file: fs::File,
+    mods: Vec<(PathBuf, ModPackEntry)>,
+    source_files: Vec<Vec<walkdir::DirEntry>>,
+    total: usize,
+    operation_id: &str
+) -> CmdResult<usize> {
+    let mut zip = zip::ZipWriter::new(io::BufWriter::new(file));
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let mut processed = 0;
+    for ((source, entry), entries) in mods.iter().zip(source_files) {
+        zip.add_directory(format!("{}/", entry.folder), options)
+            .map_err(|e| format!("Failed to add '{}' to the archive: {}", entry.folder, e))?;
+        for dir_entry in entries {
+            let relative = dir_entry.path().strip_prefix(source).unwrap_or(dir_entry.path()).to_string_lossy().replace("\\", "/");
+            let archive_path = format!("{}/{}", entry.folder, relative);
+            if dir_entry.file_type().is_dir() {
+                zip.add_directory(format!("{}/", archive_path), options)
+                    .map_err(|e| format!("Failed to add '{}' to the archive: {}", archive_path, e))?;
+                continue;
+            }
+            if !dir_entry.file_type().is_file() {
+                continue; // Links inside mod folders aren't followed
             }
+            let large_file = dir_entry.metadata().map_or(false, |meta| meta.len() >= u32::MAX as u64);
+            zip.start_file(archive_path.as_str(), options.large_file(large_file))
+                .map_err(|e| format!("Failed to add '{}' to the archive: {}", archive_path, e))?;
+            let mut source_file = fs::File::open(extended_length_path(dir_entry.path()))
+                .map_err(|e| io_error_message("Failed to read", dir_entry.path(), &e))?;
+            io::copy(&mut source_file, &mut zip).map_err(|e| io_error_message("Failed to compress", dir_entry.path(), &e))?;
+
+            processed += 1;
+            app_handle.emit_all(EXPORT_PROGRESS_EVENT, &ImportProgress {
+                operation_id: operation_id.to_string(),
+                processed,
+                total,
+                current_file: Some(archive_path.clone()),
+                message: format!("Adding: {}", archive_path),
+            }).ok();
+        }
+    }
 
-            // Try matching stem against Categories
-            if final_deduced_category_slug.is_none() {
-                let cleaned_stem = clean_and_extract_name(stem);
-                println!("[analyze_archive] Trying archive filename stem for Category: '{}' (cleaned lowercase: '{}')", stem, cleaned_stem);
+    let manifest = ModPackManifest {
+        format_version: MOD_PACK_FORMAT_VERSION,
+        exported_at: (unix_timestamp_millis() / 1000) as i64,
+        mods: mods.into_iter().map(|(_, entry)| entry).collect(),
+    };
+    zip.start_file(MOD_PACK_MANIFEST_FILENAME, options).map_err(|e| format!("Failed to add manifest: {}", e))?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|e| format!("Failed to write manifest: {}", e))?;
+    zip.finish().and_then(|mut writer| writer.flush().map_err(ZipError::Io))
+        .map_err(|e| format!("Failed to finish archive: {}", e))?;
+    Ok(manifest.mods.len())
+}
 
-                // Prio 1: Exact slug (original stem)
-                if maps.category_slug_to_id.contains_key(stem) {
-                    final_deduced_category_slug = Some(stem.to_string());
-                    println!("[analyze_archive]   -> Matched category from filename via exact slug: {}", stem);
-                }
-                // Prio 2: Exact lowercase cleaned name match -> original slug
-                else if let Some(slug) = maps.lowercase_category_name_to_slug.get(&cleaned_stem) {
-                    final_deduced_category_slug = Some(slug.clone());
-                    println!("[analyze_archive]   -> Matched category from filename via exact cleaned name: {} -> {}", cleaned_stem, slug);
-                }
-                // Prio 3: Known name contains cleaned stem part
-                else {
-                    let stem_words: Vec<&str> = cleaned_stem.split_whitespace().filter(|w| w.len() > 2).collect();
-                    if !stem_words.is_empty() {
-                        'cat_loop: for (cat_name_lower, cat_slug) in &maps.lowercase_category_name_to_slug {
-                            for word in &stem_words {
-                                if cat_name_lower.contains(word) {
-                                    final_deduced_category_slug = Some(cat_slug.clone());
-                                    println!("[analyze_archive]   -> Matched category from filename via word contains: '{}' contains '{}' -> {}", cat_name_lower, word, cat_slug);
-                                    break 'cat_loop;
-                                }
-                            }
-                        }
-                    }
+// Writes categories, entities (with custom images), the active profile's assets (with their
+// tags) and all presets to a single JSON file. Returns how many assets were exported.
+#[command]
+fn export_library(dest_path: String, db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<usize> {
+    println!("[export_library] Exporting library to {}", dest_path);
+    let images_dir = get_app_data_dir(&app_handle).map_err(|e| e.to_string())?.join(ENTITY_IMAGES_FOLDER_NAME);
+    let export = {
+        let conn = db_state.conn()?;
+
+        let categories = conn.prepare("SELECT name, slug FROM categories ORDER BY id")
+            .and_then(|mut stmt| stmt.query_map([], |row| Ok(LibraryExportCategory { name: row.get(0)?, slug: row.get(1)? }))?
+                .collect::<SqlResult<Vec<_>>>())
+            .map_err(|e| format!("Failed to read categories: {}", e))?;
+
+        let entities = conn.prepare(
+            "SELECT c.slug, e.name, e.slug, e.description, e.details, e.base_image
+             FROM entities e JOIN categories c ON e.category_id = c.id ORDER BY e.id"
+        ).and_then(|mut stmt| stmt.query_map([], |row| Ok(LibraryExportEntity {
+                category_slug: row.get(0)?, name: row.get(1)?, slug: row.get(2)?,
+                description: row.get(3)?, details: row.get(4)?, base_image: row.get(5)?, image_data: None,
+            }))?.collect::<SqlResult<Vec<_>>>())
+            .map_err(|e| format!("Failed to read entities: {}", e))?;
+        // Stored custom images are plain file names; anything else is a bundled image
+        let entities = entities.into_iter().map(|mut entity| {
+            if let Some(stored_name) = entity.base_image.as_deref().filter(|name| !name.is_empty() && !name.contains(['/', '\\'])) {
+                entity.image_data = fs::read(images_dir.join(stored_name)).ok();
+            }
+            entity
+        }).collect::<Vec<_>>();
+
+        let mut asset_tags: HashMap<i64, Vec<String>> = HashMap::new();
+        conn.prepare("SELECT at.asset_id, t.name FROM asset_tags at JOIN tags t ON at.tag_id = t.id ORDER BY t.name COLLATE NOCASE")
+            .and_then(|mut stmt| stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<SqlResult<Vec<_>>>())
+            .map_err(|e| format!("Failed to read asset tags: {}", e))?
+            .into_iter()
+            .for_each(|(asset_id, tag)| asset_tags.entry(asset_id).or_default().push(tag));
+
+        let assets = conn.prepare(
+            "SELECT a.id, a.folder_name, e.slug, a.name, a.description, a.image_filename, a.author, a.category_tag,
+                    a.notes, a.source_url, a.version, a.is_favorite, a.is_pinned, a.is_locked
+             FROM active_assets a JOIN entities e ON a.entity_id = e.id ORDER BY a.folder_name"
+        ).and_then(|mut stmt| stmt.query_map([], |row| Ok(LibraryExportAsset {
+                tags: asset_tags.remove(&row.get::<_, i64>(0)?).unwrap_or_default(),
+                folder_name: row.get::<_, String>(1)?.replace("\\", "/"),
+                entity_slug: row.get(2)?, name: row.get(3)?, description: row.get(4)?, image_filename: row.get(5)?,
+                author: row.get(6)?, category_tag: row.get(7)?, notes: row.get(8)?, source_url: row.get(9)?,
+                version: row.get(10)?, is_favorite: row.get(11)?, is_pinned: row.get(12)?, is_locked: row.get(13)?,
+            }))?.collect::<SqlResult<Vec<_>>>())
+            .map_err(|e| format!("Failed to read assets: {}", e))?;
+
+        let tags = conn.prepare("SELECT name FROM tags ORDER BY name COLLATE NOCASE")
+            .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect::<SqlResult<Vec<String>>>())
+            .map_err(|e| format!("Failed to read tags: {}", e))?;
+
+        let preset_rows = conn.prepare("SELECT id, name, is_favorite FROM active_presets WHERE name NOT LIKE ?1 || '%' ORDER BY id")
+            .and_then(|mut stmt| stmt.query_map(params![AUTO_SNAPSHOT_PRESET_PREFIX], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?)))?
+                .collect::<SqlResult<Vec<_>>>())
+            .map_err(|e| format!("Failed to read presets: {}", e))?;
+        let mut preset_asset_stmt = conn.prepare(
+            "SELECT a.folder_name, pa.is_enabled FROM preset_assets pa JOIN active_assets a ON pa.asset_id = a.id
+             WHERE pa.preset_id = ?1 ORDER BY a.folder_name"
+        ).map_err(|e| format!("Failed to prepare preset asset fetch: {}", e))?;
+        let mut presets = Vec::with_capacity(preset_rows.len());
+        for (preset_id, name, is_favorite) in preset_rows {
+            let assets = preset_asset_stmt.query_map(params![preset_id], |row| Ok(PresetExportEntry {
+                folder_name: row.get::<_, String>(0)?.replace("\\", "/"),
+                is_enabled: row.get::<_, i64>(1)? == 1,
+            })).and_then(|rows| rows.collect::<SqlResult<Vec<_>>>())
+                .map_err(|e| format!("Failed to read assets of preset '{}': {}", name, e))?;
+            presets.push(LibraryExportPreset { name, is_favorite, assets });
+        }
+
+        LibraryExportFile {
+            format_version: LIBRARY_EXPORT_FORMAT_VERSION,
+            exported_at: (unix_timestamp_millis() / 1000) as i64,
+            categories, entities, assets, tags, presets,
+        }
+    };
+
+    let json = serde_json::to_string_pretty(&export).map_err(|e| format!("Failed to serialize library: {}", e))?;
+    fs::write(&dest_path, json).map_err(|e| format!("Failed to write library file '{}': {}", dest_path, e))?;
+    println!("[export_library] Exported {} categories, {} entities, {} assets, {} presets.",
+        export.categories.len(), export.entities.len(), export.assets.len(), export.presets.len());
+    Ok(export.assets.len())
+}
+
+// Merges a file written by export_library into the current database in one transaction.
+// Missing categories, entities, tags and presets are added; assets are matched by folder path
+// in the active profile (rows are created for folders that exist on disk but aren't scanned
+// yet) and get their tags back. Existing values are never overwritten: the file only fills in
+// empty fields, and entities, assets and presets whose values differ are reported as conflicts.
+#[command]
+fn import_library(src_path: String, db_state: State<DbState>, config_state: State<CachedConfigState>, app_handle: AppHandle) -> CmdResult<LibraryImportResult> {
+    println!("[import_library] Importing library from {}", src_path);
+    let content = fs::read_to_string(&src_path).map_err(|e| format!("Failed to read library file '{}': {}", src_path, e))?;
+    let import: LibraryExportFile = serde_json::from_str(&content).map_err(|e| format!("Invalid library file: {}", e))?;
+    if import.format_version > LIBRARY_EXPORT_FORMAT_VERSION {
+        return Err(format!("Library file version {} is newer than supported version {}.", import.format_version, LIBRARY_EXPORT_FORMAT_VERSION));
+    }
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).ok();
+    let images_dir = get_app_data_dir(&app_handle).map_err(|e| e.to_string())?.join(ENTITY_IMAGES_FOLDER_NAME);
+    // (entity slug, stored image name, contents) of custom images, written once the import is committed
+    let mut pending_images: Vec<(String, String, Vec<u8>)> = Vec::new();
+
+    let mut result = LibraryImportResult::default();
+    let mut conn = db_state.conn()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // --- Categories ---
+    for category in &import.categories {
+        let existing: Option<String> = tx.query_row("SELECT name FROM categories WHERE slug = ?1", params![category.slug], |row| row.get(0))
+            .optional().map_err(|e| format!("DB error checking category '{}': {}", category.slug, e))?;
+        if existing.is_some() { continue; }
+        let inserted = tx.execute("INSERT OR IGNORE INTO categories (name, slug) VALUES (?1, ?2)", params![category.name, category.slug])
+            .map_err(|e| format!("Failed to add category '{}': {}", category.name, e))?;
+        if inserted == 0 {
+            result.conflicts.push(format!("Category '{}' was not added: another category already uses that name.", category.name));
+            continue;
+        }
+        insert_other_entity(&tx, tx.last_insert_rowid(), &category.slug)
+            .map_err(|e| format!("Failed to add fallback entity for category '{}': {}", category.name, e))?;
+        result.categories_added += 1;
+    }
+
+    // --- Entities ---
+    for entity in &import.entities {
+        let existing: Option<(String, String, Option<String>)> = tx.query_row(
+            "SELECT e.name, c.slug, e.description FROM entities e JOIN categories c ON e.category_id = c.id WHERE e.slug = ?1",
+            params![entity.slug], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).optional().map_err(|e| format!("DB error checking entity '{}': {}", entity.slug, e))?;
+        if let Some((name, category_slug, description)) = existing {
+            if name != entity.name || category_slug != entity.category_slug || (description.is_some() && entity.description.is_some() && description != entity.description) {
+                result.conflicts.push(format!("Entity '{}' already exists with different details and was kept as is.", entity.slug));
+            }
+            continue;
+        }
+        let category_id: Option<i64> = tx.query_row("SELECT id FROM categories WHERE slug = ?1", params![entity.category_slug], |row| row.get(0))
+            .optional().map_err(|e| format!("DB error checking category '{}': {}", entity.category_slug, e))?;
+        let Some(category_id) = category_id else {
+            result.warnings.push(format!("Skipped entity '{}': category '{}' not found.", entity.name, entity.category_slug));
+            continue;
+        };
+        if let (Some(image_data), Some(stored_name)) = (&entity.image_data, entity.base_image.as_deref()) {
+            if !stored_name.contains(['/', '\\']) {
+                pending_images.push((entity.slug.clone(), stored_name.to_string(), image_data.clone()));
+            }
+        }
+        tx.execute(
+            "INSERT INTO entities (category_id, name, slug, description, details, base_image) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![category_id, entity.name, entity.slug, entity.description, entity.details, entity.base_image],
+        ).map_err(|e| format!("Failed to add entity '{}': {}", entity.name, e))?;
+        result.entities_added += 1;
+    }
+
+    // --- Tags (including ones no asset uses) ---
+    for tag in import.tags.iter().chain(import.assets.iter().flat_map(|asset| asset.tags.iter())) {
+        let tag = tag.trim();
+        if tag.is_empty() { continue; }
+        tx.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", params![tag])
+            .map_err(|e| format!("Failed to create tag '{}': {}", tag, e))?;
+    }
+
+    // --- Assets ---
+    for asset in &import.assets {
+        let folder_name = asset.folder_name.replace("\\", "/");
+        let entity_id: Option<i64> = tx.query_row("SELECT id FROM entities WHERE slug = ?1", params![asset.entity_slug], |row| row.get(0))
+            .optional().map_err(|e| format!("DB error checking entity '{}': {}", asset.entity_slug, e))?;
+        let existing: Option<(i64, LibraryExportAsset)> = tx.query_row(
+            "SELECT a.id, e.slug, a.name, a.description, a.image_filename, a.author, a.category_tag, a.notes, a.source_url, a.version,
+                    a.is_favorite, a.is_pinned, a.is_locked
+             FROM active_assets a JOIN entities e ON a.entity_id = e.id WHERE a.folder_name = ?1",
+            params![folder_name],
+            |row| Ok((row.get(0)?, LibraryExportAsset {
+                folder_name: folder_name.clone(), entity_slug: row.get(1)?, name: row.get(2)?, description: row.get(3)?,
+                image_filename: row.get(4)?, author: row.get(5)?, category_tag: row.get(6)?, notes: row.get(7)?,
+                source_url: row.get(8)?, version: row.get(9)?, is_favorite: row.get(10)?, is_pinned: row.get(11)?,
+                is_locked: row.get(12)?, tags: Vec::new(),
+            })),
+        ).optional().map_err(|e| format!("Failed to look up asset '{}': {}", folder_name, e))?;
+
+        let asset_id = match existing {
+            Some((id, current)) => {
+                // Empty fields are filled from the file; set fields that differ are reported
+                let optional_fields = [
+                    ("description", &current.description, &asset.description),
+                    ("image", &current.image_filename, &asset.image_filename),
+                    ("author", &current.author, &asset.author),
+                    ("category tag", &current.category_tag, &asset.category_tag),
+                    ("notes", &current.notes, &asset.notes),
+                    ("source URL", &current.source_url, &asset.source_url),
+                    ("version", &current.version, &asset.version),
+                ];
+                let fills_empty_field = optional_fields.iter().any(|(_, current, incoming)| current.is_none() && incoming.is_some());
+                let mut differing: Vec<&str> = optional_fields.iter()
+                    .filter(|(_, current, incoming)| current.is_some() && incoming.is_some() && current != incoming)
+                    .map(|(field, _, _)| *field)
+                    .collect();
+                if current.entity_slug != asset.entity_slug { differing.push("entity"); }
+                if current.name != asset.name { differing.push("name"); }
+                if current.is_favorite != asset.is_favorite { differing.push("favorite flag"); }
+                if current.is_pinned != asset.is_pinned { differing.push("pinned flag"); }
+                if current.is_locked != asset.is_locked { differing.push("locked flag"); }
+                if !differing.is_empty() {
+                    result.conflicts.push(format!("'{}' kept its current {}.", folder_name, differing.join(", ")));
                 }
-                if final_deduced_category_slug.is_none() {
-                    println!("[analyze_archive]   -> No category match found from filename.");
+                if fills_empty_field {
+                    tx.execute(
+                        "UPDATE assets SET description = COALESCE(description, ?1), image_filename = COALESCE(image_filename, ?2), author = COALESCE(author, ?3),
+                                category_tag = COALESCE(category_tag, ?4), notes = COALESCE(notes, ?5), source_url = COALESCE(source_url, ?6), version = COALESCE(version, ?7),
+                                updated_at = CAST(strftime('%s','now') AS INTEGER)
+                         WHERE id = ?8",
+                        params![asset.description, asset.image_filename, asset.author, asset.category_tag,
+                                asset.notes, asset.source_url, asset.version, id],
+                    ).map_err(|e| format!("Failed to update asset '{}': {}", folder_name, e))?;
+                    result.assets_updated += 1;
                 }
+                id
             }
-        } else {
-            println!("[analyze_archive] Could not get filename stem.");
+            None => {
+                let on_disk = base_mods_path.as_deref().and_then(|base| resolve_asset_disk_state(base, &folder_name));
+                let (Some(entity_id), Some((is_enabled, _))) = (entity_id, on_disk) else {
+                    result.warnings.push(format!("Skipped '{}': mod not found in library or on disk.", folder_name));
+                    continue;
+                };
+                tx.execute(
+                    "INSERT INTO assets (entity_id, name, description, folder_name, image_filename, author, category_tag, notes, source_url, version, is_favorite, is_pinned, is_locked, is_enabled)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                    params![entity_id, asset.name, asset.description, folder_name, asset.image_filename, asset.author, asset.category_tag,
+                            asset.notes, asset.source_url, asset.version, asset.is_favorite, asset.is_pinned, asset.is_locked, is_enabled],
+                ).map_err(|e| format!("Failed to add asset '{}': {}", folder_name, e))?;
+                result.assets_added += 1;
+                tx.last_insert_rowid()
+            }
+        };
+        for tag in asset.tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            tx.execute("INSERT OR IGNORE INTO asset_tags (asset_id, tag_id) SELECT ?1, id FROM tags WHERE name = ?2", params![asset_id, tag])
+                .map_err(|e| format!("Failed to tag '{}' with '{}': {}", folder_name, tag, e))?;
+        }
+    }
+
+    // --- Presets ---
+    for preset in &import.presets {
+        let name = preset.name.trim();
+        if name.is_empty() { continue; }
+        let exists: bool = tx.query_row("SELECT EXISTS(SELECT 1 FROM active_presets WHERE LOWER(name) = LOWER(?1))", params![name], |row| row.get(0))
+            .map_err(|e| format!("DB error checking preset name: {}", e))?;
+        if exists {
+            result.conflicts.push(format!("Preset '{}' already exists and was not overwritten.", name));
+            continue;
+        }
+        tx.execute("INSERT INTO presets (name, is_favorite) VALUES (?1, ?2)", params![name, preset.is_favorite])
+            .map_err(|e| format!("Failed to insert preset '{}': {}", name, e))?;
+        let preset_id = tx.last_insert_rowid();
+        let mut skipped = 0;
+        for entry in &preset.assets {
+            let changes = tx.execute(
+                "INSERT OR REPLACE INTO preset_assets (preset_id, asset_id, is_enabled) SELECT ?1, id, ?2 FROM active_assets WHERE folder_name = ?3",
+                params![preset_id, entry.is_enabled, entry.folder_name.replace("\\", "/")],
+            ).map_err(|e| format!("Failed to save preset state for '{}': {}", entry.folder_name, e))?;
+            if changes == 0 { skipped += 1; }
+        }
+        if skipped > 0 {
+            result.warnings.push(format!("Preset '{}': {} mod(s) not found in library.", name, skipped));
+        }
+        result.presets_added += 1;
+    }
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    for (entity_slug, stored_name, image_data) in pending_images {
+        let stored_path = images_dir.join(&stored_name);
+        if let Err(e) = fs::create_dir_all(&images_dir).and_then(|_| fs::write(&stored_path, image_data)) {
+            result.warnings.push(format!("Entity '{}': failed to restore its image: {}", entity_slug, e));
+            conn.execute("UPDATE entities SET base_image = NULL WHERE slug = ?1", params![entity_slug]).ok();
+        }
+    }
+    drop(conn);
+    if result.categories_added > 0 || result.entities_added > 0 {
+        config_state.invalidate_deduction_maps();
+    }
+    println!("[import_library] Added {} categories, {} entities, {} assets, {} presets; updated {} assets; {} conflicts, {} warnings.",
+        result.categories_added, result.entities_added, result.assets_added, result.presets_added,
+        result.assets_updated, result.conflicts.len(), result.warnings.len());
+    Ok(result)
+}
+
+#[command]
+fn toggle_preset_favorite(preset_id: i64, is_favorite: bool, db_state: State<DbState>) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    let fav_value = if is_favorite { 1 } else { 0 };
+    conn.execute(
+        "UPDATE presets SET is_favorite = ?1 WHERE id = (SELECT id FROM active_presets WHERE id = ?2)",
+        params![fav_value, preset_id],
+    )
+    .map_err(|e| format!("Failed to update favorite status: {}", e))?;
+    Ok(())
+}
+
+#[command]
+fn delete_preset(preset_id: i64, db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    // The shortcut row goes with the preset, but the OS-level hotkey has to be released here
+    if let Ok(accelerator) = conn.query_row("SELECT accelerator FROM preset_shortcuts WHERE preset_id = ?1", params![preset_id], |row| row.get::<_, String>(0)) {
+        if let Err(e) = app_handle.global_shortcut_manager().unregister(&accelerator) {
+            eprintln!("[delete_preset] Failed to release shortcut '{}': {}", accelerator, e);
         }
+    }
+    // Foreign key cascade should delete from preset_assets automatically
+    let changes = conn.execute("DELETE FROM presets WHERE id = (SELECT id FROM active_presets WHERE id = ?1)", params![preset_id])
+                      .map_err(|e| format!("Failed to delete preset: {}", e))?;
+    if changes == 0 {
+        Err(format!("Preset with ID {} not found.", preset_id))
     } else {
-        println!("[analyze_archive] Skipping filename deduction (already found entity and category).");
+        Ok(())
     }
-    // --- End Filename Deduction ---
+}
 
+// --- Preset shortcuts ---
 
-    // --- 4. Final Category Lookup (If needed) ---
-    if final_deduced_entity_slug.is_some() && final_deduced_category_slug.is_none() {
-        let entity_slug = final_deduced_entity_slug.as_ref().unwrap();
-        println!("[analyze_archive] Entity slug '{}' found, but category slug is missing. Looking up category...", entity_slug);
-        if let Some(cat_slug) = maps.entity_slug_to_category_slug.get(entity_slug) {
-            final_deduced_category_slug = Some(cat_slug.clone());
-            println!("[analyze_archive]   -> Found category slug '{}' from entity map.", cat_slug);
-        } else {
-            eprintln!("[analyze_archive]   -> Warning: Could not find category slug for deduced entity slug '{}' in maps!", entity_slug);
+#[derive(Serialize, Debug, Clone)]
+struct PresetShortcut {
+    preset_id: i64,
+    preset_name: String,
+    accelerator: String,
+}
+
+// Binds `accelerator` system-wide to applying the preset. Errors from Tauri cover both bad
+// accelerator strings and hotkeys another application already owns.
+fn register_preset_hotkey(app_handle: &AppHandle, preset_id: i64, accelerator: &str) -> Result<(), String> {
+    let handle = app_handle.clone();
+    app_handle.global_shortcut_manager()
+        .register(accelerator, move || {
+            println!("[preset_shortcut] Hotkey pressed, applying preset {}", preset_id);
+            let handle = handle.clone();
+            async_runtime::spawn(async move {
+                let result = apply_preset(preset_id, None, None, handle.state::<DbState>(), handle.state::<CancellationState>(), handle.clone()).await;
+                if let Err(e) = result {
+                    eprintln!("[preset_shortcut] Applying preset {} failed: {}", preset_id, e);
+                }
+            });
+        })
+        .map_err(|e| format!("Could not register shortcut '{}': {}. Check the key combination, or whether another application already uses it.", accelerator, e))
+}
+
+// Re-registers the saved bindings; called once from setup
+fn register_saved_preset_shortcuts(app_handle: &AppHandle) {
+    let shortcuts: Vec<(i64, String)> = {
+        let db_state = app_handle.state::<DbState>();
+        let conn = match db_state.conn() {
+            Ok(conn) => conn,
+            Err(e) => { eprintln!("[register_saved_preset_shortcuts] {}", e); return; }
+        };
+        match conn.prepare("SELECT preset_id, accelerator FROM preset_shortcuts")
+            .and_then(|mut stmt| stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<SqlResult<Vec<_>>>()) {
+            Ok(rows) => rows,
+            Err(e) => { eprintln!("[register_saved_preset_shortcuts] Failed to load shortcuts: {}", e); return; }
+        }
+    };
+    for (preset_id, accelerator) in shortcuts {
+        match register_preset_hotkey(app_handle, preset_id, &accelerator) {
+            Ok(()) => println!("[register_saved_preset_shortcuts] '{}' -> preset {}", accelerator, preset_id),
+            Err(e) => eprintln!("[register_saved_preset_shortcuts] {}", e),
         }
     }
-    // --- End Final Category Lookup ---
+}
 
+#[command]
+fn register_preset_shortcut(preset_id: i64, accelerator: String, db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<()> {
+    let accelerator = accelerator.trim().to_string();
+    if accelerator.is_empty() {
+        return Err("Shortcut cannot be empty".to_string());
+    }
+    let conn = db_state.conn()?;
+    conn.query_row("SELECT 1 FROM active_presets WHERE id = ?1", params![preset_id], |_| Ok(()))
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Preset with ID {} not found.", preset_id),
+            _ => format!("Failed to fetch preset: {}", e),
+        })?;
+    let taken_by: Option<(i64, String)> = conn.query_row(
+        "SELECT p.id, p.name FROM preset_shortcuts s JOIN active_presets p ON s.preset_id = p.id WHERE s.accelerator = ?1",
+        params![accelerator], |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().map_err(|e| format!("DB error checking shortcut: {}", e))?;
+    match taken_by {
+        Some((id, _)) if id == preset_id => return Ok(()),
+        Some((_, name)) => return Err(format!("'{}' is already bound to preset '{}'.", accelerator, name)),
+        None => {}
+    }
+    let previous: Option<String> = conn.query_row("SELECT accelerator FROM preset_shortcuts WHERE preset_id = ?1", params![preset_id], |row| row.get(0))
+        .optional().map_err(|e| format!("DB error checking shortcut: {}", e))?;
 
-    // --- Fallback name deduction & final cleanup ---
-    // Use cleaned archive name if INI name wasn't found or was empty after cleaning
-    if deduced_mod_name.is_none() || deduced_mod_name.as_deref() == Some("") {
-        deduced_mod_name = file_path.file_stem()
-            .and_then(OsStr::to_str)
-            .map(|s| clean_and_extract_name(s)); // Use cleaner here too
-        println!("[analyze_archive] Used archive filename for deduced name: {:?}", deduced_mod_name);
+    register_preset_hotkey(&app_handle, preset_id, &accelerator)?;
+    if let Err(e) = conn.execute("INSERT OR REPLACE INTO preset_shortcuts (preset_id, accelerator) VALUES (?1, ?2)", params![preset_id, accelerator]) {
+        app_handle.global_shortcut_manager().unregister(&accelerator).ok();
+        return Err(format!("Failed to save shortcut: {}", e));
     }
-    // Final cleanup on whatever name we ended up with
-    if let Some(name) = &deduced_mod_name {
-        let cleaned = clean_and_extract_name(name); // Use cleaner
-        if !cleaned.is_empty() {
-            // If cleaning didn't result in empty, use the cleaned version
-            deduced_mod_name = Some(cleaned);
-        } else {
-            // If cleaning resulted in empty, revert to original file stem as last resort
-            deduced_mod_name = file_path.file_stem().and_then(OsStr::to_str).map(String::from);
-            println!("[analyze_archive] Warning: Name cleanup resulted in empty string, using raw file stem: {:?}", deduced_mod_name);
+    if let Some(previous) = previous {
+        if let Err(e) = app_handle.global_shortcut_manager().unregister(&previous) {
+            eprintln!("[register_preset_shortcut] Failed to release previous shortcut '{}': {}", previous, e);
+        }
+    }
+    println!("[register_preset_shortcut] '{}' -> preset {}", accelerator, preset_id);
+    Ok(())
+}
+
+#[command]
+fn unregister_preset_shortcut(preset_id: i64, db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    let accelerator: String = conn.query_row("SELECT accelerator FROM preset_shortcuts WHERE preset_id = ?1", params![preset_id], |row| row.get(0))
+        .optional().map_err(|e| format!("DB error reading shortcut: {}", e))?
+        .ok_or_else(|| format!("Preset {} has no shortcut.", preset_id))?;
+    if let Err(e) = app_handle.global_shortcut_manager().unregister(&accelerator) {
+        eprintln!("[unregister_preset_shortcut] Failed to release '{}': {}", accelerator, e);
+    }
+    conn.execute("DELETE FROM preset_shortcuts WHERE preset_id = ?1", params![preset_id])
+        .map_err(|e| format!("Failed to delete shortcut: {}", e))?;
+    println!("[unregister_preset_shortcut] Removed '{}' from preset {}", accelerator, preset_id);
+    Ok(())
+}
+
+#[command]
+fn get_preset_shortcuts(db_state: State<DbState>) -> CmdResult<Vec<PresetShortcut>> {
+    let conn = db_state.conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT s.preset_id, p.name, s.accelerator FROM preset_shortcuts s JOIN active_presets p ON s.preset_id = p.id ORDER BY p.name COLLATE NOCASE"
+    ).map_err(|e| e.to_string())?;
+    let shortcuts = stmt.query_map([], |row| Ok(PresetShortcut { preset_id: row.get(0)?, preset_name: row.get(1)?, accelerator: row.get(2)? }))
+        .map_err(|e| e.to_string())?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(shortcuts)
+}
+
+// --- Command to get Dashboard Stats ---
+#[command]
+async fn get_dashboard_stats(app_handle: AppHandle) -> CmdResult<DashboardStats> {
+    run_disk_stat_task(app_handle, |handle| dashboard_stats(&handle.state::<DbState>(), &handle.state::<CachedConfigState>())).await
+}
+
+fn dashboard_stats(db_state: &DbState, config_state: &CachedConfigState) -> CmdResult<DashboardStats> {
+    let base_mods_path = match cached_mods_base_path(config_state, db_state) {
+        Ok(p) => p,
+        Err(_) => {
+             // If base path isn't set, return default zeroed stats
+            return Ok(DashboardStats {
+                total_mods: 0,
+                enabled_mods: 0,
+                disabled_mods: 0,
+                uncategorized_mods: 0,
+                category_counts: HashMap::new(),
+            });
+        }
+    };
+
+    let conn = db_state.conn()?;
+
+    // 1. Total Mods
+    let total_mods = conn.query_row("SELECT COUNT(*) FROM active_assets", [], |row| row.get::<_, i64>(0))
+                         .map_err(|e| format!("Failed to get total mod count: {}", e))?;
+
+    // 2. Uncategorized Mods
+    let uncategorized_mods = conn.query_row(
+        "SELECT COUNT(a.id) FROM active_assets a JOIN entities e ON a.entity_id = e.id WHERE e.slug LIKE '%-other'",
+        [],
+        |row| row.get::<_, i64>(0)
+    ).map_err(|e| format!("Failed to get uncategorized mod count: {}", e))?;
+
+    // 3. Category Counts
+    let mut category_counts = HashMap::new();
+    let mut cat_stmt = conn.prepare(
+        "SELECT c.name, COUNT(a.id)
+         FROM categories c
+         JOIN entities e ON c.id = e.category_id
+         JOIN active_assets a ON e.id = a.entity_id
+         GROUP BY c.name
+         HAVING COUNT(a.id) > 0" // Only include categories with mods
+    ).map_err(|e| format!("Failed to prepare category count query: {}", e))?;
+
+    let cat_rows = cat_stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+    }).map_err(|e| format!("Failed to execute category count query: {}", e))?;
+
+    for row_result in cat_rows {
+        match row_result {
+            Ok((name, count)) => { category_counts.insert(name, count); }
+            Err(e) => { eprintln!("[get_dashboard_stats] Error processing category count row: {}", e); }
         }
     }
 
+    // 4. Enabled/Disabled Count (stored state; only assets with no known state hit the disk)
+    match reconcile_asset_enabled_states(&conn, &base_mods_path, true) {
+        Ok(summary) if summary.updated > 0 => println!("[get_dashboard_stats] Filled in enabled state for {} assets", summary.updated),
+        Ok(_) => {}
+        Err(e) => eprintln!("[get_dashboard_stats] Failed to resolve unknown enabled states: {}", e),
+    }
+    // Assets missing on disk have no state and count as neither
+    let (enabled_mods, disabled_mods): (i64, i64) = conn.query_row(
+        "SELECT COALESCE(SUM(is_enabled = 1), 0), COALESCE(SUM(is_enabled = 0), 0) FROM active_assets",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| format!("Failed to count enabled mods: {}", e))?;
 
-    // --- Final Log ---
-    println!("[analyze_archive] Final Deductions: Name={:?}, Author={:?}, Category={:?}, Entity={:?}, Preview={:?}, RawINI Target={:?}, RawINI Type={:?}",
-        deduced_mod_name, deduced_author, final_deduced_category_slug, final_deduced_entity_slug, detected_preview_internal_path, raw_ini_target_found, raw_ini_type_found);
-
-    // --- Return Result ---
-    Ok(ArchiveAnalysisResult {
-        file_path: file_path_str,
-        entries,
-        deduced_mod_name,
-        deduced_author,
-        deduced_category_slug: final_deduced_category_slug,
-        deduced_entity_slug: final_deduced_entity_slug,
-        raw_ini_type: raw_ini_type_found,
-        raw_ini_target: raw_ini_target_found,
-        detected_preview_internal_path,
+    Ok(DashboardStats {
+        total_mods,
+        enabled_mods,
+        disabled_mods,
+        uncategorized_mods,
+        category_counts,
     })
 }
 
+
+// --- Command to get App Version ---
 #[command]
-fn read_archive_file_content(archive_path_str: String, internal_file_path: String) -> CmdResult<Vec<u8>> {
-    println!("[read_archive_file_content] Reading '{}' from archive '{}'", internal_file_path, archive_path_str);
-    let archive_path = PathBuf::from(&archive_path_str);
-    if !archive_path.is_file() { return Err(format!("Archive file not found: {}", archive_path.display())); }
+fn get_app_version() -> String {
+    // Read from environment variable set by build script/Cargo
+    env!("CARGO_PKG_VERSION").to_string()
+}
 
-    let extension = archive_path.extension().and_then(|os| os.to_str()).map(|s| s.to_lowercase());
-    let internal_path_normalized = internal_file_path.replace("\\", "/");
+#[command]
+async fn get_entities_by_category_with_counts(category_slug: String, app_handle: AppHandle) -> CmdResult<Vec<EntityWithCounts>> {
+    run_disk_stat_task(app_handle, move |handle| {
+        entities_by_category_with_counts(&category_slug, &handle.state::<DbState>(), &handle.state::<CachedConfigState>())
+    }).await
+}
 
-    match extension.as_deref() {
-        Some("zip") => {
-            let file = fs::File::open(&archive_path).map_err(|e| format!("Zip Read: Failed open: {}", e))?;
-            let mut archive = ZipArchive::new(file).map_err(|e| format!("Zip Read: Failed read archive: {}", e))?;
+fn entities_by_category_with_counts(category_slug: &str, db_state: &DbState, config_state: &CachedConfigState) -> CmdResult<Vec<EntityWithCounts>> {
+    println!("[get_entities_with_counts] Fetching for category: {}", category_slug);
 
-            // --- FIX: Assign match result to variable and return it ---
-            let result = match archive.by_name(&internal_path_normalized) {
-                Ok(mut file_in_zip) => {
-                    let mut buffer = Vec::with_capacity(file_in_zip.size() as usize);
-                    match file_in_zip.read_to_end(&mut buffer) {
-                        Ok(_) => Ok(buffer), // Successful read
-                        Err(e) => Err(format!("Zip Read: Failed read content: {}", e)),
-                    }
-                },
-                Err(ZipError::FileNotFound) => Err(format!("Zip Read: Internal file '{}' not found.", internal_file_path)),
-                Err(e) => Err(format!("Zip Read: Error accessing internal file '{}': {}", internal_file_path, e)),
-            };
-            result // Return the result stored in the variable
-            // --- END FIX ---
+    let base_mods_path = match cached_mods_base_path(config_state, db_state) {
+        Ok(p) => p,
+        Err(_) => {
+            println!("[get_entities_with_counts] Mods folder not set. Returning empty list.");
+            return Ok(Vec::new());
         }
-        Some("7z") => {
-            // --- 7z logic remains the same as previously corrected ---
-            let mut found_content: Option<Vec<u8>> = None;
-            let mut found_error: Option<String> = None;
-            let mut archive = sevenz_rust::SevenZReader::open(&archive_path_str, Password::empty())
-                .map_err(|e| format!("7z Read: Failed open: {}", e))?;
+    };
 
-            archive.for_each_entries(|entry, reader| {
-                if found_content.is_some() || found_error.is_some() { return Ok(false); }
-                let entry_name_normalized = entry.name().replace("\\", "/");
-                if entry_name_normalized == internal_path_normalized {
-                    let mut content_bytes = Vec::new();
-                    let mut buffer = [0u8; 4096];
-                    let read_result: Result<(), io::Error> = (|| { // Use closure for ? propagation
-                        loop {
-                            let bytes_read = reader.read(&mut buffer)?;
-                            if bytes_read == 0 { break; }
-                            content_bytes.extend_from_slice(&buffer[..bytes_read]);
-                        }
-                        Ok(())
-                    })(); // Immediately invoke
+    let conn = db_state.conn()?;
 
-                    match read_result {
-                        Ok(()) => found_content = Some(content_bytes),
-                        Err(e) => found_error = Some(format!("7z Read: Error reading content '{}': {}", internal_file_path, e)),
-                    }
-                    return Ok(false); // Stop processing after finding (or failing to read) the file
-                }
-                Ok(true)
-            })
-            .map_err(|e: sevenz_rust::Error| format!("7z Read: Error iterating entries: {}", e))?;
+    // 1. Get Category ID
+    let category_id: i64 = conn.query_row(
+        "SELECT id FROM categories WHERE slug = ?1",
+        params![category_slug],
+        |row| row.get(0),
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("Category '{}' not found", category_slug),
+        _ => format!("DB Error getting category ID: {}", e),
+    })?;
 
-            if let Some(content) = found_content { Ok(content) }
-            else if let Some(err) = found_error { Err(err) }
-            else { Err(format!("7z Read: Internal file '{}' not found.", internal_file_path)) }
-        }
-        Some("rar") => {
-            let mut archive = Archive::new(&archive_path_str)
-                .open_for_processing() // Need Process mode to read content
-                .map_err(|e| e.to_string())?;
-            let mut found_content: Option<Vec<u8>> = None;
+    // 2. Get Entities for the Category
+    let mut entity_stmt = conn.prepare(
+         "SELECT e.id, e.category_id, e.name, e.slug, e.details, e.base_image
+          FROM entities e
+          WHERE e.category_id = ?1
+          ORDER BY CASE WHEN e.slug LIKE '%-other' THEN 0 ELSE 1 END ASC, e.name ASC"
+     ).map_err(|e| format!("Failed to prepare entity query: {}", e))?;
 
-            loop {
-                match archive.read_header() {
-                    Ok(Some(header_state)) => { // Returns OpenArchive<..., CursorBeforeFile>
-                        let entry_filename = &header_state.entry().filename; // Access header via entry()
-                        let entry_name_normalized = entry_filename.to_string_lossy().replace("\\", "/");
+    let entity_rows_iter = entity_stmt.query_map(params![category_id], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, Option<String>>(5)?,
+        ))
+    }).map_err(|e| format!("Failed to query entities: {}", e))?;
 
-                        if entry_name_normalized == internal_path_normalized {
-                            // Found the file, process it using read()
-                            match header_state.read() {
-                                Ok((bytes, _next_archive_state)) => { // Successfully read
-                                    found_content = Some(bytes);
-                                    break; // Found and read, exit loop
-                                }
-                                Err(e) => { // Error during reading
-                                    return Err(format!("Rar Read: Error reading content '{}': {}", internal_file_path, e));
+    let mut results: Vec<EntityWithCounts> = Vec::new();
+
+    // *** FIX: Apply .map_err() to the prepare call ***
+    let mut asset_folder_stmt = conn.prepare("SELECT folder_name FROM active_assets WHERE entity_id = ?1")
+                                     .map_err(|e| format!("Failed to prepare asset folder query: {}", e))?; // Prepare asset query once
+
+    for entity_result in entity_rows_iter {
+        match entity_result {
+            Ok((id, cat_id, name, slug, details, base_image)) => {
+                // 3. For each entity, get its assets and check disk status
+                let mut total_mods_for_entity = 0;
+                let mut enabled_mods_for_entity = 0;
+
+                // Map potential errors when querying assets for *this specific* entity
+                let asset_folder_rows_result = asset_folder_stmt.query_map(params![id], |row| row.get::<_, String>(0));
+
+                match asset_folder_rows_result {
+                     Ok(rows) => {
+                        for folder_result in rows {
+                            match folder_result {
+                                Ok(clean_relative_path_str) => {
+                                    total_mods_for_entity += 1;
+
+                                    let clean_relative_path = PathBuf::from(clean_relative_path_str.replace("\\", "/"));
+                                    let filename_osstr = clean_relative_path.file_name().unwrap_or_default();
+                                    let filename_str = filename_osstr.to_string_lossy();
+                                    if filename_str.is_empty() { continue; }
+
+                                    // Check only enabled state path
+                                    let full_path_if_enabled = base_mods_path.join(&clean_relative_path);
+                                    if full_path_if_enabled.is_dir() {
+                                        enabled_mods_for_entity += 1;
+                                    }
                                 }
+                                Err(e) => eprintln!("[get_entities_with_counts] Error fetching asset folder row for entity {}: {}", id, e),
                             }
-                        } else {
-                            // Not the file we want, skip it
-                            archive = header_state.skip().map_err(|e| e.to_string())?; // Skip and update archive state
                         }
                     }
-                    Ok(None) => break, // End of archive, file not found
-                    Err(e) => return Err(format!("Rar Read: Error reading header: {}", e)),
+                    // Log the error but don't stop the whole process for one entity's assets failing
+                    Err(e) => eprintln!("[get_entities_with_counts] Error querying asset folders for entity {}: {}", id, e),
                 }
+
+                results.push(EntityWithCounts {
+                    id,
+                    category_id: cat_id,
+                    name,
+                    slug,
+                    details,
+                    base_image,
+                    total_mods: total_mods_for_entity,
+                    enabled_mods: enabled_mods_for_entity,
+                });
             }
-            found_content.ok_or_else(|| format!("Rar Read: Internal file '{}' not found.", internal_file_path))
+            Err(e) => eprintln!("[get_entities_with_counts] Error processing entity row: {}", e),
         }
-        _ => Err(format!("Unsupported archive type for reading: {:?}", extension)),
     }
-}
-
-#[command]
-fn import_archive(
-    archive_path_str: String,
-    target_entity_slug: String,
-    selected_internal_root: String, // Frontend still provides this, empty means "extract all"
-    mod_name: String,
-    description: Option<String>,
-    author: Option<String>,
-    category_tag: Option<String>,
-    image_data: Option<Vec<u8>>,
-    selected_preview_absolute_path: Option<String>,
-    preset_ids: Option<Vec<i64>>,
-    db_state: State<DbState>
-) -> CmdResult<()> {
-    println!("[import_archive] Importing '{}', internal path '{}' for entity '{}'. Image Data Provided: {}. Add to presets: {:?}",
-        archive_path_str,
-        if selected_internal_root.is_empty() { "(Extract All)" } else { &selected_internal_root }, // Indicate if extracting all
-        target_entity_slug,
-        image_data.is_some(),
-        preset_ids);
-
-    // --- Basic Validation & Setup ---
-    if mod_name.trim().is_empty() { return Err("Mod Name cannot be empty.".to_string()); }
-    if target_entity_slug.trim().is_empty() { return Err("Target Entity must be selected.".to_string()); }
-    let archive_path = PathBuf::from(&archive_path_str);
-    if !archive_path.is_file() { return Err(format!("Archive file not found: {}", archive_path.display())); }
-
-    let mut conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-
-    let base_mods_path_str = get_setting_value(&conn_guard, SETTINGS_KEY_MODS_FOLDER)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "Mods folder path not set".to_string())?;
-    let base_mods_path = PathBuf::from(base_mods_path_str);
-
-    let (target_category_slug, target_entity_id): (String, i64) = conn_guard.query_row(
-        "SELECT c.slug, e.id FROM entities e JOIN categories c ON e.category_id = c.id WHERE e.slug = ?1",
-        params![target_entity_slug], |row| Ok((row.get(0)?, row.get(1)?)),
-    ).map_err(|e| match e {
-        rusqlite::Error::QueryReturnedNoRows => format!("Target entity '{}' not found.", target_entity_slug),
-        _ => format!("DB Error get target entity: {}", e)
-    })?;
-
-    let target_mod_folder_name = mod_name.trim().replace(" ", "_").replace(".", "_").replace("'", "").replace("\"", "");
-    if target_mod_folder_name.is_empty() { return Err("Mod Name results in invalid folder name.".to_string()); }
-    let final_mod_dest_path = base_mods_path.join(&target_category_slug).join(&target_entity_slug).join(&target_mod_folder_name);
-
-    fs::create_dir_all(&final_mod_dest_path)
-        .map_err(|e| format!("Failed create dest directory '{}': {}", final_mod_dest_path.display(), e))?;
-    println!("[import_archive] Target destination folder created/ensured: {}", final_mod_dest_path.display());
-
-    let tx = conn_guard.transaction().map_err(|e| format!("Failed start import transaction: {}", e))?;
-
-    // --- Extraction Logic ---
-    println!("[import_archive] Starting extraction...");
-    let extension = archive_path.extension().and_then(|os| os.to_str()).map(|s| s.to_lowercase());
-    // Normalize and prepare the prefix path IF a root was selected
-    let prefix_to_extract_norm = selected_internal_root.replace("\\", "/");
-    let prefix_to_extract = prefix_to_extract_norm.strip_suffix('/').unwrap_or(&prefix_to_extract_norm);
-    let prefix_path = Path::new(prefix_to_extract);
-    let extract_all = prefix_to_extract.is_empty(); // Flag to determine if extracting all
-    println!("[import_archive] Extract All Mode: {}", extract_all);
-    let mut files_extracted_count = 0;
-
-    let extraction_result: Result<usize, String> = (|| {
-        match extension.as_deref() {
-        Some("zip") => {
-             let file = fs::File::open(&archive_path).map_err(|e| format!("Zip Extract: Failed open: {}", e))?;
-             let mut archive = ZipArchive::new(file).map_err(|e| format!("Zip Extract: Failed read archive: {}", e))?;
-             for i in 0..archive.len() {
-                  let mut file_in_zip = archive.by_index(i).map_err(|e| format!("Zip Extract: Failed read entry #{}: {}", i, e))?;
-                  let internal_path_obj_opt = file_in_zip.enclosed_name().map(|p| p.to_path_buf());
-                  if internal_path_obj_opt.is_none() { continue; }
-                  let internal_path_obj = internal_path_obj_opt.unwrap();
-
-                  let (should_extract, relative_path_to_dest_obj) = if extract_all {
-                      // Extracting all: always extract, relative path is the full internal path
-                      (true, internal_path_obj.clone()) // Clone needed as we check it later
-                  } else {
-                      // Specific root selected: check prefix
-                      let should = internal_path_obj.starts_with(prefix_path);
-                      let relative_path = if should {
-                          // Strip the prefix if it matches
-                          internal_path_obj.strip_prefix(prefix_path).map(|p| p.to_path_buf()).ok()
-                      } else {
-                          None
-                      };
-                      (should && relative_path.is_some(), relative_path.unwrap_or_default())
-                  };
 
-                  if !should_extract || relative_path_to_dest_obj.as_os_str().is_empty() { continue; }
-                  let outpath = final_mod_dest_path.join(&relative_path_to_dest_obj);
+    println!("[get_entities_with_counts] Found {} entities with counts for category '{}'", results.len(), category_slug);
+    Ok(results)
+}
 
-                  if file_in_zip.is_dir() {
-                      fs::create_dir_all(&outpath).map_err(|e| format!("Zip Extract: Failed create dir '{}': {}", outpath.display(), e))?;
-                  } else {
-                      if let Some(p) = outpath.parent() { if !p.exists() { fs::create_dir_all(&p).map_err(|e| format!("Zip Extract: Failed create parent '{}': {}", p.display(), e))?; } }
-                      let mut outfile = fs::File::create(&outpath).map_err(|e| format!("Zip Extract: Failed create file '{}': {}", outpath.display(), e))?;
-                      std::io::copy(&mut file_in_zip, &mut outfile).map_err(|e| format!("Zip Extract: Failed copy content '{}': {}", outpath.display(), e))?;
-                      files_extracted_count += 1;
-                  }
-             }
+// Disk usage per entity and category, from the size_bytes cached during scans.
+// `refresh` re-measures every asset folder; otherwise only assets without a cached size are measured.
+#[command]
+async fn get_storage_report(refresh: bool, db_state: State<'_, DbState>) -> CmdResult<StorageReport> {
+    println!("[get_storage_report] Building report (refresh: {})", refresh);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    let to_measure: Vec<(i64, String)> = {
+        let conn = db_state.conn()?;
+        let sql = if refresh {
+            "SELECT id, folder_name FROM active_assets"
+        } else {
+            "SELECT id, folder_name FROM active_assets WHERE size_bytes IS NULL"
+        };
+        let mut stmt = conn.prepare(sql).map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?.replace("\\", "/"))))
+            .map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
+    }; // Lock released while walking folders
+
+    if !to_measure.is_empty() {
+        println!("[get_storage_report] Measuring {} asset folders...", to_measure.len());
+        let measure_task = async_runtime::spawn_blocking(move || {
+            to_measure.par_iter()
+                .map(|(asset_id, clean_relative_path)| {
+                    let size = resolve_asset_disk_state(&base_mods_path, clean_relative_path)
+                        .map(|(_, current_relative_path)| folder_size_bytes(&base_mods_path.join(current_relative_path)) as i64);
+                    (*asset_id, size)
+                })
+                .collect::<Vec<_>>()
+        });
+        let measured = measure_task.await.map_err(|e| format!("Storage measurement task failed: {}", e))?;
+
+        let mut conn = db_state.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+        {
+            let mut update_stmt = tx.prepare("UPDATE assets SET size_bytes = ?1 WHERE id = ?2")
+                .map_err(|e| format!("Failed to prepare size update: {}", e))?;
+            for (asset_id, size) in &measured {
+                update_stmt.execute(params![size, asset_id])
+                    .map_err(|e| format!("Failed to store size for asset {}: {}", asset_id, e))?;
+            }
         }
-        Some("7z") => {
-            let mut archive = sevenz_rust::SevenZReader::open(&archive_path_str, Password::empty())
-                .map_err(|e| format!("7z Extract: Failed open: {}", e))?;
-             archive.for_each_entries(|entry, reader| {
-                 let internal_path_str = entry.name().replace("\\", "/");
-                 let internal_path_obj = PathBuf::from(&internal_path_str);
+        tx.commit().map_err(|e| format!("Failed to commit sizes: {}", e))?;
+    }
 
-                 let (should_extract, relative_path_to_dest_obj) = if extract_all {
-                      (true, internal_path_obj.clone())
-                 } else {
-                      let should = internal_path_obj.starts_with(prefix_path);
-                      let relative_path = if should { internal_path_obj.strip_prefix(prefix_path).map(|p| p.to_path_buf()).ok() } else { None };
-                      (should && relative_path.is_some(), relative_path.unwrap_or_default())
-                 };
-                 if !should_extract || relative_path_to_dest_obj.as_os_str().is_empty() { return Ok(true); } // Skip to next
-                 let outpath = final_mod_dest_path.join(&relative_path_to_dest_obj);
+    let conn = db_state.conn()?;
+    let read_usage = |sql: &str| -> CmdResult<Vec<StorageUsage>> {
+        let mut stmt = conn.prepare(sql).map_err(|e| format!("Failed to prepare storage query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok(StorageUsage {
+            slug: row.get(0)?,
+            name: row.get(1)?,
+            asset_count: row.get(2)?,
+            size_bytes: row.get(3)?,
+        })).map_err(|e| format!("Failed to query storage usage: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read storage rows: {}", e))
+    };
+    let by_entity = read_usage(
+        "SELECT e.slug, e.name, COUNT(a.id), COALESCE(SUM(a.size_bytes), 0) FROM active_assets a JOIN entities e ON a.entity_id = e.id
+         GROUP BY e.id ORDER BY 4 DESC, e.name",
+    )?;
+    let by_category = read_usage(
+        "SELECT c.slug, c.name, COUNT(a.id), COALESCE(SUM(a.size_bytes), 0) FROM active_assets a JOIN entities e ON a.entity_id = e.id JOIN categories c ON e.category_id = c.id
+         GROUP BY c.id ORDER BY 4 DESC, c.name",
+    )?;
+    let unmeasured_assets: i64 = conn.query_row("SELECT COUNT(*) FROM active_assets WHERE size_bytes IS NULL", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to count unmeasured assets: {}", e))?;
+    let total_bytes = by_category.iter().map(|c| c.size_bytes).sum();
+
+    println!("[get_storage_report] Total: {} bytes across {} entities ({} assets unmeasured)", total_bytes, by_entity.len(), unmeasured_assets);
+    Ok(StorageReport { total_bytes, by_entity, by_category, unmeasured_assets })
+}
 
-                 if entry.is_directory() {
-                    fs::create_dir_all(&outpath)?;
-                 } else {
-                    if let Some(p) = outpath.parent() { if !p.exists() { fs::create_dir_all(&p)?; }}
-                    let mut outfile = fs::File::create(&outpath)?;
-                    let mut buffer = [0u8; 4096];
-                    loop {
-                        let bytes_read = reader.read(&mut buffer)?;
-                        if bytes_read == 0 { break; }
-                        outfile.write_all(&buffer[..bytes_read])?;
-                    }
-                    files_extracted_count += 1;
-                 }
-                 Ok(true) // Continue to next entry
-             })
-             .map_err(|e: sevenz_rust::Error| format!("7z Extract: Error processing entries: {}", e))?;
+// Persists a user-defined order for an entity's mods. Assets of the entity missing from
+// `ordered_ids` keep their relative order after the listed ones.
+#[command]
+fn reorder_assets(entity_id: i64, ordered_ids: Vec<i64>, db_state: State<DbState>) -> CmdResult<()> {
+    println!("[reorder_assets] Reordering {} assets of entity {}", ordered_ids.len(), entity_id);
+    let mut conn = db_state.conn()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let current_order: Vec<i64> = {
+        let mut stmt = tx.prepare("SELECT id FROM active_assets WHERE entity_id = ?1 ORDER BY sort_order, name")
+            .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map(params![entity_id], |row| row.get(0))
+            .map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
+    };
+    let entity_asset_ids: HashSet<i64> = current_order.iter().copied().collect();
+    let mut seen = HashSet::new();
+    for asset_id in &ordered_ids {
+        if !entity_asset_ids.contains(asset_id) {
+            return Err(format!("Asset {} does not belong to entity {}", asset_id, entity_id));
         }
-        Some("rar") => {
-            let mut archive = Archive::new(&archive_path_str).open_for_processing()
-                .map_err(|e| e.to_string())?;
-            loop {
-                match archive.read_header().map_err(|e| e.to_string())? {
-                    Some(header_state) => {
-                        let entry_filename = &header_state.entry().filename;
-                        let internal_path_str = entry_filename.to_string_lossy().replace("\\", "/").to_string();
-                        let internal_path_obj = PathBuf::from(&internal_path_str);
+        if !seen.insert(*asset_id) {
+            return Err(format!("Asset {} is listed more than once", asset_id));
+        }
+    }
 
-                        let (should_extract, relative_path_to_dest_obj) = if extract_all {
-                            (true, internal_path_obj.clone())
-                        } else {
-                            let should = internal_path_obj.starts_with(prefix_path);
-                            let relative_path = if should { internal_path_obj.strip_prefix(prefix_path).map(|p| p.to_path_buf()).ok() } else { None };
-                            (should && relative_path.is_some(), relative_path.unwrap_or_default())
-                        };
-                        if !should_extract || relative_path_to_dest_obj.as_os_str().is_empty() {
-                            archive = header_state.skip().map_err(|e| e.to_string())?;
-                            continue; // Skip to next
-                        }
-                        let outpath = final_mod_dest_path.join(&relative_path_to_dest_obj);
+    let final_order = ordered_ids.iter().copied()
+        .chain(current_order.into_iter().filter(|id| !seen.contains(id)));
+    {
+        let mut update_stmt = tx.prepare("UPDATE assets SET sort_order = ?1 WHERE id = ?2")
+            .map_err(|e| format!("Failed to prepare sort update: {}", e))?;
+        for (position, asset_id) in final_order.enumerate() {
+            update_stmt.execute(params![position as i64 + 1, asset_id])
+                .map_err(|e| format!("Failed to update sort order for asset {}: {}", asset_id, e))?;
+        }
+    }
+    tx.commit().map_err(|e| format!("Failed to commit new order: {}", e))?;
+    println!("[reorder_assets] Entity {} reordered.", entity_id);
+    Ok(())
+}
 
-                        if header_state.entry().is_directory() {
-                            fs::create_dir_all(&outpath).map_err(|e| format!("Rar Extract: Failed create dir '{}': {}", outpath.display(), e))?;
-                            archive = header_state.skip().map_err(|e| e.to_string())?;
-                        } else {
-                            if let Some(p) = outpath.parent() { if !p.exists() { fs::create_dir_all(&p).map_err(|e| format!("Rar Extract: Failed create parent '{}': {}", p.display(), e))?; }}
-                            archive = header_state.extract_to(&outpath).map_err(|e| e.to_string())?;
-                            files_extracted_count += 1;
-                        }
-                    }
-                    None => break, // End of archive
-                }
+// Measures every asset without a stored content hash (scans with scan_content_hashing off leave
+// changed folders unhashed). Folders are walked one at a time under the disk stat limiter and
+// committed every HASH_BATCH_SIZE assets, so an interrupted run resumes where it stopped.
+#[command]
+async fn compute_missing_hashes(operation_id: Option<String>, app_handle: AppHandle) -> CmdResult<usize> {
+    let operation_id = operation_id.unwrap_or_else(new_operation_id);
+    run_disk_stat_task(app_handle, move |handle| missing_hashes_task(&handle, &operation_id)).await
+}
+
+fn missing_hashes_task(app_handle: &AppHandle, operation_id: &str) -> CmdResult<usize> {
+    let db_state = app_handle.state::<DbState>();
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    let unhashed: Vec<(i64, String)> = {
+        let conn = db_state.conn()?;
+        let mut stmt = conn.prepare("SELECT id, folder_name FROM active_assets WHERE content_hash IS NULL ORDER BY id")
+            .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?.replace("\\", "/"))))
+            .map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
+    };
+    let total = unhashed.len();
+    println!("[compute_missing_hashes] {} assets without a stored hash", total);
+
+    let mut hashed_count = 0;
+    let mut processed = 0;
+    for batch in unhashed.chunks(HASH_BATCH_SIZE) {
+        // Folders missing on disk stay unhashed until a scan prunes them
+        let measured: Vec<(i64, FolderMeasurement)> = batch.iter()
+            .filter_map(|(asset_id, clean_relative_path)| {
+                let (_, current_relative_path) = resolve_asset_disk_state(&base_mods_path, clean_relative_path)?;
+                Some((*asset_id, measure_folder(&base_mods_path.join(current_relative_path))))
+            })
+            .collect();
+
+        let mut conn = db_state.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+        {
+            // A scan running meanwhile may already have stored a fresher hash
+            let mut update_stmt = tx.prepare("UPDATE assets SET size_bytes = ?1, content_hash = ?2 WHERE id = ?3 AND content_hash IS NULL")
+                .map_err(|e| format!("Failed to prepare hash update: {}", e))?;
+            for (asset_id, (size, hash)) in &measured {
+                hashed_count += update_stmt.execute(params![size, hash, asset_id])
+                    .map_err(|e| format!("Failed to store hash for asset {}: {}", asset_id, e))?;
             }
         }
-        _ => return Err(format!("Unsupported archive type for extraction: {:?}", extension)),
-        }
-        Ok(files_extracted_count) // Return count on success
-    })();
+        tx.commit().map_err(|e| format!("Failed to commit hashes: {}", e))?;
+
+        processed += batch.len();
+        app_handle.emit_all(HASH_PROGRESS_EVENT, &ApplyProgress {
+            operation_id: operation_id.to_string(),
+            processed,
+            total,
+            current_asset_id: batch.last().map(|(asset_id, _)| *asset_id),
+            message: format!("Hashed {}/{} assets", processed, total),
+        }).unwrap_or_else(|e| eprintln!("Failed to emit hash progress: {}", e));
+    }
 
-    // Handle extraction result
-    let files_extracted_count = extraction_result.map_err(|e| {
-         fs::remove_dir_all(&final_mod_dest_path).ok();
-         e
-    })?;
-    println!("[import_archive] Extracted {} files.", files_extracted_count);
+    println!("[compute_missing_hashes] Stored {} hashes", hashed_count);
+    Ok(hashed_count)
+}
 
-    // --- Handle Preview Image ---
-    let mut image_filename_for_db: Option<String> = None;
-    if let Some(data) = image_data {
-        println!("[import_archive] Handling provided image data ({} bytes)", data.len());
-        let target_image_path = final_mod_dest_path.join(TARGET_IMAGE_FILENAME);
-        match fs::write(&target_image_path, data) {
-            Ok(_) => {
-                println!("[import_archive] Image data written successfully to '{}'.", target_image_path.display());
-                image_filename_for_db = Some(TARGET_IMAGE_FILENAME.to_string());
-            }
-            Err(e) => {
-                eprintln!("[import_archive] ERROR: Failed to save pasted image data to '{}': {}. Preview will be missing.", target_image_path.display(), e);
+// Groups assets whose folders have identical file sets (see measure_folder). Hashes come from
+// the last scan or compute_missing_hashes; anything still unhashed is measured here first.
+// Deleting one copy is left to the caller via delete_asset.
+#[command]
+async fn find_duplicate_assets(db_state: State<'_, DbState>) -> CmdResult<Vec<DuplicateAssetGroup>> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+
+    let unhashed: Vec<(i64, String)> = {
+        let conn = db_state.conn()?;
+        let mut stmt = conn.prepare("SELECT id, folder_name FROM active_assets WHERE content_hash IS NULL")
+            .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?.replace("\\", "/"))))
+            .map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
+    };
+
+    if !unhashed.is_empty() {
+        println!("[find_duplicate_assets] Hashing {} assets without a stored hash...", unhashed.len());
+        let measure_task = async_runtime::spawn_blocking(move || {
+            unhashed.par_iter()
+                .filter_map(|(asset_id, clean_relative_path)| {
+                    let (_, current_relative_path) = resolve_asset_disk_state(&base_mods_path, clean_relative_path)?;
+                    Some((*asset_id, measure_folder(&base_mods_path.join(current_relative_path))))
+                })
+                .collect::<Vec<_>>()
+        });
+        let measured = measure_task.await.map_err(|e| format!("Hashing task failed: {}", e))?;
+
+        let mut conn = db_state.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+        {
+            let mut update_stmt = tx.prepare("UPDATE assets SET size_bytes = ?1, content_hash = ?2 WHERE id = ?3")
+                .map_err(|e| format!("Failed to prepare hash update: {}", e))?;
+            for (asset_id, (size, hash)) in &measured {
+                update_stmt.execute(params![size, hash, asset_id])
+                    .map_err(|e| format!("Failed to store hash for asset {}: {}", asset_id, e))?;
             }
         }
+        tx.commit().map_err(|e| format!("Failed to commit hashes: {}", e))?;
     }
-    else if let Some(user_preview_path_str) = selected_preview_absolute_path {
-        println!("[import_archive] Handling selected image file path: {}", user_preview_path_str);
-        let source_path = PathBuf::from(&user_preview_path_str);
-        if source_path.is_file() {
-            let target_image_path = final_mod_dest_path.join(TARGET_IMAGE_FILENAME);
-            match fs::copy(&source_path, &target_image_path) {
-                Ok(_) => {
-                    println!("[import_archive] Image file copied successfully to '{}'.", target_image_path.display());
-                    image_filename_for_db = Some(TARGET_IMAGE_FILENAME.to_string());
-                }
-                Err(e) => {
-                    eprintln!("[import_archive] ERROR: Failed copy user preview to '{}': {}. Preview will be missing.", target_image_path.display(), e);
-                }
-            }
-        } else {
-             println!("[import_archive] Warning: Selected preview file '{}' not found, skipping.", user_preview_path_str);
+
+    let conn = db_state.conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT a.content_hash, a.id, a.name, a.folder_name, e.slug, a.size_bytes
+         FROM active_assets a JOIN entities e ON a.entity_id = e.id
+         WHERE a.content_hash IN (SELECT content_hash FROM active_assets WHERE content_hash IS NOT NULL GROUP BY content_hash HAVING COUNT(*) > 1)
+         ORDER BY a.content_hash, a.folder_name"
+    ).map_err(|e| format!("Failed to prepare duplicate query: {}", e))?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, DuplicateAssetEntry {
+        asset_id: row.get(1)?,
+        name: row.get(2)?,
+        folder_name: row.get::<_, String>(3)?.replace("\\", "/"),
+        entity_slug: row.get(4)?,
+        size_bytes: row.get(5)?,
+    })))
+        .map_err(|e| format!("Failed to query duplicates: {}", e))?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| format!("Failed to read duplicates: {}", e))?;
+
+    let mut groups: Vec<DuplicateAssetGroup> = Vec::new();
+    for (content_hash, entry) in rows {
+        match groups.last_mut() {
+            Some(group) if group.content_hash == content_hash => group.assets.push(entry),
+            _ => groups.push(DuplicateAssetGroup { content_hash, assets: vec![entry] }),
         }
     }
-    else {
-        let potential_extracted_image_path = final_mod_dest_path.join(TARGET_IMAGE_FILENAME);
-        if potential_extracted_image_path.is_file() {
-            println!("[import_archive] Using extracted {} as preview.", TARGET_IMAGE_FILENAME);
-            image_filename_for_db = Some(TARGET_IMAGE_FILENAME.to_string());
+    println!("[find_duplicate_assets] Found {} duplicate groups", groups.len());
+    Ok(groups)
+}
+
+// Compares the assets table with the mod folders on disk without changing anything.
+// Mod folders are detected like the scanner does (top-level INI, children not descended into),
+// and staged mods count as present at their clean path.
+#[command]
+async fn audit_library(db_state: State<'_, DbState>) -> CmdResult<LibraryAudit> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    if !base_mods_path.is_dir() {
+        return Err(format!("Mods directory path is not a valid directory: {}", base_mods_path.display()));
+    }
+
+    let assets: Vec<(i64, String, String)> = {
+        let conn = db_state.conn()?;
+        let mut stmt = conn.prepare("SELECT id, name, folder_name FROM active_assets ORDER BY folder_name")
+            .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?.replace("\\", "/"))))
+            .map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
+    }; // Lock released while walking the mods folder
+
+    let walk_root = base_mods_path.clone();
+    let disk_task = async_runtime::spawn_blocking(move || {
+        let mut disk_paths: Vec<String> = Vec::new();
+        let mut walker = WalkDir::new(&walk_root).min_depth(1).into_iter();
+        while let Some(entry_result) = walker.next() {
+            let entry = match entry_result { Ok(entry) => entry, Err(_) => continue };
+            if !entry.file_type().is_dir() { continue; }
+            // Staged mods (symlink mode) are listed by their clean path; the staging folder itself
+            // is only descended into
+            let rel = match mods_relative_path(&walk_root, entry.path()) { Some((rel, _)) => rel, None => continue };
+            if rel.as_os_str().is_empty() || !has_ini_file(&entry.path().to_path_buf()) { continue; }
+            walker.skip_current_dir();
+            let rel_str = rel.to_string_lossy().replace("\\", "/");
+            disk_paths.push(clean_folder_relative_path(&rel_str));
+        }
+        disk_paths
+    });
+    let mut disk_paths = disk_task.await.map_err(|e| format!("Library audit task failed: {}", e))?;
+    disk_paths.sort();
+    disk_paths.dedup();
+
+    let disk_exact: HashSet<&str> = disk_paths.iter().map(String::as_str).collect();
+    let disk_by_lower: HashMap<String, &str> = disk_paths.iter().map(|p| (p.to_lowercase(), p.as_str())).collect();
+
+    let mut audit = LibraryAudit { missing_on_disk: Vec::new(), untracked_folders: Vec::new(), case_mismatches: Vec::new() };
+    let mut matched_lower: HashSet<String> = HashSet::new();
+    for (asset_id, name, folder_name) in assets {
+        let lower = folder_name.to_lowercase();
+        if disk_exact.contains(folder_name.as_str()) {
+            matched_lower.insert(lower);
+        } else if let Some(disk_path) = disk_by_lower.get(&lower) {
+            audit.case_mismatches.push(AuditCaseMismatch { asset_id, name, stored_path: folder_name, disk_path: disk_path.to_string() });
+            matched_lower.insert(lower);
         } else {
-             println!("[import_archive] No pasted, selected, or extracted preview found.");
+            audit.missing_on_disk.push(AuditAssetEntry { asset_id, name, folder_name });
         }
     }
-    println!("[import_archive] Image handling complete. Filename to save in DB: {:?}", image_filename_for_db);
-
-    // --- Add to Database ---
-    let relative_path_for_db = Path::new(&target_category_slug).join(&target_entity_slug).join(&target_mod_folder_name);
-    let relative_path_for_db_str = relative_path_for_db.to_string_lossy().replace("\\", "/");
+    audit.untracked_folders = disk_paths.iter()
+        .filter(|p| !matched_lower.contains(&p.to_lowercase()))
+        .cloned()
+        .collect();
+    audit.untracked_folders.sort();
 
-    let check_existing: Option<i64> = tx.query_row(
-        "SELECT id FROM assets WHERE entity_id = ?1 AND folder_name = ?2",
-        params![target_entity_id, relative_path_for_db_str], |row| row.get(0)
-    ).optional().map_err(|e| format!("DB error check existing import '{}': {}", relative_path_for_db_str, e))?;
+    println!("[audit_library] Missing on disk: {}, untracked folders: {}, case mismatches: {}",
+        audit.missing_on_disk.len(), audit.untracked_folders.len(), audit.case_mismatches.len());
+    Ok(audit)
+}
 
-    if check_existing.is_some() {
-        fs::remove_dir_all(&final_mod_dest_path).ok();
-        return Err(format!("Database entry already exists for '{}'. Aborting.", relative_path_for_db_str));
+// Active assets whose folder exists neither enabled nor disabled. Refuses when the mods folder is
+// missing or too few assets were found, since an unmounted drive looks like a deleted library.
+fn prune_candidates(conn: &Connection, base_mods_path: &Path, prune_guard: PruneGuard) -> Result<Vec<ScanPreviewEntry>, String> {
+    if !base_mods_path.is_dir() {
+        return Err(format!("Mods directory path is not a valid directory: {}. Nothing can be pruned.", base_mods_path.display()));
+    }
+    let mut stmt = conn.prepare("SELECT a.id, a.name, a.folder_name, e.slug FROM active_assets a JOIN entities e ON a.entity_id = e.id ORDER BY e.slug, a.name")
+        .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+    let candidates = stmt.query_map([], |row| Ok(ScanPreviewEntry {
+        asset_id: Some(row.get(0)?),
+        name: row.get(1)?,
+        folder_name: row.get::<_, String>(2)?.replace("\\", "/"),
+        entity_slug: row.get(3)?,
+    })).map_err(|e| format!("Failed to query assets: {}", e))?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| format!("Failed to read asset rows: {}", e))?;
+    let known_count = candidates.len();
+    let candidates = candidates.into_iter()
+        .filter(|entry| resolve_asset_disk_state(base_mods_path, &entry.folder_name).is_none())
+        .collect::<Vec<_>>();
+    if !candidates.is_empty() && !prune_guard.force_prune {
+        let found_ratio = (known_count - candidates.len()) as f64 / known_count as f64;
+        if found_ratio < prune_guard.min_found_ratio {
+            return Err(format!(
+                "Only {} of {} known mods were found on disk ({:.0}% < {:.0}%). Possible unmounted drive or wrong mods folder; force pruning if they were really removed.",
+                known_count - candidates.len(), known_count, found_ratio * 100.0, prune_guard.min_found_ratio * 100.0
+            ));
+        }
     }
+    Ok(candidates)
+}
 
-    println!("[import_archive] Adding asset to DB: entity_id={}, name={}, path={}, image={:?}", target_entity_id, mod_name, relative_path_for_db_str, image_filename_for_db);
-    tx.execute(
-        "INSERT INTO assets (entity_id, name, description, folder_name, image_filename, author, category_tag) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![
-            target_entity_id, mod_name.trim(),
-            description, relative_path_for_db_str,
-            image_filename_for_db, author, category_tag
-        ]
-    ).map_err(|e| {
-        fs::remove_dir_all(&final_mod_dest_path).ok();
-        format!("Failed add imported mod to database: {}", e)
-    })?;
+// Assets a scan would prune. Nothing is re-deduced, so this is cheap enough to run on startup.
+#[command]
+fn get_prune_candidates(
+    min_found_ratio: Option<f64>,
+    force_prune: Option<bool>,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>
+) -> CmdResult<Vec<ScanPreviewEntry>> {
+    let base_mods_path = cached_mods_base_path(&config_state, &db_state).map_err(|e| e.to_string())?;
+    let conn = db_state.conn()?;
+    let candidates = prune_candidates(&conn, &base_mods_path, PruneGuard::from_options(min_found_ratio, force_prune))?;
+    println!("[get_prune_candidates] {} assets missing from disk", candidates.len());
+    Ok(candidates)
+}
 
-    let new_asset_id = tx.last_insert_rowid();
-    println!("[import_archive] Asset inserted with ID: {}", new_asset_id);
+// Deletes the rows of assets the user confirmed from get_prune_candidates. The candidates are
+// computed again under the same guard, so a folder that reappeared in the meantime is kept.
+#[command]
+fn prune_missing_assets(
+    asset_ids: Vec<i64>,
+    min_found_ratio: Option<f64>,
+    force_prune: Option<bool>,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>
+) -> CmdResult<usize> {
+    println!("[prune_missing_assets] Pruning {} assets", asset_ids.len());
+    let base_mods_path = cached_mods_base_path(&config_state, &db_state).map_err(|e| e.to_string())?;
+    let mut conn = db_state.conn()?;
+    ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+    let missing_ids: HashSet<i64> = prune_candidates(&conn, &base_mods_path, PruneGuard::from_options(min_found_ratio, force_prune))?
+        .into_iter()
+        .filter_map(|entry| entry.asset_id)
+        .collect();
 
-    // --- Add to Presets ---
-    if let Some(ids) = preset_ids {
-        if !ids.is_empty() {
-            println!("[import_archive] Adding new asset {} to presets: {:?}", new_asset_id, ids);
-            let mut insert_preset_stmt = tx.prepare_cached(
-                "INSERT OR IGNORE INTO preset_assets (preset_id, asset_id, is_enabled) VALUES (?1, ?2, ?3)"
-            ).map_err(|e| format!("Failed prepare preset asset insert: {}", e))?;
-            for preset_id in ids {
-                 insert_preset_stmt.execute(params![preset_id, new_asset_id, 1]) // Default to enabled state 1 when importing
-                    .map_err(|e| format!("Failed insert new asset {} into preset {}: {}", new_asset_id, preset_id, e))?;
-            }
-            println!("[import_archive] Finished adding asset {} to presets.", new_asset_id);
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut pruned_count = 0;
+    for asset_id in asset_ids {
+        if !missing_ids.contains(&asset_id) {
+            println!("[prune_missing_assets] Asset {} is not missing from disk, keeping it", asset_id);
+            continue;
         }
+        pruned_count += tx.execute("DELETE FROM assets WHERE id = ?1", params![asset_id]).map_err(|e| e.to_string())?;
     }
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
-    // --- Commit Transaction ---
-    tx.commit().map_err(|e| {
-        fs::remove_dir_all(&final_mod_dest_path).ok();
-        format!("Failed to commit import transaction: {}", e)
-    })?;
+    println!("[prune_missing_assets] Pruned {} asset entries", pruned_count);
+    Ok(pruned_count)
+}
 
-   println!("[import_archive] Import successful for '{}'", mod_name);
-   Ok(())
+// Folders under the mods folder that no asset row points to and that contain no INI at any
+// depth: DISABLED_ leftovers of half-failed renames and folders at mod level that lost their INI.
+#[command]
+async fn find_orphan_folders(db_state: State<'_, DbState>) -> CmdResult<Vec<OrphanFolder>> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    if !base_mods_path.is_dir() {
+        return Err(format!("Mods directory path is not a valid directory: {}", base_mods_path.display()));
+    }
+    let tracked_paths: HashSet<String> = {
+        let conn = db_state.conn()?;
+        load_tracked_folder_names(&conn)?
+    }; // Lock released while walking the mods folder
+
+    let walk_root = base_mods_path.clone();
+    let orphan_task = async_runtime::spawn_blocking(move || {
+        let mut orphans: Vec<OrphanFolder> = Vec::new();
+        let mut walker = WalkDir::new(&walk_root).min_depth(1).into_iter();
+        while let Some(entry_result) = walker.next() {
+            let entry = match entry_result { Ok(entry) => entry, Err(_) => continue };
+            if !entry.file_type().is_dir() { continue; }
+            let path = entry.path().to_path_buf();
+            // Mod folders belong to the scanner; their children are variants
+            if has_ini_file(&path) {
+                walker.skip_current_dir();
+                continue;
+            }
+            let rel = match path.strip_prefix(&walk_root) {
+                Ok(rel) => rel.to_string_lossy().replace("\\", "/"),
+                Err(_) => continue,
+            };
+            // A tracked mod that lost its INI is reported by audit_library, not here
+            if tracked_paths.contains(&clean_folder_relative_path(&rel).to_lowercase()) {
+                walker.skip_current_dir();
+                continue;
+            }
+            let is_disabled = is_disabled_folder_name(&entry.file_name().to_string_lossy());
+            // category/entity/mod: anything shallower is library structure, not a mod
+            if !is_disabled && entry.depth() < 3 { continue; }
+            if is_orphan_folder(&path, &rel, &tracked_paths) {
+                orphans.push(OrphanFolder { size_bytes: folder_size_bytes(&path), path: rel });
+                walker.skip_current_dir();
+            }
+        }
+        orphans
+    });
+    let orphans = orphan_task.await.map_err(|e| format!("Orphan folder search failed: {}", e))?;
+    println!("[find_orphan_folders] Found {} orphaned folders", orphans.len());
+    Ok(orphans)
 }
 
+// Moves an orphaned folder (path relative to the mods folder, as returned by find_orphan_folders)
+// to the trash. Refuses anything outside the mods folder or anything that is not orphaned.
 #[command]
-fn create_preset(name: String, db_state: State<DbState>) -> CmdResult<Preset> {
-    let name = name.trim();
-    if name.is_empty() {
-        return Err("Preset name cannot be empty.".to_string());
+fn delete_orphan_folder(path: String, db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<()> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let rel_norm = path.replace("\\", "/").trim_matches('/').to_string();
+    let rel_path = PathBuf::from(&rel_norm);
+    if rel_norm.is_empty() || rel_path.is_absolute()
+        || rel_path.components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+        return Err(format!("Invalid relative path: '{}'", path));
+    }
+    let full_path = base_mods_path.join(&rel_path);
+    if !full_path.is_dir() {
+        return Err(format!("Folder not found: {}", full_path.display()));
+    }
+    // Symlinks could still point outside the mods folder
+    let canonical_base = base_mods_path.canonicalize().map_err(|e| format!("Failed to resolve mods folder: {}", e))?;
+    let canonical_path = full_path.canonicalize().map_err(|e| format!("Failed to resolve '{}': {}", full_path.display(), e))?;
+    if canonical_path == canonical_base || !path_is_same_or_within(&canonical_path, &canonical_base) {
+        return Err(format!("Refusing to delete '{}': not inside the mods folder.", full_path.display()));
     }
-    println!("[create_preset] Attempting to create preset: '{}'", name);
 
-    let base_mods_path = get_mods_base_path_from_settings(&db_state)
-        .map_err(|e| format!("Cannot create preset: {}", e))?;
+    let tracked_paths = {
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+        load_tracked_folder_names(&conn)?
+    };
+    if !is_orphan_folder(&full_path, &rel_norm, &tracked_paths) {
+        return Err(format!("Folder '{}' is not orphaned (it contains an INI or belongs to a mod).", rel_norm));
+    }
 
-    let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    let mut conn = conn_guard;
+    let trash_dir = get_trash_dir(&app_handle).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&trash_dir).map_err(|e| format!("Failed to create trash folder '{}': {}", trash_dir.display(), e))?;
+    let folder_name = full_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let trash_path = trash_dir.join(format!("{}_{}", unix_timestamp_millis(), folder_name));
+    println!("[delete_orphan_folder] Moving folder to trash: {} -> {}", full_path.display(), trash_path.display());
+    // move_dir copies across drives, so the folder is never deleted permanently
+    move_dir(&extended_length_path(&full_path), &extended_length_path(&trash_path))
+        .map_err(|e| format!("Failed to move folder '{}' to the trash, nothing was deleted: {}", full_path.display(), e))?;
+    Ok(())
+}
 
-    // Use a block scope for the transaction
-    let preset_id = { // Start block scope for tx
-        let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+// Lower-cased clean relative paths of all active assets
+fn load_tracked_folder_names(conn: &Connection) -> CmdResult<HashSet<String>> {
+    let mut stmt = conn.prepare("SELECT folder_name FROM active_assets")
+        .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+    let rows = stmt.query_map([], |row| Ok(row.get::<_, String>(0)?.replace("\\", "/").to_lowercase()))
+        .map_err(|e| format!("Failed to query assets: {}", e))?;
+    rows.collect::<SqlResult<HashSet<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))
+}
 
-        // Check if name exists
-        let existing_count: i64 = tx.query_row(
-            "SELECT COUNT(*) FROM presets WHERE LOWER(name) = LOWER(?1)",
-            params![name],
-            |row| row.get(0),
-        ).map_err(|e| format!("DB error checking preset name: {}", e))?;
+// An orphan has no asset row (in either enabled or disabled form) and no INI file at any depth
+fn is_orphan_folder(path: &Path, relative_path: &str, tracked_paths: &HashSet<String>) -> bool {
+    if tracked_paths.contains(&clean_folder_relative_path(relative_path).to_lowercase()) { return false; }
+    !WalkDir::new(path).into_iter()
+        .filter_map(|e| e.ok())
+        .any(|entry| entry.file_type().is_file() && entry.path().extension().map_or(false, |ext| ext.eq_ignore_ascii_case("ini")))
+}
 
-        if existing_count > 0 {
-            // Rollback happens automatically when tx is dropped on error return
-            return Err(format!("Preset name '{}' already exists.", name));
-        }
+// Points one asset at a different folder (relative to the mods folder, enabled or disabled form)
+// without a rescan. The folder must exist and must not belong to another asset.
+#[command]
+fn repair_asset_path(asset_id: i64, new_relative_path: String, db_state: State<DbState>) -> CmdResult<String> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
 
-        // Insert new preset
-        tx.execute("INSERT INTO presets (name) VALUES (?1)", params![name])
-            .map_err(|e| format!("Failed to insert preset: {}", e))?;
-        let new_preset_id = tx.last_insert_rowid();
-        println!("[create_preset] Inserted preset with ID: {}", new_preset_id);
+    let rel_norm = new_relative_path.replace("\\", "/").trim_matches('/').to_string();
+    let rel_path = PathBuf::from(&rel_norm);
+    if rel_norm.is_empty() || rel_path.is_absolute()
+        || rel_path.components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+        return Err(format!("Invalid relative path: '{}'", new_relative_path));
+    }
+    // Store the clean form, as the scanner does
+    let clean_relative_path = clean_folder_relative_path(&rel_norm);
+    let is_enabled = match resolve_asset_disk_state(&base_mods_path, &clean_relative_path) {
+        Some((is_enabled, _)) => is_enabled,
+        None => return Err(format!("Folder '{}' (or its disabled variant) does not exist in the mods folder.", clean_relative_path)),
+    };
 
-        // Use another block scope for the statement and iteration
-        { // Start block scope for stmt
-            let mut stmt = tx.prepare("SELECT id, folder_name FROM assets")
-                .map_err(|e| format!("Failed to prepare asset fetch: {}", e))?;
-            let asset_iter_result = stmt.query_map([], |row| {
-                Ok((
-                    row.get::<_, i64>(0)?,
-                    row.get::<_, String>(1)?.replace("\\", "/"),
-                ))
-            });
+    let conn = db_state.conn()?;
+    let current = get_asset_location_info(&conn, asset_id).map_err(|e| e.to_string())?;
+    let owner: Option<i64> = conn.query_row(
+        "SELECT id FROM active_assets WHERE folder_name = ?1 AND id != ?2",
+        params![clean_relative_path, asset_id], |row| row.get(0)
+    ).optional().map_err(|e| format!("Failed to check existing assets: {}", e))?;
+    if let Some(other_id) = owner {
+        return Err(format!("Folder '{}' already belongs to asset {}.", clean_relative_path, other_id));
+    }
 
-            match asset_iter_result {
-                Ok(asset_iter) => {
-                    for asset_result in asset_iter {
-                        match asset_result {
-                            Ok((asset_id, clean_relative_path_str)) => {
-                                let clean_relative_path = PathBuf::from(&clean_relative_path_str);
-                                let filename_osstr = clean_relative_path.file_name().unwrap_or_default();
-                                let filename_str = filename_osstr.to_string_lossy();
-                                if filename_str.is_empty() { continue; }
+    conn.execute("UPDATE assets SET folder_name = ?1, is_enabled = ?2 WHERE id = ?3", params![clean_relative_path, is_enabled, asset_id])
+        .map_err(|e| format!("Failed to update asset path: {}", e))?;
+    println!("[repair_asset_path] Asset {}: '{}' -> '{}'", asset_id, current.clean_relative_path, clean_relative_path);
+    Ok(clean_relative_path)
+}
 
-                                let disabled_filename = format!("{}{}", DISABLED_PREFIX, filename_str);
-                                let relative_parent_path = clean_relative_path.parent();
+// Targeted refresh for a handful of entity cards (e.g. pinned entities, or after a preset apply or
+// bulk toggle). Disk checks share the disk stat limiter with the category view.
+#[command]
+async fn get_entity_counts(entity_slugs: Vec<String>, app_handle: AppHandle) -> CmdResult<Vec<EntityCounts>> {
+    run_disk_stat_task(app_handle, move |handle| {
+        entity_counts(&entity_slugs, &handle.state::<DbState>(), &handle.state::<CachedConfigState>())
+    }).await
+}
 
-                                let full_path_if_enabled = base_mods_path.join(&clean_relative_path);
-                                let full_path_if_disabled = match relative_parent_path {
-                                    Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_filename),
-                                    _ => base_mods_path.join(&disabled_filename),
-                                };
+fn entity_counts(entity_slugs: &[String], db_state: &DbState, config_state: &CachedConfigState) -> CmdResult<Vec<EntityCounts>> {
+    println!("[get_entity_counts] Fetching counts for {} entities", entity_slugs.len());
+    if entity_slugs.is_empty() {
+        return Ok(Vec::new());
+    }
 
-                                let is_currently_enabled = if full_path_if_enabled.is_dir() { 1 }
-                                                            else if full_path_if_disabled.is_dir() { 0 }
-                                                            else {
-                                                                println!("[create_preset] Warning: Asset ID {} folder not found on disk during preset save (path: {}). Skipping.", asset_id, clean_relative_path_str);
-                                                                continue;
-                                                            };
-
-                                tx.execute(
-                                    "INSERT INTO preset_assets (preset_id, asset_id, is_enabled) VALUES (?1, ?2, ?3)",
-                                    params![new_preset_id, asset_id, is_currently_enabled],
-                                ).map_err(|e| format!("Failed to save state for asset {}: {}", asset_id, e))?;
-                            }
-                            Err(e) => return Err(format!("Error fetching asset row: {}", e)), // Rollbacks on return
-                        }
-                    }
-                }
-                Err(e) => return Err(format!("Error preparing asset iterator: {}", e)), // Rollbacks on return
-            }
-        } // End block scope for stmt - stmt is dropped here, releasing borrow on tx
+    let base_mods_path = match cached_mods_base_path(config_state, db_state) {
+        Ok(p) => Some(p),
+        Err(_) => {
+            println!("[get_entity_counts] Mods folder not set. Enabled counts will be zero.");
+            None
+        }
+    };
 
-        // Commit the transaction
-        tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    // Fetch all folder paths for the requested entities, one query per chunk of slugs.
+    // Duplicates are dropped first so a slug split across chunks isn't counted twice.
+    let mut seen_slugs = HashSet::new();
+    let unique_slugs: Vec<&String> = entity_slugs.iter().filter(|slug| seen_slugs.insert(*slug)).collect();
+    let mut entity_folders: Vec<(String, Option<String>)> = Vec::new();
+    {
+        let conn = db_state.conn()?;
+        for slug_chunk in unique_slugs.chunks(SQL_IN_LIST_CHUNK_SIZE) {
+            let placeholders = slug_chunk.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+            let sql = format!(
+                "SELECT e.slug, a.folder_name FROM entities e LEFT JOIN active_assets a ON a.entity_id = e.id WHERE e.slug IN ({})",
+                placeholders
+            );
+            let mut stmt = conn.prepare(&sql)
+                .map_err(|e| format!("Failed to prepare entity counts query: {}", e))?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(slug_chunk.iter()), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+            }).map_err(|e| format!("Failed to query entity counts: {}", e))?;
+            for row in rows {
+                entity_folders.push(row.map_err(|e| format!("Failed to read entity counts rows: {}", e))?);
+            }
+        }
+    } // Lock released before disk checks
+
+    let mut counts_by_slug: HashMap<String, (i64, i64)> = HashMap::new();
+    for (slug, folder_name_opt) in entity_folders {
+        let counts = counts_by_slug.entry(slug).or_insert((0, 0));
+        let clean_relative_path_str = match folder_name_opt {
+            Some(f) => f.replace("\\", "/"),
+            None => continue, // Entity exists but has no assets
+        };
+        counts.0 += 1;
 
-        new_preset_id // Return the ID from the block
-    }; // End block scope for tx
+        if let Some(base) = &base_mods_path {
+            // Check only enabled state path, same as the category view
+            if base.join(&clean_relative_path_str).is_dir() {
+                counts.1 += 1;
+            }
+        }
+    }
 
-    println!("[create_preset] Preset '{}' created successfully.", name);
+    // Preserve the requested order, skipping unknown slugs
+    let results: Vec<EntityCounts> = entity_slugs.iter()
+        .filter_map(|slug| counts_by_slug.get(slug).map(|(total, enabled)| EntityCounts {
+            slug: slug.clone(),
+            total_mods: *total,
+            enabled_mods: *enabled,
+        }))
+        .collect();
 
-    Ok(Preset { id: preset_id, name: name.to_string(), is_favorite: false })
+    println!("[get_entity_counts] Returning counts for {} entities", results.len());
+    Ok(results)
 }
 
+// Pinning appends the entity to the end of the quick bar; pinning it again keeps its place
+#[command]
+fn pin_entity(entity_slug: String, pinned: bool, db_state: State<DbState>) -> CmdResult<()> {
+    let conn = db_state.conn()?;
+    let entity_id: i64 = conn.query_row("SELECT id FROM entities WHERE slug = ?1", params![entity_slug], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to look up entity '{}': {}", entity_slug, e))?
+        .ok_or_else(|| format!("Entity '{}' not found", entity_slug))?;
+    if pinned {
+        conn.execute(
+            "INSERT OR IGNORE INTO pinned_entities (entity_id, sort_order) SELECT ?1, COALESCE(MAX(sort_order), 0) + 1 FROM pinned_entities",
+            params![entity_id],
+        ).map_err(|e| format!("Failed to pin entity '{}': {}", entity_slug, e))?;
+    } else {
+        conn.execute("DELETE FROM pinned_entities WHERE entity_id = ?1", params![entity_id])
+            .map_err(|e| format!("Failed to unpin entity '{}': {}", entity_slug, e))?;
+    }
+    println!("[pin_entity] Entity '{}' pinned: {}", entity_slug, pinned);
+    Ok(())
+}
 
+// Quick bar entities with their counts. Disk checks share the disk stat limiter with the category view.
 #[command]
-fn get_presets(db_state: State<DbState>) -> CmdResult<Vec<Preset>> {
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    let mut stmt = conn.prepare("SELECT id, name, is_favorite FROM presets ORDER BY name ASC")
-        .map_err(|e| e.to_string())?;
-    let preset_iter = stmt.query_map([], |row| {
-        Ok(Preset {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            is_favorite: row.get::<_, i64>(2)? == 1,
-        })
-    }).map_err(|e| e.to_string())?;
-    preset_iter.collect::<SqlResult<Vec<Preset>>>().map_err(|e| e.to_string())
+async fn get_pinned_entities(app_handle: AppHandle) -> CmdResult<Vec<EntityWithCounts>> {
+    run_disk_stat_task(app_handle, |handle| {
+        let db_state = handle.state::<DbState>();
+        let mut entities = {
+            let conn = db_state.conn()?;
+            let mut stmt = conn.prepare(
+                "SELECT e.id, e.category_id, e.name, e.slug, e.details, e.base_image
+                 FROM pinned_entities p JOIN entities e ON e.id = p.entity_id
+                 ORDER BY p.sort_order, e.name"
+            ).map_err(|e| format!("Failed to prepare pinned entity query: {}", e))?;
+            let rows = stmt.query_map([], |row| Ok(EntityWithCounts {
+                id: row.get(0)?,
+                category_id: row.get(1)?,
+                name: row.get(2)?,
+                slug: row.get(3)?,
+                details: row.get(4)?,
+                base_image: row.get(5)?,
+                total_mods: 0,
+                enabled_mods: 0,
+            })).map_err(|e| format!("Failed to query pinned entities: {}", e))?;
+            rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read pinned entities: {}", e))?
+        }; // Lock released before disk checks
+
+        let slugs: Vec<String> = entities.iter().map(|entity| entity.slug.clone()).collect();
+        let counts_by_slug: HashMap<String, EntityCounts> = entity_counts(&slugs, &db_state, &handle.state::<CachedConfigState>())?
+            .into_iter()
+            .map(|counts| (counts.slug.clone(), counts))
+            .collect();
+        for entity in entities.iter_mut() {
+            if let Some(counts) = counts_by_slug.get(&entity.slug) {
+                entity.total_mods = counts.total_mods;
+                entity.enabled_mods = counts.enabled_mods;
+            }
+        }
+
+        println!("[get_pinned_entities] Returning {} pinned entities", entities.len());
+        Ok(entities)
+    }).await
 }
 
+// Persists the quick bar order. Pinned entities missing from `entity_slugs` keep their relative
+// order after the listed ones.
 #[command]
-fn get_favorite_presets(db_state: State<DbState>) -> CmdResult<Vec<Preset>> {
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    let mut stmt = conn.prepare(
-        "SELECT id, name, is_favorite FROM presets WHERE is_favorite = 1 ORDER BY name ASC LIMIT 3"
-    ).map_err(|e| e.to_string())?;
-    let preset_iter = stmt.query_map([], |row| {
-        Ok(Preset {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            is_favorite: row.get::<_, i64>(2)? == 1,
-        })
-    }).map_err(|e| e.to_string())?;
-    preset_iter.collect::<SqlResult<Vec<Preset>>>().map_err(|e| e.to_string())
+fn reorder_pinned_entities(entity_slugs: Vec<String>, db_state: State<DbState>) -> CmdResult<()> {
+    let mut conn = db_state.conn()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let current_order: Vec<(i64, String)> = {
+        let mut stmt = tx.prepare("SELECT p.entity_id, e.slug FROM pinned_entities p JOIN entities e ON e.id = p.entity_id ORDER BY p.sort_order, e.name")
+            .map_err(|e| format!("Failed to prepare pinned entity query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to query pinned entities: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read pinned entities: {}", e))?
+    };
+    let id_by_slug: HashMap<&str, i64> = current_order.iter().map(|(id, slug)| (slug.as_str(), *id)).collect();
+    let mut seen = HashSet::new();
+    for slug in &entity_slugs {
+        let entity_id = id_by_slug.get(slug.as_str()).ok_or_else(|| format!("Entity '{}' is not pinned", slug))?;
+        if !seen.insert(*entity_id) {
+            return Err(format!("Entity '{}' is listed more than once", slug));
+        }
+    }
+
+    let final_order = entity_slugs.iter().map(|slug| id_by_slug[slug.as_str()])
+        .chain(current_order.iter().map(|(id, _)| *id).filter(|id| !seen.contains(id)))
+        .collect::<Vec<_>>();
+    {
+        let mut update_stmt = tx.prepare("UPDATE pinned_entities SET sort_order = ?1 WHERE entity_id = ?2")
+            .map_err(|e| format!("Failed to prepare sort update: {}", e))?;
+        for (position, entity_id) in final_order.into_iter().enumerate() {
+            update_stmt.execute(params![position as i64 + 1, entity_id])
+                .map_err(|e| format!("Failed to update sort order for entity {}: {}", entity_id, e))?;
+        }
+    }
+    tx.commit().map_err(|e| format!("Failed to commit new order: {}", e))?;
+    println!("[reorder_pinned_entities] Quick bar reordered ({} entities).", entity_slugs.len());
+    Ok(())
 }
 
 #[command]
-async fn apply_preset(preset_id: i64, db_state: State<'_, DbState>, app_handle: AppHandle) -> CmdResult<()> {
-    println!("[apply_preset] Applying preset ID: {}", preset_id);
-
-    // Clone app_handle for potential use in error emission later
-    let app_handle_clone = app_handle.clone();
+fn overwrite_preset(preset_id: i64, db_state: State<DbState>) -> CmdResult<()> {
+    println!("[overwrite_preset] Attempting to overwrite preset ID: {}", preset_id);
 
-    // --- Get base path first ---
     let base_mods_path = get_mods_base_path_from_settings(&db_state)
-        .map_err(|e| format!("Cannot apply preset: {}", e))?;
+        .map_err(|e| format!("Cannot overwrite preset (failed to get mods path): {}", e))?;
 
-    // --- Fetch preset assets ---
-    let preset_assets_to_apply = { // Use block scope for connection lock
-        let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-        let mut stmt = conn.prepare(
-            "SELECT pa.asset_id, pa.is_enabled, a.folder_name, a.name
-             FROM preset_assets pa
-             JOIN assets a ON pa.asset_id = a.id
-             WHERE pa.preset_id = ?1"
-        ).map_err(|e| format!("Failed to prepare fetch for preset assets: {}", e))?;
-
-        let preset_assets_iter_result = stmt.query_map(params![preset_id], |row| {
-            Ok((
-                row.get::<_, i64>(0)?,                   // asset_id
-                row.get::<_, i64>(1)? == 1,              // desired_is_enabled (bool)
-                row.get::<_, String>(2)?.replace("\\", "/"), // clean_relative_path
-                row.get::<_, String>(3)?,               // asset_name
-            ))
-        });
+    let conn_guard = db_state.conn()?;
+    let mut conn = conn_guard; // Get mutable access to the pooled connection
 
-        match preset_assets_iter_result {
-             Ok(iter) => iter.collect::<SqlResult<Vec<(i64, bool, String, String)>>>() // Include name
-                              .map_err(|e| format!("Failed to collect preset assets: {}", e))?,
-             Err(e) => return Err(format!("Error preparing preset asset iterator: {}", e)),
-        }
-    }; // Connection lock released here
+    // Use a transaction for atomicity
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
 
-    let total_assets = preset_assets_to_apply.len();
-    println!("[apply_preset] Found {} assets in preset.", total_assets);
+    // 1. Delete existing asset states for this preset
+    println!("[overwrite_preset] Deleting old asset states for preset {}", preset_id);
+    let delete_count = tx.execute("DELETE FROM preset_assets WHERE preset_id = ?1", params![preset_id])
+        .map_err(|e| format!("Failed to delete old preset asset states: {}", e))?;
+    println!("[overwrite_preset] Deleted {} old entries.", delete_count);
 
-    // --- Emit START event ---
-    app_handle.emit_all(PRESET_APPLY_START_EVENT, total_assets).ok();
+    // 2. Save the current disk state of every asset (enabled, disabled or staged)
+    save_current_state_to_preset(&tx, &base_mods_path, preset_id)?;
 
-    let mut processed_count = 0;
-    let mut errors = Vec::new();
+    // 3. Commit the transaction
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
-    for (asset_id, desired_is_enabled, clean_relative_path_str, asset_name) in preset_assets_to_apply {
-        processed_count += 1;
+    println!("[overwrite_preset] Preset ID {} overwritten successfully.", preset_id);
+    Ok(())
+}
 
-        // --- Emit PROGRESS event ---
-        let progress_message = format!("Processing: {} ({}/{})", asset_name, processed_count, total_assets);
-        app_handle.emit_all(PRESET_APPLY_PROGRESS_EVENT, &ApplyProgress {
-            processed: processed_count,
-            total: total_assets,
-            current_asset_id: Some(asset_id),
-            message: progress_message.clone(),
-        }).ok();
-        println!("[apply_preset] {}", progress_message); // Also log to console
+#[command]
+fn get_ini_keybinds(asset_id: i64, db_state: State<DbState>) -> Result<Vec<KeybindInfo>, String> { // CmdResult is Result<T, String>
+    println!("[get_ini_keybinds] COMMAND START for asset ID: {}", asset_id);
 
-        // --- Filesystem logic ---
-        let clean_relative_path = PathBuf::from(&clean_relative_path_str);
-        let filename_osstr = clean_relative_path.file_name().unwrap_or_default();
-        let filename_str = filename_osstr.to_string_lossy();
-        if filename_str.is_empty() {
-            let err_msg = format!("Skipping asset ID {}: Invalid folder name '{}'.", asset_id, clean_relative_path_str);
-            println!("[apply_preset] {}", err_msg);
-            errors.push(err_msg);
-            continue;
-        }
+    let result: Result<Vec<KeybindInfo>, String> = (|| {
+        println!("[get_ini_keybinds] Attempting to acquire DB lock...");
+        let conn_guard = db_state.conn().map_err(|e| {
+            eprintln!("[get_ini_keybinds] ERROR: {}", e);
+            e
+        })?;
+        println!("[get_ini_keybinds] DB lock acquired.");
+        let conn = &*conn_guard; // Dereference the guard to get the connection
 
-        let enabled_filename = filename_str.to_string();
-        let disabled_filename = format!("{}{}", DISABLED_PREFIX, filename_str);
-        let relative_parent_path = clean_relative_path.parent();
+        println!("[get_ini_keybinds] Attempting to get base mods path setting directly...");
+        // --- FIX: Provide arguments to query_row ---
+        let mods_folder_path_str_opt: Option<String> = conn.query_row(
+            "SELECT value FROM settings WHERE key = ?1", // SQL query
+            params![SETTINGS_KEY_MODS_FOLDER],          // Parameters
+            |row| row.get(0),                           // Mapping closure
+        ).optional().map_err(|e| format!("DB error fetching mods folder setting: {}", e))?;
+        // --- End Fix ---
 
-        let construct_full_path = |name: &str| -> PathBuf {
-            match relative_parent_path {
-                Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(name),
-                _ => base_mods_path.join(name),
-            }
-        };
+        let base_mods_path = mods_folder_path_str_opt
+            .map(PathBuf::from)
+            .ok_or_else(|| {
+                eprintln!("[get_ini_keybinds] ERROR: Mods folder path not set in settings.");
+                "Mods folder path not set".to_string()
+            })?;
+        println!("[get_ini_keybinds] Base mods path obtained directly: {}", base_mods_path.display());
 
-        let full_path_if_enabled = construct_full_path(&enabled_filename);
-        let full_path_if_disabled = construct_full_path(&disabled_filename);
 
-        let current_path_on_disk: Option<PathBuf>;
-        let current_is_enabled: bool;
+        println!("[get_ini_keybinds] Calling find_asset_ini_paths...");
+        let ini_paths = find_asset_ini_paths(conn, asset_id, &base_mods_path)
+            .map_err(|e| {
+                eprintln!("[get_ini_keybinds] ERROR from find_asset_ini_paths: {}", e);
+                format!("Error finding INI paths: {}", e)
+            })?;
+        println!("[get_ini_keybinds] find_asset_ini_paths returned {} paths.", ini_paths.len());
 
-        if full_path_if_enabled.is_dir() {
-            current_path_on_disk = Some(full_path_if_enabled);
-            current_is_enabled = true;
-        } else if full_path_if_disabled.is_dir() {
-            current_path_on_disk = Some(full_path_if_disabled);
-            current_is_enabled = false;
-        } else {
-            let err_msg = format!("Skipping asset '{}' (ID {}): Folder not found on disk (path: '{}').", asset_name, asset_id, clean_relative_path_str);
-            println!("[apply_preset] {}", err_msg);
-            errors.push(err_msg);
-            continue;
-        }
+        // --- Release the lock explicitly before file I/O ---
+        drop(conn_guard);
+        println!("[get_ini_keybinds] DB lock released before file parsing.");
 
-        if current_is_enabled != desired_is_enabled {
-            let target_path = if desired_is_enabled {
-                construct_full_path(&enabled_filename)
-            } else {
-                construct_full_path(&disabled_filename)
-            };
-            let source_path = current_path_on_disk.unwrap();
-            println!("[apply_preset] Renaming '{}' -> '{}' (Desired Enabled: {})", source_path.display(), target_path.display(), desired_is_enabled);
-            match fs::rename(&source_path, &target_path) {
-                Ok(_) => { /* Success */ }
-                Err(e) => {
-                     let err_msg = format!("Failed to rename asset '{}' (ID {}): {}", asset_name, asset_id, e);
-                     println!("[apply_preset] Error: {}", err_msg);
-                     errors.push(err_msg);
-                }
-            }
+        if ini_paths.is_empty() {
+             println!("[get_ini_keybinds] No INI files found for asset ID {}", asset_id);
+             return Ok(Vec::new()); // Return empty early if no INIs exist
         }
-        // Optional: Short delay for UI updates if needed
-        // tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-    } // End loop
-
-    println!("[apply_preset] Finished applying preset ID {}. Errors: {}", preset_id, errors.len());
 
-    if errors.is_empty() {
-        // --- Emit COMPLETE event ---
-        let summary = format!("Successfully applied preset ({} mods processed).", total_assets);
-        app_handle.emit_all(PRESET_APPLY_COMPLETE_EVENT, &summary).ok();
-        Ok(())
-    } else {
-        // --- Emit ERROR event ---
-        let combined_errors = errors.join("\n");
-        let error_summary = format!("Preset application completed with {} error(s).", errors.len());
-        // You might want to send the full errors separately or just the summary
-        app_handle_clone.emit_all(PRESET_APPLY_ERROR_EVENT, &error_summary).ok();
-        Err(format!("{}\nDetails:\n{}", error_summary, combined_errors)) // Return error details too
-    }
-}
+        let mut found_keybinds: Vec<KeybindInfo> = Vec::new();
 
+        for ini_path in ini_paths {
+            println!("[get_ini_keybinds] Parsing INI at: {}", ini_path.display());
 
-#[command]
-fn toggle_preset_favorite(preset_id: i64, is_favorite: bool, db_state: State<DbState>) -> CmdResult<()> {
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    let fav_value = if is_favorite { 1 } else { 0 };
-    conn.execute(
-        "UPDATE presets SET is_favorite = ?1 WHERE id = ?2",
-        params![fav_value, preset_id],
-    )
-    .map_err(|e| format!("Failed to update favorite status: {}", e))?;
-    Ok(())
-}
+            let file = match File::open(&ini_path) {
+                 Ok(f) => f,
+                 Err(e) => {
+                     eprintln!("[get_ini_keybinds] ERROR: Failed to open INI file {}: {}. Skipping.", ini_path.display(), e);
+                     continue; // Skip to the next file if this one can't be opened
+                 }
+            };
+            let reader = BufReader::new(file);
 
-#[command]
-fn delete_preset(preset_id: i64, db_state: State<DbState>) -> CmdResult<()> {
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    // Foreign key cascade should delete from preset_assets automatically
-    let changes = conn.execute("DELETE FROM presets WHERE id = ?1", params![preset_id])
-                      .map_err(|e| format!("Failed to delete preset: {}", e))?;
-    if changes == 0 {
-        Err(format!("Preset with ID {} not found.", preset_id))
-    } else {
-        Ok(())
-    }
-}
+            let mut current_section_title: Option<String> = None;
+            let mut found_constants_tag = false; // Track if we are past '; Constants'
 
-// --- Command to get Dashboard Stats ---
-#[command]
-fn get_dashboard_stats(db_state: State<DbState>) -> CmdResult<DashboardStats> {
-    let base_mods_path = match get_mods_base_path_from_settings(&db_state) {
-        Ok(p) => p,
-        Err(_) => {
-             // If base path isn't set, return default zeroed stats
-            return Ok(DashboardStats {
-                total_mods: 0,
-                enabled_mods: 0,
-                disabled_mods: 0,
-                uncategorized_mods: 0,
-                category_counts: HashMap::new(),
-            });
-        }
-    };
+            for line_result in reader.lines() {
+                let line_raw = match line_result {
+                    Ok(l) => l,
+                    Err(_) => {
+                         println!("[get_ini_keybinds] Warning: Skipping unreadable line in {}", ini_path.display());
+                         continue;
+                    }
+                };
+                let line = line_raw.trim(); // Trimmed version for processing
 
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
+                // Check for '; Constants' tag
+                 if !found_constants_tag && line.starts_with(';') && line[1..].trim_start().to_lowercase().contains("constants") {
+                    println!("[get_ini_keybinds] Found '; Constants' marker in {}", ini_path.display());
+                    found_constants_tag = true;
+                    continue; // Move to next line after finding the marker
+                }
 
-    // 1. Total Mods
-    let total_mods = conn.query_row("SELECT COUNT(*) FROM assets", [], |row| row.get::<_, i64>(0))
-                         .map_err(|e| format!("Failed to get total mod count: {}", e))?;
+                // Only process sections/keys if constants tag was found
+                if found_constants_tag {
+                    if line.starts_with('[') && line.ends_with(']') {
+                        let section_name = line[1..line.len()-1].trim().to_string();
+                        // Check if it's a keybind section
+                        if section_name.to_lowercase().starts_with("key") {
+                            current_section_title = Some(section_name); // Store the title
+                        } else {
+                            current_section_title = None; // Reset title if not a keybind section
+                        }
+                    } else if current_section_title.is_some() && line.to_lowercase().starts_with("key") && line.contains('=') {
+                         // Only process 'key =' lines if we are inside a valid [Key...] section *after* '; Constants'
+                         if let Some(value_part) = line.splitn(2, '=').nth(1) {
+                             let keybind_value = value_part.trim().to_string();
+                             if !keybind_value.is_empty() {
+                                  // .unwrap() is safe because we checked is_some()
+                                  found_keybinds.push(KeybindInfo {
+                                      title: current_section_title.clone().unwrap(),
+                                      key: keybind_value,
+                                  });
+                             }
+                         }
+                    }
+                }
+            } // End line loop
 
-    // 2. Uncategorized Mods
-    let uncategorized_mods = conn.query_row(
-        "SELECT COUNT(a.id) FROM assets a JOIN entities e ON a.entity_id = e.id WHERE e.slug LIKE '%-other'",
-        [],
-        |row| row.get::<_, i64>(0)
-    ).map_err(|e| format!("Failed to get uncategorized mod count: {}", e))?;
+             // Check if keybinds were found in *this* file (after Constants)
+             if !found_keybinds.is_empty() {
+                println!("[get_ini_keybinds] SUCCESS: Found {} keybinds (after Constants) in {}. Returning.", found_keybinds.len(), ini_path.display());
+                return Ok(found_keybinds); // Found binds, return them
+            } else {
+                 println!("[get_ini_keybinds] No keybinds found (after Constants) in {}. Checking next file.", ini_path.display());
+             }
+        } // --- End loop through INI paths ---
 
-    // 3. Category Counts
-    let mut category_counts = HashMap::new();
-    let mut cat_stmt = conn.prepare(
-        "SELECT c.name, COUNT(a.id)
-         FROM categories c
-         JOIN entities e ON c.id = e.category_id
-         JOIN assets a ON e.id = a.entity_id
-         GROUP BY c.name
-         HAVING COUNT(a.id) > 0" // Only include categories with mods
-    ).map_err(|e| format!("Failed to prepare category count query: {}", e))?;
+        // If loop finishes, no keybinds were found in any file
+        println!("[get_ini_keybinds] No keybinds found (after Constants) after checking all INI files for asset ID {}", asset_id);
+        Ok(Vec::new()) // Return empty Vec<KeybindInfo>
 
-    let cat_rows = cat_stmt.query_map([], |row| {
-        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
-    }).map_err(|e| format!("Failed to execute category count query: {}", e))?;
+    })(); // Execute the closure
 
-    for row_result in cat_rows {
-        match row_result {
-            Ok((name, count)) => { category_counts.insert(name, count); }
-            Err(e) => { eprintln!("[get_dashboard_stats] Error processing category count row: {}", e); }
-        }
-    }
+    println!("[get_ini_keybinds] COMMAND END for asset ID: {}", asset_id);
+    result // Return the result of the closure (Result<Vec<KeybindInfo>, String>)
+}
 
-    // 4. Enabled/Disabled Count (Disk Check)
-    let mut enabled_mods = 0;
-    let mut disabled_mods = 0;
-    let mut disk_check_errors = 0;
+// 3DMigoto skips files and folders prefixed with DISABLED; the app's own marker also counts
+fn is_skipped_by_loader(name: &str) -> bool {
+    name.to_uppercase().starts_with(MIGOTO_DISABLED_PREFIX) || is_disabled_folder_name(name)
+}
 
-    // Fetch folder names for checking
-    let mut asset_folders_stmt = conn.prepare("SELECT folder_name FROM assets")
-        .map_err(|e| format!("Failed to prepare asset folder fetch: {}", e))?;
-    let asset_folder_rows = asset_folders_stmt.query_map([], |row| row.get::<_, String>(0))
-        .map_err(|e| format!("Failed to query asset folders: {}", e))?;
+// Every INI file under a folder (recursive), sorted. With `active_only`, files and subfolders
+// 3DMigoto skips (e.g. inactive variants) are left out and not descended into.
+fn walk_ini_files(folder: &Path, active_only: bool) -> Vec<PathBuf> {
+    let mut ini_paths: Vec<PathBuf> = WalkDir::new(folder).min_depth(1).into_iter()
+        .filter_entry(|entry| !active_only || !is_skipped_by_loader(&entry.file_name().to_string_lossy()))
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("ini")))
+        .collect();
+    ini_paths.sort();
+    ini_paths
+}
 
-    for folder_result in asset_folder_rows {
-        match folder_result {
-            Ok(clean_relative_path_str) => {
-                 let clean_relative_path = PathBuf::from(clean_relative_path_str.replace("\\", "/"));
-                 let filename_osstr = clean_relative_path.file_name().unwrap_or_default();
-                 let filename_str = filename_osstr.to_string_lossy();
-                 if filename_str.is_empty() { continue; }
+// Lists every INI inside a mod folder (recursive) that 3DMigoto would load
+fn collect_active_ini_files(mod_folder_path: &Path) -> Vec<PathBuf> {
+    walk_ini_files(mod_folder_path, true)
+}
 
-                 let disabled_filename = format!("{}{}", DISABLED_PREFIX, filename_str);
-                 let relative_parent_path = clean_relative_path.parent();
+// Reads `namespace = ...` directives from every active INI inside a mod folder
+fn read_ini_namespaces(mod_folder_path: &Path) -> Vec<String> {
+    let mut namespaces = Vec::new();
+    for ini_path in collect_active_ini_files(mod_folder_path) {
+        let path = ini_path.as_path();
+        let file = match File::open(path) {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("[read_ini_namespaces] Failed to open INI file {}: {}. Skipping.", path.display(), e);
+                continue;
+            }
+        };
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            let line = line.trim();
+            if line.starts_with(';') || !line.to_lowercase().starts_with("namespace") {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                if key.trim().eq_ignore_ascii_case("namespace") && !value.is_empty() {
+                    namespaces.push(value.to_string());
+                }
+            }
+        }
+    }
+    namespaces
+}
 
-                 let full_path_if_enabled = base_mods_path.join(&clean_relative_path);
-                 let full_path_if_disabled = match relative_parent_path {
-                    Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_filename),
-                    _ => base_mods_path.join(&disabled_filename),
-                 };
+// Reads (hash, match_first_index) pairs from the [TextureOverride*]/[ShaderOverride*] sections of
+// every active INI inside a mod folder. Hashes are lowercased.
+fn read_ini_override_hashes(mod_folder_path: &Path) -> HashSet<(String, Option<String>)> {
+    let mut hashes = HashSet::new();
+    for ini_path in collect_active_ini_files(mod_folder_path) {
+        let ini_content = match fs::read_to_string(&ini_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("[read_ini_override_hashes] Failed to read INI file {}: {}. Skipping.", ini_path.display(), e);
+                continue;
+            }
+        };
+        // (hash, match_first_index) of the override section being read
+        let mut current: Option<(Option<String>, Option<String>)> = None;
+        let mut flush = |current: &mut Option<(Option<String>, Option<String>)>| {
+            if let Some((Some(hash), first_index)) = current.take() {
+                hashes.insert((hash, first_index));
+            }
+        };
+        for line in ini_content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                flush(&mut current);
+                let section = line[1..line.len() - 1].trim().to_lowercase();
+                if section.starts_with("textureoverride") || section.starts_with("shaderoverride") {
+                    current = Some((None, None));
+                }
+                continue;
+            }
+            let Some((hash, first_index)) = current.as_mut() else { continue };
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                match key.trim().to_lowercase().as_str() {
+                    "hash" if !value.is_empty() => *hash = Some(value.to_lowercase()),
+                    "match_first_index" if !value.is_empty() => *first_index = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        flush(&mut current);
+    }
+    hashes
+}
 
-                 if full_path_if_enabled.is_dir() {
-                     enabled_mods += 1;
-                 } else if full_path_if_disabled.is_dir() {
-                     disabled_mods += 1;
-                 } else {
-                     // Folder not found in either state - might have been deleted since last scan
-                     // We don't count it as enabled or disabled.
-                     disk_check_errors += 1;
-                 }
+// Extracts normalized `key`/`back` bindings from [Key...] sections of every active INI in a mod folder
+fn read_ini_key_bindings(mod_folder_path: &Path) -> HashSet<String> {
+    let normalize = |v: &str| v.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let mut bindings = HashSet::new();
+    for ini_path in collect_active_ini_files(mod_folder_path) {
+        let ini_content = match fs::read_to_string(&ini_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("[read_ini_key_bindings] Failed to read INI file {}: {}. Skipping.", ini_path.display(), e);
+                continue;
+            }
+        };
+
+        if let Ok(ini) = Ini::load_from_str(&ini_content) {
+            for (section_name, section) in ini.iter() {
+                let is_key_section = section_name.map_or(false, |n| n.trim().to_lowercase().starts_with("key"));
+                if !is_key_section {
+                    continue;
+                }
+                for (prop, value) in section.iter() {
+                    let prop_lower = prop.trim().to_lowercase();
+                    if (prop_lower == "key" || prop_lower == "back") && !value.trim().is_empty() {
+                        bindings.insert(normalize(value));
+                    }
+                }
+            }
+        } else {
+            // 3DMigoto INIs often contain command lists the INI parser rejects; fall back to a line scan
+            let mut in_key_section = false;
+            for line in ini_content.lines().map(|l| l.trim()) {
+                if line.starts_with('[') && line.ends_with(']') {
+                    in_key_section = line[1..line.len() - 1].trim().to_lowercase().starts_with("key");
+                } else if in_key_section && !line.starts_with(';') {
+                    if let Some((prop, value)) = line.split_once('=') {
+                        let prop_lower = prop.trim().to_lowercase();
+                        if (prop_lower == "key" || prop_lower == "back") && !value.trim().is_empty() {
+                            bindings.insert(normalize(value));
+                        }
+                    }
+                }
             }
-            Err(e) => { eprintln!("[get_dashboard_stats] Error fetching asset folder row: {}", e); }
         }
     }
+    bindings
+}
 
-    Ok(DashboardStats {
-        total_mods,
-        enabled_mods,
-        disabled_mods,
-        uncategorized_mods,
-        category_counts,
-    })
+// Lists the [Key...] sections of every active INI in a mod folder with their key/back values.
+// Line-based, since 3DMigoto INIs often contain command lists the INI parser rejects.
+fn read_ini_keybind_entries(mod_folder_path: &Path) -> Vec<AssetKeybind> {
+    let mut entries = Vec::new();
+    let mut ini_paths = collect_active_ini_files(mod_folder_path);
+    ini_paths.sort();
+    for ini_path in ini_paths {
+        let ini_content = match fs::read_to_string(&ini_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("[read_ini_keybind_entries] Failed to read INI file {}: {}. Skipping.", ini_path.display(), e);
+                continue;
+            }
+        };
+        let ini_file = ini_path.strip_prefix(mod_folder_path).unwrap_or(&ini_path).to_string_lossy().replace("\\", "/");
+
+        let mut pending_comments: Vec<String> = Vec::new();
+        let mut current: Option<AssetKeybind> = None;
+        for line in ini_content.lines().map(|l| l.trim()) {
+            if line.starts_with('[') && line.ends_with(']') {
+                if let Some(entry) = current.take().filter(|e| e.key.is_some() || e.back.is_some()) {
+                    entries.push(entry);
+                }
+                let section = line[1..line.len() - 1].trim().to_string();
+                if section.to_lowercase().starts_with("key") {
+                    let description = Some(pending_comments.join(" ")).filter(|d| !d.is_empty());
+                    current = Some(AssetKeybind { ini_file: ini_file.clone(), section, key: None, back: None, description });
+                }
+                pending_comments.clear();
+            } else if let Some(comment) = line.strip_prefix(';') {
+                let comment = comment.trim();
+                if !comment.is_empty() { pending_comments.push(comment.to_string()); }
+            } else if line.is_empty() {
+                pending_comments.clear();
+            } else if let (Some(entry), Some((prop, value))) = (current.as_mut(), line.split_once('=')) {
+                let value = value.trim();
+                if value.is_empty() { continue; }
+                match prop.trim().to_lowercase().as_str() {
+                    "key" => entry.key = Some(value.to_string()),
+                    "back" => entry.back = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        if let Some(entry) = current.filter(|e| e.key.is_some() || e.back.is_some()) {
+            entries.push(entry);
+        }
+    }
+    entries
 }
 
+// Fetches (asset_id, clean folder_name) for all assets, or only those of one entity or category
+fn fetch_asset_folders(conn: &Connection, entity_slug: Option<&str>, category_slug: Option<&str>) -> SqlResult<Vec<(i64, String)>> {
+    let map_row = |row: &rusqlite::Row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?.replace("\\", "/")));
+    match (entity_slug, category_slug) {
+        (Some(slug), _) => {
+            let mut stmt = conn.prepare("SELECT a.id, a.folder_name FROM active_assets a JOIN entities e ON a.entity_id = e.id WHERE e.slug = ?1")?;
+            let rows = stmt.query_map(params![slug], map_row)?;
+            rows.collect()
+        }
+        (None, Some(slug)) => {
+            let mut stmt = conn.prepare(
+                "SELECT a.id, a.folder_name FROM active_assets a JOIN entities e ON a.entity_id = e.id JOIN categories c ON e.category_id = c.id WHERE c.slug = ?1"
+            )?;
+            let rows = stmt.query_map(params![slug], map_row)?;
+            rows.collect()
+        }
+        (None, None) => {
+            let mut stmt = conn.prepare("SELECT id, folder_name FROM active_assets")?;
+            let rows = stmt.query_map([], map_row)?;
+            rows.collect()
+        }
+    }
+}
 
-// --- Command to get App Version ---
+// All key/back bindings of an asset, aggregated across its INI files (enabled or disabled folder)
 #[command]
-fn get_app_version() -> String {
-    // Read from environment variable set by build script/Cargo
-    env!("CARGO_PKG_VERSION").to_string()
+fn get_asset_keybinds(asset_id: i64, db_state: State<DbState>) -> CmdResult<Vec<AssetKeybind>> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let clean_relative_path = {
+        let conn = db_state.conn()?;
+        get_asset_location_info(&conn, asset_id).map_err(|e| e.to_string())?.clean_relative_path
+    }; // Lock released before parsing INIs
+
+    let (_, current_relative_path) = resolve_asset_disk_state(&base_mods_path, &clean_relative_path)
+        .ok_or_else(|| format!("Mod folder not found on disk (path: '{}')", clean_relative_path))?;
+    let keybinds = read_ini_keybind_entries(&base_mods_path.join(current_relative_path));
+    println!("[get_asset_keybinds] Asset {}: {} keybind sections", asset_id, keybinds.len());
+    Ok(keybinds)
 }
 
 #[command]
-fn get_entities_by_category_with_counts(category_slug: String, db_state: State<DbState>) -> CmdResult<Vec<EntityWithCounts>> {
-    println!("[get_entities_with_counts] Fetching for category: {}", category_slug);
-
-    let base_mods_path = match get_mods_base_path_from_settings(&db_state) {
-        Ok(p) => p,
-        Err(_) => {
-            println!("[get_entities_with_counts] Mods folder not set. Returning empty list.");
-            return Ok(Vec::new());
-        }
-    };
+fn detect_keybind_conflicts(entity_slug: Option<String>, db_state: State<DbState>) -> CmdResult<Vec<KeybindConflict>> {
+    println!("[detect_keybind_conflicts] Checking entity {:?}", entity_slug);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
 
-    let conn = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
+    let assets = {
+        let conn = db_state.conn()?;
+        fetch_asset_folders(&conn, entity_slug.as_deref(), None).map_err(|e| format!("Failed to query assets: {}", e))?
+    }; // Lock released before parsing INIs
 
-    // 1. Get Category ID
-    let category_id: i64 = conn.query_row(
-        "SELECT id FROM categories WHERE slug = ?1",
-        params![category_slug],
-        |row| row.get(0),
-    ).map_err(|e| match e {
-        rusqlite::Error::QueryReturnedNoRows => format!("Category '{}' not found", category_slug),
-        _ => format!("DB Error getting category ID: {}", e),
-    })?;
+    // key -> asset ids binding it (enabled mods only)
+    let mut assets_by_key: HashMap<String, Vec<i64>> = HashMap::new();
+    for (asset_id, folder_name) in assets {
+        let enabled_path = base_mods_path.join(&folder_name);
+        if !enabled_path.is_dir() {
+            continue;
+        }
+        for key in read_ini_key_bindings(&enabled_path) {
+            assets_by_key.entry(key).or_default().push(asset_id);
+        }
+    }
 
-    // 2. Get Entities for the Category
-    let mut entity_stmt = conn.prepare(
-         "SELECT e.id, e.category_id, e.name, e.slug, e.details, e.base_image
-          FROM entities e
-          WHERE e.category_id = ?1
-          ORDER BY CASE WHEN e.slug LIKE '%-other' THEN 0 ELSE 1 END ASC, e.name ASC"
-     ).map_err(|e| format!("Failed to prepare entity query: {}", e))?;
+    let mut conflicts = Vec::new();
+    for (key, asset_ids) in assets_by_key.iter().filter(|(_, ids)| ids.len() > 1) {
+        for (i, first) in asset_ids.iter().enumerate() {
+            for second in &asset_ids[i + 1..] {
+                conflicts.push(KeybindConflict { key: key.clone(), first_asset_id: *first, second_asset_id: *second });
+            }
+        }
+    }
+    conflicts.sort_by(|a, b| a.key.cmp(&b.key).then(a.first_asset_id.cmp(&b.first_asset_id)));
 
-    let entity_rows_iter = entity_stmt.query_map(params![category_id], |row| {
-        Ok((
-            row.get::<_, i64>(0)?,
-            row.get::<_, i64>(1)?,
-            row.get::<_, String>(2)?,
-            row.get::<_, String>(3)?,
-            row.get::<_, Option<String>>(4)?,
-            row.get::<_, Option<String>>(5)?,
-        ))
-    }).map_err(|e| format!("Failed to query entities: {}", e))?;
+    println!("[detect_keybind_conflicts] Found {} conflicting pairs", conflicts.len());
+    Ok(conflicts)
+}
 
-    let mut results: Vec<EntityWithCounts> = Vec::new();
+// A binding is one or more modifiers followed by exactly one key (VK_/XB_ name, single character or hex code)
+fn is_valid_keybind_value(value: &str) -> bool {
+    let tokens: Vec<String> = value.split_whitespace().map(|t| t.to_uppercase()).collect();
+    let key_count = tokens.iter().filter(|t| !KEYBIND_MODIFIER_TOKENS.contains(*t)).count();
+    if key_count != 1 {
+        return false;
+    }
+    tokens.iter().all(|t| {
+        KEYBIND_MODIFIER_TOKENS.contains(t)
+            || KEYBIND_KEY_TOKENS.contains(t)
+            || (t.chars().count() == 1 && t.chars().all(|c| c.is_ascii_graphic()))
+            || t.strip_prefix("0X").map_or(false, |hex| !hex.is_empty() && hex.len() <= 2 && u8::from_str_radix(hex, 16).is_ok())
+    })
+}
 
-    // *** FIX: Apply .map_err() to the prepare call ***
-    let mut asset_folder_stmt = conn.prepare("SELECT folder_name FROM assets WHERE entity_id = ?1")
-                                     .map_err(|e| format!("Failed to prepare asset folder query: {}", e))?; // Prepare asset query once
+// Rewrites `key`/`back` lines equal to `old_key` inside `[section]`, leaving every other line untouched.
+// Done line by line rather than through Ini::write so comments (e.g. "; Constants") and 3DMigoto
+// command lists survive. Returns the new content and how many entries changed.
+fn rewrite_ini_keybind(content: &str, section: &str, old_key: &str, new_key: &str) -> (String, usize) {
+    let normalize = |v: &str| v.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let old_key_normalized = normalize(old_key);
+    let mut in_target_section = false;
+    let mut changed = 0;
+    let mut output = String::with_capacity(content.len() + new_key.len());
+
+    for raw_line in content.split_inclusive('\n') {
+        let line_body = raw_line.trim_end_matches(['\r', '\n']);
+        let line_ending = &raw_line[line_body.len()..];
+        let trimmed = line_body.trim().trim_start_matches('\u{feff}');
+
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            in_target_section = trimmed[1..trimmed.len() - 1].trim().eq_ignore_ascii_case(section.trim());
+        } else if in_target_section && !trimmed.starts_with(';') {
+            if let Some((prop, value)) = line_body.split_once('=') {
+                let prop_lower = prop.trim().to_lowercase();
+                if (prop_lower == "key" || prop_lower == "back") && normalize(value) == old_key_normalized {
+                    output.push_str(&format!("{}= {}{}", prop, new_key.trim(), line_ending));
+                    changed += 1;
+                    continue;
+                }
+            }
+        }
+        output.push_str(raw_line);
+    }
+    (output, changed)
+}
 
-    for entity_result in entity_rows_iter {
-        match entity_result {
-            Ok((id, cat_id, name, slug, details, base_image)) => {
-                // 3. For each entity, get its assets and check disk status
-                let mut total_mods_for_entity = 0;
-                let mut enabled_mods_for_entity = 0;
+// Applies rewrite_ini_keybind to one INI file, keeping a timestamped .bak copy of the original.
+// The file is left untouched if nothing matched. Returns how many entries changed.
+fn write_ini_keybind_change(ini_path: &Path, original: &str, section: &str, old_key: &str, new_key: &str) -> Result<usize, String> {
+    let (rewritten, changed) = rewrite_ini_keybind(original, section, old_key, new_key);
+    if changed == 0 {
+        return Ok(0);
+    }
 
-                // Map potential errors when querying assets for *this specific* entity
-                let asset_folder_rows_result = asset_folder_stmt.query_map(params![id], |row| row.get::<_, String>(0));
+    // If the ini crate could read the original, make sure the rewrite didn't break that
+    if Ini::load_from_str(original).is_ok() {
+        Ini::load_from_str(&rewritten)
+            .map_err(|e| format!("Rewritten INI '{}' failed to parse, leaving it unchanged: {}", ini_path.display(), e))?;
+    }
 
-                match asset_folder_rows_result {
-                     Ok(rows) => {
-                        for folder_result in rows {
-                            match folder_result {
-                                Ok(clean_relative_path_str) => {
-                                    total_mods_for_entity += 1;
+    let backup_path = PathBuf::from(format!("{}.{}.bak", ini_path.display(), unix_timestamp_millis()));
+    fs::copy(ini_path, &backup_path)
+        .map_err(|e| format!("Failed to back up '{}' before editing: {}", ini_path.display(), e))?;
+    fs::write(ini_path, rewritten)
+        .map_err(|e| format!("Failed to write '{}' (backup at '{}'): {}", ini_path.display(), backup_path.display(), e))?;
 
-                                    let clean_relative_path = PathBuf::from(clean_relative_path_str.replace("\\", "/"));
-                                    let filename_osstr = clean_relative_path.file_name().unwrap_or_default();
-                                    let filename_str = filename_osstr.to_string_lossy();
-                                    if filename_str.is_empty() { continue; }
+    println!("[write_ini_keybind_change] Updated {} entries in {} (backup: {})", changed, ini_path.display(), backup_path.display());
+    Ok(changed)
+}
 
-                                    // Check only enabled state path
-                                    let full_path_if_enabled = base_mods_path.join(&clean_relative_path);
-                                    if full_path_if_enabled.is_dir() {
-                                        enabled_mods_for_entity += 1;
-                                    }
-                                }
-                                Err(e) => eprintln!("[get_entities_with_counts] Error fetching asset folder row for entity {}: {}", id, e),
-                            }
-                        }
-                    }
-                    // Log the error but don't stop the whole process for one entity's assets failing
-                    Err(e) => eprintln!("[get_entities_with_counts] Error querying asset folders for entity {}: {}", id, e),
-                }
+#[command]
+fn remap_asset_keybind(
+    asset_id: i64,
+    section: String,
+    old_key: String,
+    new_key: String,
+    db_state: State<DbState>
+) -> CmdResult<usize> {
+    println!("[remap_asset_keybind] Asset {}: [{}] '{}' -> '{}'", asset_id, section, old_key, new_key);
+    if !is_valid_keybind_value(&new_key) {
+        return Err(format!("'{}' is not a recognized key binding (expected e.g. 'VK_F5', 'ctrl alt 1' or 'no_modifiers x').", new_key));
+    }
+    if section.trim().is_empty() || old_key.trim().is_empty() {
+        return Err("Section and current key must not be empty.".to_string());
+    }
 
-                results.push(EntityWithCounts {
-                    id,
-                    category_id: cat_id,
-                    name,
-                    slug,
-                    details,
-                    base_image,
-                    total_mods: total_mods_for_entity,
-                    enabled_mods: enabled_mods_for_entity,
-                });
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let ini_paths = {
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+        find_asset_ini_paths(&conn, asset_id, &base_mods_path).map_err(|e| format!("Error finding INI paths: {}", e))?
+    }; // Lock released before file I/O
+
+    let mut total_changed = 0;
+    for ini_path in ini_paths {
+        let original = match fs::read_to_string(&ini_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("[remap_asset_keybind] Failed to read INI file {}: {}. Skipping.", ini_path.display(), e);
+                continue;
             }
-            Err(e) => eprintln!("[get_entities_with_counts] Error processing entity row: {}", e),
-        }
+        };
+        total_changed += write_ini_keybind_change(&ini_path, &original, &section, &old_key, &new_key)?;
     }
 
-    println!("[get_entities_with_counts] Found {} entities with counts for category '{}'", results.len(), category_slug);
-    Ok(results)
+    if total_changed == 0 {
+        return Err(format!("No key/back entry '{}' found in section [{}] for asset {}.", old_key, section, asset_id));
+    }
+    Ok(total_changed)
 }
 
+// Moves one of an enabled mod's conflicting bindings to a key no enabled mod uses.
+// `to_key` is tried first when given; if it is empty or taken, the first free entry of
+// KEYBIND_AUTO_CANDIDATES is used. If the mod has several conflicting keys, the first one
+// (alphabetically) is resolved; call again for the rest. Returns the key that was chosen.
 #[command]
-fn overwrite_preset(preset_id: i64, db_state: State<DbState>) -> CmdResult<()> {
-    println!("[overwrite_preset] Attempting to overwrite preset ID: {}", preset_id);
+fn auto_resolve_keybind_conflict(asset_id: i64, to_key: String, db_state: State<DbState>) -> CmdResult<String> {
+    println!("[auto_resolve_keybind_conflict] Asset {}, preferred key '{}'", asset_id, to_key);
+    let to_key = to_key.trim().to_string();
+    if !to_key.is_empty() && !is_valid_keybind_value(&to_key) {
+        return Err(format!("'{}' is not a recognized key binding (expected e.g. 'VK_F5', 'ctrl alt 1' or 'no_modifiers x').", to_key));
+    }
 
-    let base_mods_path = get_mods_base_path_from_settings(&db_state)
-        .map_err(|e| format!("Cannot overwrite preset (failed to get mods path): {}", e))?;
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let (clean_relative_path, assets) = {
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+        let clean_relative_path = get_asset_location_info(&conn, asset_id).map_err(|e| e.to_string())?.clean_relative_path;
+        let assets = fetch_asset_folders(&conn, None, None).map_err(|e| format!("Failed to query assets: {}", e))?;
+        (clean_relative_path, assets)
+    }; // Lock released before parsing INIs
+
+    let mod_folder_path = base_mods_path.join(&clean_relative_path);
+    if !mod_folder_path.is_dir() {
+        return Err(format!("Asset {} is not enabled; only enabled mods can conflict.", asset_id));
+    }
 
-    let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
-    let mut conn = conn_guard; // Get mutable access to the MutexGuard content
+    // Keys bound by every other enabled mod
+    let mut used_by_others: HashSet<String> = HashSet::new();
+    for (other_id, folder_name) in assets {
+        if other_id == asset_id { continue; }
+        let enabled_path = base_mods_path.join(&folder_name);
+        if enabled_path.is_dir() {
+            used_by_others.extend(read_ini_key_bindings(&enabled_path));
+        }
+    }
 
-    // Use a transaction for atomicity
-    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let normalize = |v: &str| v.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let own_entries = read_ini_keybind_entries(&mod_folder_path);
+    let own_keys: HashSet<String> = own_entries.iter()
+        .flat_map(|entry| entry.key.iter().chain(entry.back.iter()))
+        .map(|value| normalize(value))
+        .collect();
+    let mut conflicting: Vec<&String> = own_keys.iter().filter(|key| used_by_others.contains(*key)).collect();
+    conflicting.sort();
+    let conflicting_key = conflicting.first()
+        .map(|key| key.to_string())
+        .ok_or_else(|| format!("Asset {} has no key bindings that conflict with other enabled mods.", asset_id))?;
+
+    // The replacement must not collide with anything, including this mod's own other bindings
+    let is_free = |key: &str| !used_by_others.contains(&normalize(key)) && !own_keys.contains(&normalize(key));
+    let new_key = if !to_key.is_empty() && is_free(&to_key) {
+        to_key
+    } else {
+        if !to_key.is_empty() {
+            println!("[auto_resolve_keybind_conflict] Preferred key '{}' is taken, picking a free one", to_key);
+        }
+        KEYBIND_AUTO_CANDIDATES.iter()
+            .find(|candidate| is_free(candidate))
+            .map(|candidate| candidate.to_string())
+            .ok_or_else(|| "No free key left in the candidate list; remap this binding manually.".to_string())?
+    };
 
-    // 1. Delete existing asset states for this preset
-    println!("[overwrite_preset] Deleting old asset states for preset {}", preset_id);
-    let delete_count = tx.execute("DELETE FROM preset_assets WHERE preset_id = ?1", params![preset_id])
-        .map_err(|e| format!("Failed to delete old preset asset states: {}", e))?;
-    println!("[overwrite_preset] Deleted {} old entries.", delete_count);
+    let mut total_changed = 0;
+    for entry in own_entries.iter().filter(|entry| {
+        entry.key.iter().chain(entry.back.iter()).any(|value| normalize(value) == conflicting_key)
+    }) {
+        let ini_path = mod_folder_path.join(&entry.ini_file);
+        let original = fs::read_to_string(&ini_path)
+            .map_err(|e| format!("Failed to read INI file {}: {}", ini_path.display(), e))?;
+        total_changed += write_ini_keybind_change(&ini_path, &original, &entry.section, &conflicting_key, &new_key)?;
+    }
+    if total_changed == 0 {
+        return Err(format!("Could not rewrite binding '{}' for asset {}.", conflicting_key, asset_id));
+    }
 
-    // 2. Fetch all current assets from the main assets table
-    let mut assets_to_save = Vec::<(i64, String)>::new(); // (asset_id, clean_relative_path)
-    { // Scope for the statement
-        let mut stmt = tx.prepare("SELECT id, folder_name FROM assets")
-           .map_err(|e| format!("Failed to prepare asset fetch statement: {}", e))?;
-        let asset_iter = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
-                             .map_err(|e| format!("Failed to create asset query iterator: {}", e))?;
-
-        for row_result in asset_iter {
-            match row_result {
-                Ok((asset_id, folder_name)) => {
-                    assets_to_save.push((asset_id, folder_name.replace("\\", "/")));
-                }
-                Err(e) => {
-                    // Log error for the specific row but continue fetching others
-                    eprintln!("[overwrite_preset] Error fetching asset row from DB: {}", e);
-                    // Optionally rollback transaction and return error
-                    // tx.rollback().ok(); // Attempt rollback // Don't rollback here, let the error propagate
-                    // return Err(format!("Error fetching asset row from DB: {}", e)); // Let caller handle rollback on error
-                }
-            }
+    println!("[auto_resolve_keybind_conflict] Asset {}: '{}' -> '{}' ({} entries)", asset_id, conflicting_key, new_key, total_changed);
+    Ok(new_key)
+}
+
+// --- INI backups ---
+// Each backup is a folder <app data>/ini_backups/<asset id>/<backup id>/ holding the mod's INI
+// files at their paths inside the mod folder. Backup IDs are creation times in Unix milliseconds.
+
+#[derive(Serialize, Debug, Clone)]
+struct IniBackup {
+    backup_id: String,
+    created_at: i64, // Unix milliseconds
+    files: Vec<String>, // Relative to the mod folder
+}
+
+fn get_ini_backup_dir(app_handle: &AppHandle, asset_id: i64) -> Result<PathBuf, AppError> {
+    Ok(get_app_data_dir(app_handle)?.join(INI_BACKUP_FOLDER_NAME).join(asset_id.to_string()))
+}
+
+// Deletes the INI backups of an asset that no longer exists. Failures are only logged.
+fn remove_ini_backups(app_handle: &AppHandle, asset_id: i64) {
+    let backup_dir = match get_ini_backup_dir(app_handle, asset_id) {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("[remove_ini_backups] Could not resolve backup folder for asset {}: {}", asset_id, e);
+            return;
+        }
+    };
+    if backup_dir.is_dir() {
+        if let Err(e) = fs::remove_dir_all(&backup_dir) {
+            eprintln!("[remove_ini_backups] Failed to delete '{}': {}", backup_dir.display(), e);
         }
     }
-    println!("[overwrite_preset] Fetched {} assets to check for saving.", assets_to_save.len());
+}
 
+// Deletes INI backups left behind by assets that are gone from the DB
+fn prune_orphaned_ini_backups(app_handle: &AppHandle, conn: &Connection) -> SqlResult<usize> {
+    let backups_root = match get_app_data_dir(app_handle) {
+        Ok(dir) => dir.join(INI_BACKUP_FOLDER_NAME),
+        Err(_) => return Ok(0),
+    };
+    let asset_ids: Vec<i64> = match fs::read_dir(&backups_root) {
+        Ok(entries) => entries.filter_map(|e| e.ok())
+            .filter_map(|entry| entry.file_name().to_string_lossy().parse::<i64>().ok())
+            .collect(),
+        Err(_) => return Ok(0),
+    };
+    let mut removed_count = 0;
+    for asset_id in asset_ids {
+        let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM assets WHERE id = ?1)", params![asset_id], |row| row.get(0))?;
+        if !exists {
+            remove_ini_backups(app_handle, asset_id);
+            removed_count += 1;
+        }
+    }
+    Ok(removed_count)
+}
 
-    // --- FIX: Add block scope for insert_stmt ---
-    let mut saved_count = 0;
-    let mut not_found_count = 0;
-    { // Start scope for insert_stmt
-        // 3. Iterate through fetched assets, check disk state, and insert into preset_assets
-        let mut insert_stmt = tx.prepare(
-            "INSERT INTO preset_assets (preset_id, asset_id, is_enabled) VALUES (?1, ?2, ?3)"
-        ).map_err(|e| format!("Failed to prepare insert statement for preset assets: {}", e))?;
+// (relative path with forward slashes, full path) of every INI file under a folder, sorted
+fn collect_ini_files(folder: &Path) -> Vec<(String, PathBuf)> {
+    walk_ini_files(folder, false).into_iter()
+        .filter_map(|path| {
+            let relative = path.strip_prefix(folder).ok()?.to_string_lossy().replace("\\", "/");
+            Some((relative, path))
+        })
+        .collect()
+}
 
+// Backups of one asset, newest first
+fn read_ini_backups(asset_backup_dir: &Path) -> Vec<IniBackup> {
+    let mut backups: Vec<IniBackup> = fs::read_dir(asset_backup_dir)
+        .map(|entries| entries
+            .filter_map(|e| e.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| {
+                let backup_id = entry.file_name().to_string_lossy().to_string();
+                let created_at = backup_id.parse::<i64>().ok()?;
+                let files = collect_ini_files(&entry.path()).into_iter().map(|(relative, _)| relative).collect();
+                Some(IniBackup { backup_id, created_at, files })
+            })
+            .collect())
+        .unwrap_or_default();
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    backups
+}
 
-        for (asset_id, clean_relative_path_str) in assets_to_save {
-            let clean_relative_path = PathBuf::from(&clean_relative_path_str);
-            let filename_osstr = clean_relative_path.file_name().unwrap_or_default();
-            let filename_str = filename_osstr.to_string_lossy();
-            if filename_str.is_empty() { continue; }
+// Copies the INI files of a mod folder into a new backup, then drops backups beyond
+// INI_BACKUP_MAX_PER_ASSET. Returns None if the folder has no INIs or they are identical to the
+// latest backup, so repeated toggles don't push real edits out of the history.
+fn create_ini_backup(app_handle: &AppHandle, asset_id: i64, mod_folder_path: &Path) -> Result<Option<String>, AppError> {
+    let ini_files = collect_ini_files(mod_folder_path);
+    if ini_files.is_empty() {
+        return Ok(None);
+    }
+    let asset_backup_dir = get_ini_backup_dir(app_handle, asset_id)?;
+    if let Some(latest) = read_ini_backups(&asset_backup_dir).first() {
+        let latest_dir = asset_backup_dir.join(&latest.backup_id);
+        let unchanged = latest.files.len() == ini_files.len()
+            && ini_files.iter().all(|(relative, path)| match (fs::read(path), fs::read(latest_dir.join(relative))) {
+                (Ok(current), Ok(backed_up)) => current == backed_up,
+                _ => false,
+            });
+        if unchanged {
+            return Ok(None);
+        }
+    }
 
-            // Check enabled state on disk
-            let full_path_if_enabled = base_mods_path.join(&clean_relative_path);
-            let is_currently_enabled_on_disk = if full_path_if_enabled.is_dir() {
-                1 // Enabled
-            } else {
-                // Check disabled state only to confirm it exists somewhere, otherwise skip saving
-                let disabled_filename = format!("{}{}", DISABLED_PREFIX, filename_str);
-                let relative_parent_path = clean_relative_path.parent();
-                let full_path_if_disabled = match relative_parent_path {
-                    Some(parent) if parent.as_os_str().len() > 0 => base_mods_path.join(parent).join(&disabled_filename),
-                    _ => base_mods_path.join(&disabled_filename),
-                };
-                if full_path_if_disabled.is_dir() {
-                    0 // Disabled
-                } else {
-                    // Folder not found in either state - skip saving its state for this preset
-                    println!("[overwrite_preset] Warning: Asset ID {} folder not found on disk during preset save (path: {}). Skipping.", asset_id, clean_relative_path_str);
-                    not_found_count += 1;
-                    continue; // Skip to next asset
-                }
-            };
+    let mut created_at = unix_timestamp_millis();
+    while asset_backup_dir.join(created_at.to_string()).exists() {
+        created_at += 1;
+    }
+    let backup_id = created_at.to_string();
+    let backup_dir = asset_backup_dir.join(&backup_id);
+    for (relative, path) in &ini_files {
+        let destination = backup_dir.join(relative);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(path, &destination)?;
+    }
+    println!("[create_ini_backup] Backed up {} INI files of asset {} as {}", ini_files.len(), asset_id, backup_id);
 
-            // Insert the current state into the preset
-            insert_stmt.execute(params![preset_id, asset_id, is_currently_enabled_on_disk])
-                .map_err(|e| format!("Failed to save state for asset {}: {}", asset_id, e))?;
-            saved_count += 1;
+    for old_backup in read_ini_backups(&asset_backup_dir).iter().skip(INI_BACKUP_MAX_PER_ASSET) {
+        if let Err(e) = fs::remove_dir_all(asset_backup_dir.join(&old_backup.backup_id)) {
+            eprintln!("[create_ini_backup] Failed to remove old backup {} of asset {}: {}", old_backup.backup_id, asset_id, e);
         }
-    } // --- End scope for insert_stmt --- `insert_stmt` is dropped here, releasing the borrow on `tx`
+    }
+    Ok(Some(backup_id))
+}
 
+#[command]
+fn list_ini_backups(asset_id: i64, app_handle: AppHandle) -> CmdResult<Vec<IniBackup>> {
+    let asset_backup_dir = get_ini_backup_dir(&app_handle, asset_id).map_err(|e| e.to_string())?;
+    Ok(read_ini_backups(&asset_backup_dir))
+}
 
-    // 4. Commit the transaction (Now safe as insert_stmt is out of scope)
-    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+// Writes a backup's INI files back into the mod folder. The current INIs are backed up first,
+// so a restore can itself be rolled back. INIs added since the backup are left alone.
+#[command]
+fn restore_ini_backup(
+    asset_id: i64,
+    backup_id: String,
+    db_state: State<DbState>,
+    config_state: State<CachedConfigState>,
+    app_handle: AppHandle
+) -> CmdResult<usize> {
+    if backup_id.is_empty() || !backup_id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Invalid backup ID '{}'.", backup_id));
+    }
+    {
+        let conn = db_state.conn()?;
+        ensure_not_safe_mode(&conn).map_err(|e| e.to_string())?;
+    }
+    let backup_dir = get_ini_backup_dir(&app_handle, asset_id).map_err(|e| e.to_string())?.join(&backup_id);
+    if !backup_dir.is_dir() {
+        return Err(format!("Backup {} of asset {} not found.", backup_id, asset_id));
+    }
+    let mod_folder_path = resolve_asset_folder_on_disk(asset_id, &db_state, &config_state)?;
+
+    create_ini_backup(&app_handle, asset_id, &mod_folder_path)
+        .map_err(|e| format!("Failed to back up the current INI files before restoring: {}", e))?;
+    let backed_up_files = collect_ini_files(&backup_dir);
+    for (relative, path) in &backed_up_files {
+        let destination = mod_folder_path.join(relative);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).map_err(|e| io_error_message("Failed to create", parent, &e))?;
+        }
+        fs::copy(path, &destination).map_err(|e| io_error_message("Failed to restore", &destination, &e))?;
+    }
 
-    println!("[overwrite_preset] Preset ID {} overwritten successfully. Saved state for {} assets (skipped {} not found).", preset_id, saved_count, not_found_count);
-    Ok(())
+    println!("[restore_ini_backup] Restored {} INI files of asset {} from backup {}", backed_up_files.len(), asset_id, backup_id);
+    Ok(backed_up_files.len())
 }
 
 #[command]
-fn get_ini_keybinds(asset_id: i64, db_state: State<DbState>) -> Result<Vec<KeybindInfo>, String> { // CmdResult is Result<T, String>
-    println!("[get_ini_keybinds] COMMAND START for asset ID: {}", asset_id);
-
-    let result: Result<Vec<KeybindInfo>, String> = (|| {
-        println!("[get_ini_keybinds] Attempting to acquire DB lock...");
-        let conn_guard = db_state.0.lock().map_err(|_| {
-            eprintln!("[get_ini_keybinds] ERROR: DB lock poisoned!");
-            "DB lock poisoned".to_string()
-        })?;
-        println!("[get_ini_keybinds] DB lock acquired.");
-        let conn = &*conn_guard; // Dereference the guard to get the connection
+fn find_namespace_collisions(
+    entity_slug: Option<String>,
+    category_slug: Option<String>,
+    db_state: State<DbState>
+) -> CmdResult<Vec<NamespaceCollision>> {
+    println!("[find_namespace_collisions] Checking entity {:?} / category {:?}", entity_slug, category_slug);
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
 
-        println!("[get_ini_keybinds] Attempting to get base mods path setting directly...");
-        // --- FIX: Provide arguments to query_row ---
-        let mods_folder_path_str_opt: Option<String> = conn.query_row(
-            "SELECT value FROM settings WHERE key = ?1", // SQL query
-            params![SETTINGS_KEY_MODS_FOLDER],          // Parameters
-            |row| row.get(0),                           // Mapping closure
-        ).optional().map_err(|e| format!("DB error fetching mods folder setting: {}", e))?;
-        // --- End Fix ---
+    // Fetch candidate assets within the requested scope, then release the lock before disk I/O
+    let assets: Vec<(i64, String)> = {
+        let conn = db_state.conn()?;
+        fetch_asset_folders(&conn, entity_slug.as_deref(), category_slug.as_deref())
+            .map_err(|e| format!("Failed to query assets: {}", e))?
+    };
 
-        let base_mods_path = mods_folder_path_str_opt
-            .map(PathBuf::from)
-            .ok_or_else(|| {
-                eprintln!("[get_ini_keybinds] ERROR: Mods folder path not set in settings.");
-                "Mods folder path not set".to_string()
-            })?;
-        println!("[get_ini_keybinds] Base mods path obtained directly: {}", base_mods_path.display());
+    // Group enabled assets by namespace (3DMigoto namespaces are case-insensitive)
+    let mut by_namespace: HashMap<String, (String, Vec<i64>)> = HashMap::new();
+    for (asset_id, folder_name) in assets {
+        let enabled_path = base_mods_path.join(folder_name.replace("\\", "/"));
+        if !enabled_path.is_dir() {
+            continue; // Disabled or missing mods can't clash
+        }
+        let unique_namespaces: HashSet<String> = read_ini_namespaces(&enabled_path).into_iter().collect();
+        for namespace in unique_namespaces {
+            let entry = by_namespace.entry(namespace.to_lowercase()).or_insert_with(|| (namespace.clone(), Vec::new()));
+            if !entry.1.contains(&asset_id) {
+                entry.1.push(asset_id);
+            }
+        }
+    }
 
+    let mut collisions: Vec<NamespaceCollision> = by_namespace.into_values()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|(namespace, asset_ids)| NamespaceCollision { namespace, asset_ids })
+        .collect();
+    collisions.sort_by(|a, b| a.namespace.to_lowercase().cmp(&b.namespace.to_lowercase()));
 
-        println!("[get_ini_keybinds] Calling find_asset_ini_paths...");
-        let ini_paths = find_asset_ini_paths(conn, asset_id, &base_mods_path)
-            .map_err(|e| {
-                eprintln!("[get_ini_keybinds] ERROR from find_asset_ini_paths: {}", e);
-                format!("Error finding INI paths: {}", e)
-            })?;
-        println!("[get_ini_keybinds] find_asset_ini_paths returned {} paths.", ini_paths.len());
+    println!("[find_namespace_collisions] Found {} colliding namespaces", collisions.len());
+    Ok(collisions)
+}
 
-        // --- Release the lock explicitly before file I/O ---
-        drop(conn_guard);
-        println!("[get_ini_keybinds] DB lock released before file parsing.");
+// Current mod folder of an asset, enabled or disabled
+fn resolve_asset_folder_on_disk(asset_id: i64, db_state: &DbState, config_state: &CachedConfigState) -> CmdResult<PathBuf> {
+    let base_mods_path = cached_mods_base_path(config_state, db_state).map_err(|e| e.to_string())?;
+    let folder_name: String = {
+        let conn = db_state.conn()?;
+        conn.query_row("SELECT folder_name FROM active_assets WHERE id = ?1", params![asset_id], |row| row.get(0))
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Asset with ID {} not found.", asset_id),
+                _ => format!("Failed to read asset {}: {}", asset_id, e),
+            })?
+    };
+    let (_, relative_path) = resolve_asset_disk_state(&base_mods_path, &folder_name.replace("\\", "/"))
+        .ok_or_else(|| format!("Mod folder for asset {} not found on disk.", asset_id))?;
+    Ok(base_mods_path.join(relative_path))
+}
 
-        if ini_paths.is_empty() {
-             println!("[get_ini_keybinds] No INI files found for asset ID {}", asset_id);
-             return Ok(Vec::new()); // Return empty early if no INIs exist
+// Every INI in a mod folder (disabled ones included) with its sections and key counts, for
+// showing what a mod does without opening the files
+#[command]
+fn get_asset_ini_overview(asset_id: i64, db_state: State<DbState>, config_state: State<CachedConfigState>) -> CmdResult<Vec<IniFileOverview>> {
+    let mod_folder_path = resolve_asset_folder_on_disk(asset_id, &db_state, &config_state)?;
+    let active_inis: HashSet<PathBuf> = collect_active_ini_files(&mod_folder_path).into_iter().collect();
+    let ini_paths = walk_ini_files(&mod_folder_path, false);
+
+    let mut overview = Vec::with_capacity(ini_paths.len());
+    for ini_path in ini_paths {
+        let content = match fs::read(&ini_path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => return Err(format!("Failed to read INI '{}': {}", ini_path.display(), e)),
+        };
+        let mut sections: Vec<IniSectionSummary> = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') { continue; }
+            if line.starts_with('[') && line.ends_with(']') {
+                sections.push(IniSectionSummary { name: line[1..line.len() - 1].trim().to_string(), key_count: 0 });
+            } else if line.contains('=') {
+                // Keys before the first section header have no section to count under
+                if let Some(section) = sections.last_mut() {
+                    section.key_count += 1;
+                }
+            }
         }
+        overview.push(IniFileOverview {
+            ini_file: ini_path.strip_prefix(&mod_folder_path).unwrap_or(&ini_path).to_string_lossy().replace("\\", "/"),
+            is_active: active_inis.contains(&ini_path),
+            sections,
+        });
+    }
 
-        let mut found_keybinds: Vec<KeybindInfo> = Vec::new();
-
-        for ini_path in ini_paths {
-            println!("[get_ini_keybinds] Parsing INI at: {}", ini_path.display());
-
-            let file = match File::open(&ini_path) {
-                 Ok(f) => f,
-                 Err(e) => {
-                     eprintln!("[get_ini_keybinds] ERROR: Failed to open INI file {}: {}. Skipping.", ini_path.display(), e);
-                     continue; // Skip to the next file if this one can't be opened
-                 }
-            };
-            let reader = BufReader::new(file);
+    println!("[get_asset_ini_overview] Asset {}: {} INI file(s)", asset_id, overview.len());
+    Ok(overview)
+}
 
-            let mut current_section_title: Option<String> = None;
-            let mut found_constants_tag = false; // Track if we are past '; Constants'
+// Checks that a mod folder has an active INI, that its section headers are well-formed and that
+// every `filename = ...` it references exists. References are resolved relative to the INI that
+// contains them, like 3DMigoto does.
+#[command]
+fn validate_asset(asset_id: i64, db_state: State<DbState>, config_state: State<CachedConfigState>) -> CmdResult<AssetValidation> {
+    let mod_folder_path = resolve_asset_folder_on_disk(asset_id, &db_state, &config_state)?;
+    let validation = validate_mod_folder(asset_id, &mod_folder_path)?;
+    println!("[validate_asset] Asset {}: {} INI(s), {} missing referenced file(s), {} syntax error(s)",
+        asset_id, validation.ini_files.len(), validation.missing_files.len(), validation.syntax_errors.len());
+    Ok(validation)
+}
 
-            for line_result in reader.lines() {
-                let line_raw = match line_result {
-                    Ok(l) => l,
-                    Err(_) => {
-                         println!("[get_ini_keybinds] Warning: Skipping unreadable line in {}", ini_path.display());
-                         continue;
-                    }
+// 3DMigoto INIs use command lists (if/endif, run = ...) that strict INI parsers reject, so only
+// errors 3DMigoto itself trips over are flagged: unterminated or empty section headers.
+fn validate_mod_folder(asset_id: i64, mod_folder_path: &Path) -> CmdResult<AssetValidation> {
+    let mut validation = AssetValidation { asset_id, ini_files: Vec::new(), missing_files: Vec::new(), syntax_errors: Vec::new(), is_valid: false };
+    for ini_path in collect_active_ini_files(mod_folder_path) {
+        let ini_relative = ini_path.strip_prefix(mod_folder_path).unwrap_or(&ini_path).to_string_lossy().replace("\\", "/");
+        let content = match fs::read(&ini_path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => return Err(format!("Failed to read INI '{}': {}", ini_path.display(), e)),
+        };
+        let ini_dir = ini_path.parent().unwrap_or(mod_folder_path);
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with(';') { continue; }
+            if line.starts_with('[') {
+                let header_error = match line.find(']') {
+                    None => Some("Section header is missing its closing ']'"),
+                    Some(end) if line[1..end].trim().is_empty() => Some("Section header has no name"),
+                    Some(_) => None,
                 };
-                let line = line_raw.trim(); // Trimmed version for processing
-
-                // Check for '; Constants' tag
-                 if !found_constants_tag && line.starts_with(';') && line[1..].trim_start().to_lowercase().contains("constants") {
-                    println!("[get_ini_keybinds] Found '; Constants' marker in {}", ini_path.display());
-                    found_constants_tag = true;
-                    continue; // Move to next line after finding the marker
+                if let Some(message) = header_error {
+                    validation.syntax_errors.push(IniSyntaxError { ini_file: ini_relative.clone(), line: index + 1, message: message.to_string() });
                 }
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else { continue };
+            if !key.trim().eq_ignore_ascii_case("filename") { continue; }
+            let reference = value.trim().trim_matches('"');
+            if reference.is_empty() { continue; }
+            if !ini_dir.join(reference.replace('\\', "/")).is_file() {
+                validation.missing_files.push(MissingIniReference { ini_file: ini_relative.clone(), line: index + 1, reference: reference.to_string() });
+            }
+        }
+        validation.ini_files.push(ini_relative);
+    }
+    validation.is_valid = !validation.ini_files.is_empty() && validation.missing_files.is_empty() && validation.syntax_errors.is_empty();
+    Ok(validation)
+}
 
-                // Only process sections/keys if constants tag was found
-                if found_constants_tag {
-                    if line.starts_with('[') && line.ends_with(']') {
-                        let section_name = line[1..line.len()-1].trim().to_string();
-                        // Check if it's a keybind section
-                        if section_name.to_lowercase().starts_with("key") {
-                            current_section_title = Some(section_name); // Store the title
-                        } else {
-                            current_section_title = None; // Reset title if not a keybind section
-                        }
-                    } else if current_section_title.is_some() && line.to_lowercase().starts_with("key") && line.contains('=') {
-                         // Only process 'key =' lines if we are inside a valid [Key...] section *after* '; Constants'
-                         if let Some(value_part) = line.splitn(2, '=').nth(1) {
-                             let keybind_value = value_part.trim().to_string();
-                             if !keybind_value.is_empty() {
-                                  // .unwrap() is safe because we checked is_some()
-                                  found_keybinds.push(KeybindInfo {
-                                      title: current_section_title.clone().unwrap(),
-                                      key: keybind_value,
-                                  });
-                             }
-                         }
-                    }
-                }
-            } // End line loop
+// Runs validate_asset's checks on every enabled mod and returns the ones that fail, for the
+// dashboard's "N active mods are broken" warning. Disabled and missing mods are skipped.
+#[command]
+async fn get_broken_enabled_assets(app_handle: AppHandle) -> CmdResult<Vec<BrokenAsset>> {
+    run_disk_stat_task(app_handle, |handle| broken_enabled_assets(&handle.state::<DbState>(), &handle.state::<CachedConfigState>())).await
+}
 
-             // Check if keybinds were found in *this* file (after Constants)
-             if !found_keybinds.is_empty() {
-                println!("[get_ini_keybinds] SUCCESS: Found {} keybinds (after Constants) in {}. Returning.", found_keybinds.len(), ini_path.display());
-                return Ok(found_keybinds); // Found binds, return them
-            } else {
-                 println!("[get_ini_keybinds] No keybinds found (after Constants) in {}. Checking next file.", ini_path.display());
-             }
-        } // --- End loop through INI paths ---
+fn broken_enabled_assets(db_state: &DbState, config_state: &CachedConfigState) -> CmdResult<Vec<BrokenAsset>> {
+    let base_mods_path = cached_mods_base_path(config_state, db_state).map_err(|e| e.to_string())?;
+    let assets: Vec<(i64, String, String, String, String, String)> = {
+        let conn = db_state.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT a.id, a.name, a.folder_name, e.slug, e.name, c.slug
+             FROM active_assets a
+             JOIN entities e ON a.entity_id = e.id
+             JOIN categories c ON e.category_id = c.id
+             ORDER BY a.name COLLATE NOCASE"
+        ).map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((
+            row.get(0)?, row.get(1)?, row.get::<_, String>(2)?.replace("\\", "/"), row.get(3)?, row.get(4)?, row.get(5)?,
+        ))).map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
+    }; // Connection released before disk checks
+
+    let mut broken = Vec::new();
+    for (asset_id, name, clean_relative_path, entity_slug, entity_name, category_slug) in assets {
+        let current_relative_path = match resolve_asset_disk_state(&base_mods_path, &clean_relative_path) {
+            Some((true, current_relative_path)) => current_relative_path,
+            _ => continue,
+        };
+        let (validation, error) = match validate_mod_folder(asset_id, &base_mods_path.join(current_relative_path)) {
+            Ok(validation) if validation.is_valid => continue,
+            Ok(validation) => (Some(validation), None),
+            Err(e) => (None, Some(e)),
+        };
+        broken.push(BrokenAsset { asset_id, name, entity_slug, entity_name, category_slug, validation, error });
+    }
 
-        // If loop finishes, no keybinds were found in any file
-        println!("[get_ini_keybinds] No keybinds found (after Constants) after checking all INI files for asset ID {}", asset_id);
-        Ok(Vec::new()) // Return empty Vec<KeybindInfo>
+    println!("[get_broken_enabled_assets] {} enabled mod(s) are broken", broken.len());
+    Ok(broken)
+}
 
-    })(); // Execute the closure
+// Groups enabled mods that override the same texture/shader hash; 3DMigoto only applies one of
+// them, which is the usual cause of a model breaking when two mods are on.
+#[command]
+fn detect_hash_conflicts(db_state: State<DbState>) -> CmdResult<Vec<HashConflict>> {
+    println!("[detect_hash_conflicts] Scanning enabled mods");
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    let assets: Vec<(i64, String)> = {
+        let conn = db_state.conn()?;
+        let mut stmt = conn.prepare("SELECT id, folder_name FROM active_assets ORDER BY id")
+            .map_err(|e| format!("Failed to prepare asset query: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| format!("Failed to query assets: {}", e))?;
+        rows.collect::<SqlResult<Vec<_>>>().map_err(|e| format!("Failed to read asset rows: {}", e))?
+    };
 
-    println!("[get_ini_keybinds] COMMAND END for asset ID: {}", asset_id);
-    result // Return the result of the closure (Result<Vec<KeybindInfo>, String>)
+    let mut by_hash: HashMap<(String, Option<String>), Vec<i64>> = HashMap::new();
+    for (asset_id, folder_name) in assets {
+        let enabled_path = base_mods_path.join(folder_name.replace("\\", "/"));
+        if !enabled_path.is_dir() {
+            continue; // Disabled or missing mods can't clash
+        }
+        for key in read_ini_override_hashes(&enabled_path) {
+            by_hash.entry(key).or_default().push(asset_id);
+        }
+    }
+
+    let mut conflicts: Vec<HashConflict> = by_hash.into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|((hash, match_first_index), asset_ids)| HashConflict { hash, match_first_index, asset_ids })
+        .collect();
+    conflicts.sort_by(|a, b| (&a.hash, &a.match_first_index).cmp(&(&b.hash, &b.match_first_index)));
+
+    println!("[detect_hash_conflicts] Found {} conflicting hashes", conflicts.len());
+    Ok(conflicts)
 }
 
 #[command]
@@ -4333,7 +13271,7 @@ fn open_asset_folder(asset_id: i64, db_state: State<DbState>) -> CmdResult<()> {
     let result = (|| {
         // ... (Lock acquisition, base path fetch, asset info fetch - remain the same) ...
         println!("[open_asset_folder] Attempting to acquire DB lock...");
-        let conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
+        let conn_guard = db_state.conn()?;
         println!("[open_asset_folder] DB lock acquired.");
         let conn = &*conn_guard;
 
@@ -4363,7 +13301,7 @@ fn open_asset_folder(asset_id: i64, db_state: State<DbState>) -> CmdResult<()> {
 
         let filename_osstr = relative_path_buf.file_name().ok_or_else(|| format!("Could not extract filename from DB path: {}", asset_info.clean_relative_path))?;
         let filename_str = filename_osstr.to_string_lossy();
-        let disabled_filename = format!("{}{}", DISABLED_PREFIX, filename_str);
+        let disabled_filename = disabled_folder_name(&filename_str);
         let relative_parent_path = relative_path_buf.parent();
 
         let full_path_if_enabled = base_mods_path.join(&relative_path_buf);
@@ -4386,40 +13324,12 @@ fn open_asset_folder(asset_id: i64, db_state: State<DbState>) -> CmdResult<()> {
         match mod_folder_path_on_disk {
             Some(mod_path) => {
                 println!("[open_asset_folder] Target mod folder: {}", mod_path.display());
-
-                let command_name;
-                let arg;
-
-                // --- FIX: Ensure backslashes for Windows explorer ---
-                if cfg!(target_os = "windows") {
-                    command_name = "explorer";
-                    // Convert to string and explicitly replace forward slashes
-                    arg = mod_path.to_string_lossy().replace("/", "\\");
-                } else if cfg!(target_os = "macos") {
-                    command_name = "open";
-                    arg = mod_path.to_str().ok_or("Invalid UTF-8 path string for macOS")?.to_string();
-                } else {
-                    command_name = "xdg-open";
-                    arg = mod_path.to_str().ok_or("Invalid UTF-8 path string for Linux")?.to_string();
-                }
-                // --- End Fix ---
-
-                println!("Executing: {} \"{}\"", command_name, arg);
-
-                match Command::new(command_name).args(&[arg]).spawn() {
-                    Ok((_, _child)) => {
-                        println!("File explorer command spawned successfully.");
-                        Ok(())
-                    },
-                    Err(e) => {
-                        eprintln!("Failed to spawn file explorer command '{}': {}", command_name, e);
-                        Err(format!("Failed to open folder using '{}': {}", command_name, e))
-                    }
-                }
+                // Select the folder itself rather than opening it, so enabled/disabled state is visible
+                reveal_in_file_manager(&mod_path)
             }
             None => {
                  println!("[open_asset_folder] Mod folder not found on disk for asset ID {}", asset_id);
-                 Err(format!("Mod folder not found for asset ID {}", asset_id))
+                 Err(format!("Mod folder not found on disk for asset ID {} (expected '{}' or its disabled variant).", asset_id, asset_info.clean_relative_path))
             }
         }
 
@@ -4429,6 +13339,25 @@ fn open_asset_folder(asset_id: i64, db_state: State<DbState>) -> CmdResult<()> {
     result
 }
 
+// Opens the stored source_url (e.g. the GameBanana page) in the default browser
+#[command]
+fn open_asset_source(asset_id: i64, db_state: State<DbState>, app_handle: AppHandle) -> CmdResult<()> {
+    let source_url: Option<String> = {
+        let conn = db_state.conn()?;
+        conn.query_row("SELECT source_url FROM active_assets WHERE id = ?1", params![asset_id], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to fetch source URL for asset {}: {}", asset_id, e))?
+            .ok_or_else(|| format!("Asset with ID {} not found.", asset_id))?
+    };
+    let source_url = source_url.filter(|url| !url.trim().is_empty())
+        .ok_or_else(|| format!("Asset {} has no source URL.", asset_id))?;
+    // Stored URLs predating validation may not be http(s)
+    let source_url = validate_source_url(&source_url)?;
+    println!("[open_asset_source] Opening {} for asset {}", source_url, asset_id);
+    tauri::api::shell::open(&app_handle.shell_scope(), &source_url, None)
+        .map_err(|e| format!("Failed to open '{}': {}", source_url, e))
+}
+
 #[command]
 fn add_asset_to_presets(asset_id: i64, preset_ids: Vec<i64>, db_state: State<DbState>) -> CmdResult<()> {
     if preset_ids.is_empty() {
@@ -4439,10 +13368,10 @@ fn add_asset_to_presets(asset_id: i64, preset_ids: Vec<i64>, db_state: State<DbS
     let base_mods_path = get_mods_base_path_from_settings(&db_state)
         .map_err(|e| format!("Cannot add/update presets (failed to get mods path): {}", e))?;
 
-    let mut conn_guard = db_state.0.lock().map_err(|_| "DB lock poisoned".to_string())?;
+    let mut conn_guard = db_state.conn()?;
 
     // Use a transaction for atomicity
-    let tx = conn_guard.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let tx = conn_guard.transaction_with_behavior(TransactionBehavior::Immediate).map_err(|e| format!("Failed to start transaction: {}", e))?;
 
     // Get current enabled state *once* before the loop
     let current_is_enabled = match get_current_asset_enabled_state(&tx, asset_id, &base_mods_path) {
@@ -4567,6 +13496,117 @@ fn run_traveler_migration(db_state: State<DbState>, app_handle: AppHandle) -> Cm
     run_traveler_migration_logic(&db_state, &app_handle)
 }
 
+// Returns the path with the DISABLED_ prefix removed from its final component
+fn strip_disabled_from_path(path: &Path) -> PathBuf {
+    match path.file_name() {
+        Some(name) => {
+            let name_str = name.to_string_lossy();
+            let clean = strip_disabled_marker(&name_str).unwrap_or(&name_str);
+            path.with_file_name(clean)
+        }
+        None => path.to_path_buf(),
+    }
+}
+
+// Drops enable/disable renames (ModName <-> DISABLED_ModName) from a batch of changed paths
+fn filter_toggle_renames(changed_paths: HashSet<PathBuf>) -> Vec<PathBuf> {
+    let mut by_clean_path: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for path in changed_paths {
+        by_clean_path.entry(strip_disabled_from_path(&path)).or_default().push(path);
+    }
+    by_clean_path.into_iter()
+        .filter_map(|(clean, variants)| {
+            let has_enabled = variants.iter().any(|p| *p == clean);
+            let has_disabled = variants.iter().any(|p| *p != clean);
+            // Both names changing together is a toggle, not a library change
+            if has_enabled && has_disabled { None } else { Some(variants) }
+        })
+        .flatten()
+        .collect()
+}
+
+// Only structural changes matter: folders created, deleted or renamed. Decided from the event
+// and the path alone, since a deleted path can't be checked on disk anymore.
+fn is_folder_change(kind: &EventKind, path: &Path) -> bool {
+    use notify::event::{CreateKind, ModifyKind, RemoveKind};
+    match kind {
+        EventKind::Create(CreateKind::File) | EventKind::Remove(RemoveKind::File) => false,
+        EventKind::Create(CreateKind::Folder) | EventKind::Remove(RemoveKind::Folder) => true,
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)) => !path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .map_or(false, |ext| WATCHER_IGNORED_FILE_EXTENSIONS.contains(&ext.as_str())),
+        _ => false,
+    }
+}
+
+fn start_watching_mods_folder(app_handle: &AppHandle, base_mods_path: &Path) -> Result<RecommendedWatcher, AppError> {
+    let (tx, rx) = std::sync::mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        tx.send(res).ok(); // Receiver gone means the watcher is being torn down
+    })?;
+    watcher.watch(base_mods_path, RecursiveMode::Recursive)?;
+
+    let app_handle_clone = app_handle.clone();
+    std::thread::spawn(move || {
+        let debounce = std::time::Duration::from_millis(WATCHER_DEBOUNCE_MS);
+        let mut pending: HashSet<PathBuf> = HashSet::new();
+        loop {
+            match rx.recv_timeout(debounce) {
+                Ok(Ok(event)) => {
+                    let kind = event.kind;
+                    pending.extend(event.paths.into_iter().filter(|p| is_folder_change(&kind, p)));
+                }
+                Ok(Err(e)) => eprintln!("[mod_watcher] Watch error: {}", e),
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                    if pending.is_empty() {
+                        continue;
+                    }
+                    let changed = filter_toggle_renames(std::mem::take(&mut pending));
+                    if changed.is_empty() {
+                        continue;
+                    }
+                    let changed_strs: Vec<String> = changed.iter().map(|p| p.to_string_lossy().to_string()).collect();
+                    println!("[mod_watcher] Detected {} changed paths", changed_strs.len());
+                    app_handle_clone.emit_all(MODS_CHANGED_EVENT, changed_strs)
+                        .unwrap_or_else(|e| eprintln!("Failed to emit mods changed event: {}", e));
+                }
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                    println!("[mod_watcher] Watcher dropped, stopping debounce thread.");
+                    break;
+                }
+            }
+        }
+    });
+
+    println!("[mod_watcher] Watching mods folder: {}", base_mods_path.display());
+    Ok(watcher)
+}
+
+#[command]
+fn start_mod_watcher(
+    app_handle: AppHandle,
+    db_state: State<DbState>,
+    watcher_state: State<ModWatcherState>
+) -> CmdResult<()> {
+    let base_mods_path = get_mods_base_path_from_settings(&db_state).map_err(|e| e.to_string())?;
+    if !base_mods_path.is_dir() {
+        return Err(format!("Mods directory path is not a valid directory: {}", base_mods_path.display()));
+    }
+    let watcher = start_watching_mods_folder(&app_handle, &base_mods_path).map_err(|e| e.to_string())?;
+    let mut guard = watcher_state.0.lock().map_err(|_| "Watcher lock poisoned".to_string())?;
+    *guard = Some(watcher); // Replaces (and stops) any previous watcher
+    Ok(())
+}
+
+#[command]
+fn stop_mod_watcher(watcher_state: State<ModWatcherState>) -> CmdResult<()> {
+    let mut guard = watcher_state.0.lock().map_err(|_| "Watcher lock poisoned".to_string())?;
+    if guard.take().is_some() {
+        println!("[mod_watcher] Watcher stopped.");
+    }
+    Ok(())
+}
+
 // --- Main Function ---
 fn main() {
     let context = generate_context!(); // Generates context based on tauri.conf.json
@@ -4656,6 +13696,10 @@ fn main() {
                 // This should correspond to the 'last_slug'.
                 if active_db_path.exists() {
                     println!("Archiving '{}' (from '{}') to '{}'", ACTIVE_DB_FILENAME, last_slug, last_game_archive_path.display());
+                    // A WAL left behind by an unclean exit would otherwise stay attached to the active name
+                    if let Ok(conn) = Connection::open(&active_db_path) {
+                        checkpoint_db_wal(&conn);
+                    }
                     // Attempt to rename the active DB file to its archived name.
                     if let Err(e) = fs::rename(&active_db_path, &last_game_archive_path) {
                          // If renaming fails, it's a critical error preventing the switch.
@@ -4725,8 +13769,8 @@ fn main() {
             // --- 3. Initialize DB Connection for State ---
             // Initialize the database connection using the (now correctly named) active DB file.
             // Pass the slug of the game that *should* be active now (the requested_slug).
-            let conn = match initialize_database(&app_handle, requested_slug) {
-                 Ok(c) => c,
+            let pool = match initialize_database(&app_handle, requested_slug) {
+                 Ok(p) => p,
                  Err(e) => {
                      // If database initialization fails (e.g., cannot open/create file, schema error).
                      eprintln!("FATAL: Database initialization failed: {}", e);
@@ -4738,11 +13782,19 @@ fn main() {
                      std::process::exit(1);
                  }
             };
-            println!("Database connection established for {}.", ACTIVE_DB_FILENAME);
+            println!("Database connection pool established for {}.", ACTIVE_DB_FILENAME);
+            {
+                let conn = pool.get().expect("DB connection unavailable during setup");
+                CACHED_CONFIG.load_disable_convention(&conn);
+                CACHED_CONFIG.load_preview_candidates(&conn);
+                CACHED_CONFIG.load_enable_mode(&conn);
+                load_disk_stat_concurrency(&conn);
+            }
+            prune_archive_preview_temp_files(true);
 
             // --- 4. Manage State & Final Checks ---
-            // Make the database connection available to Tauri commands via managed state.
-             app.manage(DbState(Arc::new(Mutex::new(conn))));
+            // Make the database pool available to Tauri commands via managed state.
+             app.manage(DbState(pool));
 
              // --- *** ADD MIGRATION CHECK *** ---
             println!("--- Running Post-Init Checks/Migrations ---");
@@ -4766,43 +13818,246 @@ fn main() {
 
              // Perform a final check/log for a key setting (like mods folder) from the *active* DB.
              let db_state: State<DbState> = app.state(); // Get the managed state.
-             match get_setting_value(&db_state.0.lock().expect("DB lock poisoned during setup check"), SETTINGS_KEY_MODS_FOLDER) { // Lock mutex to access connection.
-                 Ok(Some(path)) => println!("Mods folder configured in active DB to: {}", path),
+             let mut initial_watcher = None;
+             match get_setting_value(&db_state.conn().expect("DB connection unavailable during setup check"), SETTINGS_KEY_MODS_FOLDER) { // Borrow a pooled connection.
+                 Ok(Some(path)) => {
+                     println!("Mods folder configured in active DB to: {}", path);
+                     // Start watching for external changes to the mods folder
+                     let base_mods_path = PathBuf::from(&path);
+                     if base_mods_path.is_dir() {
+                         match start_watching_mods_folder(&app_handle, &base_mods_path) {
+                             Ok(w) => initial_watcher = Some(w),
+                             Err(e) => eprintln!("WARN: Failed to start mods folder watcher: {}", e),
+                         }
+                     }
+                 }
                  _ => println!("WARN: Mods folder path is not configured yet in active DB."),
              }
+             app.manage(ModWatcherState(Mutex::new(initial_watcher)));
+             app.manage(OperationLogState(Mutex::new(Vec::new())));
+             app.manage(CancellationState::default());
+             app.manage(CACHED_CONFIG.clone());
+             register_saved_preset_shortcuts(&app.handle());
              println!("--- Application Setup Complete ---");
             Ok(()) // Indicate successful setup
         })
         .invoke_handler(generate_handler![
             // List ALL exposed Tauri commands here:
             // Settings
-            get_setting, set_setting, select_directory, select_file, launch_executable,
+            get_setting, set_setting, get_safe_mode, get_disable_convention, set_disable_convention, select_directory, select_file, launch_executable, validate_launch_target,
+            create_profile, list_profiles, switch_profile,
             launch_executable_elevated,
             // Core
-            get_categories, get_category_entities, get_entities_by_category,
-            get_entity_details, get_assets_for_entity, toggle_asset_enabled,
-            get_asset_image_path, run_traveler_migration,
-            open_mods_folder,
+            get_categories, get_categories_with_counts, create_category, delete_category, resync_definitions, get_category_entities, get_entities_by_category, create_entity, delete_entity, merge_entity, set_entity_image, get_entity_image_path,
+            get_entity_details, get_entity_page, get_assets_for_entity, get_assets_for_entity_paged, toggle_asset_enabled, set_assets_enabled, disable_all_mods, enable_all_mods,
+            get_asset_variants, set_active_variant,
+            get_asset_image_path, get_asset_thumbnail, run_traveler_migration, search_assets,
+            open_mods_folder, get_app_data_path, reveal_app_data_folder,
+            start_mod_watcher, stop_mod_watcher, change_mods_folder,
             // Scan & Count
-            scan_mods_directory, get_total_asset_count,
-            get_entities_by_category_with_counts,
+            scan_mods_directory, maybe_autoscan, scan_subpath, cancel_scan, get_total_asset_count,
+            get_entities_by_category_with_counts, get_entity_counts, get_storage_report,
+            pin_entity, get_pinned_entities, reorder_pinned_entities,
+            add_deduction_rule, list_deduction_rules, delete_deduction_rule, reorder_assets,
+            toggle_asset_favorite, get_favorite_assets, get_recent_assets, get_recently_modified, get_least_used_assets, find_duplicate_assets, compute_missing_hashes,
+            toggle_asset_pinned, set_category_tags, get_exclusive_groups, set_exclusive_group, set_exclusive_group_enforced, set_asset_locked, randomize_enabled,
+            audit_library, get_prune_candidates, prune_missing_assets, repair_asset_path, find_orphan_folders, delete_orphan_folder,
             // Edit, Import, Delete (Assets)
-            update_asset_info, delete_asset, read_binary_file,
-            select_archive_file, analyze_archive,
-            import_archive,
-            read_archive_file_content,
+            update_asset_info, relocate_assets, rededuce_asset, normalize_asset_folder_names, delete_asset, duplicate_asset, undo_last_operation, read_binary_file,
+            get_trash_contents, restore_trashed_asset, empty_trash,
+            select_archive_file, select_archive_folder, analyze_archive, verify_archive, batch_analyze_archives,
+            import_archive, batch_import_archives, analyze_folder, import_folder, hash_file,
+            read_archive_file_content, extract_archive_preview,
             // Presets
-            create_preset, get_presets, get_favorite_presets, apply_preset,
+            create_preset, get_presets, get_favorite_presets, apply_preset, cancel_preset_apply, preview_preset_apply, diff_presets, identify_current_preset, get_preset_asset_details, clean_preset,
             toggle_preset_favorite, delete_preset, overwrite_preset,
-            add_asset_to_presets,
+            register_preset_shortcut, unregister_preset_shortcut, get_preset_shortcuts,
+            add_asset_to_presets, export_preset, import_preset, export_mod_list, export_assets_as_archive, export_library, import_library,
+            // Tags
+            add_tag_to_asset, remove_tag_from_asset, get_tags_for_asset, get_all_tags, delete_tag, get_assets_by_author, retag_by_author,
             // Dashboard & Version
-            get_dashboard_stats, get_app_version,
+            get_dashboard_stats, reconcile_enabled_states, get_app_version,
             // Keybinds
-            get_ini_keybinds, open_asset_folder,
+            get_ini_keybinds, get_asset_keybinds, open_asset_folder, open_asset_source, list_ini_backups, restore_ini_backup, find_namespace_collisions, detect_hash_conflicts, validate_asset, get_broken_enabled_assets, get_asset_ini_overview,
+            detect_keybind_conflicts, remap_asset_keybind, auto_resolve_keybind_conflict,
             // Multi-Game Commands
             get_available_games, get_active_game, switch_game,
             exit_app
         ])
-        .run(context) // Runs the Tauri application loop.
-        .expect("error while running tauri application"); // Panic if the app fails to run.
-}
\ No newline at end of file
+        .build(context)
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                prune_archive_preview_temp_files(true);
+                if let Some(db_state) = app_handle.try_state::<DbState>() {
+                    if let Ok(conn) = db_state.conn() {
+                        checkpoint_db_wal(&conn);
+                    }
+                }
+            }
+        }); // Runs the Tauri application loop.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fresh folder under the system temp dir; removed by the test that created it
+    fn temp_test_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gmm_test_{}_{}", label, new_operation_id()));
+        fs::create_dir_all(&dir).expect("create temp test dir");
+        dir
+    }
+
+    #[test]
+    fn extraction_path_accepts_nested_entries() {
+        let dest = temp_test_dir("extract_nested");
+        let outpath = checked_extraction_path(&dest, Path::new("textures/a.dds")).expect("nested entry is allowed");
+        assert!(outpath.ends_with("textures/a.dds"));
+        fs::remove_dir_all(&dest).ok();
+    }
+
+    #[test]
+    fn extraction_path_rejects_parent_traversal() {
+        let dest = temp_test_dir("extract_parent");
+        assert!(checked_extraction_path(&dest, Path::new("../x")).is_err());
+        assert!(checked_extraction_path(&dest, Path::new("mod/../../x")).is_err());
+        fs::remove_dir_all(&dest).ok();
+    }
+
+    #[test]
+    fn extraction_path_rejects_absolute_entries() {
+        let dest = temp_test_dir("extract_absolute");
+        let absolute = std::env::temp_dir().join("x.ini");
+        assert!(absolute.is_absolute());
+        assert!(checked_extraction_path(&dest, &absolute).is_err());
+        fs::remove_dir_all(&dest).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extraction_path_rejects_symlinked_parent() {
+        let dest = temp_test_dir("extract_symlink");
+        let outside = temp_test_dir("extract_symlink_target");
+        std::os::unix::fs::symlink(&outside, dest.join("link")).expect("create symlink");
+        assert!(checked_extraction_path(&dest, Path::new("link/x.ini")).is_err());
+        fs::remove_dir_all(&dest).ok();
+        fs::remove_dir_all(&outside).ok();
+    }
+
+    #[test]
+    fn zip_with_traversal_entry_is_rejected_before_extraction() {
+        let root = temp_test_dir("zip_traversal");
+        let dest = root.join("dest");
+        fs::create_dir_all(&dest).expect("create dest folder");
+        let archive_path = root.join("evil.zip");
+        let mut writer = zip::ZipWriter::new(fs::File::create(&archive_path).expect("create zip"));
+        writer.start_file("mod.ini", zip::write::FileOptions::default()).expect("start mod.ini");
+        writer.write_all(b"[Constants]").expect("write mod.ini");
+        writer.start_file("../evil.ini", zip::write::FileOptions::default()).expect("start evil.ini");
+        writer.write_all(b"[Constants]").expect("write evil.ini");
+        writer.finish().expect("finish zip");
+
+        assert!(check_archive_entries_to_extract(&archive_path, Some("zip"), Path::new(""), true, &dest, None).is_err());
+        assert!(!root.join("evil.ini").exists());
+        assert_eq!(fs::read_dir(&dest).expect("read dest folder").count(), 0);
+        fs::remove_dir_all(&root).ok();
+    }
+
+    fn test_asset_location(clean_relative_path: &str) -> AssetLocationInfo {
+        AssetLocationInfo {
+            id: 1,
+            clean_relative_path: clean_relative_path.to_string(),
+            entity_id: 1,
+            category_slug: "cat".to_string(),
+            entity_slug: "ent".to_string(),
+        }
+    }
+
+    #[test]
+    fn relocation_onto_same_or_nested_path_is_refused() {
+        let base = temp_test_dir("relocate_nested");
+        let mod_dir = base.join("cat/ent/Mod");
+        fs::create_dir_all(&mod_dir).expect("create mod folder");
+        let info = test_asset_location("cat/ent/Mod");
+
+        assert!(plan_asset_relocation(&base, &info, "ent", "cat").is_err());
+        assert!(plan_asset_relocation(&base, &info, "Mod", "cat/ent").is_err());
+        assert!(mod_dir.is_dir());
+        assert!(!base.join(staged_relative_path("cat/ent/Mod")).exists());
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn refused_relocation_leaves_staged_mod_staged() {
+        let base = temp_test_dir("relocate_staged");
+        let staged_dir = base.join(staged_relative_path("cat/ent/Mod"));
+        fs::create_dir_all(&staged_dir).expect("create staged mod folder");
+        let info = test_asset_location("cat/ent/Mod");
+
+        assert!(plan_asset_relocation(&base, &info, "ent", "cat").is_err());
+        assert!(staged_dir.is_dir());
+        assert!(!base.join("cat/ent").join(disabled_folder_name("Mod")).exists());
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn path_within_matches_same_path_and_children() {
+        assert!(path_is_same_or_within(Path::new("mods/char/foo"), Path::new("mods/char/foo")));
+        assert!(path_is_same_or_within(Path::new("mods/char/foo/variant"), Path::new("mods/char/foo")));
+        assert!(!path_is_same_or_within(Path::new("mods/char"), Path::new("mods/char/foo")));
+    }
+
+    #[test]
+    fn active_inis_skip_disabled_files_and_subfolders() {
+        let mod_dir = temp_test_dir("active_inis");
+        for relative in ["mod.ini", "DISABLED_old.ini", "VariantA/a.ini", "DISABLED_VariantB/b.ini"] {
+            let path = mod_dir.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).expect("create variant folder");
+            fs::write(&path, "[Constants]").expect("write ini");
+        }
+        let mut found: Vec<String> = collect_active_ini_files(&mod_dir).iter()
+            .map(|path| path.strip_prefix(&mod_dir).unwrap().to_string_lossy().replace("\\", "/"))
+            .collect();
+        found.sort();
+        assert_eq!(found, vec!["VariantA/a.ini".to_string(), "mod.ini".to_string()]);
+        fs::remove_dir_all(&mod_dir).ok();
+    }
+
+    #[test]
+    fn path_within_ignores_sibling_prefix() {
+        assert!(!path_is_same_or_within(Path::new("mods/char/foobar"), Path::new("mods/char/foo")));
+        assert!(!path_is_same_or_within(Path::new("mods/char/foo_bar/x"), Path::new("mods/char/foo")));
+    }
+
+    #[test]
+    fn path_within_follows_platform_case_rules() {
+        // Case-insensitive on Windows only, like the file system
+        let within = path_is_same_or_within(Path::new("Mods/Char/FOO/variant"), Path::new("mods/char/foo"));
+        assert_eq!(within, cfg!(target_os = "windows"));
+    }
+
+    #[test]
+    fn watcher_keeps_folder_changes_only() {
+        use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
+        // Deleted paths no longer exist, so the decision can't depend on the disk
+        let deleted_ini = Path::new("/nonexistent/mods/Char/Foo/mod.ini");
+        let deleted_folder = Path::new("/nonexistent/mods/Char/Foo");
+        assert!(!is_folder_change(&EventKind::Remove(RemoveKind::Any), deleted_ini));
+        assert!(is_folder_change(&EventKind::Remove(RemoveKind::Any), deleted_folder));
+        assert!(!is_folder_change(&EventKind::Remove(RemoveKind::File), deleted_folder));
+        assert!(is_folder_change(&EventKind::Create(CreateKind::Folder), Path::new("/nonexistent/mods/Foo.v1.2")));
+        assert!(is_folder_change(&EventKind::Modify(ModifyKind::Name(RenameMode::Any)), deleted_folder));
+        assert!(!is_folder_change(&EventKind::Modify(ModifyKind::Any), deleted_folder));
+    }
+
+    #[test]
+    fn canonicalize_existing_ancestor_keeps_missing_components() {
+        let base = temp_test_dir("canonical_ancestor");
+        let missing = base.join("new").join("mods");
+        let canonical = canonicalize_existing_ancestor(&missing).expect("resolve through existing parent");
+        assert_eq!(canonical, base.canonicalize().unwrap().join("new").join("mods"));
+        assert!(!missing.exists());
+        fs::remove_dir_all(&base).ok();
+    }
+}
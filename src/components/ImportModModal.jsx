@@ -1,8 +1,9 @@
 // src/components/ImportModModal.jsx
 import React, { useState, useEffect, useMemo, useRef, useCallback } from 'react';
 import ReactDOM from 'react-dom';
-import { invoke } from '@tauri-apps/api/tauri';
+import { invoke, convertFileSrc } from '@tauri-apps/api/tauri';
 import { open } from '@tauri-apps/api/dialog';
+import { listen } from '@tauri-apps/api/event';
 import Select from 'react-select';
 
 const reactSelectStyles = {
@@ -181,6 +182,7 @@ const styles = {
 };
 
 const FALLBACK_MOD_IMAGE_MODAL = '/images/placeholder.jpg';
+const IMPORT_PROGRESS_EVENT = "import://progress";
 
 function ImportModModal({ analysisResult, onClose, onImportSuccess }) {
     // Form State
@@ -205,11 +207,13 @@ function ImportModModal({ analysisResult, onClose, onImportSuccess }) {
     // Preview State
     const [previewImageUrl, setPreviewImageUrl] = useState(FALLBACK_MOD_IMAGE_MODAL);
     const [selectedPreviewAbsPath, setSelectedPreviewAbsPath] = useState(null);
+    const [previewInternalPath, setPreviewInternalPath] = useState(null); // Detected image inside the archive
     const previewObjectUrlRef = useRef(null);
     const [previewLoading, setPreviewLoading] = useState(false);
     const [pastedImageFile, setPastedImageFile] = useState(null);
     // Modal State
     const [isImporting, setIsImporting] = useState(false);
+    const [importProgress, setImportProgress] = useState(null); // { processed, total, current_file }
     const [error, setError] = useState('');
 
     // Cleanup Blob URL
@@ -226,6 +230,17 @@ function ImportModModal({ analysisResult, onClose, onImportSuccess }) {
     const presetOptions = useMemo(() => allPresets.map(p => ({ value: p.id, label: p.name })), [allPresets]);
     // -------------------------
 
+    // Listen for extraction progress while an import is running
+    useEffect(() => {
+        if (!isImporting) { setImportProgress(null); return; }
+        let unlisten = null;
+        let isActive = true;
+        listen(IMPORT_PROGRESS_EVENT, (event) => setImportProgress(event.payload))
+            .then(fn => { if (isActive) { unlisten = fn; } else { fn(); } })
+            .catch(err => console.error("Failed to listen for import progress:", err));
+        return () => { isActive = false; unlisten?.(); };
+    }, [isImporting]);
+
     // Fetch Categories AND Presets on Mount
     useEffect(() => {
         setCategoryLoading(true);
@@ -265,6 +280,7 @@ function ImportModModal({ analysisResult, onClose, onImportSuccess }) {
         setError('');
         setPreviewImageUrl(FALLBACK_MOD_IMAGE_MODAL);
         setSelectedPreviewAbsPath(null);
+        setPreviewInternalPath(analysisResult.detected_preview_internal_path || null);
         setPastedImageFile(null);
         cleanupPreviewObjectUrl();
 
@@ -294,25 +310,14 @@ function ImportModModal({ analysisResult, onClose, onImportSuccess }) {
 
         if (analysisResult.detected_preview_internal_path) {
             setPreviewLoading(true);
-            invoke('read_archive_file_content', {
+            invoke('extract_archive_preview', {
                 archivePathStr: analysisResult.file_path,
                 internalFilePath: analysisResult.detected_preview_internal_path
             })
-            .then(fileData => {
-                if (!isMounted || !fileData) return;
-                 try {
-                     const extension = analysisResult.detected_preview_internal_path.split('.').pop().toLowerCase();
-                     let mimeType = 'image/png';
-                     if (['jpg', 'jpeg'].includes(extension)) mimeType = 'image/jpeg';
-                     else if (extension === 'gif') mimeType = 'image/gif';
-                     else if (extension === 'webp') mimeType = 'image/webp';
-                     const blob = new Blob([new Uint8Array(fileData)], { type: mimeType });
-                     const url = URL.createObjectURL(blob);
-                     if (isMounted) {
-                        previewObjectUrlRef.current = url;
-                        setPreviewImageUrl(url);
-                     } else { URL.revokeObjectURL(url); }
-                 } catch (e) { console.error("Error creating blob for detected preview:", e); if (isMounted) setPreviewImageUrl(FALLBACK_MOD_IMAGE_MODAL); }
+            .then(tempFilePath => {
+                if (!isMounted || !tempFilePath) return;
+                // Served through the asset protocol, so the image bytes never cross IPC
+                setPreviewImageUrl(convertFileSrc(tempFilePath));
             })
             .catch(err => { console.warn("Failed to load detected preview:", err); if (isMounted) setPreviewImageUrl(FALLBACK_MOD_IMAGE_MODAL); })
             .finally(() => { if (isMounted) setPreviewLoading(false); });
@@ -385,6 +390,7 @@ function ImportModModal({ analysisResult, onClose, onImportSuccess }) {
                     console.log("Pasted image file for import:", file);
                     cleanupPreviewObjectUrl();
                     setSelectedPreviewAbsPath(null); // Clear file path selection
+                    setPreviewInternalPath(null);
                     setPastedImageFile(file); // Store the File object
 
                     const url = URL.createObjectURL(file);
@@ -414,6 +420,7 @@ function ImportModModal({ analysisResult, onClose, onImportSuccess }) {
 
             if (absolutePath) {
                 setPastedImageFile(null);
+                setPreviewInternalPath(null);
                 setSelectedPreviewAbsPath(absolutePath);
                 setPreviewLoading(true);
                 invoke('read_binary_file', { path: absolutePath })
@@ -476,18 +483,29 @@ function ImportModModal({ analysisResult, onClose, onImportSuccess }) {
         const presetIdsToSend = selectedPresets.length > 0 ? selectedPresets.map(opt => opt.value) : null;
 
         try {
-            await invoke('import_archive', {
-                archivePathStr: analysisResult.file_path,
-                targetEntitySlug: targetEntitySlugValue,
-                selectedInternalRoot: extractAllFiles ? "" : (selectedInternalRoot || ""),
-                modName: modName.trim(),
+            const request = {
+                archive_path: analysisResult.file_path,
+                target_entity_slug: targetEntitySlugValue,
+                selected_internal_root: extractAllFiles ? "" : (selectedInternalRoot || ""),
+                mod_name: modName.trim(),
                 description: description || null,
                 author: author || null,
-                categoryTag: categoryTag || null,
-                imageData: imageDataToSend,
-                selectedPreviewAbsolutePath: imageDataToSend ? null : selectedPreviewAbsPath,
-                presetIds: presetIdsToSend,
-            });
+                category_tag: categoryTag || null,
+                image_data: imageDataToSend,
+                selected_preview_absolute_path: imageDataToSend ? null : selectedPreviewAbsPath,
+                preview_internal_path: imageDataToSend ? null : previewInternalPath,
+                preset_ids: presetIdsToSend,
+                overwrite_existing: false,
+            };
+            const result = await invoke('import_archive', { request });
+            if (result?.requires_confirmation) {
+                const confirmed = window.confirm(
+                    `"${modName.trim()}" is already installed (version ${result.old_version || 'unknown'}).\n` +
+                    `Replace it with version ${result.new_version || 'unknown'} from this archive?`
+                );
+                if (!confirmed) return;
+                await invoke('import_archive', { request: { ...request, overwrite_existing: true } });
+            }
             onImportSuccess(targetEntitySlugValue, selectedCategoryOption?.value || 'characters');
         } catch (err) {
              const errorString = typeof err === 'string' ? err : (err?.message || 'Unknown import error');
@@ -651,7 +669,7 @@ function ImportModModal({ analysisResult, onClose, onImportSuccess }) {
                         onClick={handleConfirmImport}
                         disabled={isImporting || !selectedEntityOption || !modName.trim() || (!extractAllFiles && !selectedInternalRoot && analysisResult?.entries?.some(e=>e.is_dir))}
                     >
-                        {isImporting ? <><i className="fas fa-spinner fa-spin fa-fw"></i> Importing...</> : <><i className="fas fa-check fa-fw"></i> Confirm Import</>}
+                        {isImporting ? <><i className="fas fa-spinner fa-spin fa-fw"></i> {importProgress?.total ? `Extracting ${importProgress.processed}/${importProgress.total}...` : 'Importing...'}</> : <><i className="fas fa-check fa-fw"></i> Confirm Import</>}
                     </button>
                 </div>
             </div> {/* End Modal */}
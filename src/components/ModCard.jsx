@@ -187,8 +187,8 @@ function ModCard({
         setImageLoading(true); // Indicate loading process start
         // console.log(`[ModCard ${asset.id}] Image Effect: Getting image path for ${asset.image_filename}`);
 
-        // Get the absolute path from the optimized backend command
-        invoke('get_asset_image_path', { assetId: asset.id })
+        // Get a cached, downscaled thumbnail path (null when there's no usable preview)
+        invoke('get_asset_thumbnail', { assetId: asset.id, maxDim: 480 })
             .then(filePath => {
                 if (!isMounted) return;
                 if (!filePath) throw new Error("No image path found.");
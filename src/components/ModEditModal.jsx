@@ -144,7 +144,7 @@ const FALLBACK_MOD_IMAGE_MODAL = '/images/placeholder.jpg';
 
 function ModEditModal({ asset, currentEntitySlug, onClose, onSaveSuccess }) {
     // Form State
-    const [formData, setFormData] = useState({ name: '', description: '', author: '', category_tag: '' });
+    const [formData, setFormData] = useState({ name: '', description: '', author: '', category_tag: '', notes: '', source_url: '' });
     // Relocation State
     const [categories, setCategories] = useState([]);
     const [entities, setEntities] = useState([]);
@@ -187,6 +187,8 @@ function ModEditModal({ asset, currentEntitySlug, onClose, onSaveSuccess }) {
             description: asset.description || '',
             author: asset.author || '',
             category_tag: asset.category_tag || '',
+            notes: asset.notes || '',
+            source_url: asset.source_url || '',
         });
         setSelectedImageAbsPath(null);
         setPastedImageFile(null);
@@ -501,6 +503,8 @@ function ModEditModal({ asset, currentEntitySlug, onClose, onSaveSuccess }) {
                 description: formData.description || null,
                 author: formData.author || null,
                 categoryTag: formData.category_tag || null,
+                notes: formData.notes || null,
+                sourceUrl: formData.source_url.trim() || null,
                 selectedImageAbsolutePath: imageDataToSend ? null : selectedImageAbsPath, // Send path only if no data
                 imageData: imageDataToSend,
                 newTargetEntitySlug: newTargetSlug
@@ -588,6 +592,16 @@ function ModEditModal({ asset, currentEntitySlug, onClose, onSaveSuccess }) {
                         <input id="mod-category-tag" type="text" name="category_tag" value={formData.category_tag} onChange={handleInputChange} style={styles.input} placeholder="e.g., Outfit, Retexture, Effect" disabled={isSaving} />
                     </div>
 
+                    <div style={styles.formGroup}>
+                        <label style={styles.label} htmlFor="mod-source-url">Source URL:</label>
+                        <input id="mod-source-url" type="url" name="source_url" value={formData.source_url} onChange={handleInputChange} style={styles.input} placeholder="https://gamebanana.com/mods/..." disabled={isSaving} />
+                    </div>
+
+                    <div style={styles.formGroup}>
+                        <label style={styles.label} htmlFor="mod-notes">Notes:</label>
+                        <textarea id="mod-notes" name="notes" value={formData.notes} onChange={handleInputChange} style={styles.textarea} placeholder="Install notes, credits, ..." disabled={isSaving} />
+                    </div>
+
                     {/* Image Preview Section */}
                     <div style={styles.formGroup}>
                         <label style={styles.label}>Preview Image:</label>
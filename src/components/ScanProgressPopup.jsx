@@ -91,6 +91,7 @@ function ScanProgressPopup({
     summary,
     error,
     onClose,
+    onCancel, // Optional: shows a cancel button while running
     baseTitle = "处理中..." // 基础标题
 }) {
     if (!isOpen) {
@@ -142,6 +143,11 @@ function ScanProgressPopup({
                      </>
                  )}
 
+                {!isComplete && onCancel && (
+                    <button className="btn btn-outline" onClick={onCancel} style={styles.closeButton}>
+                        取消
+                    </button>
+                )}
                 {/* Show close button only on completion or error */}
                 {isComplete && (
                     <button className="btn btn-primary" onClick={onClose} style={styles.closeButton}>
@@ -177,8 +177,8 @@ function Sidebar() {
         const setupSidebarListeners = async () => {
             applyListenersSidebarRef.current.unlistenStart = await listen(PRESET_APPLY_START_EVENT, (event) => { if (applyingPresetIdSidebar !== null) { setApplyProgressDataSidebar({ processed: 0, total: event.payload || 0, message: '正在启动...' }); setApplySummarySidebar(''); setApplyErrorSidebar(''); setShowApplyPopupSidebar(true); }});
             applyListenersSidebarRef.current.unlistenProgress = await listen(PRESET_APPLY_PROGRESS_EVENT, (event) => { if (applyingPresetIdSidebar !== null && showApplyPopupSidebar) setApplyProgressDataSidebar(event.payload); });
-            applyListenersSidebarRef.current.unlistenComplete = await listen(PRESET_APPLY_COMPLETE_EVENT, (event) => { if (applyingPresetIdSidebar !== null) { if(showApplyPopupSidebar) {setApplySummarySidebar(event.payload || '预设应用成功！'); setApplyProgressDataSidebar(null);} setApplyingPresetIdSidebar(null); } });
-            applyListenersSidebarRef.current.unlistenError = await listen(PRESET_APPLY_ERROR_EVENT, (event) => { if (applyingPresetIdSidebar !== null) { if(showApplyPopupSidebar) { setApplyErrorSidebar(event.payload || '发生未知错误。'); setApplyProgressDataSidebar(null); setApplySummarySidebar('');} else { setApplyErrorSidebar(event.payload || 'An unknown error occurred.'); } setApplyingPresetIdSidebar(null); } });
+            applyListenersSidebarRef.current.unlistenComplete = await listen(PRESET_APPLY_COMPLETE_EVENT, (event) => { if (applyingPresetIdSidebar !== null) { if(showApplyPopupSidebar) {setApplySummarySidebar(event.payload?.message || '预设应用成功！'); setApplyProgressDataSidebar(null);} setApplyingPresetIdSidebar(null); } });
+            applyListenersSidebarRef.current.unlistenError = await listen(PRESET_APPLY_ERROR_EVENT, (event) => { if (applyingPresetIdSidebar !== null) { if(showApplyPopupSidebar) { setApplyErrorSidebar(event.payload?.message || '发生未知错误。'); setApplyProgressDataSidebar(null); setApplySummarySidebar('');} else { setApplyErrorSidebar(event.payload?.message || 'An unknown error occurred.'); } setApplyingPresetIdSidebar(null); } });
         };
         setupSidebarListeners();
         return () => {
@@ -115,7 +115,7 @@ function PresetPage() {
             applyListenersRef.current.unlistenComplete = await listen(PRESET_APPLY_COMPLETE_EVENT, (event) => {
                  if (applyingPresetId !== null) { // Only process if related to an ongoing apply
                     console.log("Preset Apply Complete:", event.payload);
-                    setApplySummary(event.payload || '预设应用成功！');
+                    setApplySummary(event.payload?.message || '预设应用成功！');
                     setApplyProgressData(null);
                     setShowApplyPopup(true); // Ensure popup shows completion
                     setApplyingPresetId(null); // Re-enable button
@@ -124,7 +124,7 @@ function PresetPage() {
             applyListenersRef.current.unlistenError = await listen(PRESET_APPLY_ERROR_EVENT, (event) => {
                 if (applyingPresetId !== null) { // Only process if related to an ongoing apply
                     console.error("Preset Apply Error:", event.payload);
-                    toast.error(event.payload || '应用预设时发生未知错误');
+                    toast.error(event.payload?.message || '应用预设时发生未知错误');
                     setApplyProgressData(null);
                     setApplySummary('');
                     setShowApplyPopup(true); // Ensure popup shows error
@@ -398,6 +398,7 @@ function PresetPage() {
                 summary={applySummary}
                 error={applyError}
                 onClose={closeApplyPopup}
+                onCancel={() => invoke('cancel_preset_apply').catch(err => console.error("Failed to cancel preset apply:", err))}
                 baseTitle="正在应用预设..."
             />
         </div>
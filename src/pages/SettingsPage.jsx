@@ -120,7 +120,7 @@ function SettingsPage() {
         setScanError('');
 
         try {
-            await invoke('scan_mods_directory');
+            await invoke('scan_mods_directory', { forceFull: false, dryRun: false });
         } catch (err) {
             console.error("Failed to invoke scan command:", err);
             const errorMessage = typeof err === 'string' ? err : (err.message || 'Failed to start scan');
@@ -143,7 +143,7 @@ function SettingsPage() {
              scanListenersRef.current.unlistenComplete = await listen(SCAN_COMPLETE_EVENT, (event) => {
                  console.log('Manual Scan Complete:', event.payload);
                  setShowScanPopup(true);
-                 setScanSummary(event.payload || '扫描完成！');
+                 setScanSummary(event.payload?.message || '扫描完成！');
                  setScanProgressData(null);
                  setScanError('');
                  setIsManualScanning(false);
@@ -151,7 +151,7 @@ function SettingsPage() {
              scanListenersRef.current.unlistenError = await listen(SCAN_ERROR_EVENT, (event) => {
                  console.error('Manual Scan Error Event:', event.payload);
                  setShowScanPopup(true);
-                 setScanError(event.payload || '扫描过程中发生未知错误');
+                 setScanError(event.payload?.message || '扫描过程中发生未知错误');
                  setScanProgressData(null);
                  setScanSummary('');
                  setIsManualScanning(false);
@@ -271,6 +271,7 @@ function SettingsPage() {
                 summary={scanSummary}
                 error={scanError}
                 onClose={closeScanPopup}
+                onCancel={() => invoke('cancel_scan').catch(err => console.error("Failed to cancel scan:", err))}
                 baseTitle="正在扫描模组..."
             />
         </div>